@@ -0,0 +1,375 @@
+//! 请求 ID 与写请求审计。
+//!
+//! - [`assign_request_id`]：每个请求一个 `X-Request-Id`（客户端带了合法值就
+//!   沿用，否则生成 32 位 hex），写进响应头，并作为 `request` span 的字段，
+//!   处理过程中的所有日志都带上它
+//! - [`record_writes`]：鉴权通过的 POST / PATCH / PUT / DELETE 各记一行
+//!   `audit_log`（含失败的请求）。目标 id 与变更前后摘要由 handler 通过响应
+//!   extension [`AuditNote`] 交上来；没交的（如 `/sync`、出错）只记路由与
+//!   路径参数
+//! - `GET /audit?since=&key=&target=&before=&limit=` 查询，新的在前
+//! - [`spawn_retention`]：每小时删掉超过 `audit_retention_days` 的记录
+
+use std::time::Duration;
+
+use axum::extract::{MatchedPath, Query, Request, State};
+use axum::http::header::{HeaderValue, USER_AGENT};
+use axum::http::{HeaderName, Method};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::{Extension, Json};
+use chrono::Utc;
+use rand::Rng;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tracing::{info, info_span, warn, Instrument};
+
+use super::auth;
+use super::error::ApiError;
+use super::ratelimit::client_ip;
+use super::AppState;
+use crate::config::LiveConfig;
+use crate::db::audit::{self, AuditEntry, AuditFilter};
+use crate::db::Db;
+use crate::time::{format_local, now_local_string};
+
+pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+
+/// `GET /audit` 未指定 `limit` 时的条数与上限。
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+/// 摘要里保留的字段（新建 / 删除时）。改动时只记变了的字段。
+const SUMMARY_FIELDS: [&str; 7] = [
+    "id",
+    "title",
+    "completed",
+    "parentId",
+    "name",
+    "size",
+    "contentType",
+];
+
+/// 摘要里单个值序列化后的长度上限（字符），超出截断。
+const MAX_VALUE_CHARS: usize = 200;
+
+/// 本请求的 ID，放在 request extensions 里。
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// handler 附在响应上的审计信息。
+#[derive(Debug, Clone, Default)]
+pub struct AuditNote {
+    pub target: Option<String>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+impl AuditNote {
+    pub fn created(target: impl Into<String>, after: &Value) -> Extension<Self> {
+        Extension(AuditNote {
+            target: Some(target.into()),
+            before: None,
+            after: Some(pick(after)),
+        })
+    }
+
+    pub fn updated(target: impl Into<String>, before: &Value, after: &Value) -> Extension<Self> {
+        let (before, after) = diff(before, after);
+        Extension(AuditNote {
+            target: Some(target.into()),
+            before: Some(before),
+            after: Some(after),
+        })
+    }
+
+    pub fn deleted(target: impl Into<String>, before: &Value) -> Extension<Self> {
+        Extension(AuditNote {
+            target: Some(target.into()),
+            before: Some(pick(before)),
+            after: None,
+        })
+    }
+
+    /// 记录换了形态（promote / demote）：旧记录与新记录都只记摘要。
+    pub fn converted(target: impl Into<String>, before: &Value, after: &Value) -> Extension<Self> {
+        Extension(AuditNote {
+            target: Some(target.into()),
+            before: Some(pick(before)),
+            after: Some(pick(after)),
+        })
+    }
+}
+
+/// 新建 / 删除的摘要：只留能认出是哪条记录的几个字段。
+fn pick(v: &Value) -> Value {
+    let mut out = Map::new();
+    for key in SUMMARY_FIELDS {
+        if let Some(x) = v.get(key) {
+            out.insert(key.to_string(), clip(x));
+        }
+    }
+    Value::Object(out)
+}
+
+/// 改动的摘要：变了的顶层字段各自的旧值 / 新值（不含 `updatedAt`）。
+fn diff(before: &Value, after: &Value) -> (Value, Value) {
+    let (mut b, mut a) = (Map::new(), Map::new());
+    let empty = Map::new();
+    let old = before.as_object().unwrap_or(&empty);
+    let new = after.as_object().unwrap_or(&empty);
+    for key in old
+        .keys()
+        .chain(new.keys().filter(|k| !old.contains_key(*k)))
+    {
+        if key == "updatedAt" || old.get(key) == new.get(key) {
+            continue;
+        }
+        b.insert(key.clone(), old.get(key).map_or(Value::Null, clip));
+        a.insert(key.clone(), new.get(key).map_or(Value::Null, clip));
+    }
+    (Value::Object(b), Value::Object(a))
+}
+
+/// 长文本（description、大数组）截断成字符串，避免审计表膨胀。
+fn clip(v: &Value) -> Value {
+    let s = match v {
+        Value::String(s) => s.clone(),
+        Value::Array(_) | Value::Object(_) => v.to_string(),
+        _ => return v.clone(),
+    };
+    if s.chars().count() <= MAX_VALUE_CHARS {
+        return v.clone();
+    }
+    let head: String = s.chars().take(MAX_VALUE_CHARS).collect();
+    Value::String(format!("{}…", head))
+}
+
+/// 客户端给的 ID 只收 1~128 个可见 ASCII 字符，否则另行生成。
+fn valid_request_id(s: &str) -> bool {
+    !s.is_empty() && s.len() <= 128 && s.bytes().all(|b| b.is_ascii_graphic())
+}
+
+fn new_request_id() -> String {
+    let bytes: [u8; 16] = rand::thread_rng().gen();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub async fn assign_request_id(mut req: Request, next: Next) -> Response {
+    let id = req
+        .headers()
+        .get(X_REQUEST_ID)
+        .and_then(|v| v.to_str().ok())
+        .filter(|s| valid_request_id(s))
+        .map(str::to_string)
+        .unwrap_or_else(new_request_id);
+    req.extensions_mut().insert(RequestId(id.clone()));
+
+    let span = info_span!(
+        "request",
+        id = %id,
+        method = %req.method(),
+        path = %req.uri().path(),
+    );
+    let mut resp = next.run(req).instrument(span).await;
+    if let Ok(v) = HeaderValue::from_str(&id) {
+        resp.headers_mut().insert(X_REQUEST_ID, v);
+    }
+    resp
+}
+
+pub async fn record_writes(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    if !matches!(
+        *req.method(),
+        Method::POST | Method::PATCH | Method::PUT | Method::DELETE
+    ) {
+        return next.run(req).await;
+    }
+
+    let cfg = state.config.get();
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| path.clone());
+    let request_id = req
+        .extensions()
+        .get::<RequestId>()
+        .map(|r| r.0.clone())
+        .unwrap_or_default();
+    let client_ip = client_ip(&req, cfg.trust_forwarded_for).to_string();
+    let user_agent = req
+        .headers()
+        .get(USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|s| s.chars().take(MAX_VALUE_CHARS).collect());
+
+    let resp = next.run(req).await;
+
+    let note = resp
+        .extensions()
+        .get::<AuditNote>()
+        .cloned()
+        .unwrap_or_default();
+    let entry = AuditEntry {
+        id: 0,
+        at: now_local_string(cfg.timezone),
+        request_id,
+        key_name: auth::KEY_NAME.to_string(),
+        client_ip: Some(client_ip),
+        user_agent,
+        target: note.target.or_else(|| path_param(&route, &path)),
+        method,
+        route,
+        path,
+        status: resp.status().as_u16(),
+        before: note.before,
+        after: note.after,
+    };
+    // 审计写失败不影响已经完成的业务操作，只留日志
+    if let Err(e) = state.db.with_conn(|conn| audit::insert(conn, &entry)) {
+        warn!(target: "minitodo_cloud::api", "写 audit_log 失败: {}", e);
+    }
+    resp
+}
+
+/// 路由模板里第一个 `:param` 在实际路径上对应的段（URL 解码后）。
+fn path_param(route: &str, path: &str) -> Option<String> {
+    route
+        .split('/')
+        .zip(path.split('/'))
+        .find(|(t, _)| t.starts_with(':'))
+        .map(|(_, v)| {
+            urlencoding::decode(v)
+                .map(|s| s.into_owned())
+                .unwrap_or_else(|_| v.to_string())
+        })
+}
+
+// =============================================================================
+// GET /audit
+// =============================================================================
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    pub since: Option<String>,
+    pub key: Option<String>,
+    pub target: Option<String>,
+    /// 上一页最后一条的 `id`。
+    pub before: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+pub async fn list_audit(
+    State(state): State<AppState>,
+    Query(q): Query<AuditQuery>,
+) -> Result<Json<Vec<AuditEntry>>, ApiError> {
+    if let Some(since) = &q.since {
+        let ok = chrono::NaiveDate::parse_from_str(since, "%Y-%m-%d").is_ok()
+            || chrono::NaiveDateTime::parse_from_str(since, crate::time::LOCAL_FMT).is_ok();
+        if !ok {
+            return Err(ApiError::bad_request(
+                "since must be YYYY-MM-DD or YYYY-MM-DD HH:MM:SS (server timezone)",
+            ));
+        }
+    }
+    let limit = q.limit.unwrap_or(DEFAULT_LIMIT);
+    if !(1..=MAX_LIMIT).contains(&limit) {
+        return Err(ApiError::bad_request(format!(
+            "limit must be between 1 and {}",
+            MAX_LIMIT
+        )));
+    }
+    let filter = AuditFilter {
+        since: q.since,
+        key_name: q.key,
+        target: q.target,
+        before_id: q.before,
+        limit,
+    };
+    let items = state.db.with_conn(|conn| audit::query(conn, &filter))?;
+    Ok(Json(items))
+}
+
+// =============================================================================
+// 保留期
+// =============================================================================
+
+/// 每小时清理一次过期记录；`audit_retention_days = 0` 时不清理。天数每次
+/// 从 `LiveConfig` 读，SIGHUP 改了立即生效。
+pub fn spawn_retention(live: LiveConfig, db: Db) {
+    tokio::spawn(async move {
+        let mut tick = tokio::time::interval(Duration::from_secs(3600));
+        loop {
+            tick.tick().await;
+            let cfg = live.get();
+            if cfg.audit_retention_days == 0 {
+                continue;
+            }
+            let cutoff = format_local(
+                Utc::now() - chrono::Duration::days(cfg.audit_retention_days as i64),
+                cfg.timezone,
+            );
+            match db.with_conn(|conn| audit::purge_before(conn, &cutoff)) {
+                Ok(0) => {}
+                Ok(n) => {
+                    info!(target: "minitodo_cloud", "audit_log 清理了 {} 条早于 {} 的记录", n, cutoff)
+                }
+                Err(e) => warn!(target: "minitodo_cloud", "清理 audit_log 失败: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn summaries() {
+        let before = json!({
+            "id": 1, "title": "买牛奶", "completed": false,
+            "description": "x".repeat(500), "updatedAt": "2026-05-01 10:00:00",
+        });
+        let after = json!({
+            "id": 1, "title": "买牛奶", "completed": true,
+            "description": "x".repeat(500), "updatedAt": "2026-05-02 10:00:00",
+            "completedAt": "2026-05-02 10:00:00",
+        });
+        let (b, a) = diff(&before, &after);
+        assert_eq!(b, json!({"completed": false, "completedAt": null}));
+        assert_eq!(
+            a,
+            json!({"completed": true, "completedAt": "2026-05-02 10:00:00"})
+        );
+
+        assert_eq!(
+            pick(&before),
+            json!({"id": 1, "title": "买牛奶", "completed": false})
+        );
+        let long = clip(&json!("长".repeat(300)));
+        assert_eq!(long.as_str().unwrap().chars().count(), MAX_VALUE_CHARS + 1);
+    }
+
+    #[test]
+    fn request_ids_and_path_params() {
+        assert!(valid_request_id("abc-123_DEF.4"));
+        assert!(!valid_request_id(""));
+        assert!(!valid_request_id("has space"));
+        assert!(!valid_request_id(&"a".repeat(129)));
+        assert_eq!(new_request_id().len(), 32);
+
+        assert_eq!(
+            path_param("/todos/:id/subtasks", "/todos/C3/subtasks").as_deref(),
+            Some("C3")
+        );
+        assert_eq!(
+            path_param("/views/:name", "/views/%E4%BB%8A%E5%A4%A9").as_deref(),
+            Some("今天")
+        );
+        assert_eq!(path_param("/sync/push", "/sync/push"), None);
+    }
+}
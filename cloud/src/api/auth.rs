@@ -1,23 +1,38 @@
 //! Bearer Token middleware：`Authorization: Bearer {api_key}` 缺/错 → 401。
 //!
 //! 单 API key（来自 `config.toml`），与 prd "Out of Scope: 多 API Key / token
-//! 轮换" 一致。
+//! 轮换" 一致。无效 key 的次数由外层 [`super::ratelimit`] 统计并封禁。
 
 use axum::body::Body;
 use axum::extract::{Request, State};
-use axum::http::{header, StatusCode};
+use axum::http::{header, HeaderMap, StatusCode};
 use axum::middleware::Next;
 use axum::response::Response;
 
 use super::AppState;
 
+/// 审计记录里的 key 名。目前只有一个 API key；将来支持多 key 时换成各自的名字。
+pub const KEY_NAME: &str = "default";
+
 pub async fn require_bearer(
     State(state): State<AppState>,
     req: Request,
     next: Next,
 ) -> Result<Response, Response> {
-    let token = req
-        .headers()
+    let Some(supplied) = bearer_token(req.headers()) else {
+        return Err(unauthorized("missing bearer token"));
+    };
+
+    if !key_matches(&supplied, &state.config.get().api_key) {
+        return Err(unauthorized("invalid api key"));
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// 取 `Authorization: Bearer <token>` 里的 token；缺失或为空时 `None`。
+pub fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    headers
         .get(header::AUTHORIZATION)
         .and_then(|v| v.to_str().ok())
         .and_then(|s| {
@@ -25,18 +40,12 @@ pub async fn require_bearer(
             let (scheme, rest) = s.split_once(' ')?;
             scheme.eq_ignore_ascii_case("bearer").then_some(rest)
         })
-        .map(|s| s.trim().to_string());
-
-    let supplied = match token {
-        Some(t) if !t.is_empty() => t,
-        _ => return Err(unauthorized("missing bearer token")),
-    };
-
-    if !constant_time_eq(supplied.as_bytes(), state.config.api_key.as_bytes()) {
-        return Err(unauthorized("invalid api key"));
-    }
+        .map(|s| s.trim().to_string())
+        .filter(|t| !t.is_empty())
+}
 
-    Ok(next.run(req).await)
+pub fn key_matches(supplied: &str, api_key: &str) -> bool {
+    constant_time_eq(supplied.as_bytes(), api_key.as_bytes())
 }
 
 /// 常数时间字节比较，防止通过响应时延逐字节猜 api_key。
@@ -0,0 +1,86 @@
+//! CORS：让浏览器里的前端直接调用 API。
+//!
+//! `cors_origins` 为空时不挂这一层（默认行为与以前一致）。配置了就：
+//! - 放行列出的 Origin（`*` 表示任意）与 GET / POST / PATCH / DELETE
+//! - 预检放行 Authorization / Content-Type / X-Timezone / X-Request-Id /
+//!   Idempotency-Key，外加
+//!   `cors_allow_headers`
+//! - 暴露 X-Sync-Status / X-Last-Sync-At / Warning / Link /
+//!   Content-Disposition / Retry-After / RateLimit-* / X-Request-Id /
+//!   Idempotent-Replayed，否则前端 JS 读不到同步状态、分页链接、限流余量、
+//!   报障用的请求 ID 与是否为回放
+//!
+//! 鉴权用 Bearer token 而不是 cookie，所以不开 `Access-Control-Allow-Credentials`。
+//! 这一层挂在最外面：预检 `OPTIONS` 不带 token，必须在 auth 之前应答。
+
+use std::time::Duration;
+
+use axum::http::header::{
+    HeaderName, HeaderValue, AUTHORIZATION, CONTENT_DISPOSITION, CONTENT_TYPE, LINK, RETRY_AFTER,
+    WARNING,
+};
+use axum::http::Method;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use super::audit::X_REQUEST_ID;
+use super::headers::{X_LAST_SYNC_AT, X_SYNC_STATUS};
+use super::idempotency::{IDEMPOTENCY_KEY, IDEMPOTENT_REPLAYED};
+use super::ratelimit::{RATELIMIT_LIMIT, RATELIMIT_POLICY, RATELIMIT_REMAINING, RATELIMIT_RESET};
+use super::timezone::X_TIMEZONE;
+use crate::config::Config;
+
+/// 预检结果让浏览器缓存的时长。
+const MAX_AGE: Duration = Duration::from_secs(600);
+
+/// 按配置构造 CORS 层；未配置 `cors_origins` 时返回 `None`。
+pub fn layer(cfg: &Config) -> Option<CorsLayer> {
+    if cfg.cors_origins.is_empty() {
+        return None;
+    }
+    // Config 加载时已校验过 Origin / header 名，这里的解析不会失败
+    let origins = if cfg.cors_origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            cfg.cors_origins
+                .iter()
+                .filter_map(|o| HeaderValue::from_str(o).ok()),
+        )
+    };
+    let allow_headers = [
+        AUTHORIZATION,
+        CONTENT_TYPE,
+        X_TIMEZONE,
+        X_REQUEST_ID,
+        IDEMPOTENCY_KEY,
+    ]
+    .into_iter()
+    .chain(
+        cfg.cors_allow_headers
+            .iter()
+            .filter_map(|h| HeaderName::from_bytes(h.as_bytes()).ok()),
+    )
+    .collect::<Vec<_>>();
+
+    Some(
+        CorsLayer::new()
+            .allow_origin(origins)
+            .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
+            .allow_headers(allow_headers)
+            .expose_headers([
+                X_SYNC_STATUS,
+                X_LAST_SYNC_AT,
+                WARNING,
+                LINK,
+                CONTENT_DISPOSITION,
+                RETRY_AFTER,
+                RATELIMIT_LIMIT,
+                RATELIMIT_REMAINING,
+                RATELIMIT_RESET,
+                RATELIMIT_POLICY,
+                X_REQUEST_ID,
+                IDEMPOTENT_REPLAYED,
+            ])
+            .max_age(MAX_AGE),
+    )
+}
@@ -1,4 +1,5 @@
-//! HTTP API 错误：统一 `{"error","detail"}` JSON 响应。
+//! HTTP API 错误：统一 `{"error","detail"}` JSON 响应；字段校验失败（422）
+//! 额外带 `errors: [{field, message}]`。
 
 use axum::body::Body;
 use axum::http::{header, StatusCode};
@@ -6,16 +7,37 @@ use axum::response::{IntoResponse, Response};
 use serde::Serialize;
 
 /// 标准化错误响应。所有 handler 用 `Result<T, ApiError>` 返回。
+#[derive(Debug)]
 pub struct ApiError {
     pub status: StatusCode,
     pub code: &'static str,
     pub detail: String,
+    /// 字段级错误；仅 422 校验失败时非空。
+    pub errors: Vec<FieldError>,
+}
+
+/// 单个字段的校验错误。`field` 用 PC 端 camelCase 字段名。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
 }
 
 #[derive(Serialize)]
 struct ApiErrorBody<'a> {
     error: &'a str,
     detail: &'a str,
+    #[serde(skip_serializing_if = "<[FieldError]>::is_empty")]
+    errors: &'a [FieldError],
 }
 
 impl ApiError {
@@ -24,6 +46,7 @@ impl ApiError {
             status,
             code,
             detail: detail.into(),
+            errors: Vec::new(),
         }
     }
     pub fn bad_request(detail: impl Into<String>) -> Self {
@@ -35,6 +58,19 @@ impl ApiError {
     pub fn internal(detail: impl Into<String>) -> Self {
         Self::new(StatusCode::INTERNAL_SERVER_ERROR, "internal", detail)
     }
+    /// 422：body 结构合法但字段值不满足 PC 模型约束。
+    pub fn validation(errors: Vec<FieldError>) -> Self {
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        let detail = format!("invalid fields: {}", fields.join(", "));
+        Self {
+            errors,
+            ..Self::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "validation_failed",
+                detail,
+            )
+        }
+    }
 }
 
 impl IntoResponse for ApiError {
@@ -42,6 +78,7 @@ impl IntoResponse for ApiError {
         let body = serde_json::to_vec(&ApiErrorBody {
             error: self.code,
             detail: &self.detail,
+            errors: &self.errors,
         })
         .unwrap_or_else(|_| b"{\"error\":\"internal\"}".to_vec());
         Response::builder()
@@ -18,9 +18,10 @@ use tracing::warn;
 
 use super::AppState;
 use crate::db::repo;
+use crate::time::resolve_local;
 
-const X_SYNC_STATUS: HeaderName = HeaderName::from_static("x-sync-status");
-const X_LAST_SYNC_AT: HeaderName = HeaderName::from_static("x-last-sync-at");
+pub const X_SYNC_STATUS: HeaderName = HeaderName::from_static("x-sync-status");
+pub const X_LAST_SYNC_AT: HeaderName = HeaderName::from_static("x-last-sync-at");
 
 /// 同步状态判定结果。
 pub struct SyncStatus {
@@ -81,19 +82,11 @@ pub fn compute_sync_status(state: &AppState) -> SyncStatus {
         };
     };
 
-    let pull_dt = naive
-        .and_local_timezone(state.config.timezone_offset)
-        .single()
-        .map(|d| d.with_timezone(&Utc));
-    let Some(pull_dt) = pull_dt else {
-        return SyncStatus {
-            status: "offline",
-            last_pull_at: last_pull_at.clone(),
-        };
-    };
+    // DST 回拨那一小时里墙钟时间有两个，取较早的（偏保守，宁可早一点报 stale）
+    let pull_dt = resolve_local(naive, state.config.get().timezone).with_timezone(&Utc);
 
     let age_sec = (Utc::now() - pull_dt).num_seconds().max(0) as u64;
-    let interval = state.config.pull_interval_secs;
+    let interval = state.config.get().pull_interval_secs;
     let status = if age_sec <= interval * 2 {
         "healthy"
     } else if age_sec <= 300 {
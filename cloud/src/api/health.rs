@@ -1,11 +1,28 @@
-//! `GET /health`：基础健康检查 + 同步状态。
+//! 健康检查：
+//!
+//! - `GET /health`：基础状态 + 同步状态（需 token，兼容旧客户端）
+//! - `GET /health/live`：存活探针，不鉴权
+//! - `GET /health/ready`：就绪探针，不鉴权；进程内首次 pull 成功且 DB 可读才 200
+//! - `GET /health/details`：需 token；同步 / 队列 / WebDAV / 磁盘等明细，
+//!   会实际探测一次 WebDAV（最长 5s）
+
+use std::time::{Duration, Instant};
 
 use axum::extract::State;
+use axum::http::StatusCode;
 use axum::Json;
 use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::warn;
 
+use super::error::ApiError;
 use super::headers::compute_sync_status;
 use super::AppState;
+use crate::config::Config;
+use crate::db::repo;
+use crate::sync::webdav::WebDavClient;
+use crate::sync::{self, REMOTE_DIR};
+use crate::util;
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -23,3 +40,198 @@ pub async fn get_health(State(state): State<AppState>) -> Json<HealthResp> {
         last_pull_at: sync.last_pull_at,
     })
 }
+
+// =============================================================================
+// 探针：GET /health/live、GET /health/ready（不鉴权）
+// =============================================================================
+
+/// 存活探针：进程能处理请求即 200，不碰 DB / WebDAV。
+pub async fn get_live() -> Json<Value> {
+    Json(json!({"status": "alive"}))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadyChecks {
+    /// 进程内至少成功 pull 过一次（启动时那次或之后任一 tick）
+    pub initial_pull: bool,
+    /// SQLite 可读
+    pub db: bool,
+}
+
+/// 就绪探针：首次 pull 成功且 DB 可读 → 200，否则 503。
+pub async fn get_ready(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    let checks = ReadyChecks {
+        initial_pull: sync::is_ready(&state.ready),
+        db: db_ok(&state),
+    };
+    let ok = checks.initial_pull && checks.db;
+    let status = if ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        Json(json!({
+            "status": if ok { "ready" } else { "not_ready" },
+            "checks": checks,
+        })),
+    )
+}
+
+fn db_ok(state: &AppState) -> bool {
+    match state
+        .db
+        .with_conn(|conn| conn.query_row("SELECT COUNT(*) FROM meta", [], |r| r.get::<_, i64>(0)))
+    {
+        Ok(_) => true,
+        Err(e) => {
+            warn!(target: "minitodo_cloud::api", "readiness DB 检查失败: {}", e);
+            false
+        }
+    }
+}
+
+// =============================================================================
+// GET /health/details
+// =============================================================================
+
+/// WebDAV 探测超时：健康检查不该被慢速远端拖住。
+const WEBDAV_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DetailsResp {
+    /// 就绪、DB 可读且 WebDAV 可达 → `healthy`，否则 `degraded`
+    pub status: &'static str,
+    pub version: &'static str,
+    pub ready: bool,
+    pub sync: SyncDetails,
+    pub tombstones: i64,
+    pub webdav: WebDavDetails,
+    pub disk: DiskDetails,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncDetails {
+    /// 同 `X-Sync-Status`
+    pub status: &'static str,
+    pub last_pull_at: Option<String>,
+    pub last_pull_error: Option<String>,
+    pub last_pull_error_at: Option<String>,
+    pub last_push_at: Option<String>,
+    pub last_push_error: Option<String>,
+    pub last_push_error_at: Option<String>,
+    pub dirty: bool,
+    pub dirty_generation: i64,
+    pub dirty_generation_lag: i64,
+    pub dirty_images: usize,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDavDetails {
+    pub reachable: bool,
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskDetails {
+    pub data_dir: DirDetails,
+    pub images_dir: DirDetails,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DirDetails {
+    pub path: String,
+    /// 非 unix 平台或路径不存在时为 null
+    pub free_bytes: Option<u64>,
+}
+
+pub async fn get_details(State(state): State<AppState>) -> Result<Json<DetailsResp>, ApiError> {
+    let sync_status = compute_sync_status(&state);
+
+    let (sync_details, tombstones) = state.db.with_conn(|conn| -> rusqlite::Result<_> {
+        let details = SyncDetails {
+            status: sync_status.status,
+            last_pull_at: sync_status.last_pull_at,
+            last_pull_error: repo::get_meta(conn, "last_pull_error")?,
+            last_pull_error_at: repo::get_meta(conn, "last_pull_error_at")?,
+            last_push_at: repo::get_meta(conn, "last_push_at")?,
+            last_push_error: repo::get_meta(conn, "last_push_error")?,
+            last_push_error_at: repo::get_meta(conn, "last_push_error_at")?,
+            dirty: repo::get_meta(conn, "dirty")?.as_deref() == Some("true"),
+            dirty_generation: repo::get_dirty_generation(conn)?,
+            dirty_generation_lag: repo::dirty_generation_lag(conn)?,
+            dirty_images: repo::dirty_images_len(conn)?,
+        };
+        let tombstones: i64 = repo::count_tombstones_by_type(conn)?
+            .iter()
+            .map(|(_, n)| n)
+            .sum();
+        Ok((details, tombstones))
+    })?;
+
+    let cfg = state.config.get();
+    let webdav = tokio::task::spawn_blocking(move || probe_webdav(&cfg))
+        .await
+        .map_err(|e| ApiError::internal(format!("task panic: {}", e)))?;
+
+    let dir = |p: &std::path::Path| DirDetails {
+        path: p.display().to_string(),
+        free_bytes: util::disk_free_bytes(p),
+    };
+    let disk = DiskDetails {
+        data_dir: dir(&state.config.get().data_dir),
+        images_dir: dir(&state.config.get().images_dir),
+    };
+
+    let ready = sync::is_ready(&state.ready);
+    let status = if ready && webdav.reachable {
+        "healthy"
+    } else {
+        "degraded"
+    };
+
+    Ok(Json(DetailsResp {
+        status,
+        version: env!("CARGO_PKG_VERSION"),
+        ready,
+        sync: sync_details,
+        tombstones,
+        webdav,
+        disk,
+    }))
+}
+
+/// PROPFIND 同步目录。能连上且没被拒（非 401 / 403 / 5xx）即视为可达；
+/// 目录还不存在（404）也算可达——第一次 push 会建。
+fn probe_webdav(cfg: &Config) -> WebDavDetails {
+    let started = Instant::now();
+    let res = WebDavClient::new(&cfg.webdav_url, &cfg.webdav_username, &cfg.webdav_password)
+        .and_then(|c| c.probe(REMOTE_DIR, WEBDAV_PROBE_TIMEOUT));
+    let latency_ms = started.elapsed().as_millis() as u64;
+    match res {
+        Ok(code) => {
+            let reachable = !matches!(code, 401 | 403) && code < 500;
+            WebDavDetails {
+                reachable,
+                status: Some(code),
+                latency_ms,
+                error: (!reachable).then(|| format!("PROPFIND 返回状态 {}", code)),
+            }
+        }
+        Err(e) => WebDavDetails {
+            reachable: false,
+            status: None,
+            latency_ms,
+            error: Some(format!("{:#}", e)),
+        },
+    }
+}
@@ -0,0 +1,221 @@
+//! `Idempotency-Key`：让客户端安全地重试 POST。
+//!
+//! 挂在 `POST /todos`、`POST /todos/:id/subtasks`、`POST /images` 与 `/sync*`
+//! 上（见 `build_router`）。请求带了 `Idempotency-Key` 时：
+//! - 首次：照常执行，把响应（状态码、Content-Type、body）连同请求指纹
+//!   （method + path + body 的 SHA-256）存进 `idempotency_keys`
+//! - 窗口期（`idempotency_window_secs`）内重试：指纹一致 → 原样回放首次响应，
+//!   附 `Idempotent-Replayed: true`，handler 不再执行；指纹不同 → 422
+//! - 首次请求还没处理完又来一个 → 409，客户端稍后再试
+//!
+//! key 按鉴权通过的 API key 分开存（前缀 [`auth::KEY_NAME`]），不同 key 的
+//! 客户端用了同一个 UUID 也互不影响。
+//!
+//! handler 放在单独的 task 里跑：客户端中途断开（本功能要照顾的弱网场景）时
+//! 请求照样跑完并存下响应，重试直接拿到结果，而不是对着"处理中"的行吃 10 分钟
+//! 409。handler 5xx 或 panic 时占位行由 [`Claim`] 释放——那是服务端的临时
+//! 问题，重试应该重新执行。没带这个头的请求不受影响。
+
+use axum::body::{to_bytes, Body};
+use axum::extract::{Request, State};
+use axum::http::header::{HeaderValue, CONTENT_TYPE};
+use axum::http::{HeaderName, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::Utc;
+use ring::digest::{Context, SHA256};
+use tracing::{warn, Instrument};
+
+use super::auth;
+use super::error::ApiError;
+use super::AppState;
+use crate::db::idempotency::{self, Begin, StoredResponse};
+use crate::db::Db;
+
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+/// 为计算指纹缓冲请求 body 的上限，与 `POST /images` 的 32 MiB 一致。
+/// 其余路由的 body 上限仍由各自的 extractor 把关。
+const MAX_BUFFERED_BODY: usize = 32 * 1024 * 1024;
+
+/// key 只收 1~255 个可见 ASCII 字符（客户端一般用 UUID）。
+fn valid_key(s: &str) -> bool {
+    !s.is_empty() && s.len() <= 255 && s.bytes().all(|b| b.is_ascii_graphic())
+}
+
+fn fingerprint(method: &str, path: &str, body: &[u8]) -> String {
+    let mut ctx = Context::new(&SHA256);
+    ctx.update(method.as_bytes());
+    ctx.update(b"\n");
+    ctx.update(path.as_bytes());
+    ctx.update(b"\n");
+    ctx.update(body);
+    ctx.finish()
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 处理中的占位行。没走到 [`Claim::complete`] 就被 drop（handler 5xx、
+/// panic）时删掉占位，让重试重新执行。
+struct Claim {
+    db: Db,
+    key: String,
+    done: bool,
+}
+
+impl Claim {
+    fn complete(mut self, stored: &StoredResponse) {
+        self.done = true;
+        if let Err(e) = self
+            .db
+            .with_conn(|conn| idempotency::complete(conn, &self.key, stored))
+        {
+            warn!(target: "minitodo_cloud::api", "保存 Idempotency-Key 响应失败: {}", e);
+        }
+    }
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        if self.done {
+            return;
+        }
+        if let Err(e) = self
+            .db
+            .with_conn(|conn| idempotency::abandon(conn, &self.key))
+        {
+            warn!(target: "minitodo_cloud::api", "释放 Idempotency-Key 失败: {}", e);
+        }
+    }
+}
+
+pub async fn enforce(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let window = state.config.get().idempotency_window_secs;
+    // 同一路由上的 GET（如 `GET /todos`）不参与
+    let Some(raw) = req.headers().get(IDEMPOTENCY_KEY) else {
+        return next.run(req).await;
+    };
+    if window == 0 || req.method() != Method::POST {
+        return next.run(req).await;
+    }
+    let Some(key) = raw
+        .to_str()
+        .ok()
+        .filter(|s| valid_key(s))
+        .map(|s| format!("{}:{}", auth::KEY_NAME, s))
+    else {
+        return ApiError::bad_request("Idempotency-Key must be 1-255 visible ASCII characters")
+            .into_response();
+    };
+
+    let (parts, body) = req.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_BUFFERED_BODY).await else {
+        return ApiError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "payload_too_large",
+            "request body too large",
+        )
+        .into_response();
+    };
+    let hash = fingerprint(parts.method.as_str(), parts.uri.path(), &bytes);
+    let now = Utc::now().timestamp();
+
+    let begun = state
+        .db
+        .with_conn(|conn| idempotency::begin(conn, &key, &hash, now, window as i64));
+    match begun {
+        Ok(Begin::Fresh) => {}
+        Ok(Begin::Done(stored)) => return replay(stored),
+        Ok(Begin::InProgress) => {
+            return ApiError::new(
+                StatusCode::CONFLICT,
+                "idempotency_key_in_use",
+                "a request with this Idempotency-Key is still being processed",
+            )
+            .into_response()
+        }
+        Ok(Begin::Mismatch) => {
+            return ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "idempotency_key_reused",
+                "Idempotency-Key was already used with a different request",
+            )
+            .into_response()
+        }
+        Err(e) => return ApiError::from(e).into_response(),
+    }
+
+    let claim = Claim {
+        db: state.db.clone(),
+        key,
+        done: false,
+    };
+    let req = Request::from_parts(parts, Body::from(bytes));
+    // 单独的 task：外层 future 随断开的连接被 drop 时，handler 仍然跑完
+    let task = tokio::spawn(run_and_store(next, req, claim).in_current_span());
+    match task.await {
+        Ok(resp) => resp,
+        Err(e) => ApiError::internal(format!("handler failed: {}", e)).into_response(),
+    }
+}
+
+async fn run_and_store(next: Next, req: Request, claim: Claim) -> Response {
+    let resp = next.run(req).await;
+    if resp.status().is_server_error() {
+        return resp;
+    }
+
+    // handler 的响应 body 都是小 JSON，整个读出来存一份
+    let (parts, body) = resp.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(b) => b,
+        Err(e) => return ApiError::internal(format!("read response: {}", e)).into_response(),
+    };
+    let stored = StoredResponse {
+        status: parts.status.as_u16(),
+        content_type: parts
+            .headers
+            .get(CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+        body: bytes.to_vec(),
+    };
+    claim.complete(&stored);
+    Response::from_parts(parts, Body::from(bytes))
+}
+
+fn replay(stored: StoredResponse) -> Response {
+    let mut resp = Response::new(Body::from(stored.body));
+    *resp.status_mut() = StatusCode::from_u16(stored.status).unwrap_or(StatusCode::OK);
+    if let Some(ct) = stored
+        .content_type
+        .and_then(|ct| HeaderValue::from_str(&ct).ok())
+    {
+        resp.headers_mut().insert(CONTENT_TYPE, ct);
+    }
+    resp.headers_mut()
+        .insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_and_fingerprints() {
+        assert!(valid_key("0b7f5c1e-8a8e-4c1d-9d3b-2f0a6b1c9e42"));
+        assert!(!valid_key(""));
+        assert!(!valid_key("has space"));
+        assert!(!valid_key(&"k".repeat(256)));
+
+        let a = fingerprint("POST", "/todos", br#"{"title":"a"}"#);
+        assert_eq!(a.len(), 64);
+        assert_eq!(a, fingerprint("POST", "/todos", br#"{"title":"a"}"#));
+        assert_ne!(a, fingerprint("POST", "/todos", br#"{"title":"b"}"#));
+        assert_ne!(a, fingerprint("POST", "/todos/quick", br#"{"title":"a"}"#));
+    }
+}
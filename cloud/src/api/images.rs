@@ -6,10 +6,12 @@ use axum::body::Body;
 use axum::extract::{Multipart, Path, State};
 use axum::http::{header, StatusCode};
 use axum::response::Response;
-use axum::Json;
+use axum::{Extension, Json};
 use chrono::Utc;
 use serde::Serialize;
+use serde_json::json;
 
+use super::audit::AuditNote;
 use super::error::ApiError;
 use super::AppState;
 use crate::db::repo;
@@ -29,7 +31,7 @@ pub async fn get_image(
 ) -> Result<Response, ApiError> {
     let safe = sanitize_filename(&name)
         .ok_or_else(|| ApiError::bad_request(format!("invalid image name: {}", name)))?;
-    let full: PathBuf = state.config.images_dir.join(&safe);
+    let full: PathBuf = state.config.get().images_dir.join(&safe);
 
     let bytes = match std::fs::read(&full) {
         Ok(b) => b,
@@ -67,7 +69,7 @@ pub async fn get_image(
 pub async fn upload_image(
     State(state): State<AppState>,
     mut multipart: Multipart,
-) -> Result<Json<UploadResp>, ApiError> {
+) -> Result<(Extension<AuditNote>, Json<UploadResp>), ApiError> {
     // 接受第一个 file 字段（兼容 name="file" / name="image"）
     let mut payload: Option<(String, Vec<u8>)> = None;
     while let Some(field) = multipart
@@ -112,14 +114,14 @@ pub async fn upload_image(
     let (name, bytes) =
         payload.ok_or_else(|| ApiError::bad_request("missing file part in multipart"))?;
 
-    std::fs::create_dir_all(&state.config.images_dir).map_err(|e| {
+    std::fs::create_dir_all(&state.config.get().images_dir).map_err(|e| {
         ApiError::internal(format!(
             "create {} failed: {}",
-            state.config.images_dir.display(),
+            state.config.get().images_dir.display(),
             e
         ))
     })?;
-    let full = state.config.images_dir.join(&name);
+    let full = state.config.get().images_dir.join(&name);
     std::fs::write(&full, &bytes)
         .map_err(|e| ApiError::internal(format!("write {} failed: {}", full.display(), e)))?;
 
@@ -136,7 +138,8 @@ pub async fn upload_image(
         Ok(())
     })?;
 
-    Ok(Json(UploadResp { name }))
+    let note = AuditNote::created(name.clone(), &json!({"name": name, "size": bytes.len()}));
+    Ok((note, Json(UploadResp { name })))
 }
 
 // =============================================================================
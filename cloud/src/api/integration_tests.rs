@@ -6,8 +6,6 @@
 //! 全程不会触碰真实网络；`Db` 用临时目录里的 SQLite 文件、`images_dir` 也用
 //! tempdir，测试结束自动清理。
 
-use std::sync::Arc;
-
 use axum::body::Body;
 use axum::http::{header, Method, Request, StatusCode};
 use axum::Router;
@@ -17,7 +15,7 @@ use tempfile::TempDir;
 use tower::ServiceExt;
 
 use super::{build_router, AppState};
-use crate::config::Config;
+use crate::config::{Config, LiveConfig};
 use crate::db::{repo, Db};
 use crate::time::now_local_string;
 
@@ -36,19 +34,28 @@ struct Fixture {
 }
 
 fn fixture() -> Fixture {
+    fixture_with(|_| {})
+}
+
+/// 改过 Config 的 fixture（CORS 这类在 `build_router` 时就定下来的配置）。
+fn fixture_with(tweak: impl FnOnce(&mut Config)) -> Fixture {
     let tmp = TempDir::new().expect("tempdir");
     let data_dir = tmp.path().join("data");
     let images_dir = tmp.path().join("images");
     std::fs::create_dir_all(&data_dir).unwrap();
     std::fs::create_dir_all(&images_dir).unwrap();
 
-    let cfg = Arc::new(Config::for_tests(API_KEY, data_dir.clone(), images_dir));
+    let mut cfg = Config::for_tests(API_KEY, data_dir.clone(), images_dir);
+    tweak(&mut cfg);
+    let cfg = LiveConfig::new(cfg);
     let db = Db::open(&data_dir.join("data.db")).expect("open db");
 
     let state = AppState {
         config: cfg,
         db: db.clone(),
         sync_lock: crate::sync::new_sync_lock(),
+        ready: crate::sync::new_readiness(),
+        limiter: Default::default(),
     };
     let router = build_router(state.clone());
     Fixture {
@@ -96,6 +103,17 @@ fn req(method: Method, uri: &str, body: Option<Value>) -> Request<Body> {
     b.body(body).unwrap()
 }
 
+/// 带自定义 Content-Type 的请求（PATCH merge-patch / json-patch 用）。
+fn req_ct(method: Method, uri: &str, content_type: &str, body: Value) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, bearer())
+        .header(header::CONTENT_TYPE, content_type)
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
 fn req_no_auth(method: Method, uri: &str) -> Request<Body> {
     Request::builder()
         .method(method)
@@ -104,6 +122,12 @@ fn req_no_auth(method: Method, uri: &str) -> Request<Body> {
         .unwrap()
 }
 
+/// config 时区（Asia/Shanghai）的今天。
+fn today(fx: &Fixture) -> chrono::NaiveDate {
+    let tz = fx.state.config.get().timezone;
+    crate::time::Clock::new(tz, tz).today()
+}
+
 // =============================================================================
 // 鉴权
 // =============================================================================
@@ -157,6 +181,196 @@ async fn auth_correct_token_passes() {
     assert_eq!(status, StatusCode::OK);
 }
 
+// =============================================================================
+// CORS
+// =============================================================================
+
+fn cors_fixture() -> Fixture {
+    fixture_with(|cfg| {
+        cfg.cors_origins = vec!["https://todo.example.com".to_string()];
+        cfg.cors_allow_headers = vec!["X-Client-Version".to_string()];
+    })
+}
+
+fn preflight(origin: &str) -> Request<Body> {
+    Request::builder()
+        .method(Method::OPTIONS)
+        .uri("/todos")
+        .header(header::ORIGIN, origin)
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PATCH")
+        .header(
+            header::ACCESS_CONTROL_REQUEST_HEADERS,
+            "authorization, content-type, x-timezone, x-client-version",
+        )
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn cors_preflight_bypasses_auth() {
+    let fx = cors_fixture();
+    let (status, headers, _) = send(&fx.router, preflight("https://todo.example.com")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+        "https://todo.example.com"
+    );
+    let methods = headers
+        .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+        .unwrap()
+        .to_str()
+        .unwrap();
+    assert!(methods.contains("PATCH"), "{}", methods);
+    let allowed = headers
+        .get(header::ACCESS_CONTROL_ALLOW_HEADERS)
+        .unwrap()
+        .to_str()
+        .unwrap();
+    for h in [
+        "authorization",
+        "content-type",
+        "x-timezone",
+        "x-client-version",
+    ] {
+        assert!(allowed.contains(h), "{} not in {}", h, allowed);
+    }
+    assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+
+    // 不在白名单里的 Origin 拿不到 Allow-Origin，浏览器会拦下
+    let (_, headers, _) = send(&fx.router, preflight("https://evil.example.com")).await;
+    assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+}
+
+#[tokio::test]
+async fn cors_exposes_sync_headers_even_on_401() {
+    let fx = cors_fixture();
+    for auth in [Some(bearer()), None] {
+        let mut r = Request::builder()
+            .uri("/todos")
+            .header(header::ORIGIN, "https://todo.example.com");
+        if let Some(a) = auth {
+            r = r.header(header::AUTHORIZATION, a);
+        }
+        let (_, headers, _) = send(&fx.router, r.body(Body::empty()).unwrap()).await;
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://todo.example.com"
+        );
+        let exposed = headers
+            .get(header::ACCESS_CONTROL_EXPOSE_HEADERS)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(exposed.contains("x-sync-status"), "{}", exposed);
+        assert!(exposed.contains("link"), "{}", exposed);
+    }
+}
+
+// =============================================================================
+// 限流 / 无效 key 封禁
+// =============================================================================
+
+/// 模拟来自 `ip` 的连接（生产环境由 `into_make_service_with_connect_info` 注入）。
+fn from_ip(mut r: Request<Body>, ip: [u8; 4]) -> Request<Body> {
+    r.extensions_mut()
+        .insert(axum::extract::ConnectInfo(std::net::SocketAddr::from((
+            ip, 40000,
+        ))));
+    r
+}
+
+#[tokio::test]
+async fn rate_limit_returns_429_with_headers() {
+    let fx = fixture_with(|cfg| {
+        cfg.rate_limits.writes = Some(crate::config::RateSpec {
+            limit: 2,
+            window: std::time::Duration::from_secs(60),
+        });
+    });
+    let post = || req(Method::POST, "/todos", Some(json!({"title": "x"})));
+
+    let (status, headers, _) = send(&fx.router, post()).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(headers.get("ratelimit-limit").unwrap(), "2");
+    assert_eq!(headers.get("ratelimit-remaining").unwrap(), "1");
+    assert_eq!(headers.get("ratelimit-policy").unwrap(), "2;w=60");
+    send(&fx.router, post()).await;
+
+    let (status, headers, body) = send(&fx.router, post()).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(json_body(&body)["error"], "rate_limited");
+    assert_eq!(headers.get(header::RETRY_AFTER).unwrap(), "30");
+    assert_eq!(headers.get("ratelimit-remaining").unwrap(), "0");
+    assert!(headers.contains_key("x-sync-status"));
+
+    // 读不受写的桶影响；同一把 key 在另一台设备（IP）上有自己的桶
+    let (status, _, _) = send(&fx.router, req(Method::GET, "/todos", None)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = send(&fx.router, from_ip(post(), [192, 0, 2, 7])).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[tokio::test]
+async fn repeated_invalid_keys_lock_out_the_ip() {
+    let fx = fixture_with(|cfg| cfg.auth_max_failures = 3);
+    let attacker = [203, 0, 113, 9];
+    let wrong = || {
+        let r = Request::builder()
+            .uri("/todos")
+            .header(header::AUTHORIZATION, "Bearer guess-guess-guess")
+            .body(Body::empty())
+            .unwrap();
+        from_ip(r, attacker)
+    };
+
+    for _ in 0..3 {
+        let (status, _, _) = send(&fx.router, wrong()).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    // 封禁期内错 key、不带 key 都是 429
+    let (status, headers, body) = send(&fx.router, wrong()).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(json_body(&body)["error"], "rate_limited");
+    assert_eq!(headers.get(header::RETRY_AFTER).unwrap(), "900");
+    let (status, _, _) = send(
+        &fx.router,
+        from_ip(req_no_auth(Method::GET, "/todos"), attacker),
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // 同一 IP 带正确 key 照常放行：反代后面大家共用一个 IP，不能被别人锁在门外；
+    // 成功也不重置失败计数，封禁仍在
+    let (status, _, _) = send(
+        &fx.router,
+        from_ip(req(Method::GET, "/todos", None), attacker),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        send(&fx.router, wrong()).await.0,
+        StatusCode::TOO_MANY_REQUESTS
+    );
+
+    // 其他 IP 与探针不受影响
+    let (status, _, _) = send(&fx.router, req(Method::GET, "/todos", None)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = send(
+        &fx.router,
+        from_ip(req_no_auth(Method::GET, "/health/live"), attacker),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn cors_disabled_by_default() {
+    let fx = fixture();
+    let (status, headers, _) = send(&fx.router, preflight("https://todo.example.com")).await;
+    assert_ne!(status, StatusCode::OK);
+    assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+}
+
 // =============================================================================
 // /health
 // =============================================================================
@@ -178,7 +392,7 @@ async fn health_offline_when_no_pull() {
 #[tokio::test]
 async fn health_healthy_after_meta_set() {
     let fx = fixture();
-    let now = now_local_string(fx.state.config.timezone_offset);
+    let now = now_local_string(fx.state.config.get().timezone);
     fx.state
         .db
         .with_conn(|conn| repo::set_meta(conn, "last_pull_at", &now).unwrap());
@@ -193,6 +407,167 @@ async fn health_healthy_after_meta_set() {
     assert!(!headers.contains_key("warning"));
 }
 
+#[tokio::test]
+async fn health_live_and_ready_skip_auth() {
+    let fx = fixture();
+    let (status, headers, body) = send(&fx.router, req_no_auth(Method::GET, "/health/live")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json_body(&body)["status"], "alive");
+    assert!(headers.contains_key("x-sync-status"));
+
+    // 还没 pull 成功过 → 503
+    let (status, _, body) = send(&fx.router, req_no_auth(Method::GET, "/health/ready")).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let v = json_body(&body);
+    assert_eq!(v["status"], "not_ready");
+    assert_eq!(v["checks"], json!({"initialPull": false, "db": true}));
+
+    crate::sync::mark_ready(&fx.state.ready);
+    let (status, _, body) = send(&fx.router, req_no_auth(Method::GET, "/health/ready")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json_body(&body)["status"], "ready");
+}
+
+#[tokio::test]
+async fn health_details_reports_sync_queue_and_webdav() {
+    let fx = fixture();
+    let (status, _, _) = send(&fx.router, req_no_auth(Method::GET, "/health/details")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let _ = create_todo(&fx, json!({"title": "x"})).await;
+    fx.state.db.with_conn(|conn| {
+        repo::set_meta(conn, "dirty_images", r#"["a.png"]"#).unwrap();
+        repo::add_tombstone(conn, "todo", "9", "2026-05-13 10:00:00").unwrap();
+    });
+    // Config::for_tests 的 WebDAV 连不上：pull 失败被记进 meta
+    let (cfg, db) = (fx.state.config.get(), fx.state.db.clone());
+    let res = tokio::task::spawn_blocking(move || crate::sync::pull::pull_once(&cfg, &db))
+        .await
+        .unwrap();
+    assert!(res.is_err());
+
+    let (status, _, body) = send(&fx.router, req(Method::GET, "/health/details", None)).await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&body);
+    assert_eq!(v["status"], "degraded");
+    assert_eq!(v["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(v["ready"], false);
+    assert_eq!(v["sync"]["dirty"], true);
+    assert_eq!(v["sync"]["dirtyGeneration"], 1);
+    assert_eq!(v["sync"]["dirtyImages"], 1);
+    assert!(v["sync"]["lastPullError"]
+        .as_str()
+        .unwrap()
+        .contains("WebDAV"));
+    assert!(v["sync"]["lastPullErrorAt"].is_string());
+    assert!(v["sync"]["lastPushAt"].is_null());
+    assert_eq!(v["tombstones"], 1);
+    assert_eq!(v["webdav"]["reachable"], false);
+    assert!(v["webdav"]["error"].is_string());
+    assert!(v["disk"]["dataDir"]["path"].is_string());
+    #[cfg(unix)]
+    assert!(v["disk"]["dataDir"]["freeBytes"].as_u64().unwrap() > 0);
+}
+
+// =============================================================================
+// /openapi.json
+// =============================================================================
+
+#[tokio::test]
+async fn openapi_served_with_paths_and_sync_headers() {
+    let fx = fixture();
+    let (status, headers, body) = send(&fx.router, req(Method::GET, "/openapi.json", None)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(headers.contains_key("x-sync-status"));
+    let v = json_body(&body);
+    assert_eq!(v["openapi"], "3.0.3");
+    assert!(v["paths"]["/todos/{id}"]["patch"].is_object());
+    assert!(v["components"]["headers"]["X-Sync-Status"].is_object());
+}
+
+#[tokio::test]
+async fn openapi_requires_auth() {
+    let fx = fixture();
+    let (status, _, _) = send(&fx.router, req_no_auth(Method::GET, "/openapi.json")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+/// 路由表（OpenAPI 覆盖测试也读它）里的每一条都真的挂在 router 上：没挂上时
+/// axum 回空 body 的 404 或 405。
+#[tokio::test]
+async fn every_table_route_is_served() {
+    let fx = fixture();
+    let (status, _, body) = send(&fx.router, req(Method::GET, "/no-such-route", None)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert!(body.is_empty());
+
+    for route in crate::api::ROUTES.iter().chain(crate::api::PROBE_ROUTES) {
+        let path = route.path;
+        let uri = path.replace(":id", "1").replace(":name", "x");
+        let method = Method::from_bytes(route.method_name().as_bytes()).unwrap();
+        let (status, _, body) = send(&fx.router, req(method.clone(), &uri, None)).await;
+        assert_ne!(
+            status,
+            StatusCode::METHOD_NOT_ALLOWED,
+            "{} {}",
+            method,
+            path
+        );
+        assert!(
+            status != StatusCode::NOT_FOUND || !body.is_empty(),
+            "{} {} 没有挂到 router 上",
+            method,
+            path
+        );
+    }
+}
+
+// =============================================================================
+// /metrics
+// =============================================================================
+
+// 注册表是进程级单例、测试并行跑，只断言"出现过"而不断言精确计数。
+#[tokio::test]
+async fn metrics_records_route_templates_and_requires_auth() {
+    let fx = fixture();
+    let t = create_todo(&fx, json!({"title": "x"})).await;
+    send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", todo_id_path(&t)), None),
+    )
+    .await;
+    let (status, _, _) = send(&fx.router, req_no_auth(Method::GET, "/metrics")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, headers, raw) = send(&fx.router, req(Method::GET, "/metrics", None)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(headers[header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .starts_with("text/plain"));
+    let text = String::from_utf8(raw).unwrap();
+    assert!(text.contains(
+        "minitodo_http_requests_total{method=\"GET\",route=\"/todos/:id\",status=\"200\"}"
+    ));
+    assert!(text.contains(
+        "minitodo_http_requests_total{method=\"GET\",route=\"/metrics\",status=\"401\"}"
+    ));
+    assert!(text.contains("minitodo_sync_dirty_generation_lag 1\n"));
+}
+
+#[tokio::test]
+async fn metrics_router_serves_without_auth() {
+    let fx = fixture();
+    let router = super::build_metrics_router(fx.state.clone());
+    let (status, _, raw) = send(&router, req_no_auth(Method::GET, "/metrics")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(String::from_utf8(raw)
+        .unwrap()
+        .contains("# TYPE minitodo_db_size_bytes gauge"));
+    let (status, _, _) = send(&router, req(Method::GET, "/todos", None)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
 // =============================================================================
 // POST /todos
 // =============================================================================
@@ -282,6 +657,34 @@ async fn create_todo_non_object_body_returns_400() {
     assert_eq!(status, StatusCode::BAD_REQUEST);
 }
 
+#[tokio::test]
+async fn create_todo_invalid_fields_return_422_with_field_errors() {
+    let fx = fixture();
+    let (status, _, body) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/todos",
+            Some(json!({"title": "x", "quadrant": "banana", "completed": "yes", "custom": 1})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let v = json_body(&body);
+    assert_eq!(v["error"], "validation_failed");
+    let mut fields: Vec<&str> = v["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["field"].as_str().unwrap())
+        .collect();
+    fields.sort();
+    assert_eq!(fields, vec!["completed", "quadrant"]);
+    // 校验失败不落库
+    let (_, _, raw) = send(&fx.router, req(Method::GET, "/todos", None)).await;
+    assert_eq!(json_body(&raw).as_array().unwrap().len(), 0);
+}
+
 #[tokio::test]
 async fn create_todo_sets_dirty_flag() {
     let fx = fixture();
@@ -306,6 +709,153 @@ async fn create_todo_sets_dirty_flag() {
     assert_eq!(gen, 1, "写路径必须递增 dirty_generation");
 }
 
+// =============================================================================
+// POST /todos/quick
+// =============================================================================
+
+#[tokio::test]
+async fn quick_add_parses_and_creates_todo() {
+    let fx = fixture();
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/todos/quick",
+            Some(json!({"text": "明天下午3点 提交周报 #work !1 每周五提醒"})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let v = json_body(&raw);
+
+    // 相对日期按 config.timezone 求值；明天不是周五时顺延到之后第一个周五
+    let mut first = today(&fx) + chrono::Duration::days(1);
+    while chrono::Datelike::weekday(&first) != chrono::Weekday::Fri {
+        first += chrono::Duration::days(1);
+    }
+    let at = format!("{}T15:00:00", first.format("%Y-%m-%d"));
+
+    let todo = &v["todo"];
+    assert_eq!(todo["title"], "提交周报");
+    assert_eq!(todo["endTime"], at.as_str());
+    assert_eq!(todo["notifyAt"], at.as_str());
+    assert_eq!(todo["quadrant"], 1);
+    assert_eq!(todo["tags"], json!(["work"]));
+    assert_eq!(todo["repeatEnabled"], true);
+    assert_eq!(todo["repeatType"], "weekly");
+    assert_eq!(todo["repeatWeekdays"], "5");
+    assert!(todo["seq"].as_i64().is_some());
+    // 其余字段与 POST /todos 的默认值一致
+    assert_eq!(todo["completed"], false);
+    assert_eq!(todo["color"], "#10B981");
+
+    let parsed = &v["parsed"];
+    assert_eq!(parsed["title"], "提交周报");
+    let kinds: Vec<&str> = parsed["tokens"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["kind"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, vec!["date", "time", "tag", "quadrant", "repeat"]);
+
+    // 真正落库
+    let (status, _, raw) = send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", todo_id_path(todo)), None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json_body(&raw)["repeatType"], "weekly");
+}
+
+/// 请求方在 UTC：明天上午 9 点（UTC）= 上海同日 17:00，按上海墙钟保存。
+#[tokio::test]
+async fn quick_add_honours_x_timezone() {
+    let fx = fixture();
+    let mut r = req(
+        Method::POST,
+        "/todos/quick",
+        Some(json!({"text": "明天上午9点 开会"})),
+    );
+    r.headers_mut().insert("x-timezone", "UTC".parse().unwrap());
+    let (status, _, raw) = send(&fx.router, r).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let tomorrow_utc = chrono::Utc::now().date_naive() + chrono::Duration::days(1);
+    let at = format!("{}T17:00:00", tomorrow_utc.format("%Y-%m-%d"));
+    let v = json_body(&raw);
+    assert_eq!(v["todo"]["notifyAt"], at.as_str());
+    assert_eq!(v["parsed"]["notifyAt"], at.as_str());
+}
+
+#[tokio::test]
+async fn invalid_x_timezone_returns_400() {
+    let fx = fixture();
+    for uri in ["/todos?filter=due%20%3C%20today", "/stats"] {
+        let mut r = req(Method::GET, uri, None);
+        r.headers_mut()
+            .insert("x-timezone", "Mars/Olympus".parse().unwrap());
+        let (status, _, raw) = send(&fx.router, r).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", uri);
+        assert!(json_body(&raw)["detail"]
+            .as_str()
+            .unwrap()
+            .contains("X-Timezone"));
+    }
+
+    let mut r = req(Method::GET, "/todos?filter=due%20%3C%20today", None);
+    r.headers_mut()
+        .insert("x-timezone", "America/New_York".parse().unwrap());
+    assert_eq!(send(&fx.router, r).await.0, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn quick_add_plain_text_uses_create_defaults() {
+    let fx = fixture();
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/todos/quick",
+            Some(json!({"text": "buy milk"})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let todo = &json_body(&raw)["todo"];
+    assert_eq!(todo["title"], "buy milk");
+    assert_eq!(todo["quadrant"], 4);
+    assert!(todo.get("endTime").is_none());
+    assert!(todo.get("repeatEnabled").is_none());
+}
+
+#[tokio::test]
+async fn quick_add_rejects_missing_text_and_empty_title() {
+    let fx = fixture();
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::POST, "/todos/quick", Some(json!({}))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // 全被识别成日期 / 标签，标题为空 → 422，且不落库
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/todos/quick",
+            Some(json!({"text": "明天 #work"})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(&raw)["errors"][0]["field"], "text");
+    let (_, _, raw) = send(&fx.router, req(Method::GET, "/todos", None)).await;
+    assert_eq!(json_body(&raw).as_array().unwrap().len(), 0);
+}
+
 // =============================================================================
 // GET /todos & GET /todos/:id
 // =============================================================================
@@ -558,26 +1108,295 @@ async fn list_todos_sort_by_priority_desc() {
 }
 
 #[tokio::test]
-async fn list_todos_limit_offset() {
+async fn list_todos_multi_key_sort() {
     let fx = fixture();
-    for i in 0..5 {
-        let _ = create_todo(&fx, json!({"title": format!("t{}", i), "sortOrder": i})).await;
-    }
+    let _ = create_todo(&fx, json!({"title": "b", "priority": "high"})).await;
+    let _ = create_todo(&fx, json!({"title": "c", "priority": "low"})).await;
+    let _ = create_todo(&fx, json!({"title": "a", "priority": "high"})).await;
     let (_, _, raw) = send(
         &fx.router,
-        req(Method::GET, "/todos?sort=+sortOrder&limit=2&offset=1", None),
+        req(Method::GET, "/todos?sort=-priority,title", None),
     )
     .await;
-    let v = json_body(&raw);
-    assert_eq!(v.as_array().unwrap().len(), 2);
-    assert_eq!(v[0]["title"], "t1");
-    assert_eq!(v[1]["title"], "t2");
+    let titles: Vec<String> = json_body(&raw)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["title"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(titles, vec!["a", "b", "c"]);
 }
 
 #[tokio::test]
-async fn get_todo_default_nests_subtasks() {
+async fn list_todos_filter_expression() {
     let fx = fixture();
-    let t = create_todo(&fx, json!({"title": "parent"})).await;
+    let today = today(&fx);
+    let soon = (today + chrono::Duration::days(1)).to_string();
+    let later = (today + chrono::Duration::days(10)).to_string();
+    let a = create_todo(
+        &fx,
+        json!({"title": "Weekly report", "quadrant": 1, "dueDate": soon}),
+    )
+    .await;
+    let _ = create_todo(
+        &fx,
+        json!({"title": "Yearly report", "quadrant": 2, "dueDate": later}),
+    )
+    .await;
+    let _ = create_todo(
+        &fx,
+        json!({"title": "Report done", "quadrant": 1, "completed": true, "dueDate": soon}),
+    )
+    .await;
+    let b = create_todo(&fx, json!({"title": "Groceries", "quadrant": 3})).await;
+    let (_, _, _) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/todos/{}/subtasks", todo_id_path(&b)),
+            Some(json!({"title": "milk"})),
+        ),
+    )
+    .await;
+
+    let expr = r#"quadrant in (1,2) and not completed and due < today+3d and text ~ "report""#;
+    let uri = format!("/todos?filter={}", urlencoding(expr));
+    let (status, headers, raw) = send(&fx.router, req(Method::GET, &uri, None)).await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    assert_eq!(v.as_array().unwrap().len(), 1);
+    assert_eq!(v[0]["id"], a["id"]);
+    assert_eq!(headers.get("x-total-count").unwrap(), "1");
+
+    let uri = format!(
+        "/todos?filter={}",
+        urlencoding("has openSubtasks or quadrant = 2")
+    );
+    let (_, _, raw) = send(&fx.router, req(Method::GET, &uri, None)).await;
+    let mut titles: Vec<String> = json_body(&raw)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|x| x["title"].as_str().unwrap().to_string())
+        .collect();
+    titles.sort();
+    assert_eq!(titles, vec!["Groceries", "Yearly report"]);
+}
+
+#[tokio::test]
+async fn list_todos_filter_syntax_error_points_at_token() {
+    let fx = fixture();
+    let uri = format!("/todos?filter={}", urlencoding("completed and bogus = 2"));
+    let (status, _, raw) = send(&fx.router, req(Method::GET, &uri, None)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let detail = json_body(&raw)["detail"].as_str().unwrap().to_string();
+    assert!(detail.contains("unknown field 'bogus'"), "{}", detail);
+    assert!(detail.contains("column 15"), "{}", detail);
+    assert!(detail.contains("near 'bogus = 2'"), "{}", detail);
+}
+
+/// 测试里够用的 query 编码：空格、引号、括号、逗号、`+`、`~`。
+fn urlencoding(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' => c.to_string(),
+            _ => format!("%{:02X}", c as u32),
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn list_todos_limit_offset() {
+    let fx = fixture();
+    for i in 0..5 {
+        let _ = create_todo(&fx, json!({"title": format!("t{}", i), "sortOrder": i})).await;
+    }
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::GET, "/todos?sort=+sortOrder&limit=2&offset=1", None),
+    )
+    .await;
+    let v = json_body(&raw);
+    assert_eq!(v.as_array().unwrap().len(), 2);
+    assert_eq!(v[0]["title"], "t1");
+    assert_eq!(v[1]["title"], "t2");
+}
+
+#[tokio::test]
+async fn list_todos_sets_total_count_and_offset_link() {
+    let fx = fixture();
+    for i in 0..5 {
+        let _ = create_todo(&fx, json!({"title": format!("t{}", i), "sortOrder": i})).await;
+    }
+    let (_, headers, _) = send(
+        &fx.router,
+        req(Method::GET, "/todos?completed=false&limit=2&offset=2", None),
+    )
+    .await;
+    assert_eq!(headers.get("x-total-count").unwrap(), "5");
+    let link = headers.get("link").unwrap().to_str().unwrap();
+    assert_eq!(
+        link,
+        r#"</todos?completed=false&limit=2&offset=4>; rel="next""#
+    );
+
+    // 最后一页不带 Link
+    let (_, headers, _) = send(
+        &fx.router,
+        req(Method::GET, "/todos?limit=2&offset=4", None),
+    )
+    .await;
+    assert!(!headers.contains_key("link"));
+}
+
+#[tokio::test]
+async fn list_todos_cursor_pages_through_all_items() {
+    let fx = fixture();
+    for i in 0..5 {
+        let _ = create_todo(&fx, json!({"title": format!("t{}", i), "sortOrder": i})).await;
+    }
+    let mut titles = Vec::new();
+    let mut uri = "/todos?limit=2&cursor=".to_string();
+    let mut pages = 0;
+    loop {
+        let (status, headers, raw) = send(&fx.router, req(Method::GET, &uri, None)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(headers.get("x-total-count").unwrap(), "5");
+        let v = json_body(&raw);
+        for t in v["items"].as_array().unwrap() {
+            titles.push(t["title"].as_str().unwrap().to_string());
+        }
+        pages += 1;
+        match v["nextCursor"].as_str() {
+            Some(next) => {
+                // Link 与 nextCursor 指向同一页
+                let link = headers.get("link").unwrap().to_str().unwrap();
+                assert!(link.contains(next), "{}", link);
+                uri = format!("/todos?limit=2&cursor={}", next);
+            }
+            None => {
+                assert!(!headers.contains_key("link"));
+                break;
+            }
+        }
+    }
+    assert_eq!(pages, 3);
+    assert_eq!(titles, vec!["t0", "t1", "t2", "t3", "t4"]);
+}
+
+#[tokio::test]
+async fn list_todos_cursor_is_stable_across_inserts_and_deletes() {
+    let fx = fixture();
+    let mut created = Vec::new();
+    for i in 0..4 {
+        created.push(
+            create_todo(
+                &fx,
+                json!({"title": format!("t{}", i), "sortOrder": i * 10}),
+            )
+            .await,
+        );
+    }
+    let (_, _, raw) = send(&fx.router, req(Method::GET, "/todos?limit=2&cursor=", None)).await;
+    let v = json_body(&raw);
+    let next = v["nextCursor"].as_str().unwrap().to_string();
+
+    // 两页之间：删掉第一页的一条、在第一页范围内插入一条
+    let _ = send(
+        &fx.router,
+        req(
+            Method::DELETE,
+            &format!("/todos/{}", todo_id_path(&created[0])),
+            None,
+        ),
+    )
+    .await;
+    let _ = create_todo(&fx, json!({"title": "early", "sortOrder": 5})).await;
+
+    let (_, _, raw) = send(
+        &fx.router,
+        req(
+            Method::GET,
+            &format!("/todos?limit=2&cursor={}", next),
+            None,
+        ),
+    )
+    .await;
+    let v = json_body(&raw);
+    let titles: Vec<&str> = v["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["title"].as_str().unwrap())
+        .collect();
+    // offset 分页此时会跳过 t2；cursor 从 t1 之后继续
+    assert_eq!(titles, vec!["t2", "t3"]);
+    assert!(v["nextCursor"].is_null());
+}
+
+#[tokio::test]
+async fn list_todos_cursor_with_desc_sort() {
+    let fx = fixture();
+    for d in ["2026-05-01", "2026-05-03", "2026-05-02"] {
+        let _ = create_todo(&fx, json!({"title": d, "dueDate": d})).await;
+    }
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::GET, "/todos?sort=-dueDate&limit=2&cursor=", None),
+    )
+    .await;
+    let v = json_body(&raw);
+    assert_eq!(v["items"][0]["title"], "2026-05-03");
+    assert_eq!(v["items"][1]["title"], "2026-05-02");
+    let next = v["nextCursor"].as_str().unwrap();
+    let (_, _, raw) = send(
+        &fx.router,
+        req(
+            Method::GET,
+            &format!("/todos?sort=-dueDate&limit=2&cursor={}", next),
+            None,
+        ),
+    )
+    .await;
+    let v = json_body(&raw);
+    assert_eq!(v["items"].as_array().unwrap().len(), 1);
+    assert_eq!(v["items"][0]["title"], "2026-05-01");
+}
+
+#[tokio::test]
+async fn list_todos_cursor_rejects_garbage_sort_mismatch_and_offset() {
+    let fx = fixture();
+    for i in 0..3 {
+        let _ = create_todo(&fx, json!({"title": format!("t{}", i)})).await;
+    }
+    let (status, _, _) = send(&fx.router, req(Method::GET, "/todos?cursor=!!!", None)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, _, raw) = send(&fx.router, req(Method::GET, "/todos?limit=1&cursor=", None)).await;
+    let next = json_body(&raw)["nextCursor"].as_str().unwrap().to_string();
+    let (status, _, _) = send(
+        &fx.router,
+        req(
+            Method::GET,
+            &format!("/todos?sort=title&cursor={}", next),
+            None,
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::GET, "/todos?cursor=&offset=1", None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn get_todo_default_nests_subtasks() {
+    let fx = fixture();
+    let t = create_todo(&fx, json!({"title": "parent"})).await;
     let id = todo_id_path(&t);
     let _ = send(
         &fx.router,
@@ -723,118 +1542,359 @@ async fn patch_todo_null_value_explicitly_writes_null() {
 }
 
 #[tokio::test]
-async fn patch_todo_not_found_returns_404() {
+async fn patch_todo_merge_patch_deep_merges_and_null_deletes() {
     let fx = fixture();
-    let (status, _, _) = send(
+    let t = create_todo(
+        &fx,
+        json!({"title": "x", "notes": "gone", "meta": {"a": 1, "b": 2}}),
+    )
+    .await;
+    let id = todo_id_path(&t);
+    let (status, _, raw) = send(
         &fx.router,
-        req(Method::PATCH, "/todos/999999", Some(json!({"title": "x"}))),
+        req_ct(
+            Method::PATCH,
+            &format!("/todos/{}", id),
+            "application/merge-patch+json",
+            json!({"notes": null, "meta": {"b": 3}}),
+        ),
     )
     .await;
-    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    assert!(v.get("notes").is_none(), "null 在 merge patch 中表示删除");
+    assert_eq!(v["meta"], json!({"a": 1, "b": 3}));
+    assert_eq!(v["title"], "x");
 }
 
 #[tokio::test]
-async fn patch_todo_non_object_body_returns_400() {
+async fn patch_todo_merge_patch_cannot_delete_required_field() {
     let fx = fixture();
     let t = create_todo(&fx, json!({"title": "x"})).await;
     let id = todo_id_path(&t);
-    let (status, _, _) = send(
+    let (status, _, raw) = send(
         &fx.router,
-        req(
+        req_ct(
             Method::PATCH,
             &format!("/todos/{}", id),
-            Some(json!("oops")),
+            "application/merge-patch+json",
+            json!({"completed": null}),
         ),
     )
     .await;
-    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(&raw)["errors"][0]["field"], "completed");
 }
 
-// =============================================================================
-// DELETE /todos/:id
-// =============================================================================
-
 #[tokio::test]
-async fn delete_todo_cascades_and_writes_tombstones() {
+async fn patch_todo_json_patch_applies_ops_and_test_guards() {
     let fx = fixture();
-    let t = create_todo(&fx, json!({"title": "parent"})).await;
+    let t = create_todo(&fx, json!({"title": "x", "tags": ["a"]})).await;
     let id = todo_id_path(&t);
-    let (_, _, sub_raw) = send(
-        &fx.router,
-        req(
-            Method::POST,
-            &format!("/todos/{}/subtasks", id),
-            Some(json!({"title": "s"})),
-        ),
-    )
-    .await;
-    let sub_id = json_body(&sub_raw)["id"].as_i64().unwrap().to_string();
+    let uri = format!("/todos/{}", id);
 
-    let (status, _, _) = send(
+    let (status, _, raw) = send(
         &fx.router,
-        req(Method::DELETE, &format!("/todos/{}", id), None),
+        req_ct(
+            Method::PATCH,
+            &uri,
+            "application/json-patch+json",
+            json!([
+                {"op": "test", "path": "/completed", "value": false},
+                {"op": "replace", "path": "/completed", "value": true},
+                {"op": "add", "path": "/tags/-", "value": "b"},
+            ]),
+        ),
     )
     .await;
-    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    assert_eq!(v["completed"], true);
+    assert_eq!(v["tags"], json!(["a", "b"]));
 
-    // GET 404
-    let (s, _, _) = send(
+    // 条件编辑：test 不成立 → 409，且不落库
+    let (status, _, raw) = send(
         &fx.router,
-        req(Method::GET, &format!("/todos/{}", id), None),
+        req_ct(
+            Method::PATCH,
+            &uri,
+            "application/json-patch+json",
+            json!([
+                {"op": "test", "path": "/completed", "value": false},
+                {"op": "replace", "path": "/title", "value": "y"},
+            ]),
+        ),
     )
     .await;
-    assert_eq!(s, StatusCode::NOT_FOUND);
-
-    // tombstones：todo + subtask 都该写入
-    let stones = fx.state.db.with_conn(|c| repo::list_tombstones(c).unwrap());
-    let kinds: Vec<(String, String)> = stones
-        .iter()
-        .map(|(t, i, _)| (t.clone(), i.clone()))
-        .collect();
-    assert!(
-        kinds.contains(&("todo".to_string(), id.clone())),
-        "todo tombstone missing"
-    );
-    assert!(
-        kinds.contains(&("subtask".to_string(), sub_id)),
-        "subtask tombstone missing"
-    );
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(json_body(&raw)["error"], "patch_test_failed");
+    let (_, _, raw) = send(&fx.router, req(Method::GET, &uri, None)).await;
+    assert_eq!(json_body(&raw)["title"], "x");
 }
 
 #[tokio::test]
-async fn delete_todo_not_found_returns_404() {
+async fn patch_todo_json_patch_result_is_validated() {
     let fx = fixture();
-    let (status, _, _) = send(&fx.router, req(Method::DELETE, "/todos/999999", None)).await;
-    assert_eq!(status, StatusCode::NOT_FOUND);
+    let t = create_todo(&fx, json!({"title": "x"})).await;
+    let id = todo_id_path(&t);
+    let (status, _, _) = send(
+        &fx.router,
+        req_ct(
+            Method::PATCH,
+            &format!("/todos/{}", id),
+            "application/json-patch+json",
+            json!([{"op": "replace", "path": "/quadrant", "value": "banana"}]),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
 }
 
-// =============================================================================
-// /todos/:id/subtasks (POST) + /subtasks/:id (PATCH/DELETE)
-// =============================================================================
-
 #[tokio::test]
-async fn create_subtask_under_missing_todo_returns_404() {
+async fn patch_todo_unsupported_content_type_returns_415() {
     let fx = fixture();
-    let (status, _, body) = send(
+    let t = create_todo(&fx, json!({"title": "x"})).await;
+    let id = todo_id_path(&t);
+    let (status, _, raw) = send(
         &fx.router,
-        req(
-            Method::POST,
-            "/todos/999999/subtasks",
-            Some(json!({"title": "s"})),
+        req_ct(
+            Method::PATCH,
+            &format!("/todos/{}", id),
+            "text/plain",
+            json!({"title": "y"}),
         ),
     )
     .await;
-    assert_eq!(status, StatusCode::NOT_FOUND);
-    let v = json_body(&body);
-    assert_eq!(v["error"], "not_found");
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(json_body(&raw)["error"], "unsupported_media_type");
 }
 
 #[tokio::test]
-async fn create_subtask_missing_title_returns_400() {
+async fn patch_subtask_merge_patch_keeps_parent_id() {
     let fx = fixture();
     let t = create_todo(&fx, json!({"title": "p"})).await;
-    let id = todo_id_path(&t);
-    let (status, _, _) = send(
+    let parent = todo_id_path(&t);
+    let (_, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/todos/{}/subtasks", parent),
+            Some(json!({"title": "s", "content": "c"})),
+        ),
+    )
+    .await;
+    let sub = json_body(&raw);
+    let sid = sub["id"].as_i64().unwrap().to_string();
+    let (status, _, raw) = send(
+        &fx.router,
+        req_ct(
+            Method::PATCH,
+            &format!("/subtasks/{}", sid),
+            "application/merge-patch+json",
+            json!({"content": null, "parentId": 1}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    assert!(v.get("content").is_none());
+    assert_eq!(v["parentId"], sub["parentId"]);
+}
+
+#[tokio::test]
+async fn patch_todo_not_found_returns_404() {
+    let fx = fixture();
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::PATCH, "/todos/999999", Some(json!({"title": "x"}))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn patch_todo_invalid_field_returns_422_and_keeps_record() {
+    let fx = fixture();
+    let t = create_todo(&fx, json!({"title": "x", "quadrant": 2})).await;
+    let id = todo_id_path(&t);
+    let (status, _, body) = send(
+        &fx.router,
+        req(
+            Method::PATCH,
+            &format!("/todos/{}", id),
+            Some(json!({"quadrant": 9, "notifyAt": "next week"})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(&body)["errors"].as_array().unwrap().len(), 2);
+
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", id), None),
+    )
+    .await;
+    assert_eq!(json_body(&raw)["quadrant"], 2);
+}
+
+#[tokio::test]
+async fn patch_todo_non_object_body_returns_400() {
+    let fx = fixture();
+    let t = create_todo(&fx, json!({"title": "x"})).await;
+    let id = todo_id_path(&t);
+    let (status, _, _) = send(
+        &fx.router,
+        req(
+            Method::PATCH,
+            &format!("/todos/{}", id),
+            Some(json!("oops")),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn patch_todo_completed_flip_stamps_and_clears_completed_at() {
+    let fx = fixture();
+    let t = create_todo(&fx, json!({"title": "x"})).await;
+    assert!(t.get("completedAt").is_none());
+    let id = todo_id_path(&t);
+    let uri = format!("/todos/{}", id);
+
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::PATCH, &uri, Some(json!({"completed": true}))),
+    )
+    .await;
+    let done = json_body(&raw);
+    let stamped = done["completedAt"].as_str().unwrap().to_string();
+    assert_eq!(stamped, done["updatedAt"].as_str().unwrap());
+
+    // 未翻转：不重写
+    let (_, _, raw) = send(
+        &fx.router,
+        req(
+            Method::PATCH,
+            &uri,
+            Some(json!({"title": "y", "completed": true})),
+        ),
+    )
+    .await;
+    assert_eq!(json_body(&raw)["completedAt"], stamped.as_str());
+
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::PATCH, &uri, Some(json!({"completed": false}))),
+    )
+    .await;
+    assert!(json_body(&raw)["completedAt"].is_null());
+
+    // 显式给出的 completedAt 优先（补录）
+    let (_, _, raw) = send(
+        &fx.router,
+        req(
+            Method::PATCH,
+            &uri,
+            Some(json!({"completed": true, "completedAt": "2026-05-01 08:00:00"})),
+        ),
+    )
+    .await;
+    assert_eq!(json_body(&raw)["completedAt"], "2026-05-01 08:00:00");
+}
+
+#[tokio::test]
+async fn create_todo_completed_gets_completed_at() {
+    let fx = fixture();
+    let t = create_todo(&fx, json!({"title": "x", "completed": true})).await;
+    assert_eq!(t["completedAt"], t["createdAt"]);
+}
+
+// =============================================================================
+// DELETE /todos/:id
+// =============================================================================
+
+#[tokio::test]
+async fn delete_todo_cascades_and_writes_tombstones() {
+    let fx = fixture();
+    let t = create_todo(&fx, json!({"title": "parent"})).await;
+    let id = todo_id_path(&t);
+    let (_, _, sub_raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/todos/{}/subtasks", id),
+            Some(json!({"title": "s"})),
+        ),
+    )
+    .await;
+    let sub_id = json_body(&sub_raw)["id"].as_i64().unwrap().to_string();
+
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::DELETE, &format!("/todos/{}", id), None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+
+    // GET 404
+    let (s, _, _) = send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", id), None),
+    )
+    .await;
+    assert_eq!(s, StatusCode::NOT_FOUND);
+
+    // tombstones：todo + subtask 都该写入
+    let stones = fx.state.db.with_conn(|c| repo::list_tombstones(c).unwrap());
+    let kinds: Vec<(String, String)> = stones
+        .iter()
+        .map(|(t, i, _)| (t.clone(), i.clone()))
+        .collect();
+    assert!(
+        kinds.contains(&("todo".to_string(), id.clone())),
+        "todo tombstone missing"
+    );
+    assert!(
+        kinds.contains(&("subtask".to_string(), sub_id)),
+        "subtask tombstone missing"
+    );
+}
+
+#[tokio::test]
+async fn delete_todo_not_found_returns_404() {
+    let fx = fixture();
+    let (status, _, _) = send(&fx.router, req(Method::DELETE, "/todos/999999", None)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// =============================================================================
+// /todos/:id/subtasks (POST) + /subtasks/:id (PATCH/DELETE)
+// =============================================================================
+
+#[tokio::test]
+async fn create_subtask_under_missing_todo_returns_404() {
+    let fx = fixture();
+    let (status, _, body) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/todos/999999/subtasks",
+            Some(json!({"title": "s"})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let v = json_body(&body);
+    assert_eq!(v["error"], "not_found");
+}
+
+#[tokio::test]
+async fn create_subtask_missing_title_returns_400() {
+    let fx = fixture();
+    let t = create_todo(&fx, json!({"title": "p"})).await;
+    let id = todo_id_path(&t);
+    let (status, _, _) = send(
         &fx.router,
         req(
             Method::POST,
@@ -870,6 +1930,35 @@ async fn create_subtask_assigns_parent_id_and_defaults() {
     assert!(v["createdAt"].is_string());
 }
 
+#[tokio::test]
+async fn patch_subtask_invalid_completed_returns_422() {
+    let fx = fixture();
+    let t = create_todo(&fx, json!({"title": "p"})).await;
+    let parent = todo_id_path(&t);
+    let (_, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/todos/{}/subtasks", parent),
+            Some(json!({"title": "s"})),
+        ),
+    )
+    .await;
+    let sid = json_body(&raw)["id"].as_i64().unwrap().to_string();
+
+    let (status, _, body) = send(
+        &fx.router,
+        req(
+            Method::PATCH,
+            &format!("/subtasks/{}", sid),
+            Some(json!({"completed": "done"})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(&body)["errors"][0]["field"], "completed");
+}
+
 #[tokio::test]
 async fn patch_subtask_merges() {
     let fx = fixture();
@@ -953,131 +2042,1360 @@ async fn delete_subtask_not_found_returns_404() {
 }
 
 // =============================================================================
-// /images
+// 重排 / 移动：/todos/reorder、/todos/:id/subtasks/reorder、/subtasks/:id/move
 // =============================================================================
 
-fn multipart_body(boundary: &str, filename: &str, ct: &str, bytes: &[u8]) -> Vec<u8> {
-    let mut body = Vec::new();
-    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
-    body.extend_from_slice(
-        format!(
-            "Content-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n",
-            filename
-        )
-        .as_bytes(),
-    );
-    body.extend_from_slice(format!("Content-Type: {}\r\n\r\n", ct).as_bytes());
-    body.extend_from_slice(bytes);
-    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
-    body
+async fn create_subtask(fx: &Fixture, parent: &str, title: &str) -> Value {
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/todos/{}/subtasks", parent),
+            Some(json!({"title": title})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "create subtask failed");
+    json_body(&raw)
+}
+
+async fn sort_order_of(fx: &Fixture, path: &str) -> Value {
+    let (_, _, raw) = send(&fx.router, req(Method::GET, path, None)).await;
+    json_body(&raw)["sortOrder"].clone()
 }
 
 #[tokio::test]
-async fn upload_image_then_fetch_roundtrip() {
+async fn reorder_todos_sets_sort_order_in_list_order() {
     let fx = fixture();
-    let boundary = "----test-boundary";
-    let payload = b"\x89PNG\r\n\x1a\nFAKEDATA";
-    let body = multipart_body(boundary, "pic.png", "image/png", payload);
+    let a = todo_id_path(&create_todo(&fx, json!({"title": "a"})).await);
+    let b = todo_id_path(&create_todo(&fx, json!({"title": "b"})).await);
+    let c = todo_id_path(&create_todo(&fx, json!({"title": "c"})).await);
 
-    let r = Request::builder()
-        .method(Method::POST)
-        .uri("/images")
-        .header(header::AUTHORIZATION, bearer())
-        .header(
-            header::CONTENT_TYPE,
-            format!("multipart/form-data; boundary={}", boundary),
-        )
-        .body(Body::from(body))
-        .unwrap();
-    let (status, _, raw) = send(&fx.router, r).await;
-    assert_eq!(status, StatusCode::OK);
-    let v = json_body(&raw);
+    // 混用数字 id、字符串 id 与 C 短码（a 是第一条，seq = 1）
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/todos/reorder",
+            Some(json!({"ids": [c.parse::<i64>().unwrap(), b, "C1"]})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    let titles: Vec<&str> = v
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, vec!["c", "b", "a"]);
+    assert_eq!(v[0]["sortOrder"], 0);
+    assert_eq!(v[2]["sortOrder"], 2);
+    assert!(v[0]["subtaskCount"].is_number());
+
+    let (_, _, raw) = send(&fx.router, req(Method::GET, "/todos?sort=sortOrder", None)).await;
+    let titles: Vec<String> = json_body(&raw)
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["title"].as_str().unwrap().to_string())
+        .collect();
+    assert_eq!(titles, vec!["c", "b", "a"]);
+    let dirty = fx
+        .state
+        .db
+        .with_conn(|c| repo::get_meta(c, "dirty"))
+        .unwrap();
+    assert_eq!(dirty.as_deref(), Some("true"));
+
+    // 任一 id 不存在：404，且前面的也不生效
+    let (status, _, _) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/todos/reorder",
+            Some(json!({"ids": [a, "999999"]})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(sort_order_of(&fx, &format!("/todos/{}", a)).await, 2);
+
+    // 同一条出现两次（字面重复或短码 + id）、空列表 → 422
+    for body in [
+        json!({"ids": [a, a]}),
+        json!({"ids": [a, "c1"]}),
+        json!({"ids": []}),
+        json!({"ids": [true]}),
+        json!({}),
+    ] {
+        let (status, _, raw) =
+            send(&fx.router, req(Method::POST, "/todos/reorder", Some(body))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(json_body(&raw)["errors"][0]["field"], "ids");
+    }
+}
+
+#[tokio::test]
+async fn reorder_subtasks_only_accepts_own_subtasks() {
+    let fx = fixture();
+    let p = todo_id_path(&create_todo(&fx, json!({"title": "p"})).await);
+    let other = todo_id_path(&create_todo(&fx, json!({"title": "other"})).await);
+    let s1 = todo_id_path(&create_subtask(&fx, &p, "s1").await);
+    let s2 = todo_id_path(&create_subtask(&fx, &p, "s2").await);
+    let foreign = todo_id_path(&create_subtask(&fx, &other, "x").await);
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/todos/{}/subtasks/reorder", p),
+            Some(json!({"ids": [s2, s1]})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    assert_eq!(v[0]["title"], "s2");
+    assert_eq!(v[0]["sortOrder"], 0);
+    assert_eq!(v[1]["sortOrder"], 1);
+
+    let (_, _, raw) = send(&fx.router, req(Method::GET, &format!("/todos/{}", p), None)).await;
+    let subs = json_body(&raw)["subtasks"].clone();
+    assert_eq!(subs[0]["title"], "s2");
+    assert_eq!(subs[1]["title"], "s1");
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/todos/{}/subtasks/reorder", p),
+            Some(json!({"ids": [s1, foreign]})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(json_body(&raw)["errors"][0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("does not belong"));
+    // 整批回滚：s1 仍排第二
+    let (_, _, raw) = send(&fx.router, req(Method::GET, &format!("/todos/{}", p), None)).await;
+    assert_eq!(json_body(&raw)["subtasks"][1]["title"], "s1");
+
+    let (status, _, _) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/todos/999999/subtasks/reorder",
+            Some(json!({"ids": [s1]})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn move_subtask_updates_parent_column_without_tombstone() {
+    let fx = fixture();
+    let from = todo_id_path(&create_todo(&fx, json!({"title": "from"})).await);
+    let to = todo_id_path(&create_todo(&fx, json!({"title": "to"})).await);
+    let sid = todo_id_path(&create_subtask(&fx, &from, "moving").await);
+    let (status, _, _) = send(
+        &fx.router,
+        req(
+            Method::PATCH,
+            &format!("/subtasks/{}", sid),
+            Some(json!({"sortOrder": 0})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    for (i, title) in ["t0", "t1"].iter().enumerate() {
+        let s = create_subtask(&fx, &to, title).await;
+        send(
+            &fx.router,
+            req(
+                Method::PATCH,
+                &format!("/subtasks/{}", todo_id_path(&s)),
+                Some(json!({"sortOrder": i})),
+            ),
+        )
+        .await;
+    }
+
+    // 目标用 C 短码（to 是第二条，seq = 2）；不给 sortOrder 时排到末尾
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/subtasks/{}/move", sid),
+            Some(json!({"todoId": "C2"})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    assert_eq!(v["parentId"].as_i64().unwrap().to_string(), to);
+    assert_eq!(v["sortOrder"], 2);
+    assert_eq!(v["title"], "moving");
+
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", from), None),
+    )
+    .await;
+    assert_eq!(json_body(&raw)["subtasks"], json!([]));
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", to), None),
+    )
+    .await;
+    assert_eq!(json_body(&raw)["subtasks"][2]["title"], "moving");
+
+    let (row, tomb) = fx
+        .state
+        .db
+        .with_conn(|c| -> rusqlite::Result<_> {
+            Ok((
+                repo::get_subtask(c, &sid)?,
+                repo::has_tombstone(c, "subtask", &sid)?,
+            ))
+        })
+        .unwrap();
+    assert_eq!(row.unwrap().todo_id, to);
+    assert!(!tomb, "移动不能写 tombstone，否则 push 会把新位置也删掉");
+
+    // 显式 sortOrder
+    let (_, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/subtasks/{}/move", sid),
+            Some(json!({"todoId": from.parse::<i64>().unwrap(), "sortOrder": 5})),
+        ),
+    )
+    .await;
+    assert_eq!(json_body(&raw)["sortOrder"], 5);
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/subtasks/{}/move", sid),
+            Some(json!({})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(&raw)["errors"][0]["field"], "todoId");
+
+    let (status, _, _) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/subtasks/{}/move", sid),
+            Some(json!({"todoId": "999999"})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _, _) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/subtasks/999999/move",
+            Some(json!({"todoId": to})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn promote_subtask_creates_todo_and_tombstones_original() {
+    let fx = fixture();
+    let parent = todo_id_path(
+        &create_todo(
+            &fx,
+            json!({"title": "p", "color": "#EF4444", "quadrant": 1}),
+        )
+        .await,
+    );
+    let sid = todo_id_path(&create_subtask(&fx, &parent, "升级我").await);
+    send(
+        &fx.router,
+        req(
+            Method::PATCH,
+            &format!("/subtasks/{}", sid),
+            Some(json!({"content": "见 ![](img-1.png)", "completed": true})),
+        ),
+    )
+    .await;
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(Method::POST, &format!("/subtasks/{}/promote", sid), None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let v = json_body(&raw);
+    assert_eq!(v["title"], "升级我");
+    assert_eq!(v["description"], "见 ![](img-1.png)");
+    assert_eq!(v["completed"], true);
+    assert!(v["completedAt"].is_string());
+    assert_eq!(v["color"], "#EF4444");
+    assert_eq!(v["quadrant"], 1);
+    assert_eq!(v["seq"], 2);
+    assert_ne!(todo_id_path(&v), sid);
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", todo_id_path(&v)), None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json_body(&raw)["title"], "升级我");
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", parent), None),
+    )
+    .await;
+    assert_eq!(json_body(&raw)["subtasks"], json!([]));
+    let tomb = fx
+        .state
+        .db
+        .with_conn(|c| repo::has_tombstone(c, "subtask", &sid))
+        .unwrap();
+    assert!(
+        tomb,
+        "原子任务必须写 tombstone，否则 push merge 会从远端复活"
+    );
+
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::POST, &format!("/subtasks/{}/promote", sid), None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn demote_todo_carries_fields_and_own_subtasks() {
+    let fx = fixture();
+    let target = todo_id_path(&create_todo(&fx, json!({"title": "target"})).await);
+    create_subtask(&fx, &target, "existing").await;
+    let t = create_todo(
+        &fx,
+        json!({"title": "降级我", "description": "详情", "completed": true}),
+    )
+    .await;
+    let id = todo_id_path(&t);
+    let c1 = todo_id_path(&create_subtask(&fx, &id, "c1").await);
+    let c2 = todo_id_path(&create_subtask(&fx, &id, "c2").await);
+    send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/todos/{}/subtasks/reorder", id),
+            Some(json!({"ids": [c1, c2]})),
+        ),
+    )
+    .await;
+
+    // into 用 C 短码（target 是第一条）
+    let (status, _, raw) = send(
+        &fx.router,
+        req(Method::POST, &format!("/todos/{}/demote?into=C1", id), None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    assert_eq!(v["title"], "target");
+    let subs = v["subtasks"].as_array().unwrap();
+    let titles: Vec<&str> = subs.iter().map(|s| s["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, vec!["existing", "降级我", "c1", "c2"]);
+    assert_eq!(subs[1]["content"], "详情");
+    assert_eq!(subs[1]["completed"], true);
+    assert!(subs
+        .iter()
+        .all(|s| s["parentId"].as_i64().unwrap().to_string() == target));
+
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", id), None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (todo_tomb, c1_tomb, c1_row) = fx
+        .state
+        .db
+        .with_conn(|c| -> rusqlite::Result<_> {
+            Ok((
+                repo::has_tombstone(c, "todo", &id)?,
+                repo::has_tombstone(c, "subtask", &c1)?,
+                repo::get_subtask(c, &c1)?,
+            ))
+        })
+        .unwrap();
+    assert!(todo_tomb);
+    assert!(!c1_tomb, "挪走的子任务不能写 tombstone");
+    assert_eq!(c1_row.unwrap().todo_id, target);
+}
+
+#[tokio::test]
+async fn demote_todo_rejects_bad_target() {
+    let fx = fixture();
+    let id = todo_id_path(&create_todo(&fx, json!({"title": "a"})).await);
+
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::POST, &format!("/todos/{}/demote", id), None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(Method::POST, &format!("/todos/{}/demote?into=C1", id), None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(&raw)["errors"][0]["field"], "into");
+
+    let (status, _, _) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/todos/{}/demote?into=999999", id),
+            None,
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(todo_count(&fx), 1);
+}
+
+#[tokio::test]
+async fn duplicate_todo_deep_copies_with_shifted_dates() {
+    let fx = fixture();
+    let orig = create_todo(
+        &fx,
+        json!({
+            "title": "周会",
+            "quadrant": 2,
+            "startTime": "2026-02-13T09:00:00",
+            "endTime": "2026-02-13 10:00",
+            "notifyAt": "2026-02-13T08:45:00",
+            "notified": true,
+            "dueDate": "2026-02-14",
+            "agenda": {"custom": true},
+        }),
+    )
+    .await;
+    let id = todo_id_path(&orig);
+    create_subtask(&fx, &id, "议程").await;
+    create_subtask(&fx, &id, "纪要 ![](img_1_2.png)").await;
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/todos/{}/duplicate", id),
+            Some(json!({"shiftDays": 7})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let v = json_body(&raw);
+    assert_ne!(v["id"], orig["id"]);
+    assert_eq!(v["seq"], 2);
+    assert_eq!(v["title"], "周会");
+    assert_eq!(v["quadrant"], 2);
+    assert_eq!(v["agenda"]["custom"], true, "未知字段也要带上");
+    assert_eq!(v["notified"], false);
+    assert_eq!(v["startTime"], "2026-02-20T09:00:00");
+    assert_eq!(v["endTime"], "2026-02-20 10:00", "保持原格式");
+    assert_eq!(v["notifyAt"], "2026-02-20T08:45:00");
+    assert_eq!(v["dueDate"], "2026-02-21");
+
+    let subs = v["subtasks"].as_array().unwrap();
+    assert_eq!(subs.len(), 2);
+    // 两条子任务 sortOrder 都是 0，先后由 id 的随机后缀决定，这里不比较顺序
+    assert!(subs.iter().any(|s| s["title"] == "纪要 ![](img_1_2.png)"));
+    assert!(subs.iter().all(|s| s["parentId"] == v["id"]));
+
+    // 原 todo 及其子任务不受影响
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", id), None),
+    )
+    .await;
+    let orig_now = json_body(&raw);
+    assert_eq!(orig_now["startTime"], "2026-02-13T09:00:00");
+    let orig_sub_ids: Vec<&Value> = orig_now["subtasks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| &s["id"])
+        .collect();
+    assert!(subs.iter().all(|s| !orig_sub_ids.contains(&&s["id"])));
+    assert_eq!(todo_count(&fx), 2);
+}
+
+#[tokio::test]
+async fn duplicate_todo_resets_completion() {
+    let fx = fixture();
+    let orig = create_todo(&fx, json!({"title": "已完成", "completed": true})).await;
+    assert!(orig["completedAt"].is_string());
+    let id = todo_id_path(&orig);
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(Method::POST, &format!("/todos/{}/duplicate", id), None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let v = json_body(&raw);
+    assert_eq!(v["completed"], false);
+    assert_eq!(v["completedAt"], Value::Null);
+
+    // 原 todo 仍是完成状态
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", id), None),
+    )
+    .await;
+    let orig_now = json_body(&raw);
+    assert_eq!(orig_now["completed"], true);
+    assert_eq!(orig_now["completedAt"], orig["completedAt"]);
+}
+
+#[tokio::test]
+async fn duplicate_todo_without_body_and_bad_shift() {
+    let fx = fixture();
+    let orig = create_todo(
+        &fx,
+        json!({"title": "x", "startTime": "2026-02-13T09:00:00"}),
+    )
+    .await;
+    let path = format!("/todos/{}/duplicate", todo_id_path(&orig));
+
+    let (status, _, raw) = send(&fx.router, req(Method::POST, &path, None)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(json_body(&raw)["startTime"], "2026-02-13T09:00:00");
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(Method::POST, &path, Some(json!({"shiftDays": "7"}))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(&raw)["errors"][0]["field"], "shiftDays");
+
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::POST, "/todos/999999/duplicate", None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(todo_count(&fx), 2);
+}
+
+// =============================================================================
+// /images
+// =============================================================================
+
+fn multipart_body(boundary: &str, filename: &str, ct: &str, bytes: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+    body.extend_from_slice(
+        format!(
+            "Content-Disposition: form-data; name=\"file\"; filename=\"{}\"\r\n",
+            filename
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(format!("Content-Type: {}\r\n\r\n", ct).as_bytes());
+    body.extend_from_slice(bytes);
+    body.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+#[tokio::test]
+async fn upload_image_then_fetch_roundtrip() {
+    let fx = fixture();
+    let boundary = "----test-boundary";
+    let payload = b"\x89PNG\r\n\x1a\nFAKEDATA";
+    let body = multipart_body(boundary, "pic.png", "image/png", payload);
+
+    let r = Request::builder()
+        .method(Method::POST)
+        .uri("/images")
+        .header(header::AUTHORIZATION, bearer())
+        .header(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(Body::from(body))
+        .unwrap();
+    let (status, _, raw) = send(&fx.router, r).await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
     let name = v["name"].as_str().unwrap().to_string();
     assert!(name.starts_with("img_"));
     assert!(name.ends_with(".png"));
 
-    // GET 拿回原 bytes
-    let (s, headers, got) = send(
+    // GET 拿回原 bytes
+    let (s, headers, got) = send(
+        &fx.router,
+        req(Method::GET, &format!("/images/{}", name), None),
+    )
+    .await;
+    assert_eq!(s, StatusCode::OK);
+    assert_eq!(headers.get(header::CONTENT_TYPE).unwrap(), "image/png");
+    assert_eq!(got.as_slice(), payload);
+
+    // dirty_images meta 也要记录
+    let dirty_images = fx
+        .state
+        .db
+        .with_conn(|c| repo::get_meta(c, "dirty_images"))
+        .unwrap();
+    let arr: Vec<String> = serde_json::from_str(dirty_images.as_deref().unwrap_or("[]")).unwrap();
+    assert!(arr.contains(&name));
+}
+
+#[tokio::test]
+async fn upload_image_non_image_ext_falls_back_to_bin() {
+    let fx = fixture();
+    let boundary = "----test-boundary2";
+    let body = multipart_body(boundary, "danger.exe", "application/octet-stream", b"AAAA");
+    let r = Request::builder()
+        .method(Method::POST)
+        .uri("/images")
+        .header(header::AUTHORIZATION, bearer())
+        .header(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(Body::from(body))
+        .unwrap();
+    let (status, _, raw) = send(&fx.router, r).await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    assert!(
+        v["name"].as_str().unwrap().ends_with(".bin"),
+        "non-image ext must fall back to .bin, got {}",
+        v["name"]
+    );
+}
+
+#[tokio::test]
+async fn upload_image_missing_file_part_returns_400() {
+    let fx = fixture();
+    let boundary = "----empty-boundary";
+    // multipart 完全没有任何 part
+    let body = format!("--{}--\r\n", boundary);
+    let r = Request::builder()
+        .method(Method::POST)
+        .uri("/images")
+        .header(header::AUTHORIZATION, bearer())
+        .header(
+            header::CONTENT_TYPE,
+            format!("multipart/form-data; boundary={}", boundary),
+        )
+        .body(Body::from(body))
+        .unwrap();
+    let (status, _, _) = send(&fx.router, r).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn get_image_not_found_returns_404() {
+    let fx = fixture();
+    let (status, _, body) = send(&fx.router, req(Method::GET, "/images/nope.png", None)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let v = json_body(&body);
+    assert_eq!(v["error"], "not_found");
+}
+
+#[tokio::test]
+async fn get_image_rejects_path_traversal() {
+    let fx = fixture();
+    // axum 把 `..` 在 router 层就归一化，所以这里测一个明显非法字符串
+    // 走到 handler 的情况：包含 backslash 的 percent-encoded 名字
+    let (status, _, _) = send(&fx.router, req(Method::GET, "/images/%2e%2e", None)).await;
+    // 解码出来是 ".."，sanitize_filename 拒绝
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+// =============================================================================
+// /views（saved views）
+// =============================================================================
+
+#[tokio::test]
+async fn views_crud_roundtrip() {
+    let fx = fixture();
+    let body =
+        json!({"name": "本周 Q1", "filter": "quadrant = 1 and not completed", "sort": "-priority"});
+    let (status, _, raw) = send(&fx.router, req(Method::POST, "/views", Some(body.clone()))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let v = json_body(&raw);
+    assert_eq!(v["withSubtasks"], false);
+    assert!(v["updatedAt"].is_string());
+
+    let (status, _, raw) = send(&fx.router, req(Method::POST, "/views", Some(body))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    assert_eq!(json_body(&raw)["error"], "view_exists");
+
+    let path = "/views/%E6%9C%AC%E5%91%A8%20Q1";
+    let (status, _, raw) = send(
+        &fx.router,
+        req_ct(
+            Method::PATCH,
+            path,
+            "application/merge-patch+json",
+            json!({"sort": null, "withSubtasks": true}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    assert_eq!(v["sort"], Value::Null);
+    assert_eq!(v["withSubtasks"], true);
+
+    let (status, _, raw) = send(&fx.router, req(Method::GET, "/views", None)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json_body(&raw).as_array().unwrap().len(), 1);
+
+    let (status, _, _) = send(&fx.router, req(Method::DELETE, path, None)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _, _) = send(&fx.router, req(Method::GET, path, None)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let tomb = fx
+        .state
+        .db
+        .with_conn(|c| repo::has_tombstone(c, "view", "本周 Q1"))
+        .unwrap();
+    assert!(
+        tomb,
+        "删除 view 必须写 tombstone，防止 push merge 时被远端复活"
+    );
+}
+
+/// 删掉再同名重建的 view 必须能推上去：墓碑不清的话 push merge 会把它剔除，
+/// 紧接着一次干净的 pull 按远端清理孤儿，本地这条就没了。
+#[tokio::test]
+async fn recreated_view_survives_push_and_pull() {
+    let fx = fixture();
+    let body = json!({"name": "x", "filter": "not completed"});
+    let (status, _, _) = send(&fx.router, req(Method::POST, "/views", Some(body.clone()))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _, _) = send(&fx.router, req(Method::DELETE, "/views/x", None)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _, _) = send(&fx.router, req(Method::POST, "/views", Some(body))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let tomb = fx
+        .state
+        .db
+        .with_conn(|c| repo::has_tombstone(c, "view", "x"))
+        .unwrap();
+    assert!(!tomb, "重建后墓碑必须清掉");
+
+    // push：本地快照合并进空远端
+    let cfg = fx.state.config.get();
+    let db = &fx.state.db;
+    let local = crate::sync::push::build_local_snapshot(&cfg, db).unwrap();
+    let merged = crate::sync::push::merge_sync_data(&json!({}), &local, db, &cfg).unwrap();
+    let names: Vec<&str> = merged["settings"]["savedViews"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|v| v["name"].as_str())
+        .collect();
+    assert_eq!(names, vec!["x"]);
+
+    // PUT 成功、dirty 已清之后的一次 pull：远端快照里有它，本地不能被清理
+    db.with_conn(|c| repo::set_meta(c, "dirty", "false"))
+        .unwrap();
+    let data: crate::sync::pull::SyncData = serde_json::from_value(merged).unwrap();
+    crate::sync::pull::merge_into_sqlite(db, &data).unwrap();
+    let (status, _, _) = send(&fx.router, req(Method::GET, "/views/x", None)).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn views_reject_bad_filter_and_rename() {
+    let fx = fixture();
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/views",
+            Some(json!({"name": "x", "filter": "quadrant ="})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let v = json_body(&raw);
+    assert_eq!(v["errors"][0]["field"], "filter");
+    assert!(v["errors"][0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("column"));
+
+    let _ = send(
+        &fx.router,
+        req(Method::POST, "/views", Some(json!({"name": "x"}))),
+    )
+    .await;
+    let (status, _, raw) = send(
+        &fx.router,
+        req(Method::PATCH, "/views/x", Some(json!({"name": "y"}))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(&raw)["errors"][0]["field"], "name");
+}
+
+#[tokio::test]
+async fn view_todos_applies_filter_sort_projection_and_paging() {
+    let fx = fixture();
+    let _ = create_todo(&fx, json!({"title": "a", "quadrant": 1})).await;
+    let _ = create_todo(&fx, json!({"title": "b", "quadrant": 1})).await;
+    let _ = create_todo(&fx, json!({"title": "c", "quadrant": 2})).await;
+    let _ = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/views",
+            Some(json!({"name": "q1", "filter": "quadrant = 1", "sort": "-title", "fields": ["title"]})),
+        ),
+    )
+    .await;
+
+    let (status, headers, raw) = send(&fx.router, req(Method::GET, "/views/q1/todos", None)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers.get("x-total-count").unwrap(), "2");
+    let v = json_body(&raw);
+    assert_eq!(v[0]["title"], "b");
+    assert_eq!(v[1]["title"], "a");
+    let keys: Vec<&String> = v[0].as_object().unwrap().keys().collect();
+    assert_eq!(keys.len(), 2, "只保留 id + 投影字段：{:?}", keys);
+
+    let (_, headers, raw) = send(
+        &fx.router,
+        req(Method::GET, "/views/q1/todos?limit=1&cursor=", None),
+    )
+    .await;
+    assert_eq!(json_body(&raw)["items"].as_array().unwrap().len(), 1);
+    let link = headers.get("link").unwrap().to_str().unwrap();
+    assert!(link.starts_with("</views/q1/todos?"), "{}", link);
+
+    let (status, _, _) = send(&fx.router, req(Method::GET, "/views/nope/todos", None)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// =============================================================================
+// /templates
+// =============================================================================
+
+#[tokio::test]
+async fn template_instantiate_expands_placeholders() {
+    let fx = fixture();
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/templates",
+            Some(json!({
+                "name": "入职清单",
+                "title": "{{name}} 入职 {{date}}",
+                "description": "第 {{ week }} 周",
+                "quadrant": 2,
+                "subtasks": [
+                    {"title": "给 {{name}} 开账号"},
+                    {"title": "领电脑", "content": "找 {{mentor}}"},
+                ],
+            })),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let tpl = json_body(&raw);
+    assert_eq!(tpl["color"], "#10B981");
+    let path = format!("/templates/{}/instantiate", tpl["id"]);
+
+    // mentor 没给 → 422，逐个列出缺的 key，且不落库
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &path,
+            Some(json!({"params": {"name": "小王"}})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(&raw)["errors"][0]["field"], "params.mentor");
+    assert_eq!(todo_count(&fx), 0);
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &path,
+            Some(json!({"params": {"name": "小王", "mentor": "老李"}})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let v = json_body(&raw);
+    let date = today(&fx);
+    let week = chrono::Datelike::iso_week(&date);
+    assert_eq!(
+        v["title"],
+        format!("小王 入职 {}", date.format("%Y-%m-%d")).as_str()
+    );
+    assert_eq!(
+        v["description"],
+        format!("第 {}-W{:02} 周", week.year(), week.week()).as_str()
+    );
+    assert_eq!(v["quadrant"], 2);
+    assert_eq!(v["seq"], 1);
+    let subs = v["subtasks"].as_array().unwrap();
+    assert_eq!(subs[0]["title"], "给 小王 开账号");
+    assert_eq!(subs[1]["content"], "找 老李");
+    assert_eq!(subs[1]["sortOrder"], 1);
+
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", todo_id_path(&v)), None),
+    )
+    .await;
+    assert_eq!(json_body(&raw)["subtasks"].as_array().unwrap().len(), 2);
+
+    // 模板本身不变，可以反复实例化
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::GET, &format!("/templates/{}", tpl["id"]), None),
+    )
+    .await;
+    assert_eq!(json_body(&raw)["title"], "{{name}} 入职 {{date}}");
+}
+
+#[tokio::test]
+async fn templates_crud_validation_and_tombstone() {
+    let fx = fixture();
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::POST, "/templates", Some(json!({"title": "x"}))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/templates",
+            Some(json!({"name": "x", "title": "x", "subtasks": [{"content": "c"}]})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(&raw)["errors"][0]["field"], "subtasks");
+
+    let (_, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/templates",
+            Some(json!({"name": "周报", "title": "{{week}} 周报"})),
+        ),
+    )
+    .await;
+    let id = json_body(&raw)["id"].to_string();
+    let path = format!("/templates/{}", id);
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::PATCH,
+            &path,
+            Some(json!({"id": 1, "subtasks": [{"title": "汇总"}]})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    assert_eq!(v["id"].to_string(), id);
+    assert_eq!(v["subtasks"][0]["title"], "汇总");
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::PATCH, &path, Some(json!({"quadrant": 9}))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let (_, _, raw) = send(&fx.router, req(Method::GET, "/templates", None)).await;
+    assert_eq!(json_body(&raw).as_array().unwrap().len(), 1);
+
+    let (status, _, _) = send(&fx.router, req(Method::DELETE, &path, None)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let tomb = fx
+        .state
+        .db
+        .with_conn(|c| repo::has_tombstone(c, "template", &id))
+        .unwrap();
+    assert!(
+        tomb,
+        "删除模板必须写 tombstone，防止 push merge 时被远端复活"
+    );
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::POST, &format!("{}/instantiate", path), None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+// =============================================================================
+// /tags
+// =============================================================================
+
+#[tokio::test]
+async fn list_todos_filters_by_repeated_tag_params() {
+    let fx = fixture();
+    create_todo(&fx, json!({"title": "a", "tags": ["work", "urgent"]})).await;
+    create_todo(&fx, json!({"title": "b", "tags": ["work", "personal"]})).await;
+    create_todo(&fx, json!({"title": "c"})).await;
+
+    let titles = |raw: &[u8]| -> Vec<String> {
+        json_body(raw)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["title"].as_str().unwrap().to_string())
+            .collect()
+    };
+    let (status, headers, raw) = send(
+        &fx.router,
+        req(Method::GET, "/todos?tag=work&tag=-personal", None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(titles(&raw), vec!["a"]);
+    assert_eq!(headers["x-total-count"], "1");
+    let (_, _, raw) = send(&fx.router, req(Method::GET, "/todos?tag=-work", None)).await;
+    assert_eq!(titles(&raw), vec!["c"]);
+    let (status, _, _) = send(&fx.router, req(Method::GET, "/todos?tag=-", None)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/todos",
+            Some(json!({"title": "x", "tags": ["-no"]})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(&raw)["errors"][0]["field"], "tags");
+}
+
+#[tokio::test]
+async fn tags_crud_rename_and_delete_rewrite_todos() {
+    let fx = fixture();
+    let a = create_todo(&fx, json!({"title": "a", "tags": ["work", "home"]})).await;
+    let b = create_todo(&fx, json!({"title": "b", "tags": ["work"]})).await;
+
+    // 未登记的标签也列出来，取默认色
+    let (_, _, raw) = send(&fx.router, req(Method::GET, "/tags", None)).await;
+    assert_eq!(
+        json_body(&raw),
+        json!([
+            {"name": "home", "color": "#6B7280", "count": 1},
+            {"name": "work", "color": "#6B7280", "count": 2},
+        ])
+    );
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            "/tags",
+            Some(json!({"name": "work", "color": "#EF4444"})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(json_body(&raw)["count"], 2);
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::POST, "/tags", Some(json!({"name": "work"}))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::POST, "/tags", Some(json!({"name": "-x"}))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    // 改名：todo 里的 work → job，a 已有的 home 保留；旧名写墓碑
+    let (status, _, raw) = send(
+        &fx.router,
+        req(Method::PATCH, "/tags/work", Some(json!({"name": "job"}))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    assert_eq!(v["name"], "job");
+    assert_eq!(v["color"], "#EF4444");
+    assert_eq!(v["count"], 2);
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", todo_id_path(&a)), None),
+    )
+    .await;
+    let after = json_body(&raw);
+    assert_eq!(after["tags"], json!(["job", "home"]));
+    let tomb = fx
+        .state
+        .db
+        .with_conn(|c| repo::has_tombstone(c, "tag", "work"))
+        .unwrap();
+    assert!(tomb, "改名后旧名必须写 tombstone");
+    let (status, _, _) = send(&fx.router, req(Method::GET, "/tags/work", None)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 只在 todo 里用到的标签也能删：从 todo 摘掉
+    let (status, _, _) = send(&fx.router, req(Method::DELETE, "/tags/home", None)).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (_, _, raw) = send(&fx.router, req(Method::GET, "/todos?tag=home", None)).await;
+    assert_eq!(json_body(&raw), json!([]));
+    let (_, _, raw) = send(
+        &fx.router,
+        req(Method::GET, &format!("/todos/{}", todo_id_path(&b)), None),
+    )
+    .await;
+    assert_eq!(json_body(&raw)["tags"], json!(["job"]));
+    let (status, _, _) = send(&fx.router, req(Method::DELETE, "/tags/home", None)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // 删过再登记：墓碑清掉，push merge 不会把它剔除
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::POST, "/tags", Some(json!({"name": "work"}))),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let tomb = fx
+        .state
+        .db
+        .with_conn(|c| repo::has_tombstone(c, "tag", "work"))
+        .unwrap();
+    assert!(!tomb);
+}
+
+// =============================================================================
+// /settings
+// =============================================================================
+
+#[tokio::test]
+async fn settings_defaults_overlay_remote_and_patch_marks_dirty() {
+    let fx = fixture();
+    let (status, _, raw) = send(&fx.router, req(Method::GET, "/settings", None)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        json_body(&raw),
+        json!({
+            "viewMode": "list",
+            "textTheme": "dark",
+            "notificationType": "system",
+            "showCalendar": false,
+            "windowBgColor": "#000000",
+            "windowBgAlpha": 0.45,
+        })
+    );
+
+    // pull 下来的远端 settings：同步字段透出，窗口状态等不透出
+    fx.state
+        .db
+        .with_conn(|c| {
+            repo::set_setting(
+                c,
+                repo::SETTING_REMOTE,
+                r#"{"isFixed":true,"viewMode":"quadrant","textTheme":"light","savedViews":[]}"#,
+            )
+        })
+        .unwrap();
+    let (_, _, raw) = send(&fx.router, req(Method::GET, "/settings", None)).await;
+    let v = json_body(&raw);
+    assert_eq!(v["viewMode"], "quadrant");
+    assert_eq!(v["textTheme"], "light");
+    assert!(v.get("isFixed").is_none());
+
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::PATCH,
+            "/settings",
+            Some(json!({"textTheme": "dark", "showCalendar": true, "windowBgAlpha": 0.8})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    assert_eq!(v["viewMode"], "quadrant");
+    assert_eq!(v["textTheme"], "dark");
+    assert_eq!(v["showCalendar"], true);
+
+    let (pending, dirty) = fx
+        .state
+        .db
+        .with_conn(|c| -> rusqlite::Result<_> {
+            Ok((
+                repo::get_setting(c, repo::SETTING_PENDING)?,
+                repo::get_meta(c, "dirty")?,
+            ))
+        })
+        .unwrap();
+    let pending: Value = serde_json::from_str(&pending.expect("pending 必须落库")).unwrap();
+    assert_eq!(
+        pending,
+        json!({"textTheme": "dark", "showCalendar": true, "windowBgAlpha": 0.8})
+    );
+    assert_eq!(dirty.as_deref(), Some("true"));
+
+    // 再次 pull 覆盖远端后，未推送的修改仍然生效
+    fx.state
+        .db
+        .with_conn(|c| repo::set_setting(c, repo::SETTING_REMOTE, r#"{"textTheme":"light"}"#))
+        .unwrap();
+    let (_, _, raw) = send(&fx.router, req(Method::GET, "/settings", None)).await;
+    let v = json_body(&raw);
+    assert_eq!(v["textTheme"], "dark");
+    assert_eq!(v["viewMode"], "list");
+
+    // merge-patch 同样可用
+    let (status, _, raw) = send(
+        &fx.router,
+        req_ct(
+            Method::PATCH,
+            "/settings",
+            "application/merge-patch+json",
+            json!({"windowBgColor": "#1E293B"}),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(json_body(&raw)["windowBgColor"], "#1E293B");
+}
+
+#[tokio::test]
+async fn settings_patch_validates_against_pc_model() {
+    let fx = fixture();
+    let (status, _, raw) = send(
+        &fx.router,
+        req(
+            Method::PATCH,
+            "/settings",
+            Some(json!({"viewMode": "grid", "windowBgAlpha": 2, "isFixed": true})),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let v = json_body(&raw);
+    let mut fields: Vec<&str> = v["errors"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| e["field"].as_str().unwrap())
+        .collect();
+    fields.sort();
+    assert_eq!(fields, vec!["isFixed", "viewMode", "windowBgAlpha"]);
+
+    // merge-patch 的 null 是删除，同步字段不能删
+    let (status, _, raw) = send(
         &fx.router,
-        req(Method::GET, &format!("/images/{}", name), None),
+        req_ct(
+            Method::PATCH,
+            "/settings",
+            "application/merge-patch+json",
+            json!({"showCalendar": null}),
+        ),
     )
     .await;
-    assert_eq!(s, StatusCode::OK);
-    assert_eq!(headers.get(header::CONTENT_TYPE).unwrap(), "image/png");
-    assert_eq!(got.as_slice(), payload);
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(&raw)["errors"][0]["field"], "showCalendar");
 
-    // dirty_images meta 也要记录
-    let dirty_images = fx
+    let pending = fx
         .state
         .db
-        .with_conn(|c| repo::get_meta(c, "dirty_images"))
+        .with_conn(|c| repo::get_setting(c, repo::SETTING_PENDING))
         .unwrap();
-    let arr: Vec<String> = serde_json::from_str(dirty_images.as_deref().unwrap_or("[]")).unwrap();
-    assert!(arr.contains(&name));
-}
-
-#[tokio::test]
-async fn upload_image_non_image_ext_falls_back_to_bin() {
-    let fx = fixture();
-    let boundary = "----test-boundary2";
-    let body = multipart_body(boundary, "danger.exe", "application/octet-stream", b"AAAA");
-    let r = Request::builder()
-        .method(Method::POST)
-        .uri("/images")
-        .header(header::AUTHORIZATION, bearer())
-        .header(
-            header::CONTENT_TYPE,
-            format!("multipart/form-data; boundary={}", boundary),
-        )
-        .body(Body::from(body))
+    assert!(pending.is_none(), "校验失败不能落库");
+    let dirty = fx
+        .state
+        .db
+        .with_conn(|c| repo::get_meta(c, "dirty"))
         .unwrap();
-    let (status, _, raw) = send(&fx.router, r).await;
-    assert_eq!(status, StatusCode::OK);
-    let v = json_body(&raw);
-    assert!(
-        v["name"].as_str().unwrap().ends_with(".bin"),
-        "non-image ext must fall back to .bin, got {}",
-        v["name"]
-    );
+    assert_ne!(dirty.as_deref(), Some("true"));
 }
 
-#[tokio::test]
-async fn upload_image_missing_file_part_returns_400() {
-    let fx = fixture();
-    let boundary = "----empty-boundary";
-    // multipart 完全没有任何 part
-    let body = format!("--{}--\r\n", boundary);
-    let r = Request::builder()
-        .method(Method::POST)
-        .uri("/images")
-        .header(header::AUTHORIZATION, bearer())
-        .header(
-            header::CONTENT_TYPE,
-            format!("multipart/form-data; boundary={}", boundary),
-        )
-        .body(Body::from(body))
-        .unwrap();
-    let (status, _, _) = send(&fx.router, r).await;
-    assert_eq!(status, StatusCode::BAD_REQUEST);
-}
+// =============================================================================
+// GET /stats
+// =============================================================================
 
 #[tokio::test]
-async fn get_image_not_found_returns_404() {
+async fn stats_reports_completion_for_default_range() {
     let fx = fixture();
-    let (status, _, body) = send(&fx.router, req(Method::GET, "/images/nope.png", None)).await;
-    assert_eq!(status, StatusCode::NOT_FOUND);
-    let v = json_body(&body);
-    assert_eq!(v["error"], "not_found");
+    let t = create_todo(&fx, json!({"title": "a", "quadrant": 1})).await;
+    let _ = create_todo(
+        &fx,
+        json!({"title": "b", "quadrant": 2, "endTime": "2000-01-01 00:00:00"}),
+    )
+    .await;
+    send(
+        &fx.router,
+        req(
+            Method::PATCH,
+            &format!("/todos/{}", todo_id_path(&t)),
+            Some(json!({"completed": true})),
+        ),
+    )
+    .await;
+
+    let (status, _, raw) = send(&fx.router, req(Method::GET, "/stats", None)).await;
+    assert_eq!(status, StatusCode::OK);
+    let v = json_body(&raw);
+    let today = today(&fx);
+    assert_eq!(v["to"], today.format("%Y-%m-%d").to_string());
+    assert_eq!(v["completed"]["perDay"].as_array().unwrap().len(), 30);
+    assert_eq!(v["completed"]["total"], 1);
+    assert_eq!(v["created"], 2);
+    assert_eq!(v["overdue"]["open"], 1);
+    assert_eq!(v["byQuadrant"][0]["quadrant"], 1);
+    assert_eq!(v["byQuadrant"][0]["completed"], 1);
+    assert!(v["subtasks"]["ratio"].is_null());
 }
 
 #[tokio::test]
-async fn get_image_rejects_path_traversal() {
+async fn stats_rejects_bad_range() {
     let fx = fixture();
-    // axum 把 `..` 在 router 层就归一化，所以这里测一个明显非法字符串
-    // 走到 handler 的情况：包含 backslash 的 percent-encoded 名字
-    let (status, _, _) = send(&fx.router, req(Method::GET, "/images/%2e%2e", None)).await;
-    // 解码出来是 ".."，sanitize_filename 拒绝
-    assert_eq!(status, StatusCode::BAD_REQUEST);
+    for uri in [
+        "/stats?from=2026-13-01",
+        "/stats?from=2026-05-10&to=2026-05-01",
+        "/stats?from=2020-01-01&to=2026-01-01",
+    ] {
+        let (status, _, _) = send(&fx.router, req(Method::GET, uri, None)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", uri);
+    }
 }
 
 // =============================================================================
@@ -1242,7 +3560,7 @@ async fn pull_backfill_assigns_seq_to_pc_origin_todo() {
     // 模拟"PC 端创建的 todo 通过 pull 进入 cloud SQLite"：直接 upsert_todo，
     // 不走 API（API 才会 assign_seq）。然后调 backfill 验证它能被分配 seq。
     let fx = fixture();
-    let now = now_local_string(fx.state.config.timezone_offset);
+    let now = now_local_string(fx.state.config.get().timezone);
     fx.state.db.with_conn(|conn| {
         repo::upsert_todo(conn, "42", r#"{"id":42,"title":"from PC"}"#, &now).unwrap();
     });
@@ -1336,6 +3654,467 @@ async fn sync_push_route_fails_when_dirty_and_webdav_unreachable() {
     assert_eq!(dirty.as_deref(), Some("true"));
 }
 
+// =============================================================================
+// X-Request-Id 与审计
+// =============================================================================
+
+#[tokio::test]
+async fn request_id_is_generated_or_echoed() {
+    let fx = fixture();
+    let (_, headers, _) = send(&fx.router, req(Method::GET, "/health", None)).await;
+    let generated = headers["x-request-id"].to_str().unwrap();
+    assert_eq!(generated.len(), 32);
+    assert!(generated.bytes().all(|b| b.is_ascii_hexdigit()));
+
+    let mut r = req_no_auth(Method::GET, "/health");
+    r.headers_mut()
+        .insert("x-request-id", "client-abc.123".parse().unwrap());
+    let (status, headers, _) = send(&fx.router, r).await;
+    // 401 也带，方便对照服务端日志
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(headers["x-request-id"], "client-abc.123");
+
+    let mut r = req(Method::GET, "/health", None);
+    r.headers_mut()
+        .insert("x-request-id", "a".repeat(200).parse().unwrap());
+    let (_, headers, _) = send(&fx.router, r).await;
+    assert_eq!(headers["x-request-id"].len(), 32);
+}
+
+#[tokio::test]
+async fn audit_records_todo_writes_with_diffs() {
+    let fx = fixture();
+    let v = create_todo(&fx, json!({"title": "审计", "description": "d"})).await;
+    let id = todo_id_path(&v);
+
+    let mut r = req(
+        Method::PATCH,
+        &format!("/todos/{}", id),
+        Some(json!({"completed": true})),
+    );
+    r.headers_mut()
+        .insert("x-request-id", "patch-req-1".parse().unwrap());
+    let (status, _, _) = send(&fx.router, r).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = send(
+        &fx.router,
+        req(Method::DELETE, &format!("/todos/{}", id), None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    // 读请求与未通过鉴权的写请求不记
+    send(&fx.router, req(Method::GET, "/todos", None)).await;
+    send(&fx.router, req_no_auth(Method::POST, "/todos")).await;
+
+    let (status, _, body) = send(
+        &fx.router,
+        req(Method::GET, &format!("/audit?target={}", id), None),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let items = json_body(&body);
+    let items = items.as_array().unwrap();
+    assert_eq!(items.len(), 3);
+
+    assert_eq!(items[0]["method"], "DELETE");
+    assert_eq!(items[0]["status"], 204);
+    assert_eq!(items[0]["before"]["title"], "审计");
+    assert!(items[0]["after"].is_null());
+
+    assert_eq!(items[1]["requestId"], "patch-req-1");
+    assert_eq!(items[1]["route"], "/todos/:id");
+    assert_eq!(items[1]["keyName"], "default");
+    assert_eq!(items[1]["before"]["completed"], false);
+    assert_eq!(items[1]["after"]["completed"], true);
+    assert!(items[1]["after"]["completedAt"].is_string());
+    assert!(
+        items[1]["after"].get("description").is_none(),
+        "没变的字段不进摘要"
+    );
+
+    assert_eq!(items[2]["method"], "POST");
+    assert_eq!(items[2]["status"], 201);
+    assert_eq!(items[2]["after"]["title"], "审计");
+
+    let (_, _, body) = send(&fx.router, req(Method::GET, "/audit", None)).await;
+    assert_eq!(json_body(&body).as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn audit_covers_subtasks_images_and_sync() {
+    let fx = fixture();
+    let v = create_todo(&fx, json!({"title": "父"})).await;
+    let (_, _, body) = send(
+        &fx.router,
+        req(
+            Method::POST,
+            &format!("/todos/{}/subtasks", todo_id_path(&v)),
+            Some(json!({"title": "子"})),
+        ),
+    )
+    .await;
+    let sub_id = json_body(&body)["id"].to_string();
+    send(&fx.router, req(Method::POST, "/sync", None)).await;
+    // 失败的写请求也记，只是没有摘要；目标取路径参数
+    send(
+        &fx.router,
+        req(Method::PATCH, "/subtasks/999", Some(json!({"title": "x"}))),
+    )
+    .await;
+
+    let (_, _, body) = send(
+        &fx.router,
+        req(Method::GET, &format!("/audit?target={}", sub_id), None),
+    )
+    .await;
+    let items = json_body(&body);
+    assert_eq!(items[0]["route"], "/todos/:id/subtasks");
+    assert_eq!(items[0]["after"]["title"], "子");
+
+    let (_, _, body) = send(&fx.router, req(Method::GET, "/audit?limit=2", None)).await;
+    let items = json_body(&body);
+    assert_eq!(items.as_array().unwrap().len(), 2);
+    assert_eq!(items[0]["target"], "999");
+    assert_eq!(items[0]["status"], 404);
+    assert!(items[0]["before"].is_null());
+    assert_eq!(items[1]["route"], "/sync");
+    assert_eq!(items[1]["status"], 207);
+
+    // 翻页
+    let before = items[1]["id"].as_i64().unwrap();
+    let (_, _, body) = send(
+        &fx.router,
+        req(Method::GET, &format!("/audit?before={}", before), None),
+    )
+    .await;
+    assert_eq!(json_body(&body).as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn audit_query_filters() {
+    let fx = fixture();
+    create_todo(&fx, json!({"title": "a"})).await;
+
+    let (_, _, body) = send(&fx.router, req(Method::GET, "/audit?key=other", None)).await;
+    assert_eq!(json_body(&body), json!([]));
+    let (_, _, body) = send(
+        &fx.router,
+        req(Method::GET, "/audit?since=2999-01-01", None),
+    )
+    .await;
+    assert_eq!(json_body(&body), json!([]));
+    let (_, _, body) = send(
+        &fx.router,
+        req(Method::GET, &format!("/audit?since={}", today(&fx)), None),
+    )
+    .await;
+    assert_eq!(json_body(&body).as_array().unwrap().len(), 1);
+
+    for bad in [
+        "/audit?since=yesterday",
+        "/audit?limit=0",
+        "/audit?limit=1001",
+    ] {
+        let (status, _, _) = send(&fx.router, req(Method::GET, bad, None)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", bad);
+    }
+}
+
+// =============================================================================
+// Idempotency-Key
+// =============================================================================
+
+fn with_key(mut r: Request<Body>, key: &str) -> Request<Body> {
+    r.headers_mut()
+        .insert("idempotency-key", key.parse().unwrap());
+    r
+}
+
+fn todo_count(fx: &Fixture) -> i64 {
+    fx.state
+        .db
+        .with_conn(|c| c.query_row("SELECT COUNT(*) FROM todos", [], |r| r.get(0)))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn idempotent_create_todo_replays_first_response() {
+    let fx = fixture();
+    let body = json!({"title": "只建一次"});
+    let (s1, h1, b1) = send(
+        &fx.router,
+        with_key(req(Method::POST, "/todos", Some(body.clone())), "key-1"),
+    )
+    .await;
+    assert_eq!(s1, StatusCode::CREATED);
+    assert!(h1.get("idempotent-replayed").is_none());
+
+    let (s2, h2, b2) = send(
+        &fx.router,
+        with_key(req(Method::POST, "/todos", Some(body.clone())), "key-1"),
+    )
+    .await;
+    assert_eq!(s2, StatusCode::CREATED);
+    assert_eq!(h2["idempotent-replayed"], "true");
+    assert_eq!(h2[header::CONTENT_TYPE], "application/json");
+    assert_eq!(b1, b2, "回放首次响应，id 不变");
+    assert!(h2.contains_key("x-sync-status"));
+    assert_eq!(todo_count(&fx), 1);
+
+    // 同一个 key、不同 body → 422
+    let (status, _, raw) = send(
+        &fx.router,
+        with_key(
+            req(Method::POST, "/todos", Some(json!({"title": "另一个"}))),
+            "key-1",
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(json_body(&raw)["error"], "idempotency_key_reused");
+
+    // 新 key / 不带 key 照常创建
+    send(
+        &fx.router,
+        with_key(req(Method::POST, "/todos", Some(body.clone())), "key-2"),
+    )
+    .await;
+    send(&fx.router, req(Method::POST, "/todos", Some(body))).await;
+    assert_eq!(todo_count(&fx), 3);
+}
+
+#[tokio::test]
+async fn idempotent_errors_are_replayed_but_bad_keys_rejected() {
+    let fx = fixture();
+    // 4xx 也是确定的结果，照样回放
+    for _ in 0..2 {
+        let (status, _, _) = send(
+            &fx.router,
+            with_key(
+                req(
+                    Method::POST,
+                    "/todos/999/subtasks",
+                    Some(json!({"title": "x"})),
+                ),
+                "sub-1",
+            ),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+    let (status, _, _) = send(
+        &fx.router,
+        with_key(
+            req(Method::POST, "/todos", Some(json!({"title": "x"}))),
+            "bad key",
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    // GET 带了也不缓存
+    let (status, headers, _) =
+        send(&fx.router, with_key(req(Method::GET, "/todos", None), "g")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(headers.get("idempotent-replayed").is_none());
+}
+
+#[tokio::test]
+async fn idempotent_subtask_image_and_sync() {
+    let fx = fixture();
+    let t = create_todo(&fx, json!({"title": "父"})).await;
+    let uri = format!("/todos/{}/subtasks", todo_id_path(&t));
+    let (_, _, a) = send(
+        &fx.router,
+        with_key(req(Method::POST, &uri, Some(json!({"title": "子"}))), "s"),
+    )
+    .await;
+    let (_, _, b) = send(
+        &fx.router,
+        with_key(req(Method::POST, &uri, Some(json!({"title": "子"}))), "s"),
+    )
+    .await;
+    assert_eq!(a, b);
+
+    let boundary = "----idem-boundary";
+    let upload = || {
+        let r = Request::builder()
+            .method(Method::POST)
+            .uri("/images")
+            .header(header::AUTHORIZATION, bearer())
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={}", boundary),
+            )
+            .body(Body::from(multipart_body(
+                boundary,
+                "pic.png",
+                "image/png",
+                b"\x89PNG",
+            )))
+            .unwrap();
+        with_key(r, "img")
+    };
+    let (_, _, a) = send(&fx.router, upload()).await;
+    let (_, h, b) = send(&fx.router, upload()).await;
+    assert_eq!(a, b);
+    assert_eq!(h["idempotent-replayed"], "true");
+    let files = std::fs::read_dir(&fx.state.config.get().images_dir)
+        .unwrap()
+        .count();
+    assert_eq!(files, 1);
+
+    // /sync 的 207 同样回放，不会再触发一次同步
+    let (s1, _, _) = send(
+        &fx.router,
+        with_key(req(Method::POST, "/sync", None), "sync"),
+    )
+    .await;
+    let (s2, h, _) = send(
+        &fx.router,
+        with_key(req(Method::POST, "/sync", None), "sync"),
+    )
+    .await;
+    assert_eq!(s1, StatusCode::MULTI_STATUS);
+    assert_eq!(s2, StatusCode::MULTI_STATUS);
+    assert_eq!(h["idempotent-replayed"], "true");
+
+    // 5xx 不存：重试会重新执行
+    let (s1, _, _) = send(
+        &fx.router,
+        with_key(req(Method::POST, "/sync/pull", None), "p"),
+    )
+    .await;
+    let (s2, h, _) = send(
+        &fx.router,
+        with_key(req(Method::POST, "/sync/pull", None), "p"),
+    )
+    .await;
+    assert_eq!(s1, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(s2, StatusCode::INTERNAL_SERVER_ERROR);
+    assert!(h.get("idempotent-replayed").is_none());
+}
+
+/// 挂着 Idempotency-Key 中间件的两个测试路由：`/slow` 等 `gate` 放行后才返回，
+/// `/boom` 直接 panic。`hits` 记 handler 实际执行次数。
+fn idempotent_test_router(
+    fx: &Fixture,
+    gate: std::sync::Arc<tokio::sync::Notify>,
+    hits: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+) -> Router {
+    use std::sync::atomic::Ordering;
+    let boom_hits = hits.clone();
+    Router::new()
+        .route(
+            "/slow",
+            axum::routing::post(move || async move {
+                gate.notified().await;
+                hits.fetch_add(1, Ordering::SeqCst);
+                (StatusCode::CREATED, "done")
+            }),
+        )
+        .route(
+            "/boom",
+            axum::routing::post(move || async move {
+                // 永远成立；写成条件只是为了让闭包有个返回类型
+                if boom_hits.fetch_add(1, Ordering::SeqCst) < usize::MAX {
+                    panic!("handler panicked");
+                }
+                StatusCode::OK
+            }),
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            fx.state.clone(),
+            super::idempotency::enforce,
+        ))
+        .with_state(fx.state.clone())
+}
+
+/// 客户端在 handler 跑到一半时断开：请求照样跑完并存下响应，重试拿到回放，
+/// 而不是吃 10 分钟 409。
+#[tokio::test]
+async fn idempotent_request_finishes_after_client_disconnects() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let fx = fixture();
+    let gate = Arc::new(tokio::sync::Notify::new());
+    let hits = Arc::new(AtomicUsize::new(0));
+    let router = idempotent_test_router(&fx, gate.clone(), hits.clone());
+    let slow = || with_key(req(Method::POST, "/slow", None), "slow-1");
+
+    // 断开 = 外层 future 被 drop
+    let dropped =
+        tokio::time::timeout(std::time::Duration::from_millis(50), send(&router, slow())).await;
+    assert!(dropped.is_err());
+    let (status, _, _) = send(&router, slow()).await;
+    assert_eq!(status, StatusCode::CONFLICT, "首个请求还在跑");
+
+    gate.notify_one();
+    let mut replay = None;
+    for _ in 0..100 {
+        let (status, headers, body) = send(&router, slow()).await;
+        if status != StatusCode::CONFLICT {
+            replay = Some((status, headers, body));
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let (status, headers, body) = replay.expect("断开的请求应当跑完并存下响应");
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(headers["idempotent-replayed"], "true");
+    assert_eq!(body, b"done");
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+    // 存的 key 带上 API key 名，不同 key 的客户端互不影响
+    let stored: Vec<String> = fx
+        .state
+        .db
+        .with_conn(|c| {
+            let mut stmt = c.prepare("SELECT key FROM idempotency_keys")?;
+            let rows = stmt.query_map([], |r| r.get(0))?;
+            rows.collect::<rusqlite::Result<Vec<String>>>()
+        })
+        .unwrap();
+    assert_eq!(stored, vec![format!("{}:slow-1", super::auth::KEY_NAME)]);
+}
+
+/// handler panic 时占位行被释放，重试重新执行。
+#[tokio::test]
+async fn idempotent_claim_released_when_handler_panics() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let fx = fixture();
+    let hits = Arc::new(AtomicUsize::new(0));
+    let router = idempotent_test_router(&fx, Arc::default(), hits.clone());
+    for _ in 0..2 {
+        let (status, headers, _) = send(
+            &router,
+            with_key(req(Method::POST, "/boom", None), "boom-1"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(headers.get("idempotent-replayed").is_none());
+    }
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn idempotency_can_be_disabled() {
+    let fx = fixture_with(|cfg| cfg.idempotency_window_secs = 0);
+    for _ in 0..2 {
+        send(
+            &fx.router,
+            with_key(
+                req(Method::POST, "/todos", Some(json!({"title": "x"}))),
+                "k",
+            ),
+        )
+        .await;
+    }
+    assert_eq!(todo_count(&fx), 2);
+}
+
 // =============================================================================
 // 示例：打印 /todos 真实响应 shape（cargo test demo_ -- --ignored --nocapture）
 // =============================================================================
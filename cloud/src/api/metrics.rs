@@ -0,0 +1,38 @@
+//! `GET /metrics`（Prometheus text format）+ 记录每个请求的 HTTP 中间件。
+//!
+//! 指标定义见 `crate::metrics`。主 router 上的 `/metrics` 与其他路由一样要
+//! Bearer token；配置了 `metrics_bind` 时另起一个只有 `/metrics`、不鉴权的
+//! listener（见 `build_metrics_router`），供内网 Prometheus 直接抓取。
+
+use std::time::Instant;
+
+use axum::extract::{MatchedPath, Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use super::error::ApiError;
+use super::AppState;
+use crate::metrics;
+
+/// Prometheus text exposition format 0.0.4。
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+pub async fn get_metrics(State(state): State<AppState>) -> Result<Response, ApiError> {
+    let body = metrics::global().render(&state.db)?;
+    Ok(([(header::CONTENT_TYPE, CONTENT_TYPE)], body).into_response())
+}
+
+/// 按路由模板记请求数与耗时。没匹配到任何路由（404 fallback）记为 `unmatched`。
+pub async fn track_http(req: Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = req.method().to_string();
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|p| p.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let resp = next.run(req).await;
+    metrics::global().observe_http(&method, &route, resp.status().as_u16(), started.elapsed());
+    resp
+}
@@ -1,46 +1,232 @@
 //! HTTP API 层。
 //!
 //! 路由结构：
-//! - `/health`
-//! - `/todos`、`/todos/:id`、`/todos/:id/subtasks`
-//! - `/subtasks/:id`
+//! - `/health`、`/health/live`、`/health/ready`、`/health/details`
+//! - `/openapi.json`、`/metrics`
+//! - `/todos`、`/todos/quick`、`/todos/reorder`、`/todos/:id`、`/todos/:id/subtasks`、
+//!   `/todos/:id/subtasks/reorder`、`/todos/:id/demote`、`/todos/:id/duplicate`
+//! - `/subtasks/:id`、`/subtasks/:id/move`、`/subtasks/:id/promote`
 //! - `/images`、`/images/:name`
+//! - `/views`、`/views/:name`、`/views/:name/todos`
+//! - `/templates`、`/templates/:id`、`/templates/:id/instantiate`
+//! - `/tags`、`/tags/:name`
+//! - `/settings`
+//! - `/stats`
+//! - `/audit`
 //!
-//! 中间件洋葱：内层 auth（先校验 token）+ 外层 inject_sync_headers（所有响应
-//! 包括 401 都附 X-Sync-Status / X-Last-Sync-At）。
+//! `/health/live` 与 `/health/ready` 给负载均衡 / systemd / k8s 探针用，不经过
+//! auth；其余路由都要 Bearer token。
+//!
+//! 中间件洋葱：最内层 record_writes（鉴权通过的写请求记 audit_log）+ 内层
+//! auth（先校验 token）+ ratelimit（令牌桶与无效 key 封禁，探针不经过）+ 中层
+//! inject_sync_headers（所有响应包括 401 / 429 都附 X-Sync-Status /
+//! X-Last-Sync-At）+ 外层 track_http（按路由模板记请求数 / 耗时，401 也计入）+
+//! 最外层 assign_request_id（分配 X-Request-Id 并开 tracing span，见
+//! [`audit`]）。配置了 `cors_origins` 时再在最外面套一层
+//! CORS（见 [`cors`]），预检请求不经过 auth。
 
+pub mod audit;
 pub mod auth;
+pub mod cors;
 pub mod error;
 pub mod headers;
 pub mod health;
+pub mod idempotency;
 pub mod ids;
 pub mod images;
+pub mod metrics;
+pub mod openapi;
+pub mod patch;
+pub mod ratelimit;
+pub mod settings;
+pub mod stats;
 pub mod subtasks;
 pub mod sync;
+pub mod tags;
+pub mod templates;
+pub mod timezone;
 pub mod todos;
+pub mod validate;
+pub mod views;
 
 #[cfg(test)]
 mod integration_tests;
 
-use std::sync::Arc;
-
 use axum::extract::DefaultBodyLimit;
 use axum::middleware;
-use axum::routing::{delete, get, patch, post};
+use axum::routing::{on, MethodFilter, MethodRouter};
 use axum::Router;
 
-use crate::config::Config;
+use self::ratelimit::RateLimiter;
+use crate::config::LiveConfig;
 use crate::db::Db;
-use crate::sync::SyncLock;
+use crate::sync::{Readiness, SyncLock};
 
 /// API 路由层共享的 state。
 #[derive(Clone)]
 pub struct AppState {
-    pub config: Arc<Config>,
+    pub config: LiveConfig,
     pub db: Db,
     /// 与后台 pull / push worker 共享的同步互斥锁。只有 `/sync` 系列端点会
     /// 获取它；CRUD 写路径不拿锁，避免被慢速网络同步阻塞。
     pub sync_lock: SyncLock,
+    /// 进程内首次 pull 成功后置位，`/health/ready` 据此判断。
+    pub ready: Readiness,
+    /// 令牌桶与无效 key 计数（见 [`ratelimit`]）。
+    pub limiter: RateLimiter,
+}
+
+/// 路由表的一行：方法、路径，以及按方法生成 handler（含路由级 layer）的函数。
+/// [`build_router`] 按表注册，OpenAPI 的覆盖测试也读同一张表。
+pub(crate) struct Route {
+    pub method: MethodFilter,
+    pub path: &'static str,
+    handler: fn(&AppState, MethodFilter) -> MethodRouter<AppState>,
+}
+
+impl Route {
+    /// 大写的方法名，测试里对照 OpenAPI 与发请求用。
+    #[cfg(test)]
+    pub fn method_name(&self) -> &'static str {
+        [
+            (GET, "GET"),
+            (POST, "POST"),
+            (PATCH, "PATCH"),
+            (DELETE, "DELETE"),
+        ]
+        .into_iter()
+        .find(|(filter, _)| *filter == self.method)
+        .map(|(_, name)| name)
+        .expect("路由表只用 GET / POST / PATCH / DELETE")
+    }
+}
+
+const fn route(
+    method: MethodFilter,
+    path: &'static str,
+    handler: fn(&AppState, MethodFilter) -> MethodRouter<AppState>,
+) -> Route {
+    Route {
+        method,
+        path,
+        handler,
+    }
+}
+
+const GET: MethodFilter = MethodFilter::GET;
+const POST: MethodFilter = MethodFilter::POST;
+const PATCH: MethodFilter = MethodFilter::PATCH;
+const DELETE: MethodFilter = MethodFilter::DELETE;
+
+/// 需要 Bearer token 的路由。
+pub(crate) const ROUTES: &[Route] = &[
+    route(GET, "/health", |_, m| on(m, health::get_health)),
+    route(GET, "/health/details", |_, m| on(m, health::get_details)),
+    route(GET, "/openapi.json", |_, m| on(m, openapi::get_openapi)),
+    route(GET, "/metrics", |_, m| on(m, metrics::get_metrics)),
+    route(GET, "/todos", |_, m| on(m, todos::list_todos)),
+    route(POST, "/todos", |s, m| {
+        idempotent(s, on(m, todos::create_todo))
+    }),
+    route(POST, "/todos/quick", |_, m| on(m, todos::quick_add_todo)),
+    route(POST, "/todos/reorder", |_, m| on(m, todos::reorder_todos)),
+    route(GET, "/todos/:id", |_, m| on(m, todos::get_todo)),
+    route(PATCH, "/todos/:id", |_, m| on(m, todos::patch_todo)),
+    route(DELETE, "/todos/:id", |_, m| on(m, todos::delete_todo)),
+    route(POST, "/todos/:id/subtasks", |s, m| {
+        idempotent(s, on(m, subtasks::create_subtask))
+    }),
+    route(POST, "/todos/:id/subtasks/reorder", |_, m| {
+        on(m, subtasks::reorder_subtasks)
+    }),
+    route(POST, "/todos/:id/demote", |_, m| on(m, todos::demote_todo)),
+    route(POST, "/todos/:id/duplicate", |s, m| {
+        idempotent(s, on(m, todos::duplicate_todo))
+    }),
+    route(PATCH, "/subtasks/:id", |_, m| {
+        on(m, subtasks::patch_subtask)
+    }),
+    route(DELETE, "/subtasks/:id", |_, m| {
+        on(m, subtasks::delete_subtask)
+    }),
+    route(POST, "/subtasks/:id/move", |_, m| {
+        on(m, subtasks::move_subtask)
+    }),
+    route(POST, "/subtasks/:id/promote", |_, m| {
+        on(m, subtasks::promote_subtask)
+    }),
+    // multipart 最大 32 MiB；只放宽图片上传这一条路由，
+    // 其余路由（含 POST /todos 的 JSON body）维持 axum 默认 2 MB 上限
+    route(POST, "/images", |s, m| {
+        idempotent(
+            s,
+            on(m, images::upload_image).layer(DefaultBodyLimit::max(32 * 1024 * 1024)),
+        )
+    }),
+    route(GET, "/images/:name", |_, m| on(m, images::get_image)),
+    route(GET, "/views", |_, m| on(m, views::list_views)),
+    route(POST, "/views", |_, m| on(m, views::create_view)),
+    route(GET, "/views/:name", |_, m| on(m, views::get_view)),
+    route(PATCH, "/views/:name", |_, m| on(m, views::patch_view)),
+    route(DELETE, "/views/:name", |_, m| on(m, views::delete_view)),
+    route(GET, "/views/:name/todos", |_, m| {
+        on(m, views::list_view_todos)
+    }),
+    route(GET, "/templates", |_, m| on(m, templates::list_templates)),
+    route(POST, "/templates", |s, m| {
+        idempotent(s, on(m, templates::create_template))
+    }),
+    route(GET, "/templates/:id", |_, m| on(m, templates::get_template)),
+    route(PATCH, "/templates/:id", |_, m| {
+        on(m, templates::patch_template)
+    }),
+    route(DELETE, "/templates/:id", |_, m| {
+        on(m, templates::delete_template)
+    }),
+    route(POST, "/templates/:id/instantiate", |s, m| {
+        idempotent(s, on(m, templates::instantiate_template))
+    }),
+    route(GET, "/tags", |_, m| on(m, tags::list_tags)),
+    route(POST, "/tags", |s, m| idempotent(s, on(m, tags::create_tag))),
+    route(GET, "/tags/:name", |_, m| on(m, tags::get_tag)),
+    route(PATCH, "/tags/:name", |_, m| on(m, tags::patch_tag)),
+    route(DELETE, "/tags/:name", |_, m| on(m, tags::delete_tag)),
+    route(GET, "/settings", |_, m| on(m, settings::get_settings)),
+    route(PATCH, "/settings", |_, m| on(m, settings::patch_settings)),
+    route(GET, "/stats", |_, m| on(m, stats::get_stats)),
+    route(POST, "/sync", |s, m| idempotent(s, on(m, sync::post_sync))),
+    route(POST, "/sync/pull", |s, m| {
+        idempotent(s, on(m, sync::post_sync_pull))
+    }),
+    route(POST, "/sync/push", |s, m| {
+        idempotent(s, on(m, sync::post_sync_push))
+    }),
+    route(GET, "/audit", |_, m| on(m, audit::list_audit)),
+];
+
+/// 探针路由：不带 token，merge 在 auth 层之外。
+pub(crate) const PROBE_ROUTES: &[Route] = &[
+    route(GET, "/health/live", |_, m| on(m, health::get_live)),
+    route(GET, "/health/ready", |_, m| on(m, health::get_ready)),
+];
+
+/// `metrics_bind` 上独立 router 的路由（见 [`build_metrics_router`]）。
+pub(crate) const METRICS_ROUTES: &[Route] =
+    &[route(GET, "/metrics", |_, m| on(m, metrics::get_metrics))];
+
+/// 会产生新 id 或触发同步的 POST 支持 Idempotency-Key（见 [`idempotency`]）。
+fn idempotent(state: &AppState, router: MethodRouter<AppState>) -> MethodRouter<AppState> {
+    router.layer(middleware::from_fn_with_state(
+        state.clone(),
+        idempotency::enforce,
+    ))
+}
+
+/// 按路由表注册；同一路径的多个方法由 axum 合并到一个 MethodRouter。
+fn register(state: &AppState, table: &[Route]) -> Router<AppState> {
+    table.iter().fold(Router::new(), |router, r| {
+        router.route(r.path, (r.handler)(state, r.method))
+    })
 }
 
 pub fn build_router(state: AppState) -> Router {
@@ -50,37 +236,48 @@ pub fn build_router(state: AppState) -> Router {
     // 所有响应（包括 401）都带 X-Sync-Status；auth 401 后短路返回时也要被
     // header 注入。因此 `inject_sync_headers` 必须是外层，注册顺序后于
     // `require_bearer`。
-    Router::new()
-        .route("/health", get(health::get_health))
-        .route("/todos", get(todos::list_todos).post(todos::create_todo))
-        .route(
-            "/todos/:id",
-            get(todos::get_todo)
-                .patch(todos::patch_todo)
-                .delete(todos::delete_todo),
-        )
-        .route("/todos/:id/subtasks", post(subtasks::create_subtask))
-        .route("/subtasks/:id", patch(subtasks::patch_subtask))
-        .route("/subtasks/:id", delete(subtasks::delete_subtask))
-        .route(
-            "/images",
-            // multipart 最大 32 MiB；只放宽图片上传这一条路由，
-            // 其余路由（含 POST /todos 的 JSON body）维持 axum 默认 2 MB 上限
-            post(images::upload_image).layer(DefaultBodyLimit::max(32 * 1024 * 1024)),
-        )
-        .route("/images/:name", get(images::get_image))
-        .route("/sync", post(sync::post_sync))
-        .route("/sync/pull", post(sync::post_sync_pull))
-        .route("/sync/push", post(sync::post_sync_push))
+    // 探针路由不带 token，merge 在 auth 层之外
+    let cors = cors::layer(&state.config.get());
+    let probes = register(&state, PROBE_ROUTES);
+
+    let router = register(&state, ROUTES)
+        // 最内层：只审计通过鉴权的写请求
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            audit::record_writes,
+        ))
         // 内层：先校验 token
         .layer(middleware::from_fn_with_state(
             state.clone(),
             auth::require_bearer,
         ))
-        // 外层：无论 handler / 内层 middleware 怎么应答都注入 sync header
+        // 限流在 auth 之外：无效 key 也要计数、封禁期内连 auth 都不走
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            ratelimit::enforce,
+        ))
+        .merge(probes)
+        // 中层：无论 handler / 内层 middleware 怎么应答都注入 sync header
         .layer(middleware::from_fn_with_state(
             state.clone(),
             headers::inject_sync_headers,
         ))
-        .with_state(state)
+        // 计时从这里开始，覆盖 auth 与 header 注入
+        .layer(middleware::from_fn(metrics::track_http))
+        // request id 最先分配，后面各层的日志都落在它的 span 里
+        .layer(middleware::from_fn(audit::assign_request_id))
+        .with_state(state);
+
+    // 最外层：预检 OPTIONS 不带 token，要在 auth 之前由 CORS 层直接应答；
+    // 普通响应（含 401）也都要带上 Access-Control-Allow-Origin
+    match cors {
+        Some(cors) => router.layer(cors),
+        None => router,
+    }
+}
+
+/// `metrics_bind` 上的独立 router：只有 `/metrics`，不鉴权、不记 HTTP 指标
+/// （抓取本身不该出现在业务请求统计里）。
+pub fn build_metrics_router(state: AppState) -> Router {
+    register(&state, METRICS_ROUTES).with_state(state)
 }
@@ -0,0 +1,1830 @@
+//! `GET /openapi.json`：手写 OpenAPI 3.0 描述。
+//!
+//! 不引入 utoipa 之类的派生宏——handler 大量直接吃 `Json<Value>`（透传 PC
+//! 未知字段），派生出来的 schema 反而不准。spec 与 `build_router` 的同步由
+//! 本文件底部的测试保证：两边读同一张路由表（`api::ROUTES` 等），新增路由没写
+//! 进 spec 会直接挂测试。
+
+use axum::Json;
+use serde_json::{json, Map, Value};
+
+pub async fn get_openapi() -> Json<Value> {
+    Json(spec())
+}
+
+/// 构造完整 spec。
+pub fn spec() -> Value {
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "mini-todo cloud API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "mini-todo 云端 HTTP API。数据经 WebDAV 与 PC 端双向同步；\
+                todo / subtask 的 body 以 PC 端 camelCase JSON 为准，未知字段原样透传。",
+        },
+        "security": [{ "bearerAuth": [] }],
+        "paths": paths(),
+        "components": components(),
+    })
+}
+
+fn paths() -> Value {
+    let mut p = Map::new();
+
+    p.insert(
+        "/health".into(),
+        json!({
+            "get": op(
+                "health",
+                "健康检查 + 同步状态",
+                vec![],
+                None,
+                vec![("200", ok_json("健康状态", schema_ref("Health")))],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/health/live".into(),
+        json!({
+            "get": public(op(
+                "health",
+                "存活探针（不鉴权）",
+                vec![],
+                None,
+                vec![("200", ok_json("进程存活", json!({
+                    "type": "object",
+                    "properties": {"status": {"type": "string", "enum": ["alive"]}},
+                })))],
+            )),
+        }),
+    );
+
+    p.insert(
+        "/health/ready".into(),
+        json!({
+            "get": public(op(
+                "health",
+                "就绪探针（不鉴权）：进程内首次 pull 成功且 DB 可读",
+                vec![],
+                None,
+                vec![
+                    ("200", ok_json("已就绪", schema_ref("Ready"))),
+                    ("503", ok_json("未就绪", schema_ref("Ready"))),
+                ],
+            )),
+        }),
+    );
+
+    p.insert(
+        "/health/details".into(),
+        json!({
+            "get": op(
+                "health",
+                "健康明细：同步时间与错误、dirty、图片队列、tombstone、WebDAV 连通性、磁盘剩余、版本",
+                vec![],
+                None,
+                vec![("200", ok_json("健康明细", schema_ref("HealthDetails")))],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/openapi.json".into(),
+        json!({
+            "get": op(
+                "system",
+                "本 OpenAPI 描述",
+                vec![],
+                None,
+                vec![("200", ok_json("OpenAPI 3 文档", json!({"type": "object"})))],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/metrics".into(),
+        json!({
+            "get": op(
+                "system",
+                "Prometheus 指标（text exposition format）。配置 metrics_bind 时另有免鉴权的独立监听",
+                vec![],
+                None,
+                vec![("200", with_sync_headers(json!({
+                    "description": "请求数 / 耗时、pull / push tick、412 重试、dirty generation lag、图片队列、tombstone 数、DB 大小",
+                    "content": {"text/plain": {"schema": {"type": "string"}}},
+                })))],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/todos".into(),
+        json!({
+            "get": op(
+                "todos",
+                "列出 todo（过滤 / 排序 / 分页）",
+                [vec![param_ref("XTimezone")], list_todos_params()].concat(),
+                None,
+                vec![
+                    ("200", with_paging_headers(ok_json("todo 列表；cursor 模式下为分页对象", json!({
+                        "oneOf": [
+                            {"type": "array", "items": schema_ref("Todo")},
+                            schema_ref("TodoPage"),
+                        ],
+                    })))),
+                    ("400", err_ref("BadRequest")),
+                ],
+            ),
+            "post": idempotent(op(
+                "todos",
+                "创建 todo；id / createdAt / updatedAt 由服务端生成",
+                vec![],
+                Some(json_body(schema_ref("TodoCreate"))),
+                vec![
+                    ("201", ok_json("已创建的 todo（含 seq）", schema_ref("Todo"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                ],
+            )),
+        }),
+    );
+
+    p.insert(
+        "/todos/quick".into(),
+        json!({
+            "post": op(
+                "todos",
+                "一行自然语言快速创建 todo（日期 / 时刻 / #标签 / !象限 / 重复规则），按 X-Timezone（缺省服务端时区）解析",
+                vec![param_ref("XTimezone")],
+                Some(json_body(schema_ref("QuickAddReq"))),
+                vec![
+                    ("201", ok_json("已创建的 todo 与解析明细", schema_ref("QuickAddResp"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/todos/reorder".into(),
+        json!({
+            "post": op(
+                "todos",
+                "按 ids 顺序把 sortOrder 设为 0..n（支持 C 短码）；任一 id 不存在则整批不生效",
+                vec![],
+                Some(json_body(schema_ref("ReorderReq"))),
+                vec![
+                    ("200", ok_json("重排后的 todo 列表（不嵌套 subtasks）", json!({"type": "array", "items": schema_ref("Todo")}))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/todos/{id}".into(),
+        json!({
+            "get": op(
+                "todos",
+                "todo 详情；默认嵌套 subtasks",
+                vec![param_ref("TodoId"), with_subtasks_param("默认 true；false 时扁平化并返回 subtaskCount")],
+                None,
+                vec![
+                    ("200", ok_json("todo", schema_ref("Todo"))),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+            "patch": op(
+                "todos",
+                "更新 todo；按 Content-Type 选择浅合并 / RFC 7396 merge patch / RFC 6902 json patch",
+                vec![param_ref("TodoId")],
+                Some(patch_body(schema_ref("TodoPatch"))),
+                vec![
+                    ("200", ok_json("更新后的 todo", schema_ref("Todo"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                    ("409", err_ref("PatchTestFailed")),
+                    ("415", err_ref("UnsupportedMediaType")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+            "delete": op(
+                "todos",
+                "删除 todo 并联动删除其 subtasks（写 tombstone）",
+                vec![param_ref("TodoId")],
+                None,
+                vec![
+                    ("204", no_content("已删除")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/todos/{id}/subtasks".into(),
+        json!({
+            "post": idempotent(op(
+                "subtasks",
+                "在 todo 下创建子任务",
+                vec![param_ref("TodoId")],
+                Some(json_body(schema_ref("SubtaskCreate"))),
+                vec![
+                    ("201", ok_json("已创建的子任务", schema_ref("Subtask"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                    ("404", err_ref("NotFound")),
+                ],
+            )),
+        }),
+    );
+
+    p.insert(
+        "/todos/{id}/subtasks/reorder".into(),
+        json!({
+            "post": op(
+                "subtasks",
+                "按 ids 顺序重排该 todo 的子任务；id 属于别的 todo → 422",
+                vec![param_ref("TodoId")],
+                Some(json_body(schema_ref("ReorderReq"))),
+                vec![
+                    ("200", ok_json("重排后的子任务", json!({"type": "array", "items": schema_ref("Subtask")}))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/todos/{id}/demote".into(),
+        json!({
+            "post": op(
+                "todos",
+                "降级为 into 的子任务：标题 / 描述 / 完成状态带过去，自带的子任务一并挪到 into 下；原 todo 删除（写 tombstone）",
+                vec![
+                    param_ref("TodoId"),
+                    query_param("into", "string", "目标 todo id 或 C 短码（必填）"),
+                ],
+                None,
+                vec![
+                    ("200", ok_json("目标 todo（嵌套 subtasks）", schema_ref("Todo"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/todos/{id}/duplicate".into(),
+        json!({
+            "post": idempotent(op(
+                "todos",
+                "深拷贝 todo 与子任务：新 id / seq / 时间戳，notified 与 completed / completedAt 重置，其余字段原样；shiftDays 平移 startTime / endTime / dueDate / notifyAt",
+                vec![param_ref("TodoId")],
+                Some(json!({
+                    "required": false,
+                    "content": {"application/json": {"schema": schema_ref("DuplicateReq")}},
+                })),
+                vec![
+                    ("201", ok_json("副本（含 seq 与 subtasks）", schema_ref("Todo"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                    ("404", err_ref("NotFound")),
+                ],
+            )),
+        }),
+    );
+
+    p.insert(
+        "/subtasks/{id}/promote".into(),
+        json!({
+            "post": op(
+                "subtasks",
+                "升级为独立 todo：标题 / 内容（→ description）/ 完成状态带过去，颜色与象限沿用原父 todo；原子任务删除（写 tombstone）",
+                vec![param_ref("SubtaskId")],
+                None,
+                vec![
+                    ("201", ok_json("新建的 todo", schema_ref("Todo"))),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/subtasks/{id}/move".into(),
+        json!({
+            "post": op(
+                "subtasks",
+                "把子任务移到另一个 todo 下（同时改 parentId）；不给 sortOrder 时排到末尾",
+                vec![param_ref("SubtaskId")],
+                Some(json_body(schema_ref("MoveSubtaskReq"))),
+                vec![
+                    ("200", ok_json("移动后的子任务", schema_ref("Subtask"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/subtasks/{id}".into(),
+        json!({
+            "patch": op(
+                "subtasks",
+                "更新子任务；Content-Type 语义同 PATCH /todos/{id}",
+                vec![param_ref("SubtaskId")],
+                Some(patch_body(schema_ref("SubtaskPatch"))),
+                vec![
+                    ("200", ok_json("更新后的子任务", schema_ref("Subtask"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                    ("409", err_ref("PatchTestFailed")),
+                    ("415", err_ref("UnsupportedMediaType")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+            "delete": op(
+                "subtasks",
+                "删除子任务（写 tombstone）",
+                vec![param_ref("SubtaskId")],
+                None,
+                vec![
+                    ("204", no_content("已删除")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/images".into(),
+        json!({
+            "post": idempotent(op(
+                "images",
+                "上传图片（multipart，字段 file / image，上限 32 MiB）",
+                vec![],
+                Some(json!({
+                    "required": true,
+                    "content": {
+                        "multipart/form-data": {
+                            "schema": {
+                                "type": "object",
+                                "properties": {
+                                    "file": {"type": "string", "format": "binary"},
+                                },
+                            },
+                        },
+                    },
+                })),
+                vec![
+                    ("200", ok_json("服务端生成的文件名", schema_ref("UploadResp"))),
+                    ("400", err_ref("BadRequest")),
+                ],
+            )),
+        }),
+    );
+
+    p.insert(
+        "/images/{name}".into(),
+        json!({
+            "get": op(
+                "images",
+                "下载图片；Content-Type 按扩展名推断",
+                vec![json!({
+                    "name": "name",
+                    "in": "path",
+                    "required": true,
+                    "schema": {"type": "string"},
+                    "description": "图片文件名（不含目录）",
+                })],
+                None,
+                vec![
+                    ("200", with_sync_headers(json!({
+                        "description": "图片 bytes",
+                        "content": {
+                            "image/*": {"schema": {"type": "string", "format": "binary"}},
+                        },
+                    }))),
+                    ("400", err_ref("BadRequest")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/views".into(),
+        json!({
+            "get": op(
+                "views",
+                "列出 saved view（按 name 排序）",
+                vec![],
+                None,
+                vec![("200", ok_json("view 列表", json!({"type": "array", "items": schema_ref("View")})))],
+            ),
+            "post": op(
+                "views",
+                "创建 saved view；filter 保存时即做语法检查",
+                vec![],
+                Some(json_body(schema_ref("ViewCreate"))),
+                vec![
+                    ("201", ok_json("已创建的 view", schema_ref("View"))),
+                    ("400", err_ref("BadRequest")),
+                    ("409", err_ref("ViewExists")),
+                    ("422", err_ref("ValidationFailed")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/views/{name}".into(),
+        json!({
+            "get": op(
+                "views",
+                "view 详情",
+                vec![param_ref("ViewName")],
+                None,
+                vec![
+                    ("200", ok_json("view", schema_ref("View"))),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+            "patch": op(
+                "views",
+                "更新 view；Content-Type 语义同 PATCH /todos/{id}，name 不可改",
+                vec![param_ref("ViewName")],
+                Some(patch_body(schema_ref("ViewPatch"))),
+                vec![
+                    ("200", ok_json("更新后的 view", schema_ref("View"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                    ("409", err_ref("PatchTestFailed")),
+                    ("415", err_ref("UnsupportedMediaType")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+            "delete": op(
+                "views",
+                "删除 view（写 tombstone，同步到远端 settings.savedViews）",
+                vec![param_ref("ViewName")],
+                None,
+                vec![
+                    ("204", no_content("已删除")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/views/{name}/todos".into(),
+        json!({
+            "get": op(
+                "views",
+                "执行 view：filter / sort / withSubtasks 取自 view，分页取自本次 query；fields 非空时投影",
+                vec![
+                    param_ref("ViewName"),
+                    param_ref("XTimezone"),
+                    query_param("limit", "integer", "最多返回条数（> 0）"),
+                    query_param("offset", "integer", "跳过条数（≥ 0）；与 cursor 互斥"),
+                    query_param("cursor", "string", "keyset 分页游标，语义同 GET /todos"),
+                ],
+                None,
+                vec![
+                    ("200", with_paging_headers(ok_json("todo 列表；cursor 模式下为分页对象", json!({
+                        "oneOf": [
+                            {"type": "array", "items": schema_ref("Todo")},
+                            schema_ref("TodoPage"),
+                        ],
+                    })))),
+                    ("400", err_ref("BadRequest")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/templates".into(),
+        json!({
+            "get": op(
+                "templates",
+                "列出 todo 模板（按 name 排序）",
+                vec![],
+                None,
+                vec![("200", ok_json("模板列表", json!({"type": "array", "items": schema_ref("Template")})))],
+            ),
+            "post": idempotent(op(
+                "templates",
+                "创建模板；title / description / 子任务文本可含 {{date}} / {{week}} / {{name}} 等占位符",
+                vec![],
+                Some(json_body(schema_ref("TemplateCreate"))),
+                vec![
+                    ("201", ok_json("已创建的模板", schema_ref("Template"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                ],
+            )),
+        }),
+    );
+
+    p.insert(
+        "/templates/{id}".into(),
+        json!({
+            "get": op(
+                "templates",
+                "模板详情",
+                vec![param_ref("TemplateId")],
+                None,
+                vec![
+                    ("200", ok_json("模板", schema_ref("Template"))),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+            "patch": op(
+                "templates",
+                "更新模板；Content-Type 语义同 PATCH /todos/{id}",
+                vec![param_ref("TemplateId")],
+                Some(patch_body(schema_ref("TemplateCreate"))),
+                vec![
+                    ("200", ok_json("更新后的模板", schema_ref("Template"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                    ("409", err_ref("PatchTestFailed")),
+                    ("415", err_ref("UnsupportedMediaType")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+            "delete": op(
+                "templates",
+                "删除模板（写 tombstone，同步到远端 templates）",
+                vec![param_ref("TemplateId")],
+                None,
+                vec![
+                    ("204", no_content("已删除")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/templates/{id}/instantiate".into(),
+        json!({
+            "post": idempotent(op(
+                "templates",
+                "按模板新建 todo 与子任务：展开占位符，{{date}} / {{week}} 取请求方时区的今天，其余取 params；缺值 → 422 params.<key>",
+                vec![param_ref("TemplateId"), param_ref("XTimezone")],
+                Some(json!({
+                    "required": false,
+                    "content": {"application/json": {"schema": schema_ref("InstantiateReq")}},
+                })),
+                vec![
+                    ("201", ok_json("新建的 todo（含 seq 与 subtasks）", schema_ref("Todo"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                    ("404", err_ref("NotFound")),
+                ],
+            )),
+        }),
+    );
+
+    p.insert(
+        "/tags".into(),
+        json!({
+            "get": op(
+                "tags",
+                "列出标签：已登记颜色的 ∪ todo 里用到的（未登记的取默认色），按 name 排序，带引用数 count",
+                vec![],
+                None,
+                vec![("200", ok_json("标签列表", json!({"type": "array", "items": schema_ref("Tag")})))],
+            ),
+            "post": idempotent(op(
+                "tags",
+                "登记标签颜色；color 缺省为默认色",
+                vec![],
+                Some(json_body(schema_ref("TagFields"))),
+                vec![
+                    ("201", ok_json("已登记的标签", schema_ref("Tag"))),
+                    ("400", err_ref("BadRequest")),
+                    ("409", err_ref("TagExists")),
+                    ("422", err_ref("ValidationFailed")),
+                ],
+            )),
+        }),
+    );
+
+    p.insert(
+        "/tags/{name}".into(),
+        json!({
+            "get": op(
+                "tags",
+                "标签详情（已登记或被 todo 引用）",
+                vec![param_ref("TagName")],
+                None,
+                vec![
+                    ("200", ok_json("标签", schema_ref("Tag"))),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+            "patch": op(
+                "tags",
+                "改颜色 / 改名；Content-Type 语义同 PATCH /todos/{id}。改名会改写所有引用它的 todo，新名已登记 → 409",
+                vec![param_ref("TagName")],
+                Some(patch_body(schema_ref("TagFields"))),
+                vec![
+                    ("200", ok_json("更新后的标签", schema_ref("Tag"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                    ("409", err_ref("TagExists")),
+                    ("415", err_ref("UnsupportedMediaType")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+            "delete": op(
+                "tags",
+                "删除标签：从所有 todo 的 tags 里摘掉，写 tombstone（同步到远端 tags）",
+                vec![param_ref("TagName")],
+                None,
+                vec![
+                    ("204", no_content("已删除")),
+                    ("404", err_ref("NotFound")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/settings".into(),
+        json!({
+            "get": op(
+                "settings",
+                "跨设备同步的 PC 设置（远端 settings 叠加尚未推送的修改，缺省字段为 PC 默认值）",
+                vec![],
+                None,
+                vec![("200", ok_json("设置", schema_ref("AppSettings")))],
+            ),
+            "patch": op(
+                "settings",
+                "修改同步设置；Content-Type 语义同 PATCH /todos/{id}。改动标脏，下一轮 push 写进远端 settings",
+                vec![],
+                Some(patch_body(schema_ref("AppSettings"))),
+                vec![
+                    ("200", ok_json("修改后的设置", schema_ref("AppSettings"))),
+                    ("400", err_ref("BadRequest")),
+                    ("422", err_ref("ValidationFailed")),
+                    ("409", err_ref("PatchTestFailed")),
+                    ("415", err_ref("UnsupportedMediaType")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/stats".into(),
+        json!({
+            "get": op(
+                "stats",
+                "完成趋势 / 平均耗时 / 逾期 / 象限分布 / 子任务完成率",
+                vec![
+                    param_ref("XTimezone"),
+                    query_param("from", "string", "起始日期 YYYY-MM-DD（含）；缺省为 to 往前 29 天"),
+                    query_param("to", "string", "结束日期 YYYY-MM-DD（含）；缺省为今天。区间最长 366 天"),
+                ],
+                None,
+                vec![
+                    ("200", ok_json("统计结果", schema_ref("Stats"))),
+                    ("400", err_ref("BadRequest")),
+                ],
+            ),
+        }),
+    );
+
+    p.insert(
+        "/sync".into(),
+        json!({
+            "post": idempotent(op(
+                "sync",
+                "手动触发 pull + push",
+                vec![],
+                None,
+                vec![
+                    ("200", ok_json("全部成功", schema_ref("SyncResp"))),
+                    ("207", ok_json("部分失败", schema_ref("SyncResp"))),
+                ],
+            )),
+        }),
+    );
+
+    p.insert(
+        "/sync/pull".into(),
+        json!({
+            "post": idempotent(op(
+                "sync",
+                "仅从 WebDAV 拉取",
+                vec![],
+                None,
+                vec![
+                    ("200", ok_json("成功", schema_ref("StatusOk"))),
+                    ("500", err_ref("Internal")),
+                ],
+            )),
+        }),
+    );
+
+    p.insert(
+        "/sync/push".into(),
+        json!({
+            "post": idempotent(op(
+                "sync",
+                "仅推送到 WebDAV；meta.dirty 未置位时为 no-op",
+                vec![],
+                None,
+                vec![
+                    ("200", ok_json("成功", schema_ref("StatusOk"))),
+                    ("500", err_ref("Internal")),
+                ],
+            )),
+        }),
+    );
+
+    p.insert(
+        "/audit".into(),
+        json!({
+            "get": op(
+                "audit",
+                "写请求审计记录，新的在前",
+                vec![
+                    query_param("since", "string", "只看此后的记录：YYYY-MM-DD 或 YYYY-MM-DD HH:MM:SS（服务端 timezone）"),
+                    query_param("key", "string", "按 API key 名过滤（目前只有 default）"),
+                    query_param("target", "string", "按目标过滤：todo / subtask id、图片名、view 名"),
+                    query_param("before", "integer", "翻页：只取 id 小于该值的记录"),
+                    query_param("limit", "integer", "条数，默认 100，最大 1000"),
+                ],
+                None,
+                vec![
+                    ("200", ok_json("审计记录", json!({"type": "array", "items": schema_ref("AuditEntry")}))),
+                    ("400", err_ref("BadRequest")),
+                ],
+            ),
+        }),
+    );
+
+    Value::Object(p)
+}
+
+/// `GET /todos` 的 query，对应 `todos::ListTodosQuery`。
+fn list_todos_params() -> Vec<Value> {
+    vec![
+        query_param("completed", "string", "true / false（也接受 1/0/yes/no）"),
+        query_param("priority", "string", "high / medium / low"),
+        query_param(
+            "quadrant",
+            "string",
+            "1..4 或别名 urgent_important / important_not_urgent / urgent_not_important / not_urgent_not_important",
+        ),
+        query_param("dueDateBefore", "string", "dueDate（缺省时取 endTime）≤ 该值"),
+        query_param("dueDateAfter", "string", "dueDate（缺省时取 endTime）≥ 该值"),
+        query_param("startDate", "string", "startTime 落在该日（YYYY-MM-DD）"),
+        query_param("q", "string", "title / description 关键字"),
+        query_param(
+            "tag",
+            "string",
+            "按标签过滤，可重复：tag=work&tag=-personal 表示 tags 含 work 且不含 personal（- 前缀为排除）",
+        ),
+        query_param(
+            "filter",
+            "string",
+            "过滤表达式，如 `quadrant in (1,2) and not completed and due < today+3d and text ~ \"report\"`；\
+             支持 and/or/not/括号、in / not in、~ 子串匹配、相对日期（today/tomorrow/yesterday/now ±Nd/w/h/m，\
+             按 config 时区求值）、子任务谓词（has openSubtasks、subtasks > 2）。语法错误 → 400，detail 指明列号",
+        ),
+        query_param(
+            "sort",
+            "string",
+            "逗号分隔多键 [+-]<field>，如 -priority,+dueDate；白名单 dueDate/startTime/priority/quadrant/sortOrder/updatedAt/createdAt/title",
+        ),
+        query_param("limit", "integer", "最多返回条数（> 0）"),
+        query_param("offset", "integer", "跳过条数（≥ 0）；与 cursor 互斥"),
+        query_param(
+            "cursor",
+            "string",
+            "keyset 分页游标；传空串取第一页。出现即返回 {items, nextCursor}，未指定 limit 时页大小 50",
+        ),
+        with_subtasks_param("默认 false；true 时嵌套 subtasks，否则返回 subtaskCount"),
+    ]
+}
+
+fn components() -> Value {
+    let mut c = json!({
+        "securitySchemes": {
+            "bearerAuth": {
+                "type": "http",
+                "scheme": "bearer",
+                "description": "config.toml 中的 api_key",
+            },
+        },
+        "parameters": {
+            "TodoId": {
+                "name": "id",
+                "in": "path",
+                "required": true,
+                "schema": {"type": "string"},
+                "description": "完整 i64 id，或 C{seq} 短码（大小写不敏感）",
+            },
+            "SubtaskId": {
+                "name": "id",
+                "in": "path",
+                "required": true,
+                "schema": {"type": "string"},
+                "description": "子任务 i64 id",
+            },
+            "ViewName": {
+                "name": "name",
+                "in": "path",
+                "required": true,
+                "schema": {"type": "string"},
+                "description": "saved view 名（URL 编码）",
+            },
+            "TemplateId": template_id_param(),
+            "TagName": tag_name_param(),
+            "IdempotencyKey": idempotency_key_param(),
+            "XTimezone": {
+                "name": "X-Timezone",
+                "in": "header",
+                "required": false,
+                "schema": {"type": "string", "example": "America/New_York"},
+                "description": "请求方 IANA 时区，决定 today / now / 明天下午3点 等相对时间的含义；缺省为服务端 timezone。落库时间戳仍按服务端 timezone",
+            },
+        },
+        "headers": {
+            "X-Sync-Status": {
+                "description": "healthy | stale | offline（按最近一次成功 pull 的时间判定）",
+                "schema": {"type": "string", "enum": ["healthy", "stale", "offline"]},
+            },
+            "X-Last-Sync-At": {
+                "description": "最近一次成功 pull 的本地时间（YYYY-MM-DD HH:MM:SS）；从未成功过时缺省",
+                "schema": {"type": "string"},
+            },
+            "X-Total-Count": {
+                "description": "满足过滤条件的总条数（忽略分页）",
+                "schema": {"type": "integer"},
+            },
+            "Link": {
+                "description": "RFC 8288；有下一页时 `</todos?...>; rel=\"next\"`（view 为 `/views/{name}/todos`）",
+                "schema": {"type": "string"},
+            },
+            "X-Request-Id": request_id_header(),
+            "Warning": {
+                "description": "offline 时附 `110 - \"sync offline\"`",
+                "schema": {"type": "string"},
+            },
+        },
+        "responses": {
+            "BadRequest": error_response("请求参数或 body 不合法"),
+            "Unauthorized": error_response("缺少或错误的 Bearer token"),
+            "TooManyRequests": too_many_requests_response(),
+            "NotFound": error_response("资源不存在"),
+            "ViewExists": error_response("同名 view 已存在"),
+            "TagExists": error_response("同名标签已登记"),
+            "PatchTestFailed": error_response("json patch 的 test op 不成立（资源已被改动）"),
+            "UnsupportedMediaType": error_response("Content-Type 不是支持的 PATCH 格式"),
+            "ValidationFailed": error_response("已知字段不满足 PC 端模型约束；errors 列出逐字段原因"),
+            "Internal": error_response("服务端错误"),
+            "IdempotencyKeyInUse": error_response("同一个 Idempotency-Key 的请求仍在处理中，稍后重试"),
+            "IdempotencyKeyReused": error_response("Idempotency-Key 已用于内容不同的请求（error=idempotency_key_reused）"),
+        },
+        "schemas": {
+            "Error": {
+                "type": "object",
+                "required": ["error", "detail"],
+                "properties": {
+                    "error": {"type": "string", "description": "机器可读错误码，如 bad_request / not_found"},
+                    "detail": {"type": "string"},
+                    "errors": {
+                        "type": "array",
+                        "description": "仅 422 validation_failed 时出现",
+                        "items": schema_ref("FieldError"),
+                    },
+                },
+            },
+            "FieldError": {
+                "type": "object",
+                "required": ["field", "message"],
+                "properties": {
+                    "field": {"type": "string", "description": "camelCase 字段名"},
+                    "message": {"type": "string"},
+                },
+            },
+            "Health": {
+                "type": "object",
+                "properties": {
+                    "status": {"type": "string"},
+                    "sync": {"type": "string", "enum": ["healthy", "stale", "offline"]},
+                    "lastPullAt": {"type": "string", "nullable": true},
+                },
+            },
+            "Ready": {
+                "type": "object",
+                "properties": {
+                    "status": {"type": "string", "enum": ["ready", "not_ready"]},
+                    "checks": {
+                        "type": "object",
+                        "properties": {
+                            "initialPull": {"type": "boolean"},
+                            "db": {"type": "boolean"},
+                        },
+                    },
+                },
+            },
+            "HealthDetails": health_details_schema(),
+            "Todo": todo_schema(),
+            "TodoPage": {
+                "type": "object",
+                "properties": {
+                    "items": {"type": "array", "items": schema_ref("Todo")},
+                    "nextCursor": {
+                        "type": "string",
+                        "nullable": true,
+                        "description": "下一页游标；null 表示已到末页",
+                    },
+                },
+            },
+            "TodoCreate": {
+                "allOf": [
+                    {"$ref": "#/components/schemas/TodoPatch"},
+                    {"type": "object", "required": ["title"]},
+                ],
+            },
+            "TodoPatch": todo_fields(),
+            "Subtask": {
+                "type": "object",
+                "additionalProperties": true,
+                "properties": subtask_properties(),
+            },
+            "SubtaskCreate": {
+                "type": "object",
+                "required": ["title"],
+                "additionalProperties": true,
+                "properties": {
+                    "title": {"type": "string"},
+                    "content": {"type": "string", "nullable": true},
+                    "completed": {"type": "boolean"},
+                    "sortOrder": {"type": "integer"},
+                },
+            },
+            "SubtaskPatch": {
+                "type": "object",
+                "additionalProperties": true,
+                "properties": {
+                    "title": {"type": "string"},
+                    "content": {"type": "string", "nullable": true},
+                    "completed": {"type": "boolean"},
+                    "sortOrder": {"type": "integer"},
+                },
+            },
+            "JsonPatch": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["op", "path"],
+                    "properties": {
+                        "op": {
+                            "type": "string",
+                            "enum": ["add", "remove", "replace", "move", "copy", "test"],
+                        },
+                        "path": {"type": "string", "description": "JSON Pointer（RFC 6901）"},
+                        "from": {"type": "string"},
+                        "value": {},
+                    },
+                },
+            },
+            "View": {
+                "allOf": [
+                    {"$ref": "#/components/schemas/ViewCreate"},
+                    {
+                        "type": "object",
+                        "properties": {
+                            "createdAt": {"type": "string"},
+                            "updatedAt": {"type": "string"},
+                        },
+                    },
+                ],
+            },
+            "ViewCreate": {
+                "allOf": [
+                    {"$ref": "#/components/schemas/ViewPatch"},
+                    {"type": "object", "required": ["name"]},
+                ],
+            },
+            "ViewPatch": {
+                "type": "object",
+                "additionalProperties": true,
+                "properties": {
+                    "name": {"type": "string", "maxLength": 64},
+                    "filter": {"type": "string", "nullable": true, "description": "同 GET /todos 的 filter 表达式"},
+                    "sort": {"type": "string", "nullable": true, "description": "同 GET /todos 的 sort，可多键"},
+                    "fields": {
+                        "type": "array",
+                        "nullable": true,
+                        "items": {"type": "string"},
+                        "description": "投影：只返回这些字段（id 总是保留）；null / 空为全部",
+                    },
+                    "withSubtasks": {"type": "boolean"},
+                },
+            },
+            "QuickAddReq": {
+                "type": "object",
+                "required": ["text"],
+                "properties": {
+                    "text": {
+                        "type": "string",
+                        "example": "明天下午3点 提交周报 #work !1 每周五提醒",
+                    },
+                },
+            },
+            "QuickAddResp": {
+                "type": "object",
+                "properties": {
+                    "todo": {"$ref": "#/components/schemas/Todo"},
+                    "parsed": {"$ref": "#/components/schemas/QuickAddParsed"},
+                },
+            },
+            "QuickAddParsed": quick_add_parsed_schema(),
+            "Stats": stats_schema(),
+            "AuditEntry": audit_entry_schema(),
+            "AppSettings": app_settings_schema(),
+            "ReorderReq": reorder_req_schema(),
+            "MoveSubtaskReq": move_subtask_req_schema(),
+            "UploadResp": {
+                "type": "object",
+                "properties": {"name": {"type": "string"}},
+            },
+            "SyncResp": {
+                "type": "object",
+                "properties": {
+                    "pull": {"type": "string", "enum": ["ok", "error"]},
+                    "push": {"type": "string", "enum": ["ok", "error"]},
+                    "pullError": {"type": "string"},
+                    "pushError": {"type": "string"},
+                },
+            },
+            "StatusOk": {
+                "type": "object",
+                "properties": {"status": {"type": "string", "enum": ["ok"]}},
+            },
+        },
+    });
+    // `json!` 展开单个对象时按条目递归，schemas 已接近宏的递归上限，后加的在这里插入
+    let schemas = c["schemas"]
+        .as_object_mut()
+        .expect("components.schemas 是 object");
+    schemas.insert("Template".into(), template_schema());
+    schemas.insert("TemplateCreate".into(), template_fields());
+    schemas.insert("InstantiateReq".into(), instantiate_req_schema());
+    schemas.insert("TagFields".into(), tag_fields());
+    schemas.insert("Tag".into(), tag_schema());
+    schemas.insert(
+        "DuplicateReq".into(),
+        json!({
+            "type": "object",
+            "properties": {
+                "shiftDays": {"type": "integer", "description": "日期字段平移天数，可为负；缺省 0"},
+            },
+        }),
+    );
+    c
+}
+
+/// PC 端 `Todo` 可写字段（camelCase）。未知字段透传，故 additionalProperties。
+fn todo_fields() -> Value {
+    json!({
+        "type": "object",
+        "additionalProperties": true,
+        "properties": {
+            "title": {"type": "string"},
+            "description": {"type": "string", "nullable": true},
+            "color": {"type": "string", "description": "#RGB 或 #RRGGBB"},
+            "quadrant": {"type": "integer", "minimum": 1, "maximum": 4},
+            "notifyAt": {"type": "string", "nullable": true},
+            "notifyBefore": {"type": "integer"},
+            "notified": {"type": "boolean"},
+            "completed": {"type": "boolean"},
+            "sortOrder": {"type": "integer"},
+            "startTime": {"type": "string", "nullable": true},
+            "endTime": {"type": "string", "nullable": true},
+            "completedAt": {"type": "string", "nullable": true, "description": "completed 置 true 时由服务端记录，置 false 时清空"},
+            "dueDate": {"type": "string", "nullable": true, "description": "截止日期 YYYY-MM-DD（也接受日期时间）；空串视同未设置，过滤 / 排序时缺省取 endTime"},
+            "priority": {"type": "string", "nullable": true, "enum": ["high", "medium", "low", null]},
+            "repeatEnabled": {"type": "boolean"},
+            "repeatType": {"type": "string", "nullable": true},
+            "repeatInterval": {"type": "integer"},
+            "repeatWeekdays": {"type": "string", "nullable": true},
+            "repeatMonthDay": {"type": "integer", "nullable": true},
+            "tags": {
+                "type": "array",
+                "items": {"type": "string"},
+                "description": "标签名；不能以 - 开头、不能含 / 或 ,，不能重复",
+            },
+        },
+    })
+}
+
+fn todo_schema() -> Value {
+    let mut v = todo_fields();
+    let props = v["properties"]
+        .as_object_mut()
+        .expect("todo_fields 是 object");
+    props.insert("id".into(), json!({"type": "integer", "format": "int64"}));
+    props.insert(
+        "seq".into(),
+        json!({"type": "integer", "description": "cloud-only 短码，C{seq} 可代替 id"}),
+    );
+    props.insert("createdAt".into(), json!({"type": "string"}));
+    props.insert("updatedAt".into(), json!({"type": "string"}));
+    props.insert(
+        "subtasks".into(),
+        json!({"type": "array", "items": schema_ref("Subtask")}),
+    );
+    props.insert(
+        "subtaskCount".into(),
+        json!({"type": "integer", "description": "未嵌套 subtasks 时返回"}),
+    );
+    v
+}
+
+/// `POST /todos/quick` 响应里的 `parsed`，对应 `minitodo_quickadd::QuickAdd`。
+fn quick_add_parsed_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "title": {"type": "string"},
+            "startTime": {"type": "string", "nullable": true},
+            "endTime": {"type": "string", "nullable": true},
+            "notifyAt": {"type": "string", "nullable": true},
+            "quadrant": {"type": "integer", "nullable": true},
+            "tags": {"type": "array", "items": {"type": "string"}},
+            "repeatEnabled": {"type": "boolean"},
+            "repeatType": {"type": "string", "nullable": true, "description": "daily / weekly / monthly"},
+            "repeatInterval": {"type": "integer", "nullable": true},
+            "repeatWeekdays": {"type": "string", "nullable": true},
+            "repeatMonthDay": {"type": "integer", "nullable": true},
+            "tokens": {
+                "type": "array",
+                "description": "识别出的片段（按出现顺序）",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "kind": {"type": "string", "enum": ["tag", "quadrant", "repeat", "date", "time"]},
+                        "text": {"type": "string"},
+                    },
+                },
+            },
+        },
+    })
+}
+
+/// `GET /health/details` 响应，对应 `health::DetailsResp`。
+fn health_details_schema() -> Value {
+    let s = json!({"type": "string", "nullable": true});
+    let dir = json!({
+        "type": "object",
+        "properties": {
+            "path": {"type": "string"},
+            "freeBytes": {"type": "integer", "format": "int64", "nullable": true},
+        },
+    });
+    json!({
+        "type": "object",
+        "properties": {
+            "status": {"type": "string", "enum": ["healthy", "degraded"]},
+            "version": {"type": "string"},
+            "ready": {"type": "boolean"},
+            "sync": {
+                "type": "object",
+                "properties": {
+                    "status": {"type": "string", "enum": ["healthy", "stale", "offline"]},
+                    "lastPullAt": s,
+                    "lastPullError": s,
+                    "lastPullErrorAt": s,
+                    "lastPushAt": s,
+                    "lastPushError": s,
+                    "lastPushErrorAt": s,
+                    "dirty": {"type": "boolean"},
+                    "dirtyGeneration": {"type": "integer"},
+                    "dirtyGenerationLag": {"type": "integer"},
+                    "dirtyImages": {"type": "integer"},
+                },
+            },
+            "tombstones": {"type": "integer"},
+            "webdav": {
+                "type": "object",
+                "properties": {
+                    "reachable": {"type": "boolean"},
+                    "status": {"type": "integer", "nullable": true},
+                    "latencyMs": {"type": "integer"},
+                    "error": s,
+                },
+            },
+            "disk": {
+                "type": "object",
+                "properties": {"dataDir": dir, "imagesDir": dir},
+            },
+        },
+    })
+}
+
+/// `GET /stats` 响应，对应 `db::stats::Stats`。
+fn stats_schema() -> Value {
+    let count = json!({"type": "integer"});
+    json!({
+        "type": "object",
+        "properties": {
+            "from": {"type": "string", "format": "date"},
+            "to": {"type": "string", "format": "date"},
+            "completed": {
+                "type": "object",
+                "description": "按 completedAt 落在区间内计数",
+                "properties": {
+                    "total": count,
+                    "perDay": {"type": "array", "items": {"type": "object", "properties": {
+                        "date": {"type": "string", "format": "date"},
+                        "count": count,
+                    }}},
+                    "perWeek": {"type": "array", "items": {"type": "object", "properties": {
+                        "weekStart": {"type": "string", "format": "date", "description": "周一"},
+                        "count": count,
+                    }}},
+                },
+            },
+            "created": count,
+            "avgCompletionHours": {"type": "number", "nullable": true},
+            "overdue": {
+                "type": "object",
+                "properties": {
+                    "open": {"type": "integer", "description": "当前未完成且已过截止"},
+                    "completedLate": {"type": "integer", "description": "区间内逾期完成"},
+                },
+            },
+            "byQuadrant": {"type": "array", "items": {"type": "object", "properties": {
+                "quadrant": count,
+                "open": count,
+                "completed": count,
+                "overdue": count,
+            }}},
+            "subtasks": {
+                "type": "object",
+                "properties": {
+                    "total": count,
+                    "completed": count,
+                    "ratio": {"type": "number", "nullable": true},
+                },
+            },
+        },
+    })
+}
+
+/// `GET /audit` 的单条记录，对应 `db::audit::AuditEntry`。
+fn audit_entry_schema() -> Value {
+    let summary = json!({
+        "type": "object",
+        "nullable": true,
+        "description": "新建 / 删除时是标识字段（id、title 等），修改时是变了的字段；长文本截断",
+    });
+    json!({
+        "type": "object",
+        "properties": {
+            "id": {"type": "integer", "format": "int64"},
+            "at": {"type": "string", "description": "服务端 timezone 的本地时间"},
+            "requestId": {"type": "string", "description": "对应响应的 X-Request-Id"},
+            "keyName": {"type": "string"},
+            "clientIp": {"type": "string", "nullable": true},
+            "userAgent": {"type": "string", "nullable": true},
+            "method": {"type": "string"},
+            "route": {"type": "string", "description": "路由模板，如 /todos/:id"},
+            "path": {"type": "string"},
+            "target": {"type": "string", "nullable": true},
+            "status": {"type": "integer"},
+            "before": summary,
+            "after": summary,
+        },
+    })
+}
+
+/// `/settings` 暴露的字段，取值与 `validate::validate_settings` 一致。
+fn app_settings_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "viewMode": {"type": "string", "enum": ["list", "quadrant"]},
+            "textTheme": {"type": "string", "enum": ["light", "dark"]},
+            "notificationType": {"type": "string", "enum": ["system", "app"]},
+            "showCalendar": {"type": "boolean"},
+            "windowBgColor": {"type": "string", "description": "#RGB 或 #RRGGBB"},
+            "windowBgAlpha": {"type": "number", "minimum": 0, "maximum": 1},
+        },
+    })
+}
+
+fn reorder_req_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["ids"],
+        "properties": {
+            "ids": {
+                "type": "array",
+                "minItems": 1,
+                "items": {"oneOf": [{"type": "integer", "format": "int64"}, {"type": "string"}]},
+                "description": "新顺序，下标即 sortOrder",
+            },
+        },
+    })
+}
+
+fn move_subtask_req_schema() -> Value {
+    json!({
+        "type": "object",
+        "required": ["todoId"],
+        "properties": {
+            "todoId": {
+                "oneOf": [{"type": "integer", "format": "int64"}, {"type": "string"}],
+                "description": "目标 todo，支持 C 短码",
+            },
+            "sortOrder": {"type": "integer", "minimum": 0},
+        },
+    })
+}
+
+/// 模板可写字段，与 `validate::validate_template` 一致。
+fn template_fields() -> Value {
+    json!({
+        "type": "object",
+        "additionalProperties": true,
+        "properties": {
+            "name": {"type": "string"},
+            "title": {"type": "string", "example": "{{week}} 发版检查"},
+            "description": {"type": "string", "nullable": true},
+            "color": {"type": "string", "description": "#RGB 或 #RRGGBB"},
+            "quadrant": {"type": "integer", "minimum": 1, "maximum": 4},
+            "subtasks": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["title"],
+                    "properties": {
+                        "title": {"type": "string"},
+                        "content": {"type": "string", "nullable": true},
+                    },
+                },
+            },
+        },
+    })
+}
+
+fn template_schema() -> Value {
+    let mut v = template_fields();
+    let props = v["properties"]
+        .as_object_mut()
+        .expect("template_fields 是 object");
+    props.insert("id".into(), json!({"type": "integer", "format": "int64"}));
+    props.insert("createdAt".into(), json!({"type": "string"}));
+    props.insert("updatedAt".into(), json!({"type": "string"}));
+    v["required"] = json!(["id", "name", "title", "color", "quadrant", "subtasks"]);
+    v
+}
+
+fn tag_fields() -> Value {
+    json!({
+        "type": "object",
+        "additionalProperties": true,
+        "properties": {
+            "name": {"type": "string", "maxLength": minitodo_model::MAX_TAG_LEN, "description": "不能以 - 开头、不能含 / 或 ,"},
+            "color": {"type": "string", "description": "#RGB 或 #RRGGBB；缺省 #6B7280"},
+        },
+    })
+}
+
+fn tag_schema() -> Value {
+    let mut v = tag_fields();
+    let props = v["properties"]
+        .as_object_mut()
+        .expect("tag_fields 是 object");
+    props.insert(
+        "createdAt".into(),
+        json!({"type": "string", "description": "未登记的标签没有"}),
+    );
+    props.insert(
+        "updatedAt".into(),
+        json!({"type": "string", "description": "未登记的标签没有"}),
+    );
+    props.insert(
+        "count".into(),
+        json!({"type": "integer", "description": "tags 含该标签的 todo 数"}),
+    );
+    v["required"] = json!(["name", "color", "count"]);
+    v
+}
+
+fn instantiate_req_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "params": {
+                "type": "object",
+                "additionalProperties": {"type": "string"},
+                "description": "占位符的值，如 {\"name\": \"小王\"}；也可覆盖内置的 date / week",
+            },
+        },
+    })
+}
+
+fn subtask_properties() -> Value {
+    json!({
+        "id": {"type": "integer", "format": "int64"},
+        "parentId": {"type": "integer", "format": "int64"},
+        "title": {"type": "string"},
+        "content": {"type": "string", "nullable": true},
+        "completed": {"type": "boolean"},
+        "sortOrder": {"type": "integer"},
+        "createdAt": {"type": "string"},
+        "updatedAt": {"type": "string"},
+    })
+}
+
+// =============================================================================
+// 构造工具
+// =============================================================================
+
+/// 不经过 auth 的 operation：去掉 `op` 默认加的 401 / 429，并清空全局 security。
+fn public(mut op: Value) -> Value {
+    if let Some(o) = op.as_object_mut() {
+        if let Some(r) = o.get_mut("responses").and_then(Value::as_object_mut) {
+            r.remove("401");
+            r.remove("429");
+        }
+        o.insert("security".into(), json!([]));
+    }
+    op
+}
+
+/// 支持 `Idempotency-Key` 的 POST（见 `idempotency`）：加请求头参数与 409 / 422。
+/// 已有的 422（字段校验）保留，参数说明里提到 key 重用同样是 422。
+fn idempotent(mut op: Value) -> Value {
+    if let Some(o) = op.as_object_mut() {
+        let params = o.entry("parameters").or_insert_with(|| json!([]));
+        if let Some(arr) = params.as_array_mut() {
+            arr.push(param_ref("IdempotencyKey"));
+        }
+        if let Some(r) = o.get_mut("responses").and_then(Value::as_object_mut) {
+            r.entry("409")
+                .or_insert_with(|| err_ref("IdempotencyKeyInUse"));
+            r.entry("422")
+                .or_insert_with(|| err_ref("IdempotencyKeyReused"));
+        }
+    }
+    op
+}
+
+/// 单个 operation；所有 operation 都可能 401（auth 中间件）与 429（限流）。
+fn op(
+    tag: &str,
+    summary: &str,
+    params: Vec<Value>,
+    body: Option<Value>,
+    responses: Vec<(&str, Value)>,
+) -> Value {
+    let mut resp = Map::new();
+    for (code, r) in responses {
+        resp.insert(code.to_string(), r);
+    }
+    resp.entry("401").or_insert_with(|| err_ref("Unauthorized"));
+    resp.entry("429")
+        .or_insert_with(|| err_ref("TooManyRequests"));
+
+    let mut o = Map::new();
+    o.insert("tags".into(), json!([tag]));
+    o.insert("summary".into(), json!(summary));
+    if !params.is_empty() {
+        o.insert("parameters".into(), Value::Array(params));
+    }
+    if let Some(b) = body {
+        o.insert("requestBody".into(), b);
+    }
+    o.insert("responses".into(), Value::Object(resp));
+    Value::Object(o)
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({"$ref": format!("#/components/schemas/{}", name)})
+}
+
+fn param_ref(name: &str) -> Value {
+    json!({"$ref": format!("#/components/parameters/{}", name)})
+}
+
+fn err_ref(name: &str) -> Value {
+    json!({"$ref": format!("#/components/responses/{}", name)})
+}
+
+fn query_param(name: &str, ty: &str, description: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "required": false,
+        "schema": {"type": ty},
+        "description": description,
+    })
+}
+
+fn with_subtasks_param(description: &str) -> Value {
+    query_param("withSubtasks", "boolean", description)
+}
+
+fn json_body(schema: Value) -> Value {
+    json!({
+        "required": true,
+        "content": {"application/json": {"schema": schema}},
+    })
+}
+
+/// PATCH body：三种 Content-Type 分别对应浅合并 / RFC 7396 / RFC 6902。
+fn patch_body(schema: Value) -> Value {
+    json!({
+        "required": true,
+        "content": {
+            "application/json": {"schema": schema.clone()},
+            "application/merge-patch+json": {"schema": schema},
+            "application/json-patch+json": {"schema": schema_ref("JsonPatch")},
+        },
+    })
+}
+
+/// 每个响应都附 sync header（`inject_sync_headers` 是最外层中间件）。
+fn with_sync_headers(mut resp: Value) -> Value {
+    resp["headers"] = json!({
+        "X-Sync-Status": {"$ref": "#/components/headers/X-Sync-Status"},
+        "X-Last-Sync-At": {"$ref": "#/components/headers/X-Last-Sync-At"},
+        "Warning": {"$ref": "#/components/headers/Warning"},
+        "X-Request-Id": {"$ref": "#/components/headers/X-Request-Id"},
+    });
+    resp
+}
+
+/// `GET /todos` 额外的分页 header。
+fn with_paging_headers(mut resp: Value) -> Value {
+    resp["headers"]["X-Total-Count"] = json!({"$ref": "#/components/headers/X-Total-Count"});
+    resp["headers"]["Link"] = json!({"$ref": "#/components/headers/Link"});
+    resp
+}
+
+fn ok_json(description: &str, schema: Value) -> Value {
+    with_sync_headers(json!({
+        "description": description,
+        "content": {"application/json": {"schema": schema}},
+    }))
+}
+
+fn no_content(description: &str) -> Value {
+    with_sync_headers(json!({"description": description}))
+}
+
+fn error_response(description: &str) -> Value {
+    ok_json(description, schema_ref("Error"))
+}
+
+/// 重试安全的 POST 可带的请求头。
+fn idempotency_key_param() -> Value {
+    json!({
+        "name": "Idempotency-Key",
+        "in": "header",
+        "required": false,
+        "schema": {"type": "string", "maxLength": 255},
+        "description": "客户端生成的唯一值（如 UUID）。窗口期内用同一个 key 重试会原样返回首次响应\
+            （带 Idempotent-Replayed: true），不会重复创建；key 用于内容不同的请求 → 422",
+    })
+}
+
+/// 每个响应都带；请求里给了合法值（1~128 个可见 ASCII 字符）则原样返回。
+fn template_id_param() -> Value {
+    json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "schema": {"type": "string"},
+        "description": "模板 i64 id",
+    })
+}
+
+fn tag_name_param() -> Value {
+    json!({
+        "name": "name",
+        "in": "path",
+        "required": true,
+        "schema": {"type": "string"},
+        "description": "标签名（URL 编码）",
+    })
+}
+
+fn request_id_header() -> Value {
+    json!({
+        "description": "本次请求的 ID，出现在服务端日志与 /audit 记录里；请求可自带",
+        "schema": {"type": "string"},
+    })
+}
+
+/// 429：令牌桶耗尽，或该 IP 因连续无效 api_key 被临时封禁。
+fn too_many_requests_response() -> Value {
+    let mut r = error_response(
+        "请求过于频繁（error=rate_limited）；或该 IP 连续使用无效 api_key 被临时封禁",
+    );
+    r["headers"] = json!({
+        "Retry-After": {
+            "description": "至少等待的秒数",
+            "schema": {"type": "integer"},
+        },
+        "RateLimit-Limit": {
+            "description": "该类路由的桶容量（封禁时缺省）",
+            "schema": {"type": "integer"},
+        },
+        "RateLimit-Remaining": {
+            "description": "剩余令牌数；放行的响应也带",
+            "schema": {"type": "integer"},
+        },
+        "RateLimit-Reset": {
+            "description": "桶补满还需的秒数",
+            "schema": {"type": "integer"},
+        },
+        "RateLimit-Policy": {
+            "description": "`<limit>;w=<窗口秒数>`",
+            "schema": {"type": "string"},
+        },
+    });
+    r
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 三张路由表里的 (path, method)，path 换成 OpenAPI 的 `{id}` 写法。
+    fn registered_routes() -> Vec<(String, String)> {
+        use crate::api::{METRICS_ROUTES, PROBE_ROUTES, ROUTES};
+        ROUTES
+            .iter()
+            .chain(PROBE_ROUTES)
+            .chain(METRICS_ROUTES)
+            .map(|r| {
+                let path = r
+                    .path
+                    .split('/')
+                    .map(|seg| match seg.strip_prefix(':') {
+                        Some(name) => format!("{{{}}}", name),
+                        None => seg.to_string(),
+                    })
+                    .collect::<Vec<_>>()
+                    .join("/");
+                (path, r.method_name().to_ascii_lowercase())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn every_route_is_documented() {
+        let spec = spec();
+        let paths = spec["paths"].as_object().unwrap();
+        let mut missing = Vec::new();
+        for (path, method) in registered_routes() {
+            if paths.get(&path).and_then(|p| p.get(&method)).is_none() {
+                missing.push(format!("{} {}", method.to_uppercase(), path));
+            }
+        }
+        assert!(missing.is_empty(), "未写进 OpenAPI 的路由: {:?}", missing);
+    }
+
+    /// 取 `#[derive(Deserialize)]` 传给 `deserialize_struct` 的字段名：已按
+    /// `rename_all` 改名、不含 `#[serde(skip)]`，即 query 串里认的参数名。
+    fn query_fields<T: serde::de::DeserializeOwned>() -> Vec<&'static str> {
+        use serde::de::{Error, Visitor};
+
+        struct Introspect<'a>(&'a mut &'static [&'static str]);
+
+        impl<'de> serde::Deserializer<'de> for Introspect<'_> {
+            type Error = serde::de::value::Error;
+
+            fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+                Err(Error::custom("query 参数必须是 struct"))
+            }
+
+            fn deserialize_struct<V: Visitor<'de>>(
+                self,
+                _name: &'static str,
+                fields: &'static [&'static str],
+                _visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                *self.0 = fields;
+                Err(Error::custom("只取字段名"))
+            }
+
+            serde::forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+                bytes byte_buf option unit unit_struct newtype_struct seq tuple
+                tuple_struct map enum identifier ignored_any
+            }
+        }
+
+        let mut fields: &'static [&'static str] = &[];
+        let _ = T::deserialize(Introspect(&mut fields));
+        assert!(
+            !fields.is_empty(),
+            "{} 没有字段",
+            std::any::type_name::<T>()
+        );
+        fields.to_vec()
+    }
+
+    /// 每个 `Query<T>` extractor 的字段都写进了对应 operation 的 query 参数。
+    #[test]
+    fn query_structs_are_documented() {
+        use crate::api::audit::AuditQuery;
+        use crate::api::stats::StatsQuery;
+        use crate::api::todos::{DemoteQuery, GetTodoQuery, ListTodosQuery};
+        use crate::api::views::ViewTodosQuery;
+
+        let mut list_todos = query_fields::<ListTodosQuery>();
+        // `tag` 可重复，`#[serde(skip)]` 后由 list_todos 从原始 query 串解析
+        list_todos.push("tag");
+        let cases = [
+            ("/todos", "get", list_todos),
+            ("/todos/{id}", "get", query_fields::<GetTodoQuery>()),
+            ("/todos/{id}/demote", "post", query_fields::<DemoteQuery>()),
+            (
+                "/views/{name}/todos",
+                "get",
+                query_fields::<ViewTodosQuery>(),
+            ),
+            ("/stats", "get", query_fields::<StatsQuery>()),
+            ("/audit", "get", query_fields::<AuditQuery>()),
+        ];
+
+        let spec = spec();
+        for (path, method, fields) in cases {
+            let documented: Vec<&str> = spec["paths"][path][method]["parameters"]
+                .as_array()
+                .unwrap_or_else(|| panic!("{} {} 没有参数", method, path))
+                .iter()
+                .filter(|p| p["in"] == "query")
+                .filter_map(|p| p["name"].as_str())
+                .collect();
+            for field in fields {
+                assert!(
+                    documented.contains(&field),
+                    "{} {} 的 query {} 未写进 OpenAPI",
+                    method.to_uppercase(),
+                    path,
+                    field
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn all_refs_resolve() {
+        let spec = spec();
+        let mut refs = Vec::new();
+        collect_refs(&spec, &mut refs);
+        for r in refs {
+            let pointer = r.trim_start_matches('#');
+            assert!(spec.pointer(pointer).is_some(), "悬空 $ref: {}", r);
+        }
+    }
+
+    fn collect_refs(v: &Value, out: &mut Vec<String>) {
+        match v {
+            Value::Object(m) => {
+                for (k, child) in m {
+                    if k == "$ref" {
+                        if let Some(s) = child.as_str() {
+                            out.push(s.to_string());
+                        }
+                    } else {
+                        collect_refs(child, out);
+                    }
+                }
+            }
+            Value::Array(a) => a.iter().for_each(|c| collect_refs(c, out)),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,417 @@
+//! PATCH body 的三种语义，按请求 `Content-Type` 分派：
+//!
+//! - `application/json`：浅合并（历史行为）——body 的 top-level 字段覆盖
+//!   `data_json`，`null` 显式写入
+//! - `application/merge-patch+json`：RFC 7396 深合并，`null` 删除字段
+//! - `application/json-patch+json`：RFC 6902 操作列表（add / remove /
+//!   replace / move / copy / test），整体原子：任一 op 失败则不落任何修改
+//!
+//! 三种方式都不允许改 `id`（handler 在 apply 后强制写回），也不允许把整个
+//! record 替换成非 object。
+
+use axum::http::{header, HeaderMap, StatusCode};
+use serde_json::{Map, Value};
+
+use super::error::ApiError;
+
+pub const MERGE_PATCH: &str = "application/merge-patch+json";
+pub const JSON_PATCH: &str = "application/json-patch+json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchKind {
+    Shallow,
+    Merge,
+    Json,
+}
+
+impl PatchKind {
+    /// 按 `Content-Type` 选择语义；忽略 `; charset=...` 参数，大小写不敏感。
+    pub fn from_headers(headers: &HeaderMap) -> Result<Self, ApiError> {
+        let raw = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or("");
+        let mime = raw
+            .split(';')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase();
+        match mime.as_str() {
+            "application/json" => Ok(Self::Shallow),
+            MERGE_PATCH => Ok(Self::Merge),
+            JSON_PATCH => Ok(Self::Json),
+            _ => Err(ApiError::new(
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_media_type",
+                format!(
+                    "Content-Type must be application/json, {} or {}",
+                    MERGE_PATCH, JSON_PATCH
+                ),
+            )),
+        }
+    }
+}
+
+/// 把 `body` 按 `kind` 应用到 `target`（一个 JSON object）。失败时 `target`
+/// 保持原样。
+pub fn apply(kind: PatchKind, target: &mut Value, body: &Value) -> Result<(), ApiError> {
+    match kind {
+        PatchKind::Shallow => {
+            if !body.is_object() {
+                return Err(ApiError::bad_request("body must be a JSON object"));
+            }
+            merge_json_shallow(target, body);
+            Ok(())
+        }
+        PatchKind::Merge => {
+            if !body.is_object() {
+                return Err(ApiError::bad_request(
+                    "merge patch must be a JSON object (record cannot be replaced)",
+                ));
+            }
+            merge_patch(target, body);
+            Ok(())
+        }
+        PatchKind::Json => {
+            let Value::Array(ops) = body else {
+                return Err(ApiError::bad_request(
+                    "json patch must be an array of operations",
+                ));
+            };
+            let mut work = target.clone();
+            for (i, op) in ops.iter().enumerate() {
+                apply_op(&mut work, op).map_err(|e| e.at(i))?;
+            }
+            if !work.is_object() {
+                return Err(ApiError::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "patch_failed",
+                    "patch result must remain a JSON object",
+                ));
+            }
+            *target = work;
+            Ok(())
+        }
+    }
+}
+
+/// 浅合并：把 `patch` 的 top-level 字段覆盖到 `target`；patch 中的 `null` 也写入
+/// （表示显式置空）。这与 PC 端 PATCH 语义对齐。
+pub fn merge_json_shallow(target: &mut Value, patch: &Value) {
+    let (Value::Object(t), Value::Object(p)) = (target, patch) else {
+        return;
+    };
+    for (k, v) in p {
+        if k == "id" {
+            continue; // id 不能改
+        }
+        t.insert(k.clone(), v.clone());
+    }
+}
+
+/// RFC 7396 §2 的 MergePatch 算法。
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(p) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    let Value::Object(t) = target else {
+        unreachable!()
+    };
+    for (k, v) in p {
+        if v.is_null() {
+            t.remove(k);
+        } else {
+            merge_patch(t.entry(k.clone()).or_insert(Value::Null), v);
+        }
+    }
+}
+
+// =============================================================================
+// RFC 6902
+// =============================================================================
+
+/// 单个 op 的失败原因；`at` 补上 op 下标后转 `ApiError`。
+enum OpError {
+    /// `test` 不成立：资源当前状态与客户端预期不符 → 409
+    TestFailed(String),
+    /// op 格式错误 / 路径不存在等 → 422
+    Invalid(String),
+}
+
+impl OpError {
+    fn at(self, index: usize) -> ApiError {
+        match self {
+            OpError::TestFailed(m) => ApiError::new(
+                StatusCode::CONFLICT,
+                "patch_test_failed",
+                format!("op[{}]: {}", index, m),
+            ),
+            OpError::Invalid(m) => ApiError::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "patch_failed",
+                format!("op[{}]: {}", index, m),
+            ),
+        }
+    }
+}
+
+fn apply_op(doc: &mut Value, op: &Value) -> Result<(), OpError> {
+    let name = op
+        .get("op")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| OpError::Invalid("missing \"op\"".into()))?;
+    let path = str_member(op, "path")?;
+    match name {
+        "add" => add(doc, path, value_member(op)?.clone()),
+        "remove" => remove(doc, path).map(|_| ()),
+        "replace" => {
+            let v = value_member(op)?.clone();
+            let slot = doc
+                .pointer_mut(path)
+                .ok_or_else(|| OpError::Invalid(format!("path {} does not exist", path)))?;
+            *slot = v;
+            Ok(())
+        }
+        "move" => {
+            let from = str_member(op, "from")?;
+            if path.starts_with(from) && path[from.len()..].starts_with('/') {
+                return Err(OpError::Invalid(format!(
+                    "cannot move {} into its own child {}",
+                    from, path
+                )));
+            }
+            let v = remove(doc, from)?;
+            add(doc, path, v)
+        }
+        "copy" => {
+            let from = str_member(op, "from")?;
+            let v = doc
+                .pointer(from)
+                .cloned()
+                .ok_or_else(|| OpError::Invalid(format!("path {} does not exist", from)))?;
+            add(doc, path, v)
+        }
+        "test" => {
+            let expected = value_member(op)?;
+            match doc.pointer(path) {
+                Some(actual) if actual == expected => Ok(()),
+                Some(actual) => Err(OpError::TestFailed(format!(
+                    "test {} failed: expected {}, found {}",
+                    path, expected, actual
+                ))),
+                None => Err(OpError::TestFailed(format!(
+                    "test {} failed: path does not exist",
+                    path
+                ))),
+            }
+        }
+        other => Err(OpError::Invalid(format!("unknown op {:?}", other))),
+    }
+}
+
+fn str_member<'a>(op: &'a Value, key: &str) -> Result<&'a str, OpError> {
+    let s = op
+        .get(key)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| OpError::Invalid(format!("missing \"{}\"", key)))?;
+    if !s.is_empty() && !s.starts_with('/') {
+        return Err(OpError::Invalid(format!("invalid JSON pointer {:?}", s)));
+    }
+    Ok(s)
+}
+
+fn value_member(op: &Value) -> Result<&Value, OpError> {
+    op.get("value")
+        .ok_or_else(|| OpError::Invalid("missing \"value\"".into()))
+}
+
+/// 拆出父路径与最后一段 token（已反转义 `~1` / `~0`）。
+fn split_pointer(path: &str) -> Result<(&str, String), OpError> {
+    let idx = path
+        .rfind('/')
+        .ok_or_else(|| OpError::Invalid("cannot operate on the whole document".into()))?;
+    let token = path[idx + 1..].replace("~1", "/").replace("~0", "~");
+    Ok((&path[..idx], token))
+}
+
+fn add(doc: &mut Value, path: &str, v: Value) -> Result<(), OpError> {
+    if path.is_empty() {
+        *doc = v;
+        return Ok(());
+    }
+    let (parent, token) = split_pointer(path)?;
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(m)) => {
+            m.insert(token, v);
+            Ok(())
+        }
+        Some(Value::Array(a)) => {
+            let i = if token == "-" {
+                a.len()
+            } else {
+                array_index(&token, a.len() + 1)?
+            };
+            a.insert(i, v);
+            Ok(())
+        }
+        Some(_) => Err(OpError::Invalid(format!(
+            "parent of {} is not a container",
+            path
+        ))),
+        None => Err(OpError::Invalid(format!("path {} does not exist", parent))),
+    }
+}
+
+fn remove(doc: &mut Value, path: &str) -> Result<Value, OpError> {
+    let (parent, token) = split_pointer(path)?;
+    let missing = || OpError::Invalid(format!("path {} does not exist", path));
+    match doc.pointer_mut(parent) {
+        Some(Value::Object(m)) => m.remove(&token).ok_or_else(missing),
+        Some(Value::Array(a)) => {
+            let i = array_index(&token, a.len())?;
+            Ok(a.remove(i))
+        }
+        _ => Err(missing()),
+    }
+}
+
+/// RFC 6902 §4：数组下标不得有前导 0，且必须 < `bound`。
+fn array_index(token: &str, bound: usize) -> Result<usize, OpError> {
+    let ok = !token.is_empty()
+        && token.chars().all(|c| c.is_ascii_digit())
+        && (token == "0" || !token.starts_with('0'));
+    let i = ok
+        .then(|| token.parse::<usize>().ok())
+        .flatten()
+        .ok_or_else(|| OpError::Invalid(format!("invalid array index {:?}", token)))?;
+    if i >= bound {
+        return Err(OpError::Invalid(format!("array index {} out of bounds", i)));
+    }
+    Ok(i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn json_patch(doc: Value, ops: Value) -> Result<Value, ApiError> {
+        let mut d = doc;
+        apply(PatchKind::Json, &mut d, &ops)?;
+        Ok(d)
+    }
+
+    #[test]
+    fn content_type_dispatch() {
+        let mut h = HeaderMap::new();
+        h.insert(
+            header::CONTENT_TYPE,
+            "application/json; charset=utf-8".parse().unwrap(),
+        );
+        assert_eq!(PatchKind::from_headers(&h).unwrap(), PatchKind::Shallow);
+        h.insert(
+            header::CONTENT_TYPE,
+            "Application/Merge-Patch+JSON".parse().unwrap(),
+        );
+        assert_eq!(PatchKind::from_headers(&h).unwrap(), PatchKind::Merge);
+        h.insert(header::CONTENT_TYPE, JSON_PATCH.parse().unwrap());
+        assert_eq!(PatchKind::from_headers(&h).unwrap(), PatchKind::Json);
+        h.insert(header::CONTENT_TYPE, "text/plain".parse().unwrap());
+        let err = PatchKind::from_headers(&h).unwrap_err();
+        assert_eq!(err.status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+
+    #[test]
+    fn merge_patch_rfc7396_examples() {
+        // RFC 7396 附录 A 的部分用例
+        let mut t = json!({"a": "b", "c": {"d": "e", "f": "g"}});
+        merge_patch(&mut t, &json!({"a": "z", "c": {"f": null}}));
+        assert_eq!(t, json!({"a": "z", "c": {"d": "e"}}));
+
+        let mut t = json!({"a": [{"b": "c"}]});
+        merge_patch(&mut t, &json!({"a": [1]}));
+        assert_eq!(t, json!({"a": [1]}));
+
+        let mut t = json!({"e": null});
+        merge_patch(&mut t, &json!({"a": 1}));
+        assert_eq!(t, json!({"e": null, "a": 1}));
+    }
+
+    #[test]
+    fn json_patch_basic_ops() {
+        let doc = json!({"title": "a", "meta": {"tags": ["x"]}, "old": 1});
+        let out = json_patch(
+            doc,
+            json!([
+                {"op": "test", "path": "/title", "value": "a"},
+                {"op": "replace", "path": "/title", "value": "b"},
+                {"op": "add", "path": "/meta/tags/-", "value": "y"},
+                {"op": "add", "path": "/meta/tags/0", "value": "w"},
+                {"op": "move", "from": "/old", "path": "/new"},
+                {"op": "copy", "from": "/title", "path": "/copy"},
+                {"op": "remove", "path": "/meta/tags/1"},
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            out,
+            json!({"title": "b", "meta": {"tags": ["w", "y"]}, "new": 1, "copy": "b"})
+        );
+    }
+
+    #[test]
+    fn json_patch_escaped_pointer() {
+        let out = json_patch(
+            json!({}),
+            json!([{"op": "add", "path": "/a~1b~0c", "value": 1}]),
+        )
+        .unwrap();
+        assert_eq!(out, json!({"a/b~c": 1}));
+    }
+
+    #[test]
+    fn json_patch_failed_test_is_conflict_and_atomic() {
+        let mut doc = json!({"completed": false, "title": "a"});
+        let err = apply(
+            PatchKind::Json,
+            &mut doc,
+            &json!([
+                {"op": "replace", "path": "/title", "value": "b"},
+                {"op": "test", "path": "/completed", "value": true},
+            ]),
+        )
+        .unwrap_err();
+        assert_eq!(err.status, StatusCode::CONFLICT);
+        assert!(err.detail.starts_with("op[1]"));
+        // 第一个 op 不生效
+        assert_eq!(doc["title"], "a");
+    }
+
+    #[test]
+    fn json_patch_invalid_ops_are_422() {
+        for ops in [
+            json!([{"op": "replace", "path": "/missing", "value": 1}]),
+            json!([{"op": "remove", "path": "/arr/01"}]),
+            json!([{"op": "frobnicate", "path": "/a"}]),
+            json!([{"op": "add", "path": "", "value": [1]}]),
+            json!([{"op": "move", "from": "/o", "path": "/o/x"}]),
+        ] {
+            let err = json_patch(json!({"arr": [1, 2], "o": {}}), ops.clone()).unwrap_err();
+            assert_eq!(err.status, StatusCode::UNPROCESSABLE_ENTITY, "{}", ops);
+        }
+    }
+
+    #[test]
+    fn wrong_body_shape_is_400() {
+        let mut doc = json!({});
+        let err = apply(PatchKind::Json, &mut doc, &json!({"op": "add"})).unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+        let err = apply(PatchKind::Merge, &mut doc, &json!([1])).unwrap_err();
+        assert_eq!(err.status, StatusCode::BAD_REQUEST);
+    }
+}
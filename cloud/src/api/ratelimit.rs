@@ -0,0 +1,462 @@
+//! 限流与暴力破解防护。
+//!
+//! - 令牌桶，按路由类别（reads / writes / uploads / sync，见
+//!   [`crate::config::RateLimits`]）分开计。持有正确 api_key 的请求同时扣
+//!   "key + IP" 桶（同一把 key 在不同设备上互不挤占）和该 key 的总桶（容量是
+//!   类别额度的 [`KEY_AGGREGATE_FACTOR`] 倍，换再多 IP 也有上限），其余按 IP 计
+//! - 同一 IP 连续 `auth_max_failures` 次无效 api_key 后封禁 `auth_lockout_secs`，
+//!   期间该 IP 不带 key 或带错 key 的请求直接 429。带正确 key 的请求不受封禁
+//!   影响：反代后面所有客户端可能共用一个 IP，不能让别人猜几次 key 就把主人
+//!   锁在门外；成功也不清失败计数，否则主人的正常请求会一直替攻击者重置
+//! - 被限流的响应：429 + `Retry-After`；放行的响应带 `RateLimit-Limit` /
+//!   `RateLimit-Remaining` / `RateLimit-Reset` / `RateLimit-Policy`
+//!   （draft-ietf-httpapi-ratelimit-headers）
+//!
+//! 探针路由（`/health/live`、`/health/ready`）不经过这一层。限流参数每个请求
+//! 从 `LiveConfig` 读，SIGHUP 后立即生效；桶状态只在内存里，重启清零。
+
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{ConnectInfo, Request, State};
+use axum::http::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER};
+use axum::http::{Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use super::auth;
+use super::error::ApiError;
+use super::AppState;
+use crate::config::{RateLimits, RateSpec};
+
+pub const RATELIMIT_LIMIT: HeaderName = HeaderName::from_static("ratelimit-limit");
+pub const RATELIMIT_REMAINING: HeaderName = HeaderName::from_static("ratelimit-remaining");
+pub const RATELIMIT_RESET: HeaderName = HeaderName::from_static("ratelimit-reset");
+pub const RATELIMIT_POLICY: HeaderName = HeaderName::from_static("ratelimit-policy");
+
+const X_FORWARDED_FOR: HeaderName = HeaderName::from_static("x-forwarded-for");
+
+/// 一把 key 所有 IP 合计的桶容量 = 类别额度 × 这个倍数。
+pub const KEY_AGGREGATE_FACTOR: u32 = 4;
+
+/// 桶 / 失败记录超过这个数时顺手清理一次闲置条目，防止被大量来源 IP 撑爆内存。
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// 路由类别。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteClass {
+    Reads,
+    Writes,
+    Uploads,
+    Sync,
+}
+
+impl RouteClass {
+    pub fn of(method: &Method, path: &str) -> Self {
+        if path == "/sync" || path.starts_with("/sync/") {
+            RouteClass::Sync
+        } else if path == "/images" && method == Method::POST {
+            RouteClass::Uploads
+        } else if matches!(
+            *method,
+            Method::POST | Method::PATCH | Method::PUT | Method::DELETE
+        ) {
+            RouteClass::Writes
+        } else {
+            RouteClass::Reads
+        }
+    }
+
+    fn spec(self, limits: &RateLimits) -> Option<RateSpec> {
+        match self {
+            RouteClass::Reads => limits.reads,
+            RouteClass::Writes => limits.writes,
+            RouteClass::Uploads => limits.uploads,
+            RouteClass::Sync => limits.sync,
+        }
+    }
+}
+
+/// 计桶的身份：key 指纹（所有 IP 合计）、key 指纹 + IP，或仅 IP。key 只存
+/// 进程内随机种子的哈希。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Client {
+    Key(u64),
+    KeyIp(u64, IpAddr),
+    Ip(IpAddr),
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+struct Failures {
+    count: u32,
+    since: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// 一次取令牌的结果。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quota {
+    pub limit: u32,
+    pub remaining: u32,
+    /// 桶补满还要多少秒。
+    pub reset_secs: u64,
+    /// 被拒时：至少等多少秒才有下一个令牌。
+    pub retry_after: Option<u64>,
+}
+
+#[derive(Default)]
+struct Inner {
+    buckets: HashMap<(RouteClass, Client), Bucket>,
+    failures: HashMap<IpAddr, Failures>,
+}
+
+/// 进程内共享的限流状态，clone 即共享。
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    inner: Arc<Mutex<Inner>>,
+    seed: RandomState,
+}
+
+impl RateLimiter {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// 带正确 key 的请求要扣的两个桶：key + IP，以及 key 的总桶。
+    fn key_clients(&self, key: &str, ip: IpAddr, spec: RateSpec) -> [(Client, RateSpec); 2] {
+        let fp = self.seed.hash_one(key);
+        let aggregate = RateSpec {
+            limit: spec.limit.saturating_mul(KEY_AGGREGATE_FACTOR),
+            window: spec.window,
+        };
+        [(Client::KeyIp(fp, ip), spec), (Client::Key(fp), aggregate)]
+    }
+
+    /// 从 `(class, client)` 的桶里取一个令牌。
+    fn take(&self, class: RouteClass, client: Client, spec: RateSpec, now: Instant) -> Quota {
+        self.take_all(class, &[(client, spec)], now).0
+    }
+
+    /// 从几个桶里各取一个令牌：全部有余量才扣，任何一个不够就都不扣。
+    ///
+    /// 返回决定结果的那个桶的额度（被拒时取要等最久的，放行时取剩余最少的）
+    /// 和它的规格，响应头按它写。
+    fn take_all(
+        &self,
+        class: RouteClass,
+        clients: &[(Client, RateSpec)],
+        now: Instant,
+    ) -> (Quota, RateSpec) {
+        let mut inner = self.lock();
+        if inner.buckets.len() > PRUNE_THRESHOLD {
+            // 闲置超过最长窗口（1h）的桶必然已补满，删掉与保留等价
+            inner
+                .buckets
+                .retain(|_, b| now.duration_since(b.updated) < Duration::from_secs(3600));
+        }
+        for &(client, spec) in clients {
+            let limit = f64::from(spec.limit);
+            let per_sec = limit / spec.window.as_secs_f64();
+            let bucket = inner.buckets.entry((class, client)).or_insert(Bucket {
+                tokens: limit,
+                updated: now,
+            });
+            // 热更新把容量调小时，多出来的令牌作废
+            let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * per_sec).min(limit);
+            bucket.updated = now;
+        }
+        let allowed = clients
+            .iter()
+            .all(|(client, _)| inner.buckets[&(class, *client)].tokens >= 1.0);
+
+        let mut worst: Option<(Quota, RateSpec)> = None;
+        for &(client, spec) in clients {
+            let limit = f64::from(spec.limit);
+            let per_sec = limit / spec.window.as_secs_f64();
+            let Some(bucket) = inner.buckets.get_mut(&(class, client)) else {
+                continue;
+            };
+            let retry_after = if allowed {
+                bucket.tokens -= 1.0;
+                None
+            } else if bucket.tokens < 1.0 {
+                Some(((1.0 - bucket.tokens) / per_sec).ceil().max(1.0) as u64)
+            } else {
+                None
+            };
+            let quota = Quota {
+                limit: spec.limit,
+                remaining: bucket.tokens.floor() as u32,
+                reset_secs: ((limit - bucket.tokens) / per_sec).ceil() as u64,
+                retry_after,
+            };
+            let worse = match &worst {
+                None => true,
+                Some((w, _)) if allowed => quota.remaining < w.remaining,
+                Some((w, _)) => quota.retry_after > w.retry_after,
+            };
+            if worse {
+                worst = Some((quota, spec));
+            }
+        }
+        worst.expect("take_all 至少要一个桶")
+    }
+
+    /// 该 IP 仍在封禁期内时返回剩余秒数。
+    fn locked_for(&self, ip: IpAddr, now: Instant) -> Option<u64> {
+        let inner = self.lock();
+        let until = inner.failures.get(&ip)?.locked_until?;
+        (until > now).then(|| until.duration_since(now).as_secs_f64().ceil() as u64)
+    }
+
+    /// 记一次无效 key；累计到 `max` 次（窗口 `lockout`）时开始封禁。
+    fn record_failure(&self, ip: IpAddr, max: u32, lockout: Duration, now: Instant) {
+        let mut inner = self.lock();
+        if inner.failures.len() > PRUNE_THRESHOLD {
+            inner.failures.retain(|_, f| {
+                now.duration_since(f.since) < lockout || f.locked_until.is_some_and(|u| u > now)
+            });
+        }
+        let f = inner.failures.entry(ip).or_insert(Failures {
+            count: 0,
+            since: now,
+            locked_until: None,
+        });
+        if now.duration_since(f.since) >= lockout || f.locked_until.is_some_and(|u| u <= now) {
+            // 窗口过期或上一轮封禁已结束：重新计数
+            *f = Failures {
+                count: 0,
+                since: now,
+                locked_until: None,
+            };
+        }
+        f.count += 1;
+        if f.count >= max {
+            f.locked_until = Some(now + lockout);
+        }
+    }
+}
+
+/// 请求方 IP：直连用 socket 对端地址；`trust_forwarded_for` 时取
+/// `X-Forwarded-For` 最右边一项（最近一跳反代看到的地址，客户端伪造不了）。
+/// 拿不到连接信息时（进程内测试）记为 `0.0.0.0`。
+pub(super) fn client_ip(req: &Request, trust_forwarded_for: bool) -> IpAddr {
+    if trust_forwarded_for {
+        let forwarded = req
+            .headers()
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .last()
+            .and_then(|s| s.trim().parse().ok());
+        if let Some(ip) = forwarded {
+            return ip;
+        }
+    }
+    req.extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+}
+
+pub async fn enforce(State(state): State<AppState>, req: Request, next: Next) -> Response {
+    let cfg = state.config.get();
+    let limiter = &state.limiter;
+    let now = Instant::now();
+    let ip = client_ip(&req, cfg.trust_forwarded_for);
+
+    let valid_key =
+        auth::bearer_token(req.headers()).filter(|token| auth::key_matches(token, &cfg.api_key));
+
+    // 封禁只挡没带 key / 带错 key 的请求
+    if valid_key.is_none() && cfg.auth_max_failures > 0 {
+        if let Some(secs) = limiter.locked_for(ip, now) {
+            return too_many_requests("too many invalid api keys, try again later", secs, None);
+        }
+        if auth::bearer_token(req.headers()).is_some() {
+            let lockout = Duration::from_secs(cfg.auth_lockout_secs);
+            limiter.record_failure(ip, cfg.auth_max_failures, lockout, now);
+        }
+    }
+
+    let class = RouteClass::of(req.method(), req.uri().path());
+    let Some(spec) = class.spec(&cfg.rate_limits) else {
+        return next.run(req).await;
+    };
+    let (quota, spec) = match valid_key.as_deref() {
+        Some(token) => limiter.take_all(class, &limiter.key_clients(token, ip, spec), now),
+        None => (limiter.take(class, Client::Ip(ip), spec, now), spec),
+    };
+    if let Some(secs) = quota.retry_after {
+        return too_many_requests("rate limit exceeded", secs, Some((quota, spec)));
+    }
+
+    let mut resp = next.run(req).await;
+    insert_quota_headers(resp.headers_mut(), quota, spec);
+    resp
+}
+
+fn insert_quota_headers(headers: &mut HeaderMap, quota: Quota, spec: RateSpec) {
+    headers.insert(RATELIMIT_LIMIT, HeaderValue::from(quota.limit));
+    headers.insert(RATELIMIT_REMAINING, HeaderValue::from(quota.remaining));
+    headers.insert(RATELIMIT_RESET, HeaderValue::from(quota.reset_secs));
+    if let Ok(v) = HeaderValue::from_str(&format!("{};w={}", spec.limit, spec.window.as_secs())) {
+        headers.insert(RATELIMIT_POLICY, v);
+    }
+}
+
+fn too_many_requests(detail: &str, retry_after: u64, quota: Option<(Quota, RateSpec)>) -> Response {
+    let mut resp =
+        ApiError::new(StatusCode::TOO_MANY_REQUESTS, "rate_limited", detail).into_response();
+    resp.headers_mut()
+        .insert(RETRY_AFTER, HeaderValue::from(retry_after));
+    if let Some((quota, spec)) = quota {
+        insert_quota_headers(resp.headers_mut(), quota, spec);
+    }
+    resp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IP: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+
+    fn spec(limit: u32, secs: u64) -> RateSpec {
+        RateSpec {
+            limit,
+            window: Duration::from_secs(secs),
+        }
+    }
+
+    #[test]
+    fn route_classes() {
+        assert_eq!(RouteClass::of(&Method::GET, "/todos"), RouteClass::Reads);
+        assert_eq!(
+            RouteClass::of(&Method::GET, "/images/a.png"),
+            RouteClass::Reads
+        );
+        assert_eq!(RouteClass::of(&Method::POST, "/todos"), RouteClass::Writes);
+        assert_eq!(
+            RouteClass::of(&Method::DELETE, "/subtasks/1"),
+            RouteClass::Writes
+        );
+        assert_eq!(
+            RouteClass::of(&Method::POST, "/images"),
+            RouteClass::Uploads
+        );
+        assert_eq!(RouteClass::of(&Method::POST, "/sync"), RouteClass::Sync);
+        assert_eq!(
+            RouteClass::of(&Method::POST, "/sync/push"),
+            RouteClass::Sync
+        );
+        assert_eq!(RouteClass::of(&Method::POST, "/synced"), RouteClass::Writes);
+    }
+
+    #[test]
+    fn bucket_drains_and_refills() {
+        let limiter = RateLimiter::default();
+        let t0 = Instant::now();
+        let s = spec(3, 60);
+        let client = Client::Ip(IP);
+
+        for remaining in [2, 1, 0] {
+            let q = limiter.take(RouteClass::Reads, client, s, t0);
+            assert_eq!(q.retry_after, None);
+            assert_eq!(q.remaining, remaining);
+        }
+        let q = limiter.take(RouteClass::Reads, client, s, t0);
+        // 每 20s 补一个
+        assert_eq!(q.retry_after, Some(20));
+        assert_eq!(q.reset_secs, 60);
+
+        // 别的类别、别的身份各有各的桶
+        assert_eq!(
+            limiter.take(RouteClass::Writes, client, s, t0).retry_after,
+            None
+        );
+        let keyed = limiter.key_clients("k", IP, s)[0].0;
+        assert_eq!(
+            limiter.take(RouteClass::Reads, keyed, s, t0).retry_after,
+            None
+        );
+
+        let q = limiter.take(RouteClass::Reads, client, s, t0 + Duration::from_secs(20));
+        assert_eq!(q.retry_after, None);
+        assert_eq!(q.remaining, 0);
+        let q = limiter.take(RouteClass::Reads, client, s, t0 + Duration::from_secs(600));
+        assert_eq!(q.remaining, 2);
+    }
+
+    #[test]
+    fn lockout_after_repeated_failures() {
+        let limiter = RateLimiter::default();
+        let t0 = Instant::now();
+        let lockout = Duration::from_secs(900);
+
+        for _ in 0..2 {
+            limiter.record_failure(IP, 3, lockout, t0);
+        }
+        assert_eq!(limiter.locked_for(IP, t0), None);
+        limiter.record_failure(IP, 3, lockout, t0);
+        assert_eq!(limiter.locked_for(IP, t0), Some(900));
+        assert_eq!(
+            limiter.locked_for(IP, t0 + Duration::from_secs(600)),
+            Some(300)
+        );
+        assert_eq!(limiter.locked_for(IP, t0 + lockout), None);
+
+        // 封禁结束后重新计数，不会一次失败就又封上
+        limiter.record_failure(IP, 3, lockout, t0 + lockout);
+        assert_eq!(limiter.locked_for(IP, t0 + lockout), None);
+    }
+
+    #[test]
+    fn key_aggregate_bucket_caps_many_ips() {
+        let limiter = RateLimiter::default();
+        let t0 = Instant::now();
+        let s = spec(2, 60);
+        let ips = (1..=8).map(|n| IpAddr::V4(Ipv4Addr::new(192, 0, 2, n)));
+
+        // 每个 IP 各有 2 个，但整把 key 只有 2 × KEY_AGGREGATE_FACTOR = 8 个
+        let mut allowed = 0;
+        for ip in ips {
+            for _ in 0..2 {
+                let clients = limiter.key_clients("k", ip, s);
+                let (q, _) = limiter.take_all(RouteClass::Writes, &clients, t0);
+                if q.retry_after.is_none() {
+                    allowed += 1;
+                }
+            }
+        }
+        assert_eq!(allowed, 8);
+        let clients = limiter.key_clients("k", IpAddr::V4(Ipv4Addr::new(198, 51, 100, 1)), s);
+        let (q, policy) = limiter.take_all(RouteClass::Writes, &clients, t0);
+        assert_eq!(q.retry_after, Some(8));
+        assert_eq!(policy.limit, 8);
+
+        // 总桶不够时 key + IP 桶不被白扣
+        let fresh = IpAddr::V4(Ipv4Addr::new(198, 51, 100, 2));
+        let clients = limiter.key_clients("k", fresh, s);
+        limiter.take_all(RouteClass::Writes, &clients, t0);
+        let q = limiter.take(RouteClass::Writes, clients[0].0, s, t0);
+        assert_eq!(q.remaining, 1);
+
+        // 别的 key 不受影响
+        let clients = limiter.key_clients("other", IP, s);
+        let (q, _) = limiter.take_all(RouteClass::Writes, &clients, t0);
+        assert_eq!(q.retry_after, None);
+    }
+}
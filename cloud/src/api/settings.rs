@@ -0,0 +1,114 @@
+//! `/settings`：读写跨设备同步的 PC `AppSettings` 字段。
+//!
+//! 只暴露 viewMode / textTheme / notificationType / showCalendar /
+//! windowBgColor / windowBgAlpha 六项；窗口位置、大小、贴边隐藏等是各台 PC
+//! 自己的状态，不在此列。
+//!
+//! 数据分两层：pull 把远端 settings 整个存进 `settings.all`；`PATCH` 的改动
+//! 累积在 `settings.pending` 并标脏，下一轮 push 合并时覆盖到远端 settings 上，
+//! PUT 成功后清掉（见 `sync::push`）。GET 返回两层叠加的结果，缺的字段用 PC
+//! 默认值补齐。
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::{Extension, Json};
+use rusqlite::Connection;
+use serde_json::{json, Map, Value};
+
+use super::audit::AuditNote;
+use super::error::ApiError;
+use super::patch::{self, PatchKind};
+use super::validate;
+use super::AppState;
+use crate::db::repo;
+
+/// 与 `pc/src-tauri/src/db/models.rs::AppSettings` 的默认值一致。
+fn defaults() -> Map<String, Value> {
+    [
+        ("viewMode", json!("list")),
+        ("textTheme", json!("dark")),
+        ("notificationType", json!("system")),
+        ("showCalendar", json!(false)),
+        ("windowBgColor", json!("#000000")),
+        ("windowBgAlpha", json!(0.45)),
+    ]
+    .into_iter()
+    .map(|(k, v)| (k.to_string(), v))
+    .collect()
+}
+
+fn read_object(conn: &Connection, key: &str) -> rusqlite::Result<Map<String, Value>> {
+    Ok(repo::get_setting(conn, key)?
+        .and_then(|s| serde_json::from_str::<Map<String, Value>>(&s).ok())
+        .unwrap_or_default())
+}
+
+/// 远端 settings 叠加本地待推送的改动，只取同步字段。
+fn current(conn: &Connection) -> rusqlite::Result<Value> {
+    let remote = read_object(conn, repo::SETTING_REMOTE)?;
+    let pending = read_object(conn, repo::SETTING_PENDING)?;
+    let mut out = defaults();
+    for (key, v) in out.iter_mut() {
+        if let Some(x) = pending.get(key).or_else(|| remote.get(key)) {
+            *v = x.clone();
+        }
+    }
+    Ok(Value::Object(out))
+}
+
+// =============================================================================
+// GET /settings
+// =============================================================================
+
+pub async fn get_settings(State(state): State<AppState>) -> Result<Json<Value>, ApiError> {
+    let v = state.db.with_conn(|conn| current(conn))?;
+    Ok(Json(v))
+}
+
+// =============================================================================
+// PATCH /settings
+// =============================================================================
+
+pub async fn patch_settings(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(Extension<AuditNote>, Json<Value>), ApiError> {
+    let kind = PatchKind::from_headers(&headers)?;
+    let body: Value = serde_json::from_slice(&body)?;
+
+    let (before, after) = state
+        .db
+        .with_conn(|conn| -> Result<(Value, Value), ApiError> {
+            let before = current(conn)?;
+            let mut after = before.clone();
+            patch::apply(kind, &mut after, &body)?;
+            let Some(obj) = after.as_object() else {
+                return Err(ApiError::bad_request("settings must stay a JSON object"));
+            };
+            validate::validate_settings_change(&before, &after)?;
+
+            let changed: Vec<(&String, &Value)> = obj
+                .iter()
+                .filter(|(k, v)| before.get(k.as_str()) != Some(v))
+                .collect();
+            if !changed.is_empty() {
+                let tx = conn.transaction()?;
+                let mut pending = read_object(&tx, repo::SETTING_PENDING)?;
+                for (k, v) in changed {
+                    pending.insert(k.clone(), v.clone());
+                }
+                repo::set_setting(
+                    &tx,
+                    repo::SETTING_PENDING,
+                    &Value::Object(pending).to_string(),
+                )?;
+                repo::mark_dirty(&tx)?;
+                tx.commit()?;
+            }
+            Ok((before, after))
+        })?;
+
+    Ok((AuditNote::updated("settings", &before, &after), Json(after)))
+}
@@ -0,0 +1,67 @@
+//! `GET /stats?from=&to=`：完成趋势 / 平均耗时 / 逾期 / 象限分布 / 子任务完成率。
+//!
+//! 统计口径见 `db::stats`。`from` / `to` 为本地日期（`YYYY-MM-DD`，含两端），
+//! 缺省为截至今天的最近 30 天；"今天"按请求方时区（`X-Timezone`，缺省
+//! `config.timezone`）算。
+
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::Json;
+use chrono::{Duration, NaiveDate};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::error::ApiError;
+use super::timezone::request_clock;
+use super::AppState;
+use crate::db::stats;
+
+/// 缺省区间天数（含今天）。
+const DEFAULT_DAYS: i64 = 30;
+/// 区间上限：`perDay` 会逐日补零，避免一次请求展开出过大的数组。
+const MAX_DAYS: i64 = 366;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct StatsQuery {
+    pub from: Option<String>,
+    pub to: Option<String>,
+}
+
+pub async fn get_stats(
+    State(state): State<AppState>,
+    Query(q): Query<StatsQuery>,
+    headers: HeaderMap,
+) -> Result<Json<Value>, ApiError> {
+    let clock = request_clock(&state, &headers)?;
+    // 逾期判断与库里的 endTime（存储时区墙钟）比较
+    let now = clock.in_storage(clock.client_now());
+    let to = match q.to.as_deref() {
+        Some(s) => parse_date("to", s)?,
+        None => clock.today(),
+    };
+    let from = match q.from.as_deref() {
+        Some(s) => parse_date("from", s)?,
+        None => to - Duration::days(DEFAULT_DAYS - 1),
+    };
+    if from > to {
+        return Err(ApiError::bad_request("from must not be after to"));
+    }
+    if (to - from).num_days() >= MAX_DAYS {
+        return Err(ApiError::bad_request(format!(
+            "range must not exceed {} days",
+            MAX_DAYS
+        )));
+    }
+
+    let report = state
+        .db
+        .with_conn(|conn| stats::compute(conn, from, to, now))?;
+    Ok(Json(serde_json::to_value(report).map_err(|e| {
+        ApiError::internal(format!("serialize stats: {}", e))
+    })?))
+}
+
+fn parse_date(name: &str, s: &str) -> Result<NaiveDate, ApiError> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map_err(|_| ApiError::bad_request(format!("{} must be YYYY-MM-DD", name)))
+}
@@ -1,14 +1,20 @@
-//! `/subtasks` CRUD（独立 PATCH/DELETE）+ 嵌于 `/todos/:id/subtasks` 的 POST。
+//! `/subtasks` CRUD（独立 PATCH/DELETE）+ 嵌于 `/todos/:id/subtasks` 的 POST，
+//! 外加批量重排、跨 todo 移动与升级为独立 todo。
 
+use axum::body::Bytes;
 use axum::extract::{Path, State};
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::IntoResponse;
-use axum::Json;
-use serde_json::{json, Value};
+use axum::{Extension, Json};
+use rusqlite::Connection;
+use serde_json::{json, Map, Value};
 
-use super::error::ApiError;
+use super::audit::AuditNote;
+use super::error::{ApiError, FieldError};
 use super::ids::new_id_string;
-use super::todos::ensure_todo_exists;
+use super::patch::{self, PatchKind};
+use super::todos::{attach_seq, ensure_todo_exists, id_list, new_todo_value};
+use super::validate;
 use super::AppState;
 use crate::db::repo;
 use crate::time::now_local_string;
@@ -23,7 +29,7 @@ pub async fn create_subtask(
     State(state): State<AppState>,
     Path(raw_todo_ref): Path<String>,
     Json(body): Json<Value>,
-) -> Result<(StatusCode, Json<Value>), ApiError> {
+) -> Result<(StatusCode, Extension<AuditNote>, Json<Value>), ApiError> {
     if !body.is_object() {
         return Err(ApiError::bad_request("body must be a JSON object"));
     }
@@ -33,8 +39,11 @@ pub async fn create_subtask(
         .filter(|s| !s.trim().is_empty())
         .ok_or_else(|| ApiError::bad_request("title is required"))?
         .to_string();
+    if let Some(fields) = body.as_object() {
+        validate::validate_subtask(fields)?;
+    }
 
-    let now = now_local_string(state.config.timezone_offset);
+    let now = now_local_string(state.config.get().timezone);
     let id_str = new_id_string();
 
     // 同一事务内解析父 todo ref（支持 C 短码）+ 写 subtask。
@@ -62,7 +71,7 @@ pub async fn create_subtask(
         Ok(v)
     })?;
 
-    Ok((StatusCode::CREATED, Json(v)))
+    Ok((StatusCode::CREATED, AuditNote::created(id_str, &v), Json(v)))
 }
 
 // =============================================================================
@@ -72,72 +81,294 @@ pub async fn create_subtask(
 pub async fn patch_subtask(
     State(state): State<AppState>,
     Path(id): Path<String>,
-    Json(body): Json<Value>,
-) -> Result<Json<Value>, ApiError> {
-    if !body.is_object() {
-        return Err(ApiError::bad_request("body must be a JSON object"));
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(Extension<AuditNote>, Json<Value>), ApiError> {
+    let kind = PatchKind::from_headers(&headers)?;
+    let body: Value = serde_json::from_slice(&body)?;
+    let now = now_local_string(state.config.get().timezone);
+
+    let updated: Option<(Value, Value)> =
+        state
+            .db
+            .with_conn(|conn| -> Result<Option<(Value, Value)>, ApiError> {
+                let Some(row) = repo::get_subtask(conn, &id)? else {
+                    return Ok(None);
+                };
+                let before: Value =
+                    serde_json::from_str(&row.data_json).unwrap_or_else(|_| json!({"id": row.id}));
+                let mut current = before.clone();
+                patch::apply(kind, &mut current, &body)?;
+                validate::validate_subtask_change(&before, &current)?;
+                // id / parentId 与 subtasks 表的列绑定，PATCH 不能改
+                if let Some(obj) = current.as_object_mut() {
+                    obj.insert("id".into(), json!(id.parse::<i64>().unwrap_or(0)));
+                    obj.insert(
+                        "parentId".into(),
+                        json!(row.todo_id.parse::<i64>().unwrap_or(0)),
+                    );
+                    obj.insert("updatedAt".into(), json!(now.clone()));
+                }
+                let body_str = current.to_string();
+                repo::upsert_subtask(conn, &id, &row.todo_id, &body_str, &now)?;
+                repo::mark_dirty(conn)?;
+                Ok(Some((before, current)))
+            })?;
+
+    match updated {
+        Some((before, v)) => Ok((AuditNote::updated(id, &before, &v), Json(v))),
+        None => Err(ApiError::not_found(format!("subtask {} not found", id))),
     }
-    let now = now_local_string(state.config.timezone_offset);
+}
+
+// =============================================================================
+// POST /todos/:id/subtasks/reorder
+// =============================================================================
+
+/// body `{"ids": [...]}`：同 `POST /todos/reorder`，但 id 必须都是这个 todo 的
+/// 子任务（不存在 → 404，属于别的 todo → 422）。返回重排后的子任务列表。
+pub async fn reorder_subtasks(
+    State(state): State<AppState>,
+    Path(raw_todo_ref): Path<String>,
+    Json(body): Json<Value>,
+) -> Result<(Extension<AuditNote>, Json<Value>), ApiError> {
+    let ids = id_list(&body)?;
+    let now = now_local_string(state.config.get().timezone);
+
+    let (todo_id, before, after, list) = state.db.with_conn(
+        |conn| -> Result<(String, Value, Value, Vec<Value>), ApiError> {
+            let tx = conn.transaction()?;
+            let todo_id = ensure_todo_exists(&tx, &raw_todo_ref)?;
+
+            let mut before = Map::new();
+            let mut after = Map::new();
+            let mut list = Vec::with_capacity(ids.len());
+            let mut changed = false;
+            for (idx, id) in ids.iter().enumerate() {
+                let Some(row) = repo::get_subtask(&tx, id)? else {
+                    return Err(ApiError::not_found(format!("subtask {} not found", id)));
+                };
+                if row.todo_id != todo_id {
+                    return Err(ApiError::validation(vec![FieldError::new(
+                        "ids",
+                        format!("subtask {} does not belong to todo {}", id, raw_todo_ref),
+                    )]));
+                }
+                let mut v: Value =
+                    serde_json::from_str(&row.data_json).unwrap_or_else(|_| json!({"id": row.id}));
+                let old = v.get("sortOrder").and_then(Value::as_i64);
+                before.insert(id.clone(), json!(old));
+                after.insert(id.clone(), json!(idx));
+                if old != Some(idx as i64) {
+                    if let Some(obj) = v.as_object_mut() {
+                        obj.insert("sortOrder".into(), json!(idx));
+                        obj.insert("updatedAt".into(), json!(now.clone()));
+                    }
+                    repo::upsert_subtask(&tx, id, &todo_id, &v.to_string(), &now)?;
+                    changed = true;
+                }
+                list.push(v);
+            }
+            if changed {
+                repo::mark_dirty(&tx)?;
+            }
+            tx.commit()?;
+            Ok((todo_id, Value::Object(before), Value::Object(after), list))
+        },
+    )?;
+
+    Ok((
+        AuditNote::updated(todo_id, &before, &after),
+        Json(Value::Array(list)),
+    ))
+}
+
+// =============================================================================
+// POST /subtasks/:id/move
+// =============================================================================
 
-    let updated: Option<Value> = state
+/// body `{"todoId": ..., "sortOrder"?: n}`：把子任务挪到另一个 todo 下（`todoId`
+/// 支持 `C` 短码），`parentId` 与 `subtasks.todo_id` 列一起改；不给 `sortOrder`
+/// 时排到目标 todo 末尾。
+///
+/// 不写 tombstone：subtask tombstone 按 id 生效，写了会把挪过去的这条也删掉。
+/// 远端旧 todo 下残留的副本由 push merge 按 `updatedAt` 判定归属后剔除。
+pub async fn move_subtask(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(body): Json<Value>,
+) -> Result<(Extension<AuditNote>, Json<Value>), ApiError> {
+    let target_ref = match body.get("todoId") {
+        Some(Value::Number(n)) if n.is_i64() => n.to_string(),
+        Some(Value::String(s)) if !s.trim().is_empty() => s.trim().to_string(),
+        _ => {
+            return Err(ApiError::validation(vec![FieldError::new(
+                "todoId",
+                "is required and must be a todo id",
+            )]))
+        }
+    };
+    let sort_order = match body.get("sortOrder") {
+        None | Some(Value::Null) => None,
+        Some(v) => match v.as_i64() {
+            Some(n) if n >= 0 => Some(n),
+            _ => {
+                return Err(ApiError::validation(vec![FieldError::new(
+                    "sortOrder",
+                    "must be a non-negative integer",
+                )]))
+            }
+        },
+    };
+    let now = now_local_string(state.config.get().timezone);
+
+    let moved = state
         .db
-        .with_conn(|conn| -> rusqlite::Result<Option<Value>> {
-            let Some(row) = repo::get_subtask(conn, &id)? else {
+        .with_conn(|conn| -> Result<Option<(Value, Value)>, ApiError> {
+            let tx = conn.transaction()?;
+            let Some(row) = repo::get_subtask(&tx, &id)? else {
                 return Ok(None);
             };
-            let mut current: Value =
+            let target = ensure_todo_exists(&tx, &target_ref)?;
+            let before: Value =
                 serde_json::from_str(&row.data_json).unwrap_or_else(|_| json!({"id": row.id}));
-            merge_json_shallow(&mut current, &body);
+
+            let sort_order = match sort_order {
+                Some(n) => n,
+                None if target == row.todo_id => {
+                    before.get("sortOrder").and_then(Value::as_i64).unwrap_or(0)
+                }
+                None => next_sort_order(&tx, &target)?,
+            };
+
+            let mut current = before.clone();
             if let Some(obj) = current.as_object_mut() {
-                obj.insert("id".into(), json!(id.parse::<i64>().unwrap_or(0)));
+                obj.insert("parentId".into(), json!(target.parse::<i64>().unwrap_or(0)));
+                obj.insert("sortOrder".into(), json!(sort_order));
                 obj.insert("updatedAt".into(), json!(now.clone()));
             }
-            let body_str = current.to_string();
-            repo::upsert_subtask(conn, &id, &row.todo_id, &body_str, &now)?;
-            repo::mark_dirty(conn)?;
-            Ok(Some(current))
+            repo::upsert_subtask(&tx, &id, &target, &current.to_string(), &now)?;
+            repo::mark_dirty(&tx)?;
+            tx.commit()?;
+            Ok(Some((before, current)))
         })?;
 
-    match updated {
-        Some(v) => Ok(Json(v)),
+    match moved {
+        Some((before, v)) => Ok((AuditNote::updated(id, &before, &v), Json(v))),
         None => Err(ApiError::not_found(format!("subtask {} not found", id))),
     }
 }
 
 // =============================================================================
-// DELETE /subtasks/:id
+// POST /subtasks/:id/promote
 // =============================================================================
 
-pub async fn delete_subtask(
+/// 子任务升级为独立 todo：标题、内容（→ `description`）、完成状态带过去，颜色 /
+/// 象限沿用原父 todo，其余按 `POST /todos` 的默认值。内容里的图片按文件名引用，
+/// 不用搬。原子任务删除并写 tombstone，防止 push merge 时被远端副本复活。
+pub async fn promote_subtask(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<impl IntoResponse, ApiError> {
-    let now = now_local_string(state.config.timezone_offset);
-    let removed = state.db.with_conn(|conn| -> rusqlite::Result<bool> {
-        let tx = conn.transaction()?;
-        let existed = repo::delete_subtask(&tx, &id)?;
-        if existed {
+) -> Result<(StatusCode, Extension<AuditNote>, Json<Value>), ApiError> {
+    let now = now_local_string(state.config.get().timezone);
+
+    let promoted = state
+        .db
+        .with_conn(|conn| -> rusqlite::Result<Option<(Value, Value)>> {
+            let tx = conn.transaction()?;
+            let Some(row) = repo::get_subtask(&tx, &id)? else {
+                return Ok(None);
+            };
+            let sub: Value =
+                serde_json::from_str(&row.data_json).unwrap_or_else(|_| json!({"id": row.id}));
+            let parent: Option<Value> = repo::get_todo(&tx, &row.todo_id)?
+                .and_then(|r| serde_json::from_str(&r.data_json).ok());
+
+            let mut obj = Map::new();
+            obj.insert(
+                "title".into(),
+                sub.get("title").cloned().unwrap_or(json!("")),
+            );
+            obj.insert(
+                "description".into(),
+                sub.get("content").cloned().unwrap_or(Value::Null),
+            );
+            obj.insert(
+                "completed".into(),
+                json!(sub
+                    .get("completed")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false)),
+            );
+            for key in ["color", "quadrant"] {
+                if let Some(v) = parent.as_ref().and_then(|p| p.get(key)) {
+                    obj.insert(key.into(), v.clone());
+                }
+            }
+            let (todo_id, mut todo) = new_todo_value(obj, &now);
+
+            repo::upsert_todo(&tx, &todo_id, &todo.to_string(), &now)?;
+            repo::assign_seq(&tx, &todo_id)?;
+            repo::delete_subtask(&tx, &id)?;
             repo::add_tombstone(&tx, TOMBSTONE_SUBTASK, &id, &now)?;
             repo::mark_dirty(&tx)?;
-        }
-        tx.commit()?;
-        Ok(existed)
-    })?;
-    if removed {
-        Ok(StatusCode::NO_CONTENT)
-    } else {
-        Err(ApiError::not_found(format!("subtask {} not found", id)))
+            attach_seq(&tx, &todo_id, &mut todo);
+            tx.commit()?;
+            Ok(Some((sub, todo)))
+        })?;
+
+    match promoted {
+        Some((before, v)) => Ok((
+            StatusCode::CREATED,
+            AuditNote::converted(id, &before, &v),
+            Json(v),
+        )),
+        None => Err(ApiError::not_found(format!("subtask {} not found", id))),
     }
 }
 
-fn merge_json_shallow(target: &mut Value, patch: &Value) {
-    let (Value::Object(t), Value::Object(p)) = (target, patch) else {
-        return;
-    };
-    for (k, v) in p {
-        if k == "id" {
-            continue;
-        }
-        t.insert(k.clone(), v.clone());
+/// 排到 `todo_id` 现有子任务的末尾：最大 `sortOrder` + 1，没有子任务时为 0。
+pub(crate) fn next_sort_order(conn: &Connection, todo_id: &str) -> rusqlite::Result<i64> {
+    Ok(repo::list_subtasks_for_todo(conn, todo_id)?
+        .iter()
+        .filter_map(|s| {
+            serde_json::from_str::<Value>(&s.data_json)
+                .ok()?
+                .get("sortOrder")?
+                .as_i64()
+        })
+        .max()
+        .map_or(0, |n| n + 1))
+}
+
+// =============================================================================
+// DELETE /subtasks/:id
+// =============================================================================
+
+pub async fn delete_subtask(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let now = now_local_string(state.config.get().timezone);
+    let removed = state
+        .db
+        .with_conn(|conn| -> rusqlite::Result<Option<Value>> {
+            let tx = conn.transaction()?;
+            // 审计摘要要在删之前取
+            let before = repo::get_subtask(&tx, &id)?
+                .and_then(|row| serde_json::from_str::<Value>(&row.data_json).ok())
+                .unwrap_or_else(|| json!({"id": id}));
+            let existed = repo::delete_subtask(&tx, &id)?;
+            if existed {
+                repo::add_tombstone(&tx, TOMBSTONE_SUBTASK, &id, &now)?;
+                repo::mark_dirty(&tx)?;
+            }
+            tx.commit()?;
+            Ok(existed.then_some(before))
+        })?;
+    if let Some(before) = removed {
+        Ok((StatusCode::NO_CONTENT, AuditNote::deleted(id, &before)))
+    } else {
+        Err(ApiError::not_found(format!("subtask {} not found", id)))
     }
 }
@@ -5,6 +5,7 @@ use axum::http::StatusCode;
 use axum::Json;
 use serde::Serialize;
 use serde_json::json;
+use tracing::Span;
 
 use super::error::ApiError;
 use super::AppState;
@@ -22,17 +23,21 @@ pub struct SyncResp {
 }
 
 pub async fn post_sync(State(state): State<AppState>) -> (StatusCode, Json<SyncResp>) {
-    let cfg = state.config.clone();
+    let cfg = state.config.get();
     let db = state.db.clone();
 
     // 与后台 pull / push worker 串行：语义是"排队等待"而非"拒绝并发"。
     // 锁在 async 层获取、持有到 blocking 段结束。
     let _guard = state.sync_lock.lock().await;
 
+    // blocking 线程不继承当前 span，手动带过去，同步日志才有 request id
+    let span = Span::current();
     let (pull_res, push_res) = tokio::task::spawn_blocking(move || {
-        let p = pull::pull_once(&cfg, &db);
-        let s = push::push_tick(&cfg, &db);
-        (p, s)
+        span.in_scope(|| {
+            let p = pull::pull_once(&cfg, &db);
+            let s = push::push_tick(&cfg, &db);
+            (p, s)
+        })
     })
     .await
     .unwrap_or_else(|e| {
@@ -44,6 +49,9 @@ pub async fn post_sync(State(state): State<AppState>) -> (StatusCode, Json<SyncR
 
     let pull_ok = pull_res.is_ok();
     let push_ok = push_res.is_ok();
+    if pull_ok {
+        crate::sync::mark_ready(&state.ready);
+    }
     let status = if pull_ok && push_ok {
         StatusCode::OK
     } else {
@@ -64,14 +72,16 @@ pub async fn post_sync(State(state): State<AppState>) -> (StatusCode, Json<SyncR
 pub async fn post_sync_pull(
     State(state): State<AppState>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    let cfg = state.config.clone();
+    let cfg = state.config.get();
     let db = state.db.clone();
     let _guard = state.sync_lock.lock().await;
 
-    tokio::task::spawn_blocking(move || pull::pull_once(&cfg, &db))
+    let span = Span::current();
+    tokio::task::spawn_blocking(move || span.in_scope(|| pull::pull_once(&cfg, &db)))
         .await
         .map_err(|e| ApiError::internal(format!("task panic: {}", e)))?
         .map_err(|e| ApiError::internal(format!("pull failed: {:#}", e)))?;
+    crate::sync::mark_ready(&state.ready);
 
     Ok(Json(json!({"status": "ok"})))
 }
@@ -79,11 +89,12 @@ pub async fn post_sync_pull(
 pub async fn post_sync_push(
     State(state): State<AppState>,
 ) -> Result<Json<serde_json::Value>, ApiError> {
-    let cfg = state.config.clone();
+    let cfg = state.config.get();
     let db = state.db.clone();
     let _guard = state.sync_lock.lock().await;
 
-    tokio::task::spawn_blocking(move || push::push_tick(&cfg, &db))
+    let span = Span::current();
+    tokio::task::spawn_blocking(move || span.in_scope(|| push::push_tick(&cfg, &db)))
         .await
         .map_err(|e| ApiError::internal(format!("task panic: {}", e)))?
         .map_err(|e| ApiError::internal(format!("push failed: {:#}", e)))?;
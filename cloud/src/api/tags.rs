@@ -0,0 +1,286 @@
+//! `/tags` 标签 CRUD。
+//!
+//! 标签是 todo 上的多对多关系：每条 todo 的 `tags: [name]` 记它挂了哪些标签
+//! （`GET /todos?tag=work&tag=-personal` 按它过滤），`tags` 表只是按 name 登记
+//! 颜色的注册表。todo 里用到但没登记的标签照样出现在 `GET /tags`，颜色取
+//! [`DEFAULT_TAG_COLOR`]。改名 / 删除标签会连带改写所有引用它的 todo（并刷新
+//! 它们的 `updatedAt`，否则 push 时 LWW 会被远端旧版本盖回去）。
+//!
+//! 同步：注册表对象原样放进 sync-data 顶层的 `tags[]`（push 时按 name 与远端
+//! 逐条 LWW，pull 时写回本表），删除 / 改名走 entity_type = 'tag' 的 tombstone。
+//! 与 PC `commands/tag.rs` 一致。
+
+use std::collections::BTreeMap;
+
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use rusqlite::Connection;
+use serde_json::{json, Value};
+
+use super::audit::AuditNote;
+use super::error::ApiError;
+use super::patch::{self, PatchKind};
+use super::validate;
+use super::AppState;
+use crate::db::repo;
+use crate::time::now_local_string;
+
+pub const TOMBSTONE_TAG: &str = "tag";
+
+/// 没登记颜色的标签用的颜色，与 PC `DEFAULT_TAG_COLOR` 一致。
+pub const DEFAULT_TAG_COLOR: &str = "#6B7280";
+
+// =============================================================================
+// GET /tags
+// =============================================================================
+
+/// 注册表 ∪ todo 里用到的标签名，按 name 排序，每项带引用它的 todo 数 `count`。
+pub async fn list_tags(State(state): State<AppState>) -> Result<Json<Value>, ApiError> {
+    let tags = state.db.with_conn(|conn| -> rusqlite::Result<Vec<Value>> {
+        let mut by_name: BTreeMap<String, Value> = BTreeMap::new();
+        for row in repo::list_tags(conn)? {
+            let v = tag_json(&row);
+            by_name.insert(row.name, with_count(v, 0));
+        }
+        for (name, count) in repo::tag_usage(conn)? {
+            let entry = by_name
+                .entry(name.clone())
+                .or_insert_with(|| unregistered(&name));
+            entry["count"] = json!(count);
+        }
+        Ok(by_name.into_values().collect())
+    })?;
+    Ok(Json(Value::Array(tags)))
+}
+
+// =============================================================================
+// POST /tags
+// =============================================================================
+
+/// 登记标签颜色。标签名已经被 todo 用着也可以登记（之前按默认色显示）。
+pub async fn create_tag(
+    State(state): State<AppState>,
+    Json(body): Json<Value>,
+) -> Result<(StatusCode, Extension<AuditNote>, Json<Value>), ApiError> {
+    let Some(fields) = body.as_object() else {
+        return Err(ApiError::bad_request("body must be a JSON object"));
+    };
+    let name = fields
+        .get("name")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.trim().is_empty())
+        .ok_or_else(|| ApiError::bad_request("name is required"))?
+        .to_string();
+    validate::validate_tag(fields)?;
+
+    let now = now_local_string(state.config.get().timezone);
+    let mut obj = fields.clone();
+    obj.remove("count");
+    obj.entry("color").or_insert(json!(DEFAULT_TAG_COLOR));
+    obj.insert("createdAt".into(), json!(now.clone()));
+    obj.insert("updatedAt".into(), json!(now.clone()));
+    let v = Value::Object(obj);
+
+    let count = state.db.with_conn(|conn| -> Result<i64, ApiError> {
+        if repo::get_tag(conn, &name)?.is_some() {
+            return Err(tag_exists(&name));
+        }
+        let tx = conn.transaction()?;
+        repo::upsert_tag(&tx, &name, &v.to_string(), &now)?;
+        // 删过的同名标签重新登记：清掉墓碑，否则 push merge 会把它剔除
+        repo::remove_tombstone(&tx, TOMBSTONE_TAG, &name)?;
+        repo::mark_dirty(&tx)?;
+        let count = repo::todos_with_tag(&tx, &name)?.len() as i64;
+        tx.commit()?;
+        Ok(count)
+    })?;
+
+    let note = AuditNote::created(name, &v);
+    Ok((StatusCode::CREATED, note, Json(with_count(v, count))))
+}
+
+// =============================================================================
+// GET /tags/:name
+// =============================================================================
+
+pub async fn get_tag(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Value>, ApiError> {
+    let found = state
+        .db
+        .with_conn(|conn| -> rusqlite::Result<Option<Value>> { load_tag(conn, &name) })?;
+    match found {
+        Some(v) => Ok(Json(v)),
+        None => Err(tag_not_found(&name)),
+    }
+}
+
+// =============================================================================
+// PATCH /tags/:name
+// =============================================================================
+
+/// 改颜色和 / 或改名。改名时所有 todo 的 `tags` 里的旧名换成新名（已经挂着
+/// 新名的去重），旧名写墓碑；新名已登记 → 409。只在 todo 里出现、还没登记的
+/// 标签 PATCH 后即登记。
+pub async fn patch_tag(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(Extension<AuditNote>, Json<Value>), ApiError> {
+    let kind = PatchKind::from_headers(&headers)?;
+    let body: Value = serde_json::from_slice(&body)?;
+    let now = now_local_string(state.config.get().timezone);
+
+    let updated = state
+        .db
+        .with_conn(|conn| -> Result<Option<(Value, Value)>, ApiError> {
+            let Some(before) = load_tag(conn, &name)? else {
+                return Ok(None);
+            };
+            let mut before = before;
+            if let Some(obj) = before.as_object_mut() {
+                obj.remove("count");
+                obj.entry("createdAt").or_insert(json!(now.clone()));
+                obj.entry("updatedAt").or_insert(json!(now.clone()));
+            }
+            let mut current = before.clone();
+            patch::apply(kind, &mut current, &body)?;
+            if !current.is_object() {
+                return Err(ApiError::bad_request("tag must stay a JSON object"));
+            }
+            validate::validate_tag_change(&before, &current)?;
+            if let Some(obj) = current.as_object_mut() {
+                obj.remove("count");
+                obj.insert("updatedAt".into(), json!(now.clone()));
+            }
+            let new_name = current["name"].as_str().unwrap_or(&name).to_string();
+
+            let tx = conn.transaction()?;
+            if new_name != name {
+                if repo::get_tag(&tx, &new_name)?.is_some() {
+                    return Err(tag_exists(&new_name));
+                }
+                rewrite_todos(&tx, &name, Some(&new_name), &now)?;
+                repo::delete_tag(&tx, &name)?;
+                repo::add_tombstone(&tx, TOMBSTONE_TAG, &name, &now)?;
+                repo::remove_tombstone(&tx, TOMBSTONE_TAG, &new_name)?;
+            }
+            repo::upsert_tag(&tx, &new_name, &current.to_string(), &now)?;
+            repo::mark_dirty(&tx)?;
+            let count = repo::todos_with_tag(&tx, &new_name)?.len() as i64;
+            tx.commit()?;
+            Ok(Some((before, with_count(current, count))))
+        })?;
+
+    match updated {
+        Some((before, v)) => Ok((AuditNote::updated(name, &before, &v), Json(v))),
+        None => Err(tag_not_found(&name)),
+    }
+}
+
+// =============================================================================
+// DELETE /tags/:name
+// =============================================================================
+
+/// 删除标签：从所有 todo 的 `tags` 里摘掉，注册表删行并写墓碑。
+pub async fn delete_tag(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let now = now_local_string(state.config.get().timezone);
+    let removed = state
+        .db
+        .with_conn(|conn| -> rusqlite::Result<Option<Value>> {
+            let tx = conn.transaction()?;
+            let Some(before) = load_tag(&tx, &name)? else {
+                return Ok(None);
+            };
+            rewrite_todos(&tx, &name, None, &now)?;
+            repo::delete_tag(&tx, &name)?;
+            repo::add_tombstone(&tx, TOMBSTONE_TAG, &name, &now)?;
+            repo::mark_dirty(&tx)?;
+            tx.commit()?;
+            Ok(Some(before))
+        })?;
+    match removed {
+        Some(before) => Ok((StatusCode::NO_CONTENT, AuditNote::deleted(name, &before))),
+        None => Err(tag_not_found(&name)),
+    }
+}
+
+// =============================================================================
+// 工具
+// =============================================================================
+
+fn tag_not_found(name: &str) -> ApiError {
+    ApiError::not_found(format!("tag {} not found", name))
+}
+
+fn tag_exists(name: &str) -> ApiError {
+    ApiError::new(
+        StatusCode::CONFLICT,
+        "tag_exists",
+        format!("tag {} already exists", name),
+    )
+}
+
+fn tag_json(row: &repo::TagRow) -> Value {
+    serde_json::from_str(&row.data_json).unwrap_or_else(|_| json!({"name": row.name}))
+}
+
+/// 只在 todo 里出现、没登记的标签。
+fn unregistered(name: &str) -> Value {
+    json!({"name": name, "color": DEFAULT_TAG_COLOR})
+}
+
+fn with_count(mut v: Value, count: i64) -> Value {
+    v["count"] = json!(count);
+    v
+}
+
+/// 已登记或被 todo 引用的标签（带 `count`）；两者都不是 → `None`。
+fn load_tag(conn: &Connection, name: &str) -> rusqlite::Result<Option<Value>> {
+    let count = repo::todos_with_tag(conn, name)?.len() as i64;
+    let v = match repo::get_tag(conn, name)? {
+        Some(row) => tag_json(&row),
+        None if count > 0 => unregistered(name),
+        None => return Ok(None),
+    };
+    Ok(Some(with_count(v, count)))
+}
+
+/// 把引用 `from` 的 todo 的 `tags` 里的 `from` 换成 `to`（`None` = 摘掉），
+/// 刷新 `updatedAt`。
+fn rewrite_todos(
+    conn: &Connection,
+    from: &str,
+    to: Option<&str>,
+    now: &str,
+) -> rusqlite::Result<()> {
+    for row in repo::todos_with_tag(conn, from)? {
+        let Ok(mut v) = serde_json::from_str::<Value>(&row.data_json) else {
+            continue;
+        };
+        let old: Vec<Value> = v["tags"].as_array().cloned().unwrap_or_default();
+        let mut tags: Vec<Value> = Vec::with_capacity(old.len());
+        for t in old {
+            let t = match (t.as_str(), to) {
+                (Some(s), Some(to)) if s == from => json!(to),
+                (Some(s), None) if s == from => continue,
+                _ => t,
+            };
+            if !tags.contains(&t) {
+                tags.push(t);
+            }
+        }
+        v["tags"] = Value::Array(tags);
+        v["updatedAt"] = json!(now);
+        repo::upsert_todo(conn, &row.id, &v.to_string(), now)?;
+    }
+    Ok(())
+}
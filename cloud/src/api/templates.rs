@@ -0,0 +1,445 @@
+//! `/templates` todo 模板 CRUD + `POST /templates/:id/instantiate`。
+//!
+//! 模板 = 一条 todo 的 `title` / `description` / `color` / `quadrant` 加一份
+//! 子任务清单 `subtasks: [{title, content}]`，用于每周重复的发版清单、入职
+//! 清单这类场景。标题、描述和子任务文本里可以写 `{{date}}` / `{{week}}` /
+//! `{{name}}` 等占位符，实例化时展开（规则见 [`expand`]），与 PC
+//! `commands/template.rs` 一致。
+//!
+//! 同步：模板对象原样放进 sync-data 顶层的 `templates[]`（push 时按 id 与远端
+//! 逐条 LWW，pull 时写回本表），删除走 entity_type = 'template' 的 tombstone。
+
+use std::collections::{BTreeSet, HashMap};
+
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::{Extension, Json};
+use chrono::Datelike;
+use serde_json::{json, Map, Value};
+
+use super::audit::AuditNote;
+use super::error::{ApiError, FieldError};
+use super::ids::new_id_string;
+use super::patch::{self, PatchKind};
+use super::timezone::request_clock;
+use super::todos::new_todo_value;
+use super::validate;
+use super::AppState;
+use crate::db::repo;
+use crate::time::{now_local_string, Clock};
+
+pub const TOMBSTONE_TEMPLATE: &str = "template";
+
+// =============================================================================
+// GET /templates
+// =============================================================================
+
+pub async fn list_templates(State(state): State<AppState>) -> Result<Json<Value>, ApiError> {
+    let rows = state.db.with_conn(|conn| repo::list_templates(conn))?;
+    let templates: Vec<Value> = rows.iter().map(template_json).collect();
+    Ok(Json(Value::Array(templates)))
+}
+
+// =============================================================================
+// POST /templates
+// =============================================================================
+
+pub async fn create_template(
+    State(state): State<AppState>,
+    Json(body): Json<Value>,
+) -> Result<(StatusCode, Extension<AuditNote>, Json<Value>), ApiError> {
+    let Some(fields) = body.as_object() else {
+        return Err(ApiError::bad_request("body must be a JSON object"));
+    };
+    for key in ["name", "title"] {
+        if fields
+            .get(key)
+            .and_then(|v| v.as_str())
+            .is_none_or(|s| s.trim().is_empty())
+        {
+            return Err(ApiError::bad_request(format!("{} is required", key)));
+        }
+    }
+    validate::validate_template(fields)?;
+
+    let now = now_local_string(state.config.get().timezone);
+    let id_str = new_id_string();
+    let mut obj = fields.clone();
+    obj.insert("id".into(), json!(id_str.parse::<i64>().unwrap_or(0)));
+    obj.entry("description").or_insert(Value::Null);
+    obj.entry("color").or_insert(json!("#10B981"));
+    obj.entry("quadrant").or_insert(json!(4));
+    obj.entry("subtasks").or_insert(json!([]));
+    obj.insert("createdAt".into(), json!(now.clone()));
+    obj.insert("updatedAt".into(), json!(now.clone()));
+    let v = Value::Object(obj);
+
+    state.db.with_conn(|conn| -> rusqlite::Result<()> {
+        repo::upsert_template(conn, &id_str, &v.to_string(), &now)?;
+        repo::mark_dirty(conn)
+    })?;
+
+    Ok((StatusCode::CREATED, AuditNote::created(id_str, &v), Json(v)))
+}
+
+// =============================================================================
+// GET /templates/:id
+// =============================================================================
+
+pub async fn get_template(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, ApiError> {
+    let row = state.db.with_conn(|conn| repo::get_template(conn, &id))?;
+    match row {
+        Some(r) => Ok(Json(template_json(&r))),
+        None => Err(template_not_found(&id)),
+    }
+}
+
+// =============================================================================
+// PATCH /templates/:id
+// =============================================================================
+
+pub async fn patch_template(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(Extension<AuditNote>, Json<Value>), ApiError> {
+    let kind = PatchKind::from_headers(&headers)?;
+    let body: Value = serde_json::from_slice(&body)?;
+    let now = now_local_string(state.config.get().timezone);
+
+    let updated = state
+        .db
+        .with_conn(|conn| -> Result<Option<(Value, Value)>, ApiError> {
+            let Some(row) = repo::get_template(conn, &id)? else {
+                return Ok(None);
+            };
+            let before = template_json(&row);
+            let mut current = before.clone();
+            patch::apply(kind, &mut current, &body)?;
+            if !current.is_object() {
+                return Err(ApiError::bad_request("template must stay a JSON object"));
+            }
+            validate::validate_template_change(&before, &current)?;
+            // 防止 PATCH body 改 id
+            if let Some(obj) = current.as_object_mut() {
+                obj.insert("id".into(), json!(id.parse::<i64>().unwrap_or(0)));
+                obj.insert("updatedAt".into(), json!(now.clone()));
+            }
+            repo::upsert_template(conn, &id, &current.to_string(), &now)?;
+            repo::mark_dirty(conn)?;
+            Ok(Some((before, current)))
+        })?;
+
+    match updated {
+        Some((before, v)) => Ok((AuditNote::updated(id, &before, &v), Json(v))),
+        None => Err(template_not_found(&id)),
+    }
+}
+
+// =============================================================================
+// DELETE /templates/:id
+// =============================================================================
+
+pub async fn delete_template(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let now = now_local_string(state.config.get().timezone);
+    let removed = state
+        .db
+        .with_conn(|conn| -> rusqlite::Result<Option<Value>> {
+            let tx = conn.transaction()?;
+            let Some(row) = repo::get_template(&tx, &id)? else {
+                return Ok(None);
+            };
+            repo::delete_template(&tx, &id)?;
+            repo::add_tombstone(&tx, TOMBSTONE_TEMPLATE, &id, &now)?;
+            repo::mark_dirty(&tx)?;
+            tx.commit()?;
+            Ok(Some(template_json(&row)))
+        })?;
+    match removed {
+        Some(before) => Ok((StatusCode::NO_CONTENT, AuditNote::deleted(id, &before))),
+        None => Err(template_not_found(&id)),
+    }
+}
+
+// =============================================================================
+// POST /templates/:id/instantiate
+// =============================================================================
+
+/// 按模板新建一条 todo 及其子任务。body 可省略，或 `{"params": {"name": "…"}}`：
+/// `params` 提供占位符的值，也可以覆盖内置的 `date` / `week`（默认取请求方
+/// 时区的今天，见 `X-Timezone`）。有占位符没给值 → 422，逐个列出。
+/// 响应同 `GET /todos/:id`（带 `seq` 与 `subtasks`）。
+pub async fn instantiate_template(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Extension<AuditNote>, Json<Value>), ApiError> {
+    let clock = request_clock(&state, &headers)?;
+    let body: Value = if body.is_empty() {
+        json!({})
+    } else {
+        serde_json::from_slice(&body)?
+    };
+    let vars = template_vars(&clock, body.get("params"))?;
+
+    let tpl = state
+        .db
+        .with_conn(|conn| repo::get_template(conn, &id))?
+        .map(|r| template_json(&r))
+        .ok_or_else(|| template_not_found(&id))?;
+
+    let mut missing = BTreeSet::new();
+    let title = expand(str_of(&tpl, "title"), &vars, &mut missing);
+    let description = tpl
+        .get("description")
+        .and_then(Value::as_str)
+        .map(|s| expand(s, &vars, &mut missing));
+    let subtasks: Vec<(String, Option<String>)> = tpl
+        .get("subtasks")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+        .iter()
+        .map(|s| {
+            let title = expand(str_of(s, "title"), &vars, &mut missing);
+            let content = s
+                .get("content")
+                .and_then(Value::as_str)
+                .map(|c| expand(c, &vars, &mut missing));
+            (title, content)
+        })
+        .collect();
+
+    let mut errs: Vec<FieldError> = missing
+        .into_iter()
+        .map(|key| FieldError::new(format!("params.{}", key), "is required by the template"))
+        .collect();
+    if errs.is_empty() {
+        if title.trim().is_empty() {
+            errs.push(FieldError::new(
+                "title",
+                "is blank after expanding placeholders",
+            ));
+        }
+        for (i, (t, _)) in subtasks.iter().enumerate() {
+            if t.trim().is_empty() {
+                errs.push(FieldError::new(
+                    format!("subtasks[{}].title", i),
+                    "is blank after expanding placeholders",
+                ));
+            }
+        }
+    }
+    if !errs.is_empty() {
+        return Err(ApiError::validation(errs));
+    }
+
+    let now = now_local_string(state.config.get().timezone);
+    let mut obj = Map::new();
+    obj.insert("title".into(), json!(title));
+    obj.insert("description".into(), json!(description));
+    for key in ["color", "quadrant"] {
+        if let Some(v) = tpl.get(key) {
+            obj.insert(key.into(), v.clone());
+        }
+    }
+    let (todo_id, mut todo) = new_todo_value(obj, &now);
+    let todo_num = todo["id"].clone();
+    let subs: Vec<(String, Value)> = subtasks
+        .into_iter()
+        .enumerate()
+        .map(|(i, (title, content))| {
+            let sid = new_id_string();
+            let v = json!({
+                "id": sid.parse::<i64>().unwrap_or(0),
+                "parentId": todo_num,
+                "title": title,
+                "content": content,
+                "completed": false,
+                "sortOrder": i,
+                "createdAt": now,
+                "updatedAt": now,
+            });
+            (sid, v)
+        })
+        .collect();
+
+    let seq = state.db.with_conn(|conn| -> rusqlite::Result<i64> {
+        let tx = conn.transaction()?;
+        repo::upsert_todo(&tx, &todo_id, &todo.to_string(), &now)?;
+        let seq = repo::assign_seq(&tx, &todo_id)?;
+        for (sid, v) in &subs {
+            repo::upsert_subtask(&tx, sid, &todo_id, &v.to_string(), &now)?;
+        }
+        repo::mark_dirty(&tx)?;
+        tx.commit()?;
+        Ok(seq)
+    })?;
+
+    todo["seq"] = json!(seq);
+    todo["subtasks"] = Value::Array(subs.into_iter().map(|(_, v)| v).collect());
+    let note = AuditNote::created(todo_id, &todo);
+    Ok((StatusCode::CREATED, note, Json(todo)))
+}
+
+// =============================================================================
+// 占位符
+// =============================================================================
+
+/// 内置变量 + 调用方 `params`（同名时 `params` 优先）。
+///
+/// - `date`：`YYYY-MM-DD`
+/// - `week`：ISO 周，如 `2026-W07`
+fn template_vars(
+    clock: &Clock,
+    params: Option<&Value>,
+) -> Result<HashMap<String, String>, ApiError> {
+    let today = clock.today();
+    let week = today.iso_week();
+    let mut vars = HashMap::from([
+        ("date".to_string(), today.format("%Y-%m-%d").to_string()),
+        (
+            "week".to_string(),
+            format!("{}-W{:02}", week.year(), week.week()),
+        ),
+    ]);
+
+    let params = match params {
+        None | Some(Value::Null) => return Ok(vars),
+        Some(Value::Object(m)) => m,
+        Some(_) => {
+            return Err(ApiError::validation(vec![FieldError::new(
+                "params",
+                "must be an object of strings",
+            )]))
+        }
+    };
+    let mut errs = Vec::new();
+    for (key, v) in params {
+        match v.as_str() {
+            Some(s) => {
+                vars.insert(key.clone(), s.to_string());
+            }
+            None => errs.push(FieldError::new(
+                format!("params.{}", key),
+                "must be a string",
+            )),
+        }
+    }
+    if errs.is_empty() {
+        Ok(vars)
+    } else {
+        Err(ApiError::validation(errs))
+    }
+}
+
+/// 展开 `{{key}}`，key 两侧允许空格，只认字母 / 数字 / `_`。`vars` 里没有的
+/// key 记进 `missing`、原文保留；不像占位符的 `{{` 原样输出。
+fn expand(text: &str, vars: &HashMap<String, String>, missing: &mut BTreeSet<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let key = after.find("}}").map(|end| (end, after[..end].trim()));
+        match key {
+            Some((end, key))
+                if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+            {
+                match vars.get(key) {
+                    Some(v) => out.push_str(v),
+                    None => {
+                        missing.insert(key.to_string());
+                        out.push_str(&rest[start..start + 2 + end + 2]);
+                    }
+                }
+                rest = &after[end + 2..];
+            }
+            _ => {
+                out.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// =============================================================================
+// 工具
+// =============================================================================
+
+fn template_not_found(id: &str) -> ApiError {
+    ApiError::not_found(format!("template {} not found", id))
+}
+
+fn template_json(row: &repo::TemplateRow) -> Value {
+    serde_json::from_str(&row.data_json).unwrap_or_else(|_| json!({"id": row.id}))
+}
+
+fn str_of<'a>(v: &'a Value, key: &str) -> &'a str {
+    v.get(key).and_then(Value::as_str).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars() -> HashMap<String, String> {
+        HashMap::from([
+            ("date".to_string(), "2026-02-13".to_string()),
+            ("name".to_string(), "小王".to_string()),
+        ])
+    }
+
+    #[test]
+    fn expand_replaces_known_and_reports_missing() {
+        let mut missing = BTreeSet::new();
+        let out = expand(
+            "{{ name }} 入职 {{date}}，导师 {{mentor}}",
+            &vars(),
+            &mut missing,
+        );
+        assert_eq!(out, "小王 入职 2026-02-13，导师 {{mentor}}");
+        assert_eq!(missing.into_iter().collect::<Vec<_>>(), vec!["mentor"]);
+    }
+
+    #[test]
+    fn expand_leaves_non_placeholders_alone() {
+        let mut missing = BTreeSet::new();
+        assert_eq!(
+            expand("a {{ b {{name}} {{}} {{x-y}} {{", &vars(), &mut missing),
+            "a {{ b 小王 {{}} {{x-y}} {{"
+        );
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn builtin_vars_follow_client_today_and_params_override() {
+        let tz: chrono_tz::Tz = "Asia/Shanghai".parse().unwrap();
+        let mut clock = Clock::new(tz, tz);
+        clock.now = chrono::DateTime::parse_from_rfc3339("2026-01-01T20:00:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        let v = template_vars(&clock, None).unwrap();
+        assert_eq!(v["date"], "2026-01-02");
+        assert_eq!(v["week"], "2026-W01");
+
+        let v = template_vars(&clock, Some(&json!({"date": "明天", "name": "x"}))).unwrap();
+        assert_eq!(v["date"], "明天");
+        assert_eq!(v["name"], "x");
+
+        let err = template_vars(&clock, Some(&json!({"n": 1}))).unwrap_err();
+        assert_eq!(err.errors[0].field, "params.n");
+    }
+}
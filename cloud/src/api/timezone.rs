@@ -0,0 +1,36 @@
+//! `X-Timezone` 请求头：请求方所在的 IANA 时区（如 `America/New_York`）。
+//!
+//! 只影响"相对时间怎么理解"——`filter=` 里的 `today` / `now-2h`、快速添加的
+//! "明天下午3点"、`/stats` 默认区间的"今天"、模板实例化时的 `{{date}}` /
+//! `{{week}}`。落库的 `createdAt` / `updatedAt`
+//! 等时间戳始终按 `config.timezone` 生成（必须与 PC 端一致，否则 LWW 比较会
+//! 错乱），快速添加解析出的时间也会换算到 `config.timezone` 再保存。
+
+use axum::http::{HeaderMap, HeaderName};
+use chrono_tz::Tz;
+
+use super::error::ApiError;
+use super::AppState;
+use crate::time::Clock;
+
+pub const X_TIMEZONE: HeaderName = HeaderName::from_static("x-timezone");
+
+/// 本次请求的时钟：请求方时区取 `X-Timezone`，缺省与存储时区相同。
+/// 头存在但不是合法 IANA 时区名时 400，而不是悄悄回退。
+pub fn request_clock(state: &AppState, headers: &HeaderMap) -> Result<Clock, ApiError> {
+    let storage = state.config.get().timezone;
+    let Some(raw) = headers.get(X_TIMEZONE) else {
+        return Ok(Clock::new(storage, storage));
+    };
+    let client = raw
+        .to_str()
+        .ok()
+        .and_then(|s| s.trim().parse::<Tz>().ok())
+        .ok_or_else(|| {
+            ApiError::bad_request(format!(
+                "invalid X-Timezone '{}': expected an IANA time zone name like Asia/Shanghai",
+                String::from_utf8_lossy(raw.as_bytes())
+            ))
+        })?;
+    Ok(Clock::new(client, storage))
+}
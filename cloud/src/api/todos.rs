@@ -2,30 +2,45 @@
 //!
 //! 写路径统一：变更 → `repo::mark_dirty(conn)` 唤醒 push worker（置 dirty 并
 //! 递增 `dirty_generation`，push 据此判断推送窗口期内是否又有新写入）。
-//! merge 语义：PATCH 把请求 body 的字段覆盖到 `data_json` 上，未提及字段保留
-//! （包括 PC 端 v24/v25 加的未知字段也透传）。
+//! merge 语义：PATCH 按 Content-Type 选择浅合并 / RFC 7396 / RFC 6902（见
+//! `patch`），未提及字段保留（包括 PC 端 v24/v25 加的未知字段也透传）。已知
+//! 字段写入前按 PC 模型校验（见 `validate`），不合法 → 422。
 
-use axum::extract::{Path, Query, State};
-use axum::http::StatusCode;
-use axum::response::IntoResponse;
-use axum::Json;
+use axum::body::Bytes;
+use axum::extract::{Path, Query, RawQuery, State};
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::{Extension, Json};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::NaiveDateTime;
+use minitodo_model as model;
 use rusqlite::Connection;
 use serde::Deserialize;
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
 
-use super::error::ApiError;
+use super::audit::AuditNote;
+use super::error::{ApiError, FieldError};
 use super::ids::new_id_string;
+use super::patch::{self, PatchKind};
+use super::subtasks::next_sort_order;
+use super::timezone::request_clock;
+use super::validate;
 use super::AppState;
-use crate::db::repo::{self, ListTodosFilter};
-use crate::time::now_local_string;
+use crate::db::filter;
+use crate::db::repo::{self, ListTodosFilter, TodoCursor};
+use crate::time::{convert_local, now_local_string, Clock};
 
 const TOMBSTONE_TODO: &str = "todo";
 
+/// cursor 模式下未指定 `limit` 时的页大小。
+const DEFAULT_PAGE_SIZE: i64 = 50;
+
 // =============================================================================
 // Query 参数
 // =============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ListTodosQuery {
     pub completed: Option<String>,
@@ -35,10 +50,21 @@ pub struct ListTodosQuery {
     pub due_date_after: Option<String>,
     pub start_date: Option<String>,
     pub q: Option<String>,
+    /// 过滤表达式（见 `db::filter`），如 `quadrant in (1,2) and not completed`。
+    pub filter: Option<String>,
+    /// 逗号分隔多键排序，如 `-priority,+dueDate`。
     pub sort: Option<String>,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    /// keyset 分页游标；出现（含空串 = 第一页）即进入 cursor 模式，响应变为
+    /// `{items, nextCursor}`。
+    pub cursor: Option<String>,
     pub with_subtasks: Option<String>,
+    /// 标签过滤，可重复：`tag=work&tag=-personal`（`-` 前缀表示排除）。
+    /// serde_urlencoded 不支持重复 key 收进 `Vec`，由 `list_todos` 从原始
+    /// query 串解析（见 [`tag_params`]）。
+    #[serde(skip)]
+    pub tag: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,6 +73,12 @@ pub struct GetTodoQuery {
     pub with_subtasks: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct DemoteQuery {
+    /// 目标 todo（支持 `C` 短码）。
+    pub into: Option<String>,
+}
+
 // =============================================================================
 // GET /todos
 // =============================================================================
@@ -54,52 +86,140 @@ pub struct GetTodoQuery {
 pub async fn list_todos(
     State(state): State<AppState>,
     Query(q): Query<ListTodosQuery>,
-) -> Result<Json<Value>, ApiError> {
-    let filter = parse_list_filter(&q)?;
+    RawQuery(raw_query): RawQuery,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let clock = request_clock(&state, &headers)?;
+    let q = ListTodosQuery {
+        tag: tag_params(raw_query.as_deref()),
+        ..q
+    };
+    let (headers, body) = list_todos_page(&state, &clock, &q, raw_query.as_deref(), "/todos")?;
+    Ok((headers, Json(body)).into_response())
+}
+
+/// `GET /todos` 的主体：过滤 / 排序 / 分页，返回响应头（`X-Total-Count`、`Link`）
+/// 与 body。`path` 用于拼 `Link` 的下一页地址，saved view 复用时传自己的路径。
+pub(super) fn list_todos_page(
+    state: &AppState,
+    clock: &Clock,
+    q: &ListTodosQuery,
+    raw_query: Option<&str>,
+    path: &str,
+) -> Result<(HeaderMap, Value), ApiError> {
+    let mut filter = parse_list_filter(q, clock)?;
     let with_subtasks = parse_bool_flag(&q.with_subtasks);
 
-    let todos = state.db.with_conn(|conn| -> rusqlite::Result<Value> {
-        let rows = repo::list_todos_filtered(conn, &filter)?;
-        // 一次性 join 出 seq 表，避免逐条查询。
-        let ids: Vec<String> = rows.iter().map(|r| r.id.clone()).collect();
-        let seqs = repo::seq_map_for_todos(conn, &ids)?;
-        let mut out = Vec::with_capacity(rows.len());
-        for row in rows {
-            let mut v: Value = serde_json::from_str(&row.data_json)
-                .unwrap_or_else(|_| json!({"id": row.id, "raw": row.data_json}));
-            let id_str = id_field_as_string(&v).unwrap_or_else(|| row.id.clone());
-            if let Some(seq) = seqs.get(&row.id) {
-                if let Some(obj) = v.as_object_mut() {
-                    obj.insert("seq".into(), json!(seq));
-                }
-            }
+    // cursor 模式：默认页大小 DEFAULT_PAGE_SIZE；多取一条判断是否还有下一页。
+    let cursor_mode = q.cursor.is_some();
+    let page_size = if cursor_mode {
+        if filter.offset.is_some() {
+            return Err(ApiError::bad_request(
+                "cursor and offset cannot be combined",
+            ));
+        }
+        let spec = sort_spec(&filter);
+        filter.after = match q.cursor.as_deref().filter(|c| !c.is_empty()) {
+            Some(token) => Some(decode_cursor(token, &spec)?),
+            None => None,
+        };
+        let size = filter.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        filter.limit = Some(size + 1);
+        Some(size)
+    } else {
+        None
+    };
 
-            if with_subtasks {
-                let subs = repo::list_subtasks_for_todo(conn, &id_str)?;
-                let subs_vals: Vec<Value> = subs
-                    .into_iter()
-                    .map(|s| {
-                        serde_json::from_str::<Value>(&s.data_json)
-                            .unwrap_or_else(|_| json!({"id": s.id}))
-                    })
-                    .collect();
-                v["subtasks"] = Value::Array(subs_vals);
-            } else {
-                let n = repo::count_subtasks_for_todo(conn, &id_str)?;
-                v["subtaskCount"] = json!(n);
-                // 不嵌套时移除已有 subtasks 数组以避免 token 浪费（保留 count）
-                if v.get("subtasks").is_some() {
-                    if let Value::Object(ref mut map) = v {
-                        map.remove("subtasks");
+    let (todos, total, next) = state.db.with_conn(
+        |conn| -> rusqlite::Result<(Value, i64, Option<TodoCursor>)> {
+            let mut rows = repo::list_todos_filtered(conn, &filter)?;
+            let total = repo::count_todos_filtered(conn, &filter)?;
+            let next = match page_size {
+                Some(size) if rows.len() as i64 > size => {
+                    rows.truncate(size as usize);
+                    match rows.last() {
+                        Some(last) => repo::cursor_for_todo(conn, &filter, &last.id)?,
+                        None => None,
                     }
                 }
+                _ => None,
+            };
+            Ok((todos_to_json(conn, rows, with_subtasks)?, total, next))
+        },
+    )?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert("x-total-count", HeaderValue::from(total));
+
+    let body = if cursor_mode {
+        let next_token = next.map(|c| encode_cursor(&c, &sort_spec(&filter)));
+        if let Some(ref token) = next_token {
+            insert_next_link(&mut headers, path, raw_query, "cursor", token);
+        }
+        json!({"items": todos, "nextCursor": next_token})
+    } else {
+        // offset 模式也给 Link：limit 存在且后面还有数据时指向下一页 offset
+        if let Some(limit) = filter.limit {
+            let next_offset = filter.offset.unwrap_or(0) + limit;
+            if next_offset < total {
+                insert_next_link(
+                    &mut headers,
+                    path,
+                    raw_query,
+                    "offset",
+                    &next_offset.to_string(),
+                );
+            }
+        }
+        todos
+    };
+
+    Ok((headers, body))
+}
+
+/// 行 → API JSON：注入 seq；按需内联 subtasks 或只给 subtaskCount。
+fn todos_to_json(
+    conn: &Connection,
+    rows: Vec<repo::TodoRow>,
+    with_subtasks: bool,
+) -> rusqlite::Result<Value> {
+    // 一次性 join 出 seq 表，避免逐条查询。
+    let ids: Vec<String> = rows.iter().map(|r| r.id.clone()).collect();
+    let seqs = repo::seq_map_for_todos(conn, &ids)?;
+    let mut out = Vec::with_capacity(rows.len());
+    for row in rows {
+        let mut v: Value = serde_json::from_str(&row.data_json)
+            .unwrap_or_else(|_| json!({"id": row.id, "raw": row.data_json}));
+        let id_str = id_field_as_string(&v).unwrap_or_else(|| row.id.clone());
+        if let Some(seq) = seqs.get(&row.id) {
+            if let Some(obj) = v.as_object_mut() {
+                obj.insert("seq".into(), json!(seq));
             }
-            out.push(v);
         }
-        Ok(Value::Array(out))
-    })?;
 
-    Ok(Json(todos))
+        if with_subtasks {
+            let subs = repo::list_subtasks_for_todo(conn, &id_str)?;
+            let subs_vals: Vec<Value> = subs
+                .into_iter()
+                .map(|s| {
+                    serde_json::from_str::<Value>(&s.data_json)
+                        .unwrap_or_else(|_| json!({"id": s.id}))
+                })
+                .collect();
+            v["subtasks"] = Value::Array(subs_vals);
+        } else {
+            let n = repo::count_subtasks_for_todo(conn, &id_str)?;
+            v["subtaskCount"] = json!(n);
+            // 不嵌套时移除已有 subtasks 数组以避免 token 浪费（保留 count）
+            if v.get("subtasks").is_some() {
+                if let Value::Object(ref mut map) = v {
+                    map.remove("subtasks");
+                }
+            }
+        }
+        out.push(v);
+    }
+    Ok(Value::Array(out))
 }
 
 // =============================================================================
@@ -161,7 +281,7 @@ pub async fn get_todo(
 pub async fn create_todo(
     State(state): State<AppState>,
     Json(body): Json<Value>,
-) -> Result<(StatusCode, Json<Value>), ApiError> {
+) -> Result<(StatusCode, Extension<AuditNote>, Json<Value>), ApiError> {
     if !body.is_object() {
         return Err(ApiError::bad_request("body must be a JSON object"));
     }
@@ -171,24 +291,22 @@ pub async fn create_todo(
         .filter(|s| !s.trim().is_empty())
         .ok_or_else(|| ApiError::bad_request("title is required"))?
         .to_string();
-
-    let now = now_local_string(state.config.timezone_offset);
-    let id_str = new_id_string();
+    if let Some(fields) = body.as_object() {
+        validate::validate_todo(fields)?;
+    }
 
     let mut obj = body.as_object().cloned().unwrap_or_default();
-    // id 强制由服务端生成（i64 数字形式，与 PC SQLite AUTOINCREMENT 兼容）
-    obj.insert("id".into(), json!(id_str.parse::<i64>().unwrap_or(0)));
     obj.insert("title".into(), json!(title));
-    obj.entry("createdAt").or_insert(json!(now.clone()));
-    obj.insert("updatedAt".into(), json!(now.clone()));
-    obj.entry("completed").or_insert(json!(false));
-    obj.entry("color").or_insert(json!("#10B981"));
-    obj.entry("quadrant").or_insert(json!(4));
-    obj.entry("sortOrder").or_insert(json!(0));
-    obj.entry("notifyBefore").or_insert(json!(0));
-    obj.entry("notified").or_insert(json!(false));
+    let v = insert_new_todo(&state, obj)?;
+    let note = AuditNote::created(v["id"].to_string(), &v);
+    Ok((StatusCode::CREATED, note, Json(v)))
+}
 
-    let v = Value::Object(obj);
+/// 补齐服务端字段与 PC 默认值后写入新 todo，返回带 seq 的 API 视角 JSON。
+/// `obj` 须已通过 `validate::validate_todo`。
+fn insert_new_todo(state: &AppState, obj: Map<String, Value>) -> Result<Value, ApiError> {
+    let now = now_local_string(state.config.get().timezone);
+    let (id_str, v) = new_todo_value(obj, &now);
     let body_str = v.to_string();
 
     // 写 todo + 分配 seq 在同一事务里。seq 是 cloud-only 字段，存独立的
@@ -205,8 +323,100 @@ pub async fn create_todo(
     if let Some(obj) = v.as_object_mut() {
         obj.insert("seq".into(), json!(seq));
     }
+    Ok(v)
+}
 
-    Ok((StatusCode::CREATED, Json(v)))
+/// 新 todo：生成 id，补齐服务端字段与 PC 默认值。返回 `(id, todo JSON)`，不落库。
+pub(crate) fn new_todo_value(mut obj: Map<String, Value>, now: &str) -> (String, Value) {
+    let id_str = new_id_string();
+
+    // id 强制由服务端生成（i64 数字形式，与 PC SQLite AUTOINCREMENT 兼容）
+    obj.insert("id".into(), json!(id_str.parse::<i64>().unwrap_or(0)));
+    obj.entry("createdAt").or_insert(json!(now));
+    obj.insert("updatedAt".into(), json!(now));
+    obj.entry("completed").or_insert(json!(false));
+    if obj.get("completed") == Some(&json!(true)) {
+        obj.entry("completedAt").or_insert(json!(now));
+    }
+    obj.entry("color").or_insert(json!("#10B981"));
+    obj.entry("quadrant").or_insert(json!(4));
+    obj.entry("sortOrder").or_insert(json!(0));
+    obj.entry("notifyBefore").or_insert(json!(0));
+    obj.entry("notified").or_insert(json!(false));
+    (id_str, Value::Object(obj))
+}
+
+// =============================================================================
+// POST /todos/quick
+// =============================================================================
+
+/// 一行自然语言快速创建 todo，解析规则见 `minitodo_quickadd`（与 PC 托盘
+/// 快速添加共用）。"明天下午3点"之类按请求方时区（`X-Timezone`，缺省
+/// `config.timezone`）理解，保存前换算成 `config.timezone` 的墙钟时间。
+/// 响应 `{todo, parsed}`：`parsed` 是解析明细，含识别出的各片段原文。
+pub async fn quick_add_todo(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> Result<(StatusCode, Extension<AuditNote>, Json<Value>), ApiError> {
+    let clock = request_clock(&state, &headers)?;
+    let text = body
+        .get("text")
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.trim().is_empty())
+        .ok_or_else(|| ApiError::bad_request("text is required"))?;
+
+    let mut parsed = minitodo_quickadd::parse(text, clock.client_now().naive_local());
+    for t in [
+        &mut parsed.start_time,
+        &mut parsed.end_time,
+        &mut parsed.notify_at,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        *t = to_storage_zone(t, &clock);
+    }
+    if parsed.title.is_empty() {
+        return Err(ApiError::validation(vec![FieldError::new(
+            "text",
+            "nothing left for the title after removing date / time / tags",
+        )]));
+    }
+
+    let mut obj = Map::new();
+    obj.insert("title".into(), json!(parsed.title));
+    for (key, v) in [
+        ("startTime", &parsed.start_time),
+        ("endTime", &parsed.end_time),
+        ("notifyAt", &parsed.notify_at),
+    ] {
+        if let Some(v) = v {
+            obj.insert(key.into(), json!(v));
+        }
+    }
+    if let Some(q) = parsed.quadrant {
+        obj.insert("quadrant".into(), json!(q));
+    }
+    if !parsed.tags.is_empty() {
+        obj.insert("tags".into(), json!(parsed.tags));
+    }
+    if parsed.repeat_enabled {
+        obj.insert("repeatEnabled".into(), json!(true));
+        obj.insert("repeatType".into(), json!(parsed.repeat_type));
+        obj.insert("repeatInterval".into(), json!(parsed.repeat_interval));
+        obj.insert("repeatWeekdays".into(), json!(parsed.repeat_weekdays));
+        obj.insert("repeatMonthDay".into(), json!(parsed.repeat_month_day));
+    }
+    validate::validate_todo(&obj)?;
+
+    let todo = insert_new_todo(&state, obj)?;
+    let note = AuditNote::created(todo["id"].to_string(), &todo);
+    Ok((
+        StatusCode::CREATED,
+        note,
+        Json(json!({"todo": todo, "parsed": parsed})),
+    ))
 }
 
 // =============================================================================
@@ -216,42 +426,312 @@ pub async fn create_todo(
 pub async fn patch_todo(
     State(state): State<AppState>,
     Path(raw_id): Path<String>,
-    Json(body): Json<Value>,
-) -> Result<Json<Value>, ApiError> {
-    if !body.is_object() {
-        return Err(ApiError::bad_request("body must be a JSON object"));
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(Extension<AuditNote>, Json<Value>), ApiError> {
+    let kind = PatchKind::from_headers(&headers)?;
+    let body: Value = serde_json::from_slice(&body)?;
+
+    let now = now_local_string(state.config.get().timezone);
+
+    let updated: Option<(String, Value, Value)> =
+        state
+            .db
+            .with_conn(|conn| -> Result<Option<(String, Value, Value)>, ApiError> {
+                let Some(id) = resolve_todo_ref(conn, &raw_id)? else {
+                    return Ok(None);
+                };
+                let Some(row) = repo::get_todo(conn, &id)? else {
+                    return Ok(None);
+                };
+                let before: Value =
+                    serde_json::from_str(&row.data_json).unwrap_or_else(|_| json!({"id": row.id}));
+                let mut current = before.clone();
+                patch::apply(kind, &mut current, &body)?;
+                validate::validate_todo_change(&before, &current)?;
+                stamp_completed_at(&before, &mut current, &now);
+                // 防止 PATCH body 改 id
+                if let Some(obj) = current.as_object_mut() {
+                    obj.insert("id".into(), json!(id.parse::<i64>().unwrap_or(0)));
+                    obj.insert("updatedAt".into(), json!(now.clone()));
+                }
+                let body_str = current.to_string();
+                repo::upsert_todo(conn, &id, &body_str, &now)?;
+                repo::mark_dirty(conn)?;
+                attach_seq(conn, &id, &mut current);
+                Ok(Some((id, before, current)))
+            })?;
+
+    match updated {
+        Some((id, before, v)) => Ok((AuditNote::updated(id, &before, &v), Json(v))),
+        None => Err(ApiError::not_found(format!("todo {} not found", raw_id))),
     }
+}
+
+/// `completed` 翻转时维护 `completedAt`：false→true 记当前时间（body 显式给了
+/// 新值则以 body 为准，便于补录），true→false 清空。未翻转不动。
+fn stamp_completed_at(before: &Value, current: &mut Value, now: &str) {
+    let was = before
+        .get("completed")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let is = current
+        .get("completed")
+        .and_then(Value::as_bool)
+        .unwrap_or(false);
+    let Some(obj) = current.as_object_mut() else {
+        return;
+    };
+    match (was, is) {
+        (false, true) if obj.get("completedAt") == before.get("completedAt") => {
+            obj.insert("completedAt".into(), json!(now));
+        }
+        (true, false) => {
+            obj.insert("completedAt".into(), Value::Null);
+        }
+        _ => {}
+    }
+}
+
+// =============================================================================
+// POST /todos/reorder
+// =============================================================================
+
+/// body `{"ids": [...]}`：按数组顺序把 `sortOrder` 设为 0..n，与 PC
+/// `reorder_todos` 一致；只改 sortOrder 真变了的记录。id 支持 `C` 短码，任一
+/// id 不存在 → 404，整批不生效。返回重排后的 todos（不嵌套 subtasks）。
+pub async fn reorder_todos(
+    State(state): State<AppState>,
+    Json(body): Json<Value>,
+) -> Result<(Extension<AuditNote>, Json<Value>), ApiError> {
+    let refs = id_list(&body)?;
+    let now = now_local_string(state.config.get().timezone);
+
+    let (before, after, list) =
+        state
+            .db
+            .with_conn(|conn| -> Result<(Value, Value, Value), ApiError> {
+                let tx = conn.transaction()?;
+                let mut ids: Vec<String> = Vec::with_capacity(refs.len());
+                for r in &refs {
+                    let id = ensure_todo_exists(&tx, r)?;
+                    // `123` 与 `C1` 可能指向同一条
+                    if ids.contains(&id) {
+                        return Err(duplicate_id(r));
+                    }
+                    ids.push(id);
+                }
+
+                let mut before = Map::new();
+                let mut after = Map::new();
+                let mut changed = false;
+                for (idx, id) in ids.iter().enumerate() {
+                    let Some(row) = repo::get_todo(&tx, id)? else {
+                        return Err(ApiError::not_found(format!("todo {} not found", id)));
+                    };
+                    let mut v: Value = serde_json::from_str(&row.data_json)
+                        .unwrap_or_else(|_| json!({"id": row.id}));
+                    let old = v.get("sortOrder").and_then(Value::as_i64);
+                    before.insert(id.clone(), json!(old));
+                    after.insert(id.clone(), json!(idx));
+                    if old == Some(idx as i64) {
+                        continue;
+                    }
+                    if let Some(obj) = v.as_object_mut() {
+                        obj.insert("sortOrder".into(), json!(idx));
+                        obj.insert("updatedAt".into(), json!(now.clone()));
+                    }
+                    repo::upsert_todo(&tx, id, &v.to_string(), &now)?;
+                    changed = true;
+                }
+                if changed {
+                    repo::mark_dirty(&tx)?;
+                }
+
+                let mut rows = Vec::with_capacity(ids.len());
+                for id in &ids {
+                    rows.extend(repo::get_todo(&tx, id)?);
+                }
+                let list = todos_to_json(&tx, rows, false)?;
+                tx.commit()?;
+                Ok((Value::Object(before), Value::Object(after), list))
+            })?;
+
+    Ok((AuditNote::updated("todos", &before, &after), Json(list)))
+}
+
+// =============================================================================
+// POST /todos/:id/demote?into=
+// =============================================================================
+
+/// todo 降级为 `into` 的子任务：标题、描述（→ `content`）、完成状态带过去，排到
+/// 目标子任务末尾。子任务只有一层，它自带的子任务按原顺序接在后面，一起挪到
+/// 目标下（保留 id，同 `POST /subtasks/:id/move`）。日期、提醒、重复规则等子任务
+/// 没有的字段丢弃。原 todo 删除并写 tombstone。返回目标 todo（嵌套 subtasks）。
+pub async fn demote_todo(
+    State(state): State<AppState>,
+    Path(raw_id): Path<String>,
+    Query(q): Query<DemoteQuery>,
+) -> Result<(Extension<AuditNote>, Json<Value>), ApiError> {
+    let Some(into_ref) = q.into.filter(|s| !s.trim().is_empty()) else {
+        return Err(ApiError::bad_request("into is required"));
+    };
+    let now = now_local_string(state.config.get().timezone);
+
+    let (id, before, sub, target) =
+        state
+            .db
+            .with_conn(|conn| -> Result<(String, Value, Value, Value), ApiError> {
+                let tx = conn.transaction()?;
+                let id = ensure_todo_exists(&tx, &raw_id)?;
+                let into = ensure_todo_exists(&tx, &into_ref)?;
+                if id == into {
+                    return Err(ApiError::validation(vec![FieldError::new(
+                        "into",
+                        "cannot demote a todo into itself",
+                    )]));
+                }
+                let Some(row) = repo::get_todo(&tx, &id)? else {
+                    return Err(ApiError::not_found(format!("todo {} not found", raw_id)));
+                };
+                let before: Value =
+                    serde_json::from_str(&row.data_json).unwrap_or_else(|_| json!({"id": row.id}));
+                let into_num = json!(into.parse::<i64>().unwrap_or(0));
+                let mut order = next_sort_order(&tx, &into)?;
 
-    let now = now_local_string(state.config.timezone_offset);
+                let sub_id = new_id_string();
+                let sub = json!({
+                    "id": sub_id.parse::<i64>().unwrap_or(0),
+                    "parentId": into_num,
+                    "title": before.get("title").cloned().unwrap_or(json!("")),
+                    "content": before.get("description").cloned().unwrap_or(Value::Null),
+                    "completed": before.get("completed").and_then(Value::as_bool).unwrap_or(false),
+                    "sortOrder": order,
+                    "createdAt": now,
+                    "updatedAt": now,
+                });
+                repo::upsert_subtask(&tx, &sub_id, &into, &sub.to_string(), &now)?;
 
-    let updated: Option<Value> = state
+                for child in repo::list_subtasks_for_todo(&tx, &id)? {
+                    order += 1;
+                    let mut v: Value = serde_json::from_str(&child.data_json)
+                        .unwrap_or_else(|_| json!({"id": child.id}));
+                    if let Some(obj) = v.as_object_mut() {
+                        obj.insert("parentId".into(), into_num.clone());
+                        obj.insert("sortOrder".into(), json!(order));
+                        obj.insert("updatedAt".into(), json!(now.clone()));
+                    }
+                    repo::upsert_subtask(&tx, &child.id, &into, &v.to_string(), &now)?;
+                }
+
+                repo::delete_todo_cascade(&tx, &id)?;
+                repo::add_tombstone(&tx, TOMBSTONE_TODO, &id, &now)?;
+                repo::delete_seq(&tx, &id)?;
+                repo::mark_dirty(&tx)?;
+
+                let mut target = repo::get_todo(&tx, &into)?
+                    .and_then(|r| serde_json::from_str::<Value>(&r.data_json).ok())
+                    .unwrap_or_else(|| json!({"id": into_num}));
+                attach_seq(&tx, &into, &mut target);
+                let subs: Vec<Value> = repo::list_subtasks_for_todo(&tx, &into)?
+                    .into_iter()
+                    .map(|s| {
+                        serde_json::from_str::<Value>(&s.data_json)
+                            .unwrap_or_else(|_| json!({"id": s.id}))
+                    })
+                    .collect();
+                target["subtasks"] = Value::Array(subs);
+                tx.commit()?;
+                Ok((id, before, sub, target))
+            })?;
+
+    Ok((AuditNote::converted(id, &before, &sub), Json(target)))
+}
+
+// =============================================================================
+// POST /todos/:id/duplicate
+// =============================================================================
+
+/// 深拷贝一条 todo 及其子任务：新 id / 新 seq，`createdAt` / `updatedAt` 取当前
+/// 时间，`notified` 与完成状态（`completed` / `completedAt`）重置；其余字段（含
+/// 未知字段、排序）原样保留。
+/// body 可省略，或 `{"shiftDays": 7}`：`startTime` / `endTime` / `dueDate` /
+/// `notifyAt` 整体平移若干天（可为负）。子任务 `content` 里嵌的图片按文件名
+/// 引用，图片只增不删，副本直接沿用同一文件。响应同 `GET /todos/:id`。
+pub async fn duplicate_todo(
+    State(state): State<AppState>,
+    Path(raw_id): Path<String>,
+    body: Bytes,
+) -> Result<(StatusCode, Extension<AuditNote>, Json<Value>), ApiError> {
+    let body: Value = if body.is_empty() {
+        json!({})
+    } else {
+        serde_json::from_slice(&body)?
+    };
+    let shift = match body.get("shiftDays") {
+        None | Some(Value::Null) => 0,
+        Some(v) => v.as_i64().filter(|d| d.abs() <= 36500).ok_or_else(|| {
+            ApiError::validation(vec![FieldError::new(
+                "shiftDays",
+                "must be an integer number of days",
+            )])
+        })?,
+    };
+    let now = now_local_string(state.config.get().timezone);
+
+    let (new_id, todo) = state
         .db
-        .with_conn(|conn| -> rusqlite::Result<Option<Value>> {
-            let Some(id) = resolve_todo_ref(conn, &raw_id)? else {
-                return Ok(None);
+        .with_conn(|conn| -> Result<(String, Value), ApiError> {
+            let tx = conn.transaction()?;
+            let id = ensure_todo_exists(&tx, &raw_id)?;
+            let Some(row) = repo::get_todo(&tx, &id)? else {
+                return Err(ApiError::not_found(format!("todo {} not found", raw_id)));
             };
-            let Some(row) = repo::get_todo(conn, &id)? else {
-                return Ok(None);
-            };
-            let mut current: Value =
-                serde_json::from_str(&row.data_json).unwrap_or_else(|_| json!({"id": row.id}));
-            merge_json_shallow(&mut current, &body);
-            // 防止 PATCH body 改 id
-            if let Some(obj) = current.as_object_mut() {
-                obj.insert("id".into(), json!(id.parse::<i64>().unwrap_or(0)));
-                obj.insert("updatedAt".into(), json!(now.clone()));
+            let mut obj =
+                serde_json::from_str::<Map<String, Value>>(&row.data_json).unwrap_or_default();
+            obj.remove("createdAt");
+            obj.insert("notified".into(), json!(false));
+            // 副本是新待办：沿用原 completedAt 会显得它在创建之前就完成了
+            obj.insert("completed".into(), json!(false));
+            obj.insert("completedAt".into(), Value::Null);
+            for key in ["startTime", "endTime", "dueDate", "notifyAt"] {
+                if let Some(shifted) = obj
+                    .get(key)
+                    .and_then(Value::as_str)
+                    .and_then(|s| model::shift_days(s, shift))
+                {
+                    obj.insert(key.into(), json!(shifted));
+                }
+            }
+            let (new_id, mut todo) = new_todo_value(obj, &now);
+            let new_num = todo["id"].clone();
+            repo::upsert_todo(&tx, &new_id, &todo.to_string(), &now)?;
+            let seq = repo::assign_seq(&tx, &new_id)?;
+
+            let mut subs = Vec::new();
+            for child in repo::list_subtasks_for_todo(&tx, &id)? {
+                let mut v: Value = serde_json::from_str(&child.data_json)
+                    .unwrap_or_else(|_| json!({"id": child.id}));
+                let sid = new_id_string();
+                if let Some(sub) = v.as_object_mut() {
+                    sub.insert("id".into(), json!(sid.parse::<i64>().unwrap_or(0)));
+                    sub.insert("parentId".into(), new_num.clone());
+                    sub.insert("createdAt".into(), json!(now.clone()));
+                    sub.insert("updatedAt".into(), json!(now.clone()));
+                }
+                repo::upsert_subtask(&tx, &sid, &new_id, &v.to_string(), &now)?;
+                subs.push(v);
             }
-            let body_str = current.to_string();
-            repo::upsert_todo(conn, &id, &body_str, &now)?;
-            repo::mark_dirty(conn)?;
-            attach_seq(conn, &id, &mut current);
-            Ok(Some(current))
+            repo::mark_dirty(&tx)?;
+            tx.commit()?;
+
+            todo["seq"] = json!(seq);
+            todo["subtasks"] = Value::Array(subs);
+            Ok((new_id, todo))
         })?;
 
-    match updated {
-        Some(v) => Ok(Json(v)),
-        None => Err(ApiError::not_found(format!("todo {} not found", raw_id))),
-    }
+    let note = AuditNote::created(new_id, &todo);
+    Ok((StatusCode::CREATED, note, Json(todo)))
 }
 
 // =============================================================================
@@ -262,38 +742,44 @@ pub async fn delete_todo(
     State(state): State<AppState>,
     Path(raw_id): Path<String>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let now = now_local_string(state.config.timezone_offset);
-    let removed = state.db.with_conn(|conn| -> rusqlite::Result<bool> {
-        let tx = conn.transaction()?;
-        let id = match resolve_todo_ref(&tx, &raw_id)? {
-            Some(id) => id,
-            None => {
-                tx.commit()?;
-                return Ok(false);
-            }
-        };
-        // 先收集子任务 id：`delete_todo_cascade` 会把 subtasks 一起删掉，
-        // 若放在 cascade 之后再 query 就拿不到任何 id，导致 subtask tombstones 漏写。
-        let sub_ids: Vec<String> = tx
-            .prepare("SELECT id FROM subtasks WHERE todo_id = ?1")?
-            .query_map([&id], |r| r.get::<_, String>(0))?
-            .filter_map(|r| r.ok())
-            .collect();
-
-        let existed = repo::delete_todo_cascade(&tx, &id)?;
-        if existed {
-            repo::add_tombstone(&tx, TOMBSTONE_TODO, &id, &now)?;
-            for sid in sub_ids {
-                repo::add_tombstone(&tx, "subtask", &sid, &now)?;
+    let now = now_local_string(state.config.get().timezone);
+    let removed = state
+        .db
+        .with_conn(|conn| -> rusqlite::Result<Option<AuditNote>> {
+            let tx = conn.transaction()?;
+            let id = match resolve_todo_ref(&tx, &raw_id)? {
+                Some(id) => id,
+                None => {
+                    tx.commit()?;
+                    return Ok(None);
+                }
+            };
+            // 审计摘要要在删之前取
+            let before = repo::get_todo(&tx, &id)?
+                .and_then(|row| serde_json::from_str::<Value>(&row.data_json).ok())
+                .unwrap_or_else(|| json!({"id": id}));
+            // 先收集子任务 id：`delete_todo_cascade` 会把 subtasks 一起删掉，
+            // 若放在 cascade 之后再 query 就拿不到任何 id，导致 subtask tombstones 漏写。
+            let sub_ids: Vec<String> = tx
+                .prepare("SELECT id FROM subtasks WHERE todo_id = ?1")?
+                .query_map([&id], |r| r.get::<_, String>(0))?
+                .filter_map(|r| r.ok())
+                .collect();
+
+            let existed = repo::delete_todo_cascade(&tx, &id)?;
+            if existed {
+                repo::add_tombstone(&tx, TOMBSTONE_TODO, &id, &now)?;
+                for sid in sub_ids {
+                    repo::add_tombstone(&tx, "subtask", &sid, &now)?;
+                }
+                repo::delete_seq(&tx, &id)?;
+                repo::mark_dirty(&tx)?;
             }
-            repo::delete_seq(&tx, &id)?;
-            repo::mark_dirty(&tx)?;
-        }
-        tx.commit()?;
-        Ok(existed)
-    })?;
-    if removed {
-        Ok(StatusCode::NO_CONTENT)
+            tx.commit()?;
+            Ok(existed.then(|| AuditNote::deleted(id, &before).0))
+        })?;
+    if let Some(note) = removed {
+        Ok((StatusCode::NO_CONTENT, Extension(note)))
     } else {
         Err(ApiError::not_found(format!("todo {} not found", raw_id)))
     }
@@ -303,7 +789,19 @@ pub async fn delete_todo(
 // 工具
 // =============================================================================
 
-fn parse_list_filter(q: &ListTodosQuery) -> Result<ListTodosFilter, ApiError> {
+/// 快速添加解析出的 `YYYY-MM-DDTHH:MM:SS`（请求方时区）→ 存储时区。
+fn to_storage_zone(s: &str, clock: &Clock) -> String {
+    const FMT: &str = "%Y-%m-%dT%H:%M:%S";
+    match NaiveDateTime::parse_from_str(s, FMT) {
+        Ok(naive) => convert_local(naive, clock.client, clock.storage)
+            .format(FMT)
+            .to_string(),
+        Err(_) => s.to_string(),
+    }
+}
+
+/// `filter=` 里的相对日期按 `clock` 求值（见 `api::timezone`）。
+fn parse_list_filter(q: &ListTodosQuery, clock: &Clock) -> Result<ListTodosFilter, ApiError> {
     let completed = match q.completed.as_deref() {
         None => None,
         Some(s) => Some(
@@ -318,9 +816,37 @@ fn parse_list_filter(q: &ListTodosQuery) -> Result<ListTodosFilter, ApiError> {
                 .ok_or_else(|| ApiError::bad_request(format!("invalid quadrant: {}", s)))?,
         ),
     };
-    let sort = q.sort.as_deref().map(parse_sort);
+    let expr = match q.filter.as_deref() {
+        None => None,
+        Some(s) => filter::parse(s, clock).map_err(|e| {
+            ApiError::bad_request(format!("invalid filter: {}", describe_filter_error(s, &e)))
+        })?,
+    };
+    let sort = q
+        .sort
+        .as_deref()
+        .map(|s| {
+            s.split(',')
+                .map(str::trim)
+                .filter(|k| !k.is_empty())
+                .map(parse_sort)
+                .collect()
+        })
+        .unwrap_or_default();
     let limit = q.limit.filter(|&l| l > 0);
     let offset = q.offset.filter(|&o| o >= 0);
+    let mut tags_include = Vec::new();
+    let mut tags_exclude = Vec::new();
+    for t in &q.tag {
+        let (list, name) = match t.strip_prefix('-') {
+            Some(rest) => (&mut tags_exclude, rest),
+            None => (&mut tags_include, t.as_str()),
+        };
+        if name.trim().is_empty() {
+            return Err(ApiError::bad_request(format!("invalid tag: {:?}", t)));
+        }
+        list.push(name.to_string());
+    }
 
     Ok(ListTodosFilter {
         completed,
@@ -330,12 +856,30 @@ fn parse_list_filter(q: &ListTodosQuery) -> Result<ListTodosFilter, ApiError> {
         due_date_after: q.due_date_after.clone(),
         start_date: q.start_date.clone(),
         q: q.q.clone(),
+        tags_include,
+        tags_exclude,
+        expr,
         sort,
         limit,
         offset,
+        after: None,
     })
 }
 
+/// 原始 query 串里所有 `tag=` 的值（URL 解码，`+` 当空格）。
+fn tag_params(raw_query: Option<&str>) -> Vec<String> {
+    raw_query
+        .unwrap_or("")
+        .split('&')
+        .filter_map(|kv| kv.split_once('='))
+        .filter(|(k, _)| *k == "tag")
+        .map(|(_, v)| {
+            let v = v.replace('+', " ");
+            urlencoding::decode(&v).map(|s| s.into_owned()).unwrap_or(v)
+        })
+        .collect()
+}
+
 fn parse_bool(s: &str) -> Option<bool> {
     match s.to_ascii_lowercase().as_str() {
         "true" | "1" | "yes" => Some(true),
@@ -374,17 +918,75 @@ fn parse_sort(s: &str) -> (String, bool) {
     }
 }
 
-/// 浅合并：把 `patch` 的 top-level 字段覆盖到 `target`；patch 中的 `null` 也写入
-/// （表示显式置空）。这与 PC 端 PATCH 语义对齐。
-fn merge_json_shallow(target: &mut Value, patch: &Value) {
-    let (Value::Object(t), Value::Object(p)) = (target, patch) else {
-        return;
-    };
-    for (k, v) in p {
-        if k == "id" {
-            continue; // id 不能改
-        }
-        t.insert(k.clone(), v.clone());
+/// 当前排序的规范写法（如 `+sortOrder`），编进 cursor：换了排序再用旧 cursor
+/// 位置没有意义，直接 400。
+fn sort_spec(filter: &ListTodosFilter) -> String {
+    if filter.sort.is_empty() {
+        return "+sortOrder".to_string();
+    }
+    filter
+        .sort
+        .iter()
+        .map(|(field, asc)| format!("{}{}", if *asc { '+' } else { '-' }, field))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// 错误信息后附上出错位置起的一段原文，例如
+/// `unknown field 'bogus'; ... (at column 18) near 'bogus = 2'`。
+fn describe_filter_error(input: &str, e: &filter::FilterError) -> String {
+    let start = e.column.saturating_sub(1);
+    let snippet: String = input.chars().skip(start).take(20).collect();
+    if snippet.is_empty() {
+        e.to_string()
+    } else {
+        format!("{} near '{}'", e, snippet)
+    }
+}
+
+/// cursor token = base64url(JSON `{"s": sort_spec, "k": keys, "i": id}`)。
+/// 对客户端是不透明串，服务端可随时改内部结构。
+fn encode_cursor(cur: &TodoCursor, spec: &str) -> String {
+    let raw = json!({"s": spec, "k": cur.keys, "i": cur.id}).to_string();
+    URL_SAFE_NO_PAD.encode(raw)
+}
+
+fn decode_cursor(token: &str, spec: &str) -> Result<TodoCursor, ApiError> {
+    let invalid = || ApiError::bad_request("invalid cursor");
+    let bytes = URL_SAFE_NO_PAD.decode(token).map_err(|_| invalid())?;
+    let v: Value = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+    if v["s"].as_str() != Some(spec) {
+        return Err(ApiError::bad_request(
+            "cursor was issued for a different sort; restart from the first page",
+        ));
+    }
+    let keys = v["k"].as_array().cloned().ok_or_else(invalid)?;
+    let id = v["i"].as_str().ok_or_else(invalid)?.to_string();
+    Ok(TodoCursor { keys, id })
+}
+
+/// `Link: <{path}?...&{param}={value}>; rel="next"`；保留原 query 里的其他参数。
+fn insert_next_link(
+    headers: &mut HeaderMap,
+    path: &str,
+    raw_query: Option<&str>,
+    param: &str,
+    value: &str,
+) {
+    let mut parts: Vec<String> = raw_query
+        .unwrap_or("")
+        .split('&')
+        .filter(|kv| !kv.is_empty())
+        .filter(|kv| {
+            let key = kv.split('=').next().unwrap_or("");
+            key != param && key != "cursor" && key != "offset"
+        })
+        .map(|kv| kv.to_string())
+        .collect();
+    parts.push(format!("{}={}", param, urlencoding::encode(value)));
+    let link = format!("<{}?{}>; rel=\"next\"", path, parts.join("&"));
+    if let Ok(v) = HeaderValue::from_str(&link) {
+        headers.insert(header::LINK, v);
     }
 }
 
@@ -424,6 +1026,43 @@ pub(crate) fn attach_seq(conn: &Connection, internal_id: &str, todo_json: &mut V
     }
 }
 
+/// reorder 类请求的 body `{"ids": [...]}`：非空数组，元素是整数或非空字符串
+/// （todo 还可以是 `C` 短码）。统一转成字符串，字面重复的 id → 422。
+pub(crate) fn id_list(body: &Value) -> Result<Vec<String>, ApiError> {
+    let invalid = || {
+        ApiError::validation(vec![FieldError::new(
+            "ids",
+            "must be a non-empty array of ids",
+        )])
+    };
+    let Some(arr) = body.get("ids").and_then(Value::as_array) else {
+        return Err(invalid());
+    };
+    if arr.is_empty() {
+        return Err(invalid());
+    }
+    let mut out: Vec<String> = Vec::with_capacity(arr.len());
+    for v in arr {
+        let id = match v {
+            Value::Number(n) if n.is_i64() => n.to_string(),
+            Value::String(s) if !s.trim().is_empty() => s.trim().to_string(),
+            _ => return Err(invalid()),
+        };
+        if out.contains(&id) {
+            return Err(duplicate_id(&id));
+        }
+        out.push(id);
+    }
+    Ok(out)
+}
+
+fn duplicate_id(id: &str) -> ApiError {
+    ApiError::validation(vec![FieldError::new(
+        "ids",
+        format!("contains {} more than once", id),
+    )])
+}
+
 /// 子任务嵌套创建工具：供 subtasks 模块共用。返回**内部 todo_id**（解析过 ref）。
 pub(crate) fn ensure_todo_exists(conn: &Connection, raw: &str) -> Result<String, ApiError> {
     match resolve_todo_ref(conn, raw) {
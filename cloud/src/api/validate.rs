@@ -0,0 +1,578 @@
+//! 写路径字段校验：按 PC 端 `Todo` / `SubTask` struct 的类型约束检查 body。
+//!
+//! PC 端反序列化是严格 typed 的——cloud 若把 `quadrant: "banana"` 写进
+//! `data_json`，push 到 WebDAV 后 PC pull 整份 sync-data 都会解析失败。所以
+//! 已知字段在写入前必须满足 PC 模型；未知字段（PC 新版本加的、cloud-only 的）
+//! 不做限制，原样透传。
+//!
+//! 只校验 body 里**出现**的字段：create 时缺省字段由 handler 补默认值，
+//! PATCH 时按 patch 前后的 diff 校验（未变化字段不查，被删掉的 PC 必填字段
+//! 报错）——merge-patch 的 `null` 与 json-patch 的 `remove` 都可能删字段。
+
+use minitodo_model::{self as model, TagNameError};
+use serde_json::{Map, Value};
+
+use super::error::{ApiError, FieldError};
+
+const REPEAT_TYPES: [&str; 3] = ["daily", "weekly", "monthly"];
+
+/// PC `AppSettings` 里几个字符串枚举的取值。
+const VIEW_MODES: [&str; 2] = ["list", "quadrant"];
+const TEXT_THEMES: [&str; 2] = ["light", "dark"];
+const NOTIFICATION_TYPES: [&str; 2] = ["system", "app"];
+
+/// `GET /settings` 总是返回这几项（缺的用 PC 默认值补齐），PATCH 不能删。
+const SETTINGS_FIELDS: [&str; 6] = [
+    "viewMode",
+    "textTheme",
+    "notificationType",
+    "showCalendar",
+    "windowBgColor",
+    "windowBgAlpha",
+];
+
+/// PC `Todo` 中非 `Option`、无 `#[serde(default)]` 的字段：缺了整条反序列化失败。
+/// `id` 由 handler 强制写回，不在此列。
+const TODO_REQUIRED: [&str; 9] = [
+    "title",
+    "color",
+    "quadrant",
+    "notifyBefore",
+    "notified",
+    "completed",
+    "sortOrder",
+    "createdAt",
+    "updatedAt",
+];
+
+/// PC `SubTask` 的必填字段（`id` / `parentId` 由 handler 强制写回）。
+const SUBTASK_REQUIRED: [&str; 5] = ["title", "completed", "sortOrder", "createdAt", "updatedAt"];
+
+/// PC `Tag` 的必填字段。
+const TAG_REQUIRED: [&str; 4] = ["name", "color", "createdAt", "updatedAt"];
+
+/// PC `TodoTemplate` 的必填字段（`id` 由 handler 强制写回）。
+const TEMPLATE_REQUIRED: [&str; 7] = [
+    "name",
+    "title",
+    "color",
+    "quadrant",
+    "subtasks",
+    "createdAt",
+    "updatedAt",
+];
+
+/// 校验 todo body 中出现的已知字段。
+pub fn validate_todo(body: &Map<String, Value>) -> Result<(), ApiError> {
+    let mut errs = Vec::new();
+    for (key, v) in body {
+        let res = match key.as_str() {
+            "title" => non_empty_string(v),
+            "description" => nullable(v, string),
+            "color" => color(v),
+            "quadrant" => int_in_range(v, 1, 4),
+            "notifyAt" | "startTime" | "endTime" | "completedAt" => nullable(v, datetime),
+            "notifyBefore" => int_in_range(v, 0, i32::MAX as i64),
+            "notified" | "completed" | "repeatEnabled" => boolean(v),
+            "sortOrder" => int_in_range(v, i32::MIN as i64, i32::MAX as i64),
+            "createdAt" | "updatedAt" => datetime(v),
+            "repeatType" => nullable(v, repeat_type),
+            "repeatInterval" => int_in_range(v, 1, i32::MAX as i64),
+            "repeatWeekdays" => nullable(v, weekdays),
+            "repeatMonthDay" => nullable(v, |v| int_in_range(v, 1, 31)),
+            "tags" => tag_list(v),
+            "priority" => nullable(v, |v| one_of(v, &model::PRIORITIES)),
+            "dueDate" => nullable(v, due_date),
+            _ => Ok(()),
+        };
+        if let Err(msg) = res {
+            errs.push(FieldError::new(key.clone(), msg));
+        }
+    }
+    finish(errs)
+}
+
+/// 校验 subtask body 中出现的已知字段。
+pub fn validate_subtask(body: &Map<String, Value>) -> Result<(), ApiError> {
+    let mut errs = Vec::new();
+    for (key, v) in body {
+        let res = match key.as_str() {
+            "title" => non_empty_string(v),
+            "content" => nullable(v, string),
+            "completed" => boolean(v),
+            "sortOrder" => int_in_range(v, i32::MIN as i64, i32::MAX as i64),
+            "createdAt" | "updatedAt" => datetime(v),
+            _ => Ok(()),
+        };
+        if let Err(msg) = res {
+            errs.push(FieldError::new(key.clone(), msg));
+        }
+    }
+    finish(errs)
+}
+
+/// 校验模板 body 中出现的已知字段。`title` / `description` / 子任务里的
+/// `{{…}}` 占位符在这里只当普通文本，实例化时才展开。
+pub fn validate_template(body: &Map<String, Value>) -> Result<(), ApiError> {
+    let mut errs = Vec::new();
+    for (key, v) in body {
+        let res = match key.as_str() {
+            "name" | "title" => non_empty_string(v),
+            "description" => nullable(v, string),
+            "color" => color(v),
+            "quadrant" => int_in_range(v, 1, 4),
+            "subtasks" => template_subtasks(v),
+            "createdAt" | "updatedAt" => datetime(v),
+            _ => Ok(()),
+        };
+        if let Err(msg) = res {
+            errs.push(FieldError::new(key.clone(), msg));
+        }
+    }
+    finish(errs)
+}
+
+/// 校验标签 body 中出现的已知字段。
+pub fn validate_tag(body: &Map<String, Value>) -> Result<(), ApiError> {
+    let mut errs = Vec::new();
+    for (key, v) in body {
+        let res = match key.as_str() {
+            "name" => tag_name(v),
+            "color" => color(v),
+            "createdAt" | "updatedAt" => datetime(v),
+            _ => Ok(()),
+        };
+        if let Err(msg) = res {
+            errs.push(FieldError::new(key.clone(), msg));
+        }
+    }
+    finish(errs)
+}
+
+/// 校验 `PATCH /settings` 改动的字段。只接受 PC `AppSettings` 里跨设备同步的
+/// 那几项（窗口位置 / 大小、贴边隐藏等是各台 PC 自己的，不在此列）。
+pub fn validate_settings(body: &Map<String, Value>) -> Result<(), ApiError> {
+    let mut errs = Vec::new();
+    for (key, v) in body {
+        let res = match key.as_str() {
+            "viewMode" => one_of(v, &VIEW_MODES),
+            "textTheme" => one_of(v, &TEXT_THEMES),
+            "notificationType" => one_of(v, &NOTIFICATION_TYPES),
+            "showCalendar" => boolean(v),
+            "windowBgColor" => color(v),
+            "windowBgAlpha" => number_in_range(v, 0.0, 1.0),
+            _ => Err("is not an editable setting".into()),
+        };
+        if let Err(msg) = res {
+            errs.push(FieldError::new(key.clone(), msg));
+        }
+    }
+    finish(errs)
+}
+
+/// PATCH 后校验：只查新增 / 变化的字段，外加被删掉的必填字段。
+pub fn validate_todo_change(before: &Value, after: &Value) -> Result<(), ApiError> {
+    validate_change(before, after, &TODO_REQUIRED, validate_todo)
+}
+
+pub fn validate_subtask_change(before: &Value, after: &Value) -> Result<(), ApiError> {
+    validate_change(before, after, &SUBTASK_REQUIRED, validate_subtask)
+}
+
+pub fn validate_template_change(before: &Value, after: &Value) -> Result<(), ApiError> {
+    validate_change(before, after, &TEMPLATE_REQUIRED, validate_template)
+}
+
+pub fn validate_tag_change(before: &Value, after: &Value) -> Result<(), ApiError> {
+    validate_change(before, after, &TAG_REQUIRED, validate_tag)
+}
+
+pub fn validate_settings_change(before: &Value, after: &Value) -> Result<(), ApiError> {
+    validate_change(before, after, &SETTINGS_FIELDS, validate_settings)
+}
+
+fn validate_change(
+    before: &Value,
+    after: &Value,
+    required: &[&str],
+    check: fn(&Map<String, Value>) -> Result<(), ApiError>,
+) -> Result<(), ApiError> {
+    let empty = Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+
+    let changed: Map<String, Value> = after
+        .iter()
+        .filter(|(k, v)| before.get(k.as_str()) != Some(v))
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    let mut errs = match check(&changed) {
+        Ok(()) => Vec::new(),
+        Err(e) => e.errors,
+    };
+    for key in required {
+        if before.contains_key(*key) && !after.contains_key(*key) {
+            errs.push(FieldError::new(*key, "is required and cannot be removed"));
+        }
+    }
+    finish(errs)
+}
+
+fn finish(errs: Vec<FieldError>) -> Result<(), ApiError> {
+    if errs.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::validation(errs))
+    }
+}
+
+// =============================================================================
+// 单字段规则：Err 里是给客户端看的 message
+// =============================================================================
+
+type Check = Result<(), String>;
+
+fn nullable(v: &Value, f: impl Fn(&Value) -> Check) -> Check {
+    if v.is_null() {
+        Ok(())
+    } else {
+        f(v)
+    }
+}
+
+fn string(v: &Value) -> Check {
+    if v.is_string() {
+        Ok(())
+    } else {
+        Err("must be a string".into())
+    }
+}
+
+fn non_empty_string(v: &Value) -> Check {
+    match v.as_str() {
+        Some(s) if !s.trim().is_empty() => Ok(()),
+        Some(_) => Err("must not be blank".into()),
+        None => Err("must be a string".into()),
+    }
+}
+
+fn boolean(v: &Value) -> Check {
+    if v.is_boolean() {
+        Ok(())
+    } else {
+        Err("must be a boolean".into())
+    }
+}
+
+fn int_in_range(v: &Value, min: i64, max: i64) -> Check {
+    let Some(n) = v.as_i64() else {
+        return Err("must be an integer".into());
+    };
+    if n < min || n > max {
+        return Err(format!("must be between {} and {}", min, max));
+    }
+    Ok(())
+}
+
+fn number_in_range(v: &Value, min: f64, max: f64) -> Check {
+    let Some(n) = v.as_f64() else {
+        return Err("must be a number".into());
+    };
+    if n < min || n > max {
+        return Err(format!("must be between {} and {}", min, max));
+    }
+    Ok(())
+}
+
+fn one_of(v: &Value, allowed: &[&str]) -> Check {
+    match v.as_str() {
+        Some(s) if allowed.contains(&s) => Ok(()),
+        _ => Err(format!("must be one of {}", allowed.join(", "))),
+    }
+}
+
+fn datetime(v: &Value) -> Check {
+    let Some(s) = v.as_str() else {
+        return Err("must be a datetime string".into());
+    };
+    if model::parse_datetime(s).is_some() {
+        Ok(())
+    } else {
+        Err("must be YYYY-MM-DDTHH:MM:SS".into())
+    }
+}
+
+/// `dueDate`：`YYYY-MM-DD`，也接受带时间的写法；空串视同未设置（过滤 / 排序里
+/// 按 NULL 处理，缺省取 `endTime`）。规则与 PC 共用 `minitodo_model::is_due_date`。
+fn due_date(v: &Value) -> Check {
+    match v.as_str() {
+        Some(s) if model::is_due_date(s) => Ok(()),
+        Some(_) => Err("must be YYYY-MM-DD or YYYY-MM-DDTHH:MM:SS".into()),
+        None => Err("must be a date string".into()),
+    }
+}
+
+fn color(v: &Value) -> Check {
+    let Some(s) = v.as_str() else {
+        return Err("must be a string".into());
+    };
+    let hex = s.strip_prefix('#').unwrap_or("");
+    if matches!(hex.len(), 3 | 6) && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err("must be a hex color like #EF4444".into())
+    }
+}
+
+fn repeat_type(v: &Value) -> Check {
+    one_of(v, &REPEAT_TYPES)
+}
+
+/// `"1,3,5"`：逗号分隔的 1..=7（周一 = 1），与 PC 前端一致。
+fn weekdays(v: &Value) -> Check {
+    let Some(s) = v.as_str() else {
+        return Err("must be a string".into());
+    };
+    let ok = !s.is_empty()
+        && s.split(',')
+            .all(|p| matches!(p.trim().parse::<u8>(), Ok(1..=7)));
+    if ok {
+        Ok(())
+    } else {
+        Err("must be comma-separated weekdays 1-7".into())
+    }
+}
+
+/// 模板子任务清单：`[{title, content?}]`，与 PC `TemplateSubtask` 一致。
+fn template_subtasks(v: &Value) -> Check {
+    let Some(items) = v.as_array() else {
+        return Err("must be an array".into());
+    };
+    for (i, item) in items.iter().enumerate() {
+        let Some(obj) = item.as_object() else {
+            return Err(format!("[{}] must be an object", i));
+        };
+        let title = obj.get("title").unwrap_or(&Value::Null);
+        if let Err(msg) = non_empty_string(title) {
+            return Err(format!("[{}].title {}", i, msg));
+        }
+        if let Err(msg) = obj.get("content").map_or(Ok(()), |c| nullable(c, string)) {
+            return Err(format!("[{}].content {}", i, msg));
+        }
+    }
+    Ok(())
+}
+
+/// 标签名，规则见 `minitodo_model::check_tag_name`（与 PC 共用）。
+fn tag_name(v: &Value) -> Check {
+    let Some(s) = v.as_str() else {
+        return Err("must be a string".into());
+    };
+    model::check_tag_name(s).map_err(|e| match e {
+        TagNameError::Blank => "must not be blank".into(),
+        TagNameError::Padded => "must not have leading or trailing spaces".into(),
+        TagNameError::TooLong => format!("must be at most {} characters", model::MAX_TAG_LEN),
+        TagNameError::LeadingDash => "must not start with '-'".into(),
+        TagNameError::ForbiddenChar => "must not contain '/', ',' or control characters".into(),
+    })
+}
+
+/// todo 的 `tags`：标签名数组，与 PC `Todo.tags` 一致。
+fn tag_list(v: &Value) -> Check {
+    let Some(items) = v.as_array() else {
+        return Err("must be an array of tag names".into());
+    };
+    for (i, item) in items.iter().enumerate() {
+        tag_name(item).map_err(|msg| format!("[{}] {}", i, msg))?;
+        if items[..i].contains(item) {
+            return Err(format!("[{}] is a duplicate", i));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fields(v: Value) -> Vec<String> {
+        match validate_todo(v.as_object().unwrap()) {
+            Ok(()) => Vec::new(),
+            Err(e) => e.errors.into_iter().map(|f| f.field).collect(),
+        }
+    }
+
+    #[test]
+    fn valid_todo_passes() {
+        let v = json!({
+            "title": "a",
+            "description": null,
+            "color": "#EF4444",
+            "quadrant": 2,
+            "notifyAt": "2026-05-13T09:00:00",
+            "startTime": "2026-05-13 09:00:00",
+            "endTime": null,
+            "completed": true,
+            "repeatEnabled": true,
+            "repeatType": "weekly",
+            "repeatInterval": 2,
+            "repeatWeekdays": "1,3,5",
+            "repeatMonthDay": null,
+        });
+        assert!(fields(v).is_empty());
+    }
+
+    #[test]
+    fn unknown_fields_pass_through() {
+        assert!(fields(json!({"estimate": 123, "whatever": {"x": 1}})).is_empty());
+    }
+
+    #[test]
+    fn priority_and_due_date_follow_pc_model() {
+        let ok = json!({
+            "priority": "high",
+            "dueDate": "2026-05-20",
+        });
+        assert!(fields(ok).is_empty());
+        assert!(fields(json!({"priority": null, "dueDate": null})).is_empty());
+        assert!(fields(json!({"dueDate": "2026-05-20T18:00:00"})).is_empty());
+        assert!(fields(json!({"dueDate": ""})).is_empty());
+
+        assert_eq!(fields(json!({"priority": "urgent"})), vec!["priority"]);
+        assert_eq!(fields(json!({"priority": 3})), vec!["priority"]);
+        assert_eq!(fields(json!({"dueDate": 123})), vec!["dueDate"]);
+        assert_eq!(fields(json!({"dueDate": "next week"})), vec!["dueDate"]);
+    }
+
+    #[test]
+    fn type_errors_are_reported_per_field() {
+        let mut got = fields(json!({
+            "quadrant": "banana",
+            "completed": "yes",
+            "notifyAt": "tomorrow",
+        }));
+        got.sort();
+        assert_eq!(got, vec!["completed", "notifyAt", "quadrant"]);
+    }
+
+    #[test]
+    fn ranges_are_enforced() {
+        assert_eq!(fields(json!({"quadrant": 5})), vec!["quadrant"]);
+        assert_eq!(fields(json!({"repeatInterval": 0})), vec!["repeatInterval"]);
+        assert_eq!(
+            fields(json!({"repeatMonthDay": 32})),
+            vec!["repeatMonthDay"]
+        );
+        assert_eq!(
+            fields(json!({"repeatWeekdays": "0,8"})),
+            vec!["repeatWeekdays"]
+        );
+        assert_eq!(fields(json!({"repeatType": "hourly"})), vec!["repeatType"]);
+        assert_eq!(fields(json!({"color": "red"})), vec!["color"]);
+    }
+
+    #[test]
+    fn non_nullable_fields_reject_null() {
+        assert_eq!(fields(json!({"completed": null})), vec!["completed"]);
+        assert_eq!(fields(json!({"quadrant": null})), vec!["quadrant"]);
+    }
+
+    #[test]
+    fn change_validation_ignores_untouched_and_flags_removed_required() {
+        // 历史脏数据（quadrant 0）未被本次修改触及 → 不拦
+        let before = json!({"title": "a", "quadrant": 0, "completed": false, "description": "d"});
+        let after = json!({"title": "b", "quadrant": 0, "completed": false});
+        assert!(validate_todo_change(&before, &after).is_ok());
+
+        let after = json!({"title": "a", "quadrant": 0});
+        let err = validate_todo_change(&before, &after).unwrap_err();
+        assert_eq!(err.errors[0].field, "completed");
+    }
+
+    #[test]
+    fn subtask_rules() {
+        let err = validate_subtask(json!({"completed": 1, "content": 3}).as_object().unwrap())
+            .unwrap_err();
+        assert_eq!(err.errors.len(), 2);
+        assert!(validate_subtask(json!({"content": null, "x": 1}).as_object().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn settings_rules() {
+        let ok = json!({
+            "viewMode": "quadrant",
+            "textTheme": "light",
+            "notificationType": "app",
+            "showCalendar": true,
+            "windowBgColor": "#1E293B",
+            "windowBgAlpha": 0.6,
+        });
+        assert!(validate_settings(ok.as_object().unwrap()).is_ok());
+
+        let bad = json!({
+            "viewMode": "grid",
+            "windowBgAlpha": 1.5,
+            "showCalendar": null,
+            "isFixed": true,
+        });
+        let mut got: Vec<String> = validate_settings(bad.as_object().unwrap())
+            .unwrap_err()
+            .errors
+            .into_iter()
+            .map(|e| e.field)
+            .collect();
+        got.sort();
+        assert_eq!(
+            got,
+            vec!["isFixed", "showCalendar", "viewMode", "windowBgAlpha"]
+        );
+    }
+
+    #[test]
+    fn template_rules() {
+        let ok = json!({
+            "name": "周报",
+            "title": "{{week}} 周报",
+            "description": null,
+            "color": "#3B82F6",
+            "quadrant": 2,
+            "subtasks": [{"title": "汇总 {{date}}"}, {"title": "发送", "content": null}],
+        });
+        assert!(validate_template(ok.as_object().unwrap()).is_ok());
+
+        let err = validate_template(
+            json!({"name": " ", "subtasks": [{"title": "a"}, {"content": "x"}]})
+                .as_object()
+                .unwrap(),
+        )
+        .unwrap_err();
+        let got: Vec<(String, String)> = err
+            .errors
+            .into_iter()
+            .map(|e| (e.field, e.message))
+            .collect();
+        assert!(got.contains(&("name".into(), "must not be blank".into())));
+        assert!(got.contains(&("subtasks".into(), "[1].title must be a string".into())));
+    }
+
+    #[test]
+    fn tag_rules() {
+        assert!(fields(json!({"tags": ["work", "个人"]})).is_empty());
+        assert_eq!(fields(json!({"tags": "work"})), vec!["tags"]);
+        for bad in [
+            json!(["-x"]),
+            json!(["a/b"]),
+            json!([" "]),
+            json!(["a", "a"]),
+        ] {
+            assert_eq!(fields(json!({ "tags": bad })), vec!["tags"], "{}", bad);
+        }
+
+        let ok = json!({"name": "work", "color": "#3B82F6"});
+        assert!(validate_tag(ok.as_object().unwrap()).is_ok());
+        let err = validate_tag_change(&ok, &json!({"name": "-work"})).unwrap_err();
+        let got: Vec<String> = err.errors.into_iter().map(|e| e.field).collect();
+        assert_eq!(got, vec!["name", "color"]);
+    }
+}
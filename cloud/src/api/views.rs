@@ -0,0 +1,334 @@
+//! `/views` 具名 saved view CRUD + `GET /views/:name/todos`。
+//!
+//! 一个 view = `filter` 表达式 + `sort` + 投影字段 `fields`（+ `withSubtasks`），
+//! 以 name 为主键存在 `saved_views` 表。执行时拼成 `ListTodosQuery` 走
+//! `todos::list_todos_page`，与 `GET /todos` 的过滤 / 排序 / 分页完全一致。
+//!
+//! 同步：view 对象原样放进 sync-data 的 `settings.savedViews[]`（push 时按
+//! `updatedAt` 与远端逐条 LWW，pull 时写回本表），PC 端因此也能看到。
+
+use axum::body::Bytes;
+use axum::extract::{Path, Query, RawQuery, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use super::error::{ApiError, FieldError};
+use super::patch::{self, PatchKind};
+use super::timezone::request_clock;
+use super::todos::{list_todos_page, ListTodosQuery};
+use super::AppState;
+use crate::db::{filter, repo};
+use crate::time::{now_local_string, Clock};
+
+pub const TOMBSTONE_VIEW: &str = "view";
+
+/// view 名进 URL path，长度与字符做基本限制。
+const MAX_NAME_LEN: usize = 64;
+
+#[derive(Debug, Deserialize)]
+pub struct ViewTodosQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub cursor: Option<String>,
+}
+
+// =============================================================================
+// GET /views
+// =============================================================================
+
+pub async fn list_views(State(state): State<AppState>) -> Result<Json<Value>, ApiError> {
+    let rows = state.db.with_conn(|conn| repo::list_views(conn))?;
+    let views: Vec<Value> = rows.iter().map(view_json).collect();
+    Ok(Json(Value::Array(views)))
+}
+
+// =============================================================================
+// POST /views
+// =============================================================================
+
+pub async fn create_view(
+    State(state): State<AppState>,
+    Json(body): Json<Value>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let Some(fields) = body.as_object() else {
+        return Err(ApiError::bad_request("body must be a JSON object"));
+    };
+    let name = fields
+        .get("name")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| ApiError::bad_request("name is required"))?
+        .to_string();
+    validate_view(&state, fields)?;
+
+    let now = now_local_string(state.config.get().timezone);
+    let mut obj = fields.clone();
+    obj.insert("name".into(), json!(name));
+    obj.entry("filter").or_insert(Value::Null);
+    obj.entry("sort").or_insert(Value::Null);
+    obj.entry("fields").or_insert(Value::Null);
+    obj.entry("withSubtasks").or_insert(json!(false));
+    obj.insert("createdAt".into(), json!(now.clone()));
+    obj.insert("updatedAt".into(), json!(now.clone()));
+    let v = Value::Object(obj);
+
+    state.db.with_conn(|conn| -> Result<(), ApiError> {
+        let tx = conn.transaction()?;
+        if repo::get_view(&tx, &name)?.is_some() {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                "view_exists",
+                format!("view {} already exists", name),
+            ));
+        }
+        repo::upsert_view(&tx, &name, &v.to_string(), &now)?;
+        // 删过的同名 view 重新创建：清掉墓碑，否则 push merge 会把它剔除，
+        // 下一次干净的 pull 再按远端清理掉本地这条
+        repo::remove_tombstone(&tx, TOMBSTONE_VIEW, &name)?;
+        repo::mark_dirty(&tx)?;
+        tx.commit()?;
+        Ok(())
+    })?;
+
+    Ok((StatusCode::CREATED, Json(v)))
+}
+
+// =============================================================================
+// GET /views/:name
+// =============================================================================
+
+pub async fn get_view(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Value>, ApiError> {
+    let row = state.db.with_conn(|conn| repo::get_view(conn, &name))?;
+    match row {
+        Some(r) => Ok(Json(view_json(&r))),
+        None => Err(view_not_found(&name)),
+    }
+}
+
+// =============================================================================
+// PATCH /views/:name
+// =============================================================================
+
+pub async fn patch_view(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<Value>, ApiError> {
+    let kind = PatchKind::from_headers(&headers)?;
+    let body: Value = serde_json::from_slice(&body)?;
+    let now = now_local_string(state.config.get().timezone);
+
+    let updated = state
+        .db
+        .with_conn(|conn| -> Result<Option<Value>, ApiError> {
+            let tx = conn.transaction()?;
+            let Some(row) = repo::get_view(&tx, &name)? else {
+                return Ok(None);
+            };
+            let mut current = view_json(&row);
+            patch::apply(kind, &mut current, &body)?;
+            let Some(obj) = current.as_object_mut() else {
+                return Err(ApiError::bad_request("view must stay a JSON object"));
+            };
+            // name 是主键且进了 URL：改名 = 删除 + 新建，PATCH 不支持
+            if obj.get("name").and_then(|v| v.as_str()) != Some(name.as_str()) {
+                return Err(ApiError::validation(vec![FieldError::new(
+                    "name",
+                    "cannot be changed; create a new view instead",
+                )]));
+            }
+            validate_view(&state, obj)?;
+            obj.insert("updatedAt".into(), json!(now.clone()));
+            repo::upsert_view(&tx, &name, &current.to_string(), &now)?;
+            // 与 create_view 同理：写回的 name 不能留着墓碑
+            repo::remove_tombstone(&tx, TOMBSTONE_VIEW, &name)?;
+            repo::mark_dirty(&tx)?;
+            tx.commit()?;
+            Ok(Some(current))
+        })?;
+
+    match updated {
+        Some(v) => Ok(Json(v)),
+        None => Err(view_not_found(&name)),
+    }
+}
+
+// =============================================================================
+// DELETE /views/:name
+// =============================================================================
+
+pub async fn delete_view(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let now = now_local_string(state.config.get().timezone);
+    let removed = state.db.with_conn(|conn| -> rusqlite::Result<bool> {
+        let tx = conn.transaction()?;
+        let existed = repo::delete_view(&tx, &name)?;
+        if existed {
+            repo::add_tombstone(&tx, TOMBSTONE_VIEW, &name, &now)?;
+            repo::mark_dirty(&tx)?;
+        }
+        tx.commit()?;
+        Ok(existed)
+    })?;
+    if removed {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(view_not_found(&name))
+    }
+}
+
+// =============================================================================
+// GET /views/:name/todos
+// =============================================================================
+
+/// 执行 view：分页参数来自本次请求，其余全部来自 view 定义。响应形状、
+/// `X-Total-Count` / `Link` 与 `GET /todos` 相同；`fields` 非空时每条 todo
+/// 只保留这些字段（外加 `id`）。
+pub async fn list_view_todos(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    Query(page): Query<ViewTodosQuery>,
+    RawQuery(raw_query): RawQuery,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let clock = request_clock(&state, &headers)?;
+    let row = state
+        .db
+        .with_conn(|conn| repo::get_view(conn, &name))?
+        .ok_or_else(|| view_not_found(&name))?;
+    let view = view_json(&row);
+
+    let q = ListTodosQuery {
+        filter: str_field(&view, "filter"),
+        sort: str_field(&view, "sort"),
+        with_subtasks: Some(
+            view.get("withSubtasks")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false)
+                .to_string(),
+        ),
+        limit: page.limit,
+        offset: page.offset,
+        cursor: page.cursor,
+        ..Default::default()
+    };
+    let path = format!("/views/{}/todos", urlencoding::encode(&name));
+    let (headers, mut body) = list_todos_page(&state, &clock, &q, raw_query.as_deref(), &path)?;
+
+    if let Some(fields) = projection(&view) {
+        let items = match body.get_mut("items") {
+            Some(items) => items,
+            None => &mut body,
+        };
+        if let Some(arr) = items.as_array_mut() {
+            for todo in arr.iter_mut() {
+                project(todo, &fields);
+            }
+        }
+    }
+
+    Ok((headers, Json(body)).into_response())
+}
+
+// =============================================================================
+// 工具
+// =============================================================================
+
+fn view_not_found(name: &str) -> ApiError {
+    ApiError::not_found(format!("view {} not found", name))
+}
+
+fn view_json(row: &repo::ViewRow) -> Value {
+    serde_json::from_str(&row.data_json).unwrap_or_else(|_| json!({"name": row.name}))
+}
+
+fn str_field(view: &Value, key: &str) -> Option<String> {
+    view.get(key)
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.trim().is_empty())
+        .map(str::to_string)
+}
+
+fn projection(view: &Value) -> Option<Vec<String>> {
+    let fields: Vec<String> = view
+        .get("fields")?
+        .as_array()?
+        .iter()
+        .filter_map(|v| v.as_str().map(str::to_string))
+        .collect();
+    if fields.is_empty() {
+        None
+    } else {
+        Some(fields)
+    }
+}
+
+fn project(todo: &mut Value, fields: &[String]) {
+    if let Some(obj) = todo.as_object_mut() {
+        obj.retain(|k, _| k == "id" || fields.iter().any(|f| f == k));
+    }
+}
+
+/// 校验 view 的已知字段；`filter` 用与 `GET /todos` 相同的 parser 试解析，
+/// 保存时就把语法错误挡掉，而不是等到执行时才 400。
+fn validate_view(state: &AppState, body: &Map<String, Value>) -> Result<(), ApiError> {
+    let tz = state.config.get().timezone;
+    let mut errs = Vec::new();
+    for (key, v) in body {
+        let res: Result<(), String> = match key.as_str() {
+            "name" => match v.as_str() {
+                Some(s) if s.trim().is_empty() => Err("must not be blank".into()),
+                Some(s) if s.chars().count() > MAX_NAME_LEN => {
+                    Err(format!("must be at most {} characters", MAX_NAME_LEN))
+                }
+                Some(s) if s.chars().any(|c| c == '/' || c.is_control()) => {
+                    Err("must not contain '/' or control characters".into())
+                }
+                Some(_) => Ok(()),
+                None => Err("must be a string".into()),
+            },
+            "filter" => match v {
+                Value::Null => Ok(()),
+                // 只做语法检查，相对日期按哪个时区求值无关紧要
+                Value::String(s) => filter::parse(s, &Clock::new(tz, tz))
+                    .map(|_| ())
+                    .map_err(|e| e.to_string()),
+                _ => Err("must be a string".into()),
+            },
+            "sort" => match v {
+                Value::Null | Value::String(_) => Ok(()),
+                _ => Err("must be a string".into()),
+            },
+            "fields" => match v {
+                Value::Null => Ok(()),
+                Value::Array(a) if a.iter().all(|f| f.as_str().is_some_and(|s| !s.is_empty())) => {
+                    Ok(())
+                }
+                _ => Err("must be an array of field names".into()),
+            },
+            "withSubtasks" => match v {
+                Value::Bool(_) => Ok(()),
+                _ => Err("must be a boolean".into()),
+            },
+            _ => Ok(()),
+        };
+        if let Err(msg) = res {
+            errs.push(FieldError::new(key.clone(), msg));
+        }
+    }
+    if errs.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::validation(errs))
+    }
+}
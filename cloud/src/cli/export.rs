@@ -0,0 +1,280 @@
+//! `export --format json|csv|md`：把本地全部 todo（含 subtasks 与 cloud 短码
+//! `seq`）导出成一份文件。
+//!
+//! - json：与 `GET /todos?withSubtasks=true` 同形的数组，字段原样透传
+//! - csv：每个 todo 一行，固定列，RFC 4180 转义；子任务只给计数
+//! - md：按四象限分组的 checklist，子任务缩进一级
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use rusqlite::Connection;
+use serde_json::{json, Value};
+
+use crate::db::repo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Csv,
+    Md,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "md" | "markdown" => Ok(Format::Md),
+            other => Err(format!(
+                "unknown export format '{}' (json / csv / md)",
+                other
+            )),
+        }
+    }
+}
+
+const CSV_COLUMNS: [&str; 14] = [
+    "seq",
+    "id",
+    "title",
+    "completed",
+    "quadrant",
+    "priority",
+    "due",
+    "startTime",
+    "createdAt",
+    "updatedAt",
+    "completedAt",
+    "subtasks",
+    "subtasksDone",
+    "description",
+];
+
+const QUADRANT_NAMES: [&str; 4] = ["重要且紧急", "重要不紧急", "紧急不重要", "不紧急不重要"];
+
+/// 读出全部 todo，按 seq 升序（没有 seq 的排最后），subtasks 按 `sortOrder` 嵌入。
+pub fn collect(conn: &Connection) -> rusqlite::Result<Vec<Value>> {
+    let rows = repo::all_todos(conn)?;
+    let ids: Vec<String> = rows.iter().map(|r| r.id.clone()).collect();
+    let seqs = repo::seq_map_for_todos(conn, &ids)?;
+
+    let mut subs: HashMap<String, Vec<Value>> = HashMap::new();
+    for s in repo::all_subtasks(conn)? {
+        if let Ok(v) = serde_json::from_str::<Value>(&s.data_json) {
+            subs.entry(s.todo_id).or_default().push(v);
+        }
+    }
+
+    let mut todos: Vec<(Option<i64>, i64, Value)> = Vec::with_capacity(rows.len());
+    for r in rows {
+        let mut v: Value = serde_json::from_str(&r.data_json).unwrap_or_else(|_| json!({}));
+        let seq = seqs.get(&r.id).copied();
+        let mut children = subs.remove(&r.id).unwrap_or_default();
+        children.sort_by_key(|s| s.get("sortOrder").and_then(Value::as_i64).unwrap_or(0));
+        if let Some(obj) = v.as_object_mut() {
+            if let Some(seq) = seq {
+                obj.insert("seq".into(), json!(seq));
+            }
+            obj.insert("subtasks".into(), Value::Array(children));
+        }
+        todos.push((seq, r.id.parse().unwrap_or(i64::MAX), v));
+    }
+    todos.sort_by_key(|(seq, id, _)| (seq.is_none(), *seq, *id));
+    Ok(todos.into_iter().map(|(_, _, v)| v).collect())
+}
+
+pub fn render(format: Format, todos: &[Value]) -> String {
+    match format {
+        Format::Json => {
+            let mut s = serde_json::to_string_pretty(todos).unwrap_or_else(|_| "[]".into());
+            s.push('\n');
+            s
+        }
+        Format::Csv => render_csv(todos),
+        Format::Md => render_md(todos),
+    }
+}
+
+fn render_csv(todos: &[Value]) -> String {
+    let mut out = CSV_COLUMNS.join(",");
+    out.push_str("\r\n");
+    for t in todos {
+        let subs = subtasks(t);
+        let done = subs.iter().filter(|s| is_done(s)).count();
+        let cells = [
+            field(t, "seq"),
+            field(t, "id"),
+            field(t, "title"),
+            is_done(t).to_string(),
+            field(t, "quadrant"),
+            field(t, "priority"),
+            due(t),
+            field(t, "startTime"),
+            field(t, "createdAt"),
+            field(t, "updatedAt"),
+            field(t, "completedAt"),
+            subs.len().to_string(),
+            done.to_string(),
+            field(t, "description"),
+        ];
+        let line: Vec<String> = cells.iter().map(|c| csv_escape(c)).collect();
+        out.push_str(&line.join(","));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn render_md(todos: &[Value]) -> String {
+    let mut out = format!("# mini-todo（{} 项）\n", todos.len());
+    for (i, name) in QUADRANT_NAMES.iter().enumerate() {
+        let q = i as i64 + 1;
+        let group: Vec<&Value> = todos
+            .iter()
+            .filter(|t| t.get("quadrant").and_then(Value::as_i64).unwrap_or(4) == q)
+            .collect();
+        if group.is_empty() {
+            continue;
+        }
+        let _ = write!(out, "\n## {}\n\n", name);
+        for t in group {
+            let mark = if is_done(t) { "x" } else { " " };
+            let _ = write!(out, "- [{}] ", mark);
+            if let Some(seq) = t.get("seq").and_then(Value::as_i64) {
+                let _ = write!(out, "`C{}` ", seq);
+            }
+            out.push_str(&md_escape(&field(t, "title")));
+            let d = due(t);
+            if !d.is_empty() {
+                let _ = write!(out, "（截止 {}）", d);
+            }
+            out.push('\n');
+            for s in subtasks(t) {
+                let mark = if is_done(s) { "x" } else { " " };
+                let _ = writeln!(out, "  - [{}] {}", mark, md_escape(&field(s, "title")));
+            }
+        }
+    }
+    out
+}
+
+fn subtasks(t: &Value) -> &[Value] {
+    t.get("subtasks")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or(&[])
+}
+
+fn is_done(v: &Value) -> bool {
+    v.get("completed").and_then(Value::as_bool).unwrap_or(false)
+}
+
+/// 与 `due` 过滤字段一致：`dueDate` 优先，缺省取 `endTime`。
+fn due(t: &Value) -> String {
+    let d = field(t, "dueDate");
+    if d.is_empty() {
+        field(t, "endTime")
+    } else {
+        d
+    }
+}
+
+/// 标量字段转文本；null / 缺失为空串。
+fn field(v: &Value, key: &str) -> String {
+    match v.get(key) {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+fn csv_escape(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// 标题里的换行压成空格，避免把 checklist 断成多行。
+fn md_escape(s: &str) -> String {
+    s.replace(['\r', '\n'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<Value> {
+        vec![
+            json!({
+                "id": 1, "seq": 1, "title": "写, \"周报\"", "completed": false, "quadrant": 1,
+                "dueDate": "2026-05-20", "createdAt": "2026-05-13 10:00:00",
+                "subtasks": [
+                    {"id": 11, "title": "草稿", "completed": true},
+                    {"id": 12, "title": "发出", "completed": false},
+                ],
+            }),
+            json!({
+                "id": 2, "title": "买牛奶", "completed": true, "quadrant": 4,
+                "endTime": "2026-05-14 18:00:00", "description": "两盒\n全脂", "subtasks": [],
+            }),
+        ]
+    }
+
+    #[test]
+    fn format_parses_case_insensitively() {
+        assert_eq!("JSON".parse::<Format>(), Ok(Format::Json));
+        assert_eq!("markdown".parse::<Format>(), Ok(Format::Md));
+        assert!("xml".parse::<Format>().is_err());
+    }
+
+    #[test]
+    fn csv_has_fixed_columns_and_escapes() {
+        let out = render(Format::Csv, &sample());
+        let lines: Vec<&str> = out.split("\r\n").collect();
+        assert_eq!(lines[0], CSV_COLUMNS.join(","));
+        assert_eq!(
+            lines[1],
+            r#"1,1,"写, ""周报""",false,1,,2026-05-20,,2026-05-13 10:00:00,,,2,1,"#
+        );
+        assert!(out.contains("\r\n,2,买牛奶,true,4,,2026-05-14 18:00:00,"));
+        assert!(out.contains(",0,0,\"两盒\n全脂\"\r\n"));
+    }
+
+    #[test]
+    fn md_groups_by_quadrant_with_nested_subtasks() {
+        let out = render(Format::Md, &sample());
+        assert_eq!(
+            out,
+            "# mini-todo（2 项）\n\
+             \n## 重要且紧急\n\n\
+             - [ ] `C1` 写, \"周报\"（截止 2026-05-20）\n  - [x] 草稿\n  - [ ] 发出\n\
+             \n## 不紧急不重要\n\n\
+             - [x] 买牛奶（截止 2026-05-14 18:00:00）\n"
+        );
+    }
+
+    #[test]
+    fn collect_orders_by_seq_and_nests_subtasks() {
+        let c = Connection::open_in_memory().unwrap();
+        crate::db::schema::init(&c).unwrap();
+        repo::upsert_todo(&c, "5", r#"{"id":5,"title":"b"}"#, "x").unwrap();
+        repo::upsert_todo(&c, "9", r#"{"id":9,"title":"a"}"#, "x").unwrap();
+        repo::upsert_todo(&c, "7", r#"{"id":7,"title":"c"}"#, "x").unwrap();
+        repo::assign_seq(&c, "9").unwrap();
+        repo::assign_seq(&c, "5").unwrap();
+        repo::upsert_subtask(&c, "51", "5", r#"{"id":51,"sortOrder":2}"#, "x").unwrap();
+        repo::upsert_subtask(&c, "52", "5", r#"{"id":52,"sortOrder":1}"#, "x").unwrap();
+
+        let todos = collect(&c).unwrap();
+        let ids: Vec<i64> = todos.iter().map(|t| t["id"].as_i64().unwrap()).collect();
+        assert_eq!(ids, vec![9, 5, 7]);
+        assert_eq!(todos[1]["seq"], 2);
+        assert_eq!(todos[1]["subtasks"][0]["id"], 52);
+        assert!(todos[2].get("seq").is_none());
+        assert_eq!(todos[2]["subtasks"], json!([]));
+    }
+}
@@ -0,0 +1,447 @@
+//! 命令行：`minitodo-cloud [--config <path>] [<command> [args]]`。
+//!
+//! 不带子命令等价于 `serve`（兼容旧的 systemd unit）。除 `serve` 外都是
+//! 一次性的同步命令，直接复用 `sync::pull::pull_once` / `sync::push::push_tick`
+//! 与 `db::repo`，跑完即退出。对正在 serve 的同一个 `data_dir` 执行
+//! `pull` / `push` / `reseq` 是安全的（SQLite 自带文件锁），`restore` 则应先停服务。
+
+pub mod export;
+pub mod verify;
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use crate::config::Config;
+use crate::db::{repo, Db};
+use crate::sync::{pull, push};
+
+pub const USAGE: &str = "\
+用法: minitodo-cloud [--config <path>] [<command>]
+
+命令:
+  serve                              启动 HTTP API 与同步 worker（默认）
+  check-config                       校验配置文件并打印生效值
+  pull                               从 WebDAV 拉取一次并合并
+  push [--force]                     推送一次；--force 先标脏，无改动也推
+  backup <file>                      在线备份 SQLite 到 <file>（不覆盖已有文件）
+  restore <file>                     用备份替换本地库并标脏（先停掉 serve）
+  reseq                              重建 todo_seq，短码重新从 C1 连续编号
+  purge-tombstones [--days <n>]      清理 n 天前的 tombstone（默认 7，0 为全部）
+  verify                             对比本地与远端 sync-data，报告漂移（有漂移退出码 2）
+  export [--format json|csv|md] [--output <file>]
+                                     导出全部 todo（默认 json 到 stdout）
+
+配置文件查找顺序: --config / -c、环境变量 MINITODO_CONFIG、./config.toml；
+都没有时只用 MINITODO_* 环境变量（任一字段都可用 MINITODO_<FIELD> 覆盖）
+";
+
+/// 解析后的命令行。
+#[derive(Debug, PartialEq, Eq)]
+pub struct Cli {
+    pub config: Option<PathBuf>,
+    pub command: Command,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Serve,
+    Help,
+    CheckConfig,
+    Pull,
+    Push {
+        force: bool,
+    },
+    Backup(PathBuf),
+    Restore(PathBuf),
+    Reseq,
+    PurgeTombstones {
+        days: i64,
+    },
+    Verify,
+    Export {
+        format: export::Format,
+        output: Option<PathBuf>,
+    },
+}
+
+/// 解析 `env::args().skip(1)`。`--config` 可出现在任意位置。
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, String> {
+    let mut config = None;
+    let mut rest: Vec<String> = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(a) = args.next() {
+        match a.as_str() {
+            "--config" | "-c" => {
+                config = Some(PathBuf::from(
+                    args.next().ok_or("--config 需要一个路径参数")?,
+                ));
+            }
+            other if other.starts_with("--config=") => {
+                config = Some(PathBuf::from(&other["--config=".len()..]));
+            }
+            "--help" | "-h" => rest.insert(0, "help".into()),
+            _ => rest.push(a),
+        }
+    }
+
+    let mut rest = rest.into_iter();
+    let name = rest.next();
+    let mut opts = Opts::new(rest.collect());
+    let command = match name.as_deref() {
+        None | Some("serve") => Command::Serve,
+        Some("help") => Command::Help,
+        Some("check-config") => Command::CheckConfig,
+        Some("pull") => Command::Pull,
+        Some("push") => Command::Push {
+            force: opts.flag("--force"),
+        },
+        Some("backup") => Command::Backup(opts.positional("backup", "<file>")?),
+        Some("restore") => Command::Restore(opts.positional("restore", "<file>")?),
+        Some("reseq") => Command::Reseq,
+        Some("purge-tombstones") => {
+            let days = match opts.value("--days")? {
+                Some(v) => v
+                    .parse::<i64>()
+                    .ok()
+                    .filter(|d| *d >= 0)
+                    .ok_or_else(|| format!("--days 须为非负整数，收到 '{}'", v))?,
+                None => 7,
+            };
+            Command::PurgeTombstones { days }
+        }
+        Some("verify") => Command::Verify,
+        Some("export") => {
+            let format = match opts.value("--format")? {
+                Some(v) => v.parse()?,
+                None => export::Format::Json,
+            };
+            let output = opts.value("--output")?.map(PathBuf::from);
+            Command::Export { format, output }
+        }
+        Some(other) => return Err(format!("未知命令 '{}'", other)),
+    };
+    opts.finish()?;
+    Ok(Cli { config, command })
+}
+
+/// 子命令自己的参数。取走认识的，`finish` 时还有剩余即报错。
+struct Opts(Vec<String>);
+
+impl Opts {
+    fn new(args: Vec<String>) -> Self {
+        Opts(args)
+    }
+
+    fn flag(&mut self, name: &str) -> bool {
+        let before = self.0.len();
+        self.0.retain(|a| a != name);
+        self.0.len() != before
+    }
+
+    /// `--name value` 或 `--name=value`。
+    fn value(&mut self, name: &str) -> Result<Option<String>, String> {
+        let prefix = format!("{}=", name);
+        if let Some(i) = self.0.iter().position(|a| a.starts_with(&prefix)) {
+            let a = self.0.remove(i);
+            return Ok(Some(a[prefix.len()..].to_string()));
+        }
+        let Some(i) = self.0.iter().position(|a| a == name) else {
+            return Ok(None);
+        };
+        if i + 1 >= self.0.len() {
+            return Err(format!("{} 需要一个参数", name));
+        }
+        self.0.remove(i);
+        Ok(Some(self.0.remove(i)))
+    }
+
+    fn positional(&mut self, cmd: &str, what: &str) -> Result<PathBuf, String> {
+        match self.0.iter().position(|a| !a.starts_with("--")) {
+            Some(i) => Ok(PathBuf::from(self.0.remove(i))),
+            None => Err(format!("{} 需要参数 {}", cmd, what)),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self.0.first() {
+            Some(a) => Err(format!("无法识别的参数 '{}'", a)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 执行 `serve` / `help` 以外的子命令。返回进程退出码。
+pub fn run(command: Command, cfg: &Config) -> anyhow::Result<ExitCode> {
+    if let Command::CheckConfig = command {
+        // 证书只有真正加载一次才知道能不能用（格式 / 私钥是否配对）
+        if let Some(files) = &cfg.tls {
+            crate::tls::CertReloader::load(&files.cert, &files.key)?;
+        }
+        print_config(cfg);
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    std::fs::create_dir_all(&cfg.data_dir)
+        .map_err(|e| anyhow::anyhow!("创建 data_dir {} 失败: {}", cfg.data_dir.display(), e))?;
+    let db = Db::open(&cfg.db_path())?;
+
+    match command {
+        Command::Pull => {
+            pull::pull_once(cfg, &db)?;
+            println!("pull ok");
+        }
+        Command::Push { force } => {
+            if force {
+                db.with_conn(|conn| repo::mark_dirty(conn))?;
+            }
+            push::push_tick(cfg, &db)?;
+            let dirty = db.with_conn(|conn| repo::get_meta(conn, "dirty"))?;
+            if dirty.as_deref() == Some("true") {
+                println!("push 未完成（远端 412 或推送期间有新写入），dirty 保留给下一轮");
+            } else {
+                println!("push ok");
+            }
+        }
+        Command::Backup(dest) => {
+            db.backup_to(&dest)?;
+            println!("已备份到 {}", dest.display());
+        }
+        Command::Restore(src) => {
+            db.restore_from(&src)?;
+            println!(
+                "已从 {} 恢复并标脏；下次 serve / push 会把恢复的数据合并回远端",
+                src.display()
+            );
+        }
+        Command::Reseq => {
+            let n = db.with_conn(repo::rebuild_seq)?;
+            println!("已为 {} 个 todo 重新编号 seq", n);
+        }
+        Command::PurgeTombstones { days } => {
+            let cutoff = chrono::Utc::now()
+                .with_timezone(&cfg.timezone)
+                .checked_sub_signed(chrono::Duration::days(days))
+                .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
+                .ok_or_else(|| anyhow::anyhow!("--days {} 超出范围", days))?;
+            let n = db.with_conn(|conn| repo::purge_tombstones_before(conn, &cutoff))?;
+            println!("已清理 {} 条早于 {} 的 tombstone", n, cutoff);
+        }
+        Command::Verify => return verify_cmd(cfg, &db),
+        Command::Export { format, output } => {
+            let todos = db.with_conn(|conn| export::collect(conn))?;
+            let text = export::render(format, &todos);
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, text)
+                        .map_err(|e| anyhow::anyhow!("写入 {} 失败: {}", path.display(), e))?;
+                    eprintln!("已导出 {} 个 todo 到 {}", todos.len(), path.display());
+                }
+                None => print!("{}", text),
+            }
+        }
+        Command::Serve | Command::Help | Command::CheckConfig => unreachable!("在 main 中处理"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_config(cfg: &Config) {
+    println!("config ok");
+    println!("  webdav_url      = {}", cfg.webdav_url);
+    println!("  webdav_username = {}", cfg.webdav_username);
+    println!("  bind            = {}", cfg.bind);
+    if let Some(m) = &cfg.metrics_bind {
+        println!("  metrics_bind    = {}", m);
+    }
+    println!(
+        "  timezone        = {} ({})",
+        cfg.timezone,
+        chrono::Utc::now()
+            .with_timezone(&cfg.timezone)
+            .format("UTC%:z")
+    );
+    println!("  pull_interval   = {}s", cfg.pull_interval_secs);
+    println!("  data_dir        = {}", cfg.data_dir.display());
+    println!("  images_dir      = {}", cfg.images_dir.display());
+    if let Some(l) = &cfg.log_level {
+        println!("  log_level       = {}", l);
+    }
+    if let Some(tls) = &cfg.tls {
+        println!("  tls_cert        = {}", tls.cert.display());
+        println!("  tls_key         = {}", tls.key.display());
+    }
+    if !cfg.cors_origins.is_empty() {
+        println!("  cors_origins    = {}", cfg.cors_origins.join(", "));
+    }
+    if !cfg.cors_allow_headers.is_empty() {
+        println!(
+            "  cors_allow_headers = {}",
+            cfg.cors_allow_headers.join(", ")
+        );
+    }
+    let rate = |spec: Option<crate::config::RateSpec>| match spec {
+        Some(s) => format!("{}/{}s", s.limit, s.window.as_secs()),
+        None => "off".to_string(),
+    };
+    let limits = &cfg.rate_limits;
+    println!(
+        "  rate_limits     = reads {} / writes {} / uploads {} / sync {}",
+        rate(limits.reads),
+        rate(limits.writes),
+        rate(limits.uploads),
+        rate(limits.sync)
+    );
+    if cfg.auth_max_failures > 0 {
+        println!(
+            "  auth_lockout    = {} 次无效 key → 封禁 {}s",
+            cfg.auth_max_failures, cfg.auth_lockout_secs
+        );
+    }
+    println!("  trust_forwarded_for = {}", cfg.trust_forwarded_for);
+    println!("  audit_retention_days = {}", cfg.audit_retention_days);
+    println!(
+        "  idempotency_window_secs = {}",
+        cfg.idempotency_window_secs
+    );
+}
+
+fn verify_cmd(cfg: &Config, db: &Db) -> anyhow::Result<ExitCode> {
+    let Some(remote) = pull::fetch_remote(cfg)? else {
+        println!("远端还没有 sync-data.json.gz");
+        return Ok(ExitCode::from(2));
+    };
+    let (todos, subtasks, tombstones, dirty) = db.with_conn(|conn| -> rusqlite::Result<_> {
+        Ok((
+            repo::all_todos(conn)?,
+            repo::all_subtasks(conn)?,
+            repo::list_tombstones(conn)?,
+            repo::get_meta(conn, "dirty")?,
+        ))
+    })?;
+    let tombs = |kind: &str| -> HashSet<String> {
+        tombstones
+            .iter()
+            .filter(|(t, _, _)| t == kind)
+            .map(|(_, id, _)| id.clone())
+            .collect()
+    };
+
+    let (remote_todos, remote_subs) = verify::remote_maps(&remote);
+    let todo_drift = verify::diff(&verify::local_todos(&todos), &remote_todos, &tombs("todo"));
+    let sub_drift = verify::diff(
+        &verify::local_subtasks(&subtasks),
+        &remote_subs,
+        &tombs("subtask"),
+    );
+    print!("{}", verify::report("todos", &todo_drift));
+    print!("{}", verify::report("subtasks", &sub_drift));
+
+    if todo_drift.is_clean() && sub_drift.is_clean() {
+        return Ok(ExitCode::SUCCESS);
+    }
+    if dirty.as_deref() == Some("true") {
+        println!("（meta.dirty = true：本地有未推送的改动，部分差异会在下一次 push 后消失）");
+    }
+    Ok(ExitCode::from(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    fn cmd(s: &str) -> Command {
+        parse(args(s)).unwrap().command
+    }
+
+    #[test]
+    fn no_command_means_serve_and_config_anywhere() {
+        assert_eq!(
+            parse(args("--config /etc/m.toml")).unwrap(),
+            Cli {
+                config: Some("/etc/m.toml".into()),
+                command: Command::Serve,
+            }
+        );
+        let cli = parse(args("export -c x.toml --format csv")).unwrap();
+        assert_eq!(cli.config, Some("x.toml".into()));
+        assert_eq!(
+            cli.command,
+            Command::Export {
+                format: export::Format::Csv,
+                output: None,
+            }
+        );
+    }
+
+    #[test]
+    fn subcommand_arguments() {
+        assert_eq!(cmd("push --force"), Command::Push { force: true });
+        assert_eq!(cmd("backup /tmp/a.db"), Command::Backup("/tmp/a.db".into()));
+        assert_eq!(
+            cmd("purge-tombstones"),
+            Command::PurgeTombstones { days: 7 }
+        );
+        assert_eq!(
+            cmd("purge-tombstones --days=0"),
+            Command::PurgeTombstones { days: 0 }
+        );
+        assert_eq!(
+            cmd("export --output out.md --format md"),
+            Command::Export {
+                format: export::Format::Md,
+                output: Some("out.md".into()),
+            }
+        );
+        assert_eq!(cmd("--help"), Command::Help);
+    }
+
+    #[test]
+    fn bad_arguments_are_rejected() {
+        assert!(parse(args("frobnicate")).is_err());
+        assert!(parse(args("backup")).is_err());
+        assert!(parse(args("pull --force")).is_err());
+        assert!(parse(args("purge-tombstones --days -1")).is_err());
+        assert!(parse(args("export --format xml")).is_err());
+        assert!(parse(args("export --format")).is_err());
+    }
+
+    #[test]
+    fn backup_restore_roundtrip_marks_dirty() {
+        let tmp = TempDir::new().unwrap();
+        let cfg = Config::for_tests(
+            "test-api-key-1234567890abcdef",
+            tmp.path().join("data"),
+            tmp.path().join("images"),
+        );
+        let backup = tmp.path().join("backup.db");
+
+        let db = {
+            std::fs::create_dir_all(&cfg.data_dir).unwrap();
+            Db::open(&cfg.db_path()).unwrap()
+        };
+        db.with_conn(|conn| repo::upsert_todo(conn, "1", r#"{"id":1}"#, "x"))
+            .unwrap();
+        run(Command::Backup(backup.clone()), &cfg).unwrap();
+        assert!(db.backup_to(&backup).is_err(), "不覆盖已有备份");
+
+        db.with_conn(|conn| repo::delete_todo_cascade(conn, "1"))
+            .unwrap();
+        run(Command::Restore(backup), &cfg).unwrap();
+        db.with_conn(|conn| {
+            assert!(repo::get_todo(conn, "1").unwrap().is_some());
+            assert_eq!(
+                repo::get_meta(conn, "dirty").unwrap().as_deref(),
+                Some("true")
+            );
+        });
+
+        let junk = tmp.path().join("junk.db");
+        std::fs::write(&junk, b"not sqlite").unwrap();
+        assert!(db.restore_from(&junk).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+//! `verify`：对比本地 SQLite 与远端 `sync-data.json.gz`，按 id + `updatedAt`
+//! 报告漂移。只读，不改任何一边。
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+
+use serde_json::Value;
+
+use crate::db::repo::{SubtaskRow, TodoRow};
+use crate::sync::pull::SyncData;
+use crate::util::id_string;
+
+/// 一类记录（todo / subtask）两边的差异，id 均已排序。
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Drift {
+    pub only_local: Vec<String>,
+    pub only_remote: Vec<String>,
+    /// 远端有、本地已删（有 tombstone），下一次 push 会从远端删掉
+    pub pending_delete: Vec<String>,
+    pub local_newer: Vec<String>,
+    pub remote_newer: Vec<String>,
+}
+
+impl Drift {
+    pub fn is_clean(&self) -> bool {
+        self.only_local.is_empty()
+            && self.only_remote.is_empty()
+            && self.pending_delete.is_empty()
+            && self.local_newer.is_empty()
+            && self.remote_newer.is_empty()
+    }
+}
+
+/// `id → updatedAt` 两侧快照比较。`tombstones` 是本地该类记录的 tombstone id。
+pub fn diff(
+    local: &BTreeMap<String, String>,
+    remote: &BTreeMap<String, String>,
+    tombstones: &HashSet<String>,
+) -> Drift {
+    let mut d = Drift::default();
+    for (id, l) in local {
+        match remote.get(id) {
+            None => d.only_local.push(id.clone()),
+            Some(r) if l > r => d.local_newer.push(id.clone()),
+            Some(r) if l < r => d.remote_newer.push(id.clone()),
+            Some(_) => {}
+        }
+    }
+    for id in remote.keys().filter(|id| !local.contains_key(*id)) {
+        if tombstones.contains(id) {
+            d.pending_delete.push(id.clone());
+        } else {
+            d.only_remote.push(id.clone());
+        }
+    }
+    d
+}
+
+pub fn local_todos(rows: &[TodoRow]) -> BTreeMap<String, String> {
+    rows.iter()
+        .map(|r| (r.id.clone(), r.updated_at.clone()))
+        .collect()
+}
+
+pub fn local_subtasks(rows: &[SubtaskRow]) -> BTreeMap<String, String> {
+    rows.iter()
+        .map(|r| (r.id.clone(), r.updated_at.clone()))
+        .collect()
+}
+
+/// 远端快照拆成 `(todos, subtasks)` 两张 `id → updatedAt` 表。
+pub fn remote_maps(data: &SyncData) -> (BTreeMap<String, String>, BTreeMap<String, String>) {
+    let mut todos = BTreeMap::new();
+    let mut subs = BTreeMap::new();
+    for t in &data.todos {
+        if let Some(id) = id_string(t) {
+            todos.insert(id, updated_at(t));
+        }
+        for s in t
+            .get("subtasks")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let Some(id) = id_string(s) {
+                subs.insert(id, updated_at(s));
+            }
+        }
+    }
+    (todos, subs)
+}
+
+fn updated_at(v: &Value) -> String {
+    v.get("updatedAt")
+        .and_then(Value::as_str)
+        .unwrap_or("")
+        .to_string()
+}
+
+/// 人读报告。每类最多列出前 20 个 id。
+pub fn report(kind: &str, d: &Drift) -> String {
+    let mut out = String::new();
+    if d.is_clean() {
+        let _ = writeln!(out, "{}: in sync", kind);
+        return out;
+    }
+    let _ = writeln!(out, "{}:", kind);
+    for (label, ids) in [
+        ("only local", &d.only_local),
+        ("only remote", &d.only_remote),
+        ("pending delete", &d.pending_delete),
+        ("local newer", &d.local_newer),
+        ("remote newer", &d.remote_newer),
+    ] {
+        if ids.is_empty() {
+            continue;
+        }
+        let shown: Vec<&str> = ids.iter().take(20).map(String::as_str).collect();
+        let more = if ids.len() > 20 { ", ..." } else { "" };
+        let _ = writeln!(
+            out,
+            "  {:<15}{:>5}  {}{}",
+            label,
+            ids.len(),
+            shown.join(", "),
+            more
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn map(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn diff_classifies_each_side() {
+        let local = map(&[
+            ("1", "2026-05-13 10:00:00"),
+            ("2", "2026-05-13 12:00:00"),
+            ("3", "2026-05-13 08:00:00"),
+            ("4", "2026-05-13 10:00:00"),
+        ]);
+        let remote = map(&[
+            ("2", "2026-05-13 11:00:00"),
+            ("3", "2026-05-13 09:00:00"),
+            ("4", "2026-05-13 10:00:00"),
+            ("5", "2026-05-13 10:00:00"),
+            ("6", "2026-05-13 10:00:00"),
+        ]);
+        let tombs: HashSet<String> = ["6".to_string()].into();
+        let d = diff(&local, &remote, &tombs);
+        assert_eq!(d.only_local, vec!["1"]);
+        assert_eq!(d.local_newer, vec!["2"]);
+        assert_eq!(d.remote_newer, vec!["3"]);
+        assert_eq!(d.only_remote, vec!["5"]);
+        assert_eq!(d.pending_delete, vec!["6"]);
+        assert!(!d.is_clean());
+        assert!(report("todos", &d).contains("only remote        1  5"));
+    }
+
+    #[test]
+    fn remote_maps_flatten_nested_subtasks() {
+        let data: SyncData = serde_json::from_value(json!({
+            "todos": [
+                {"id": 1, "updatedAt": "a", "subtasks": [{"id": 11, "updatedAt": "b"}]},
+                {"id": 2, "updatedAt": "c"},
+            ],
+        }))
+        .unwrap();
+        let (todos, subs) = remote_maps(&data);
+        assert_eq!(todos, map(&[("1", "a"), ("2", "c")]));
+        assert_eq!(subs, map(&[("11", "b")]));
+        assert_eq!(
+            report("subtasks", &diff(&subs, &subs, &HashSet::new())),
+            "subtasks: in sync\n"
+        );
+    }
+}
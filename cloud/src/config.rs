@@ -1,13 +1,21 @@
-//! `config.toml` 解析与运行时配置。
+//! 配置加载与运行时配置。
 //!
-//! 缺任意必填字段时直接以清晰错误退出（在 `main.rs` 用 `expect` / `?` 转
-//! `anyhow::Error` 的 root cause 打出来），不在运行期做兜底。
+//! 来源优先级（高 → 低）：`MINITODO_<FIELD>` 环境变量 → `config.toml` → 默认值。
+//! 每个字段都另有 `<field>_file` / `MINITODO_<FIELD>_FILE` 形式，从文件读取值
+//! （去掉末尾换行），给 Docker / K8s secrets 用；同一来源里两种写法只能选一种。
+//! 不论从哪来，最后都走同一套校验，缺任意必填字段时直接以清晰错误退出，
+//! 不在运行期做兜底。
+//!
+//! 运行中收到 SIGHUP 会重新加载一遍（见 [`LiveConfig::reload`]）。
 
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 
-use chrono::FixedOffset;
+use axum::http::HeaderName;
 use chrono_tz::Tz;
 use serde::Deserialize;
+use tracing_subscriber::EnvFilter;
 
 /// 解析后的运行时配置。所有字段都已校验完毕，可以直接使用。
 #[derive(Debug, Clone)]
@@ -17,20 +25,104 @@ pub struct Config {
     pub webdav_password: String,
     pub api_key: String,
     pub bind: String,
-    /// IANA 时区，例如 `Asia/Shanghai`。保留原始 `Tz`（而不只存换算后的
-    /// offset）是为了未来支持 DST 时区时能随时重新计算 offset；当前运行时
-    /// 只读 `timezone_offset`，故 allow。
-    #[allow(dead_code)]
+    /// 可选：单独监听 `/metrics`（不鉴权）的地址，如 `127.0.0.1:9187`。
+    /// 未配置时 `/metrics` 只在 `bind` 上、与其他路由一样需要 token。
+    pub metrics_bind: Option<String>,
+    /// IANA 时区，例如 `Asia/Shanghai`。所有落库时间戳按它在**当时**的
+    /// offset 生成（见 `time::format_local`），DST 时区切换后无需重启；请求
+    /// 未带 `X-Timezone` 时，相对日期（`today+3d`）也按它求值。
     pub timezone: Tz,
-    /// `timezone` 当前时刻对应的 `FixedOffset`，用于生成与 PC SQLite
-    /// `datetime('now','localtime')` 完全一致的时间戳字符串。
-    pub timezone_offset: FixedOffset,
     pub pull_interval_secs: u64,
     pub data_dir: PathBuf,
     pub images_dir: PathBuf,
+    /// 可选：`tracing` 过滤指令（如 `info,minitodo_cloud=debug`）。设置后覆盖
+    /// `RUST_LOG`，并可随 SIGHUP 热更新。
+    pub log_level: Option<String>,
+    /// 可选：内置 TLS。配置后 `bind` 上只接受 HTTPS，证书文件变了自动重新加载
+    /// （见 `tls`）。
+    pub tls: Option<TlsFiles>,
+    /// 允许跨域访问的 Origin（如 `https://todo.example.com`），`*` 表示任意。
+    /// 为空时不处理 CORS，浏览器里的前端无法直接调用。
+    pub cors_origins: Vec<String>,
+    /// 预检请求额外放行的请求头，叠加在内置的 Authorization / Content-Type /
+    /// X-Timezone / X-Request-Id / Idempotency-Key 之上。
+    pub cors_allow_headers: Vec<String>,
+    /// 各类路由的限流（见 `api::ratelimit`），可随 SIGHUP 热更新。
+    pub rate_limits: RateLimits,
+    /// 同一 IP 连续多少次无效 api_key 后封禁；0 表示不封禁。
+    pub auth_max_failures: u32,
+    /// 封禁时长，同时也是累计失败次数的窗口。
+    pub auth_lockout_secs: u64,
+    /// 位于单层反代之后时打开：按 `X-Forwarded-For` 最右边的地址识别客户端
+    /// IP。直接对外时必须关闭，否则客户端可以伪造。
+    pub trust_forwarded_for: bool,
+    /// `audit_log` 保留天数；0 表示永久保留。
+    pub audit_retention_days: u64,
+    /// `Idempotency-Key` 的有效期（秒）：期间同一个 key 的重试回放首次响应。
+    /// 0 表示不支持（忽略该请求头）。
+    pub idempotency_window_secs: u64,
+}
+
+/// 按路由类别划分的令牌桶参数；`None` 表示该类不限流。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimits {
+    /// GET 等只读请求。
+    pub reads: Option<RateSpec>,
+    /// POST / PATCH / DELETE（`/images` 上传与 `/sync*` 除外）。
+    pub writes: Option<RateSpec>,
+    /// `POST /images`。
+    pub uploads: Option<RateSpec>,
+    /// `/sync`、`/sync/pull`、`/sync/push`。
+    pub sync: Option<RateSpec>,
+}
+
+/// 令牌桶：容量 `limit`，每 `window` 匀速补满。配置里写作 `"600/min"`
+/// （单位 `s` / `min` / `h`），`"off"` 表示不限。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateSpec {
+    pub limit: u32,
+    pub window: Duration,
+}
+
+impl RateSpec {
+    fn parse(field: &str, s: &str) -> anyhow::Result<Option<Self>> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("off") {
+            return Ok(None);
+        }
+        let invalid = || {
+            anyhow::anyhow!(
+                "配置: {} '{}' 无效，应形如 \"600/min\"（单位 s / min / h）或 \"off\"",
+                field,
+                s
+            )
+        };
+        let (limit, unit) = s.split_once('/').ok_or_else(invalid)?;
+        let limit: u32 = limit.trim().parse().map_err(|_| invalid())?;
+        let secs = match unit.trim() {
+            "s" | "sec" => 1,
+            "min" | "m" => 60,
+            "h" | "hour" => 3600,
+            _ => return Err(invalid()),
+        };
+        if limit == 0 {
+            return Err(invalid());
+        }
+        Ok(Some(RateSpec {
+            limit,
+            window: Duration::from_secs(secs),
+        }))
+    }
+}
+
+/// PEM 格式的证书链与私钥路径。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsFiles {
+    pub cert: PathBuf,
+    pub key: PathBuf,
 }
 
-/// `config.toml` 的原始反序列化结构。任意缺字段直接报错。
+/// 环境变量 / `config.toml` 合并后的原始反序列化结构。任意缺字段直接报错。
 #[derive(Debug, Deserialize)]
 struct RawConfig {
     webdav_url: String,
@@ -39,6 +131,8 @@ struct RawConfig {
     api_key: String,
     #[serde(default = "default_bind")]
     bind: String,
+    #[serde(default)]
+    metrics_bind: Option<String>,
     #[serde(default = "default_timezone")]
     timezone: String,
     #[serde(default = "default_pull_interval")]
@@ -47,8 +141,77 @@ struct RawConfig {
     data_dir: PathBuf,
     #[serde(default = "default_images_dir")]
     images_dir: PathBuf,
+    #[serde(default)]
+    log_level: Option<String>,
+    #[serde(default)]
+    tls_cert: Option<PathBuf>,
+    #[serde(default)]
+    tls_key: Option<PathBuf>,
+    #[serde(default)]
+    cors_origins: Vec<String>,
+    #[serde(default)]
+    cors_allow_headers: Vec<String>,
+    #[serde(default = "default_rate_reads")]
+    rate_limit_reads: String,
+    #[serde(default = "default_rate_writes")]
+    rate_limit_writes: String,
+    #[serde(default = "default_rate_uploads")]
+    rate_limit_uploads: String,
+    #[serde(default = "default_rate_sync")]
+    rate_limit_sync: String,
+    #[serde(default = "default_auth_max_failures")]
+    auth_max_failures: u32,
+    #[serde(default = "default_auth_lockout")]
+    auth_lockout_secs: u64,
+    #[serde(default)]
+    trust_forwarded_for: bool,
+    #[serde(default = "default_audit_retention")]
+    audit_retention_days: u64,
+    #[serde(default = "default_idempotency_window")]
+    idempotency_window_secs: u64,
 }
 
+/// 全部配置字段名。环境变量名为 `MINITODO_` + 大写字段名。
+const FIELDS: [&str; 24] = [
+    "webdav_url",
+    "webdav_username",
+    "webdav_password",
+    "api_key",
+    "bind",
+    "metrics_bind",
+    "timezone",
+    "pull_interval",
+    "data_dir",
+    "images_dir",
+    "log_level",
+    "tls_cert",
+    "tls_key",
+    "cors_origins",
+    "cors_allow_headers",
+    "rate_limit_reads",
+    "rate_limit_writes",
+    "rate_limit_uploads",
+    "rate_limit_sync",
+    "auth_max_failures",
+    "auth_lockout_secs",
+    "trust_forwarded_for",
+    "audit_retention_days",
+    "idempotency_window_secs",
+];
+
+/// 整数 / 布尔字段：环境变量 / 文件里的字符串要先转类型再反序列化。
+const INT_FIELDS: [&str; 5] = [
+    "pull_interval",
+    "auth_max_failures",
+    "auth_lockout_secs",
+    "audit_retention_days",
+    "idempotency_window_secs",
+];
+const BOOL_FIELDS: [&str; 1] = ["trust_forwarded_for"];
+
+/// 列表型字段：环境变量 / 文件里写成逗号分隔。
+const LIST_FIELDS: [&str; 2] = ["cors_origins", "cors_allow_headers"];
+
 fn default_bind() -> String {
     "127.0.0.1:8787".to_string()
 }
@@ -64,42 +227,124 @@ fn default_data_dir() -> PathBuf {
 fn default_images_dir() -> PathBuf {
     PathBuf::from("/var/lib/minitodo/images")
 }
+fn default_rate_reads() -> String {
+    "600/min".to_string()
+}
+fn default_rate_writes() -> String {
+    "120/min".to_string()
+}
+fn default_rate_uploads() -> String {
+    "20/min".to_string()
+}
+fn default_rate_sync() -> String {
+    "12/min".to_string()
+}
+fn default_auth_max_failures() -> u32 {
+    10
+}
+fn default_auth_lockout() -> u64 {
+    900
+}
+fn default_audit_retention() -> u64 {
+    90
+}
+fn default_idempotency_window() -> u64 {
+    86_400
+}
 
 impl Config {
-    /// 从指定路径加载 `config.toml`。
-    pub fn load(path: &Path) -> anyhow::Result<Self> {
-        let body = std::fs::read_to_string(path)
-            .map_err(|e| anyhow::anyhow!("无法读取配置文件 {}: {}", path.display(), e))?;
+    /// 加载配置：`path` 为 `None` 时只用环境变量（容器里可以不挂配置文件）。
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let body = match path {
+            Some(p) => Some(
+                std::fs::read_to_string(p)
+                    .map_err(|e| anyhow::anyhow!("无法读取配置文件 {}: {}", p.display(), e))?,
+            ),
+            None => None,
+        };
+        let table =
+            merge_sources(body.as_deref(), &|k| std::env::var(k).ok()).map_err(|e| match path {
+                Some(p) => e.context(format!("加载配置 {} 失败", p.display())),
+                None => e,
+            })?;
+        Self::from_table(table)
+    }
 
-        let raw: RawConfig = toml::from_str(&body)
-            .map_err(|e| anyhow::anyhow!("解析配置文件 {} 失败: {}", path.display(), e))?;
+    /// 合并后的表 → 校验 → `Config`。TOML 与环境变量共用这一套规则。
+    fn from_table(table: toml::Table) -> anyhow::Result<Self> {
+        let raw: RawConfig = toml::Value::Table(table).try_into().map_err(|e| {
+            anyhow::anyhow!(
+                "配置无效: {}（可写在 config.toml 或用 MINITODO_* 环境变量提供）",
+                e
+            )
+        })?;
 
         if raw.webdav_url.trim().is_empty() {
-            anyhow::bail!("config.toml: webdav_url 不能为空");
+            anyhow::bail!("配置: webdav_url 不能为空");
         }
         if raw.webdav_username.trim().is_empty() {
-            anyhow::bail!("config.toml: webdav_username 不能为空");
+            anyhow::bail!("配置: webdav_username 不能为空");
         }
         if raw.webdav_password.trim().is_empty() {
-            anyhow::bail!("config.toml: webdav_password 不能为空");
+            anyhow::bail!("配置: webdav_password 不能为空");
         }
         if raw.api_key.trim().is_empty() {
-            anyhow::bail!("config.toml: api_key 不能为空");
+            anyhow::bail!("配置: api_key 不能为空");
         }
         if raw.api_key.len() < 16 {
-            anyhow::bail!("config.toml: api_key 至少需要 16 个字符（建议 32+）");
+            anyhow::bail!("配置: api_key 至少需要 16 个字符（建议 32+）");
+        }
+        if let Some(m) = &raw.metrics_bind {
+            if m.trim().is_empty() {
+                anyhow::bail!("配置: metrics_bind 不能为空字符串（不需要就删掉这一行）");
+            }
+            if *m == raw.bind {
+                anyhow::bail!("配置: metrics_bind 不能与 bind 相同");
+            }
         }
         if raw.pull_interval == 0 {
-            anyhow::bail!("config.toml: pull_interval 必须 > 0");
+            anyhow::bail!("配置: pull_interval 必须 > 0");
+        }
+        if let Some(level) = &raw.log_level {
+            EnvFilter::try_new(level)
+                .map_err(|e| anyhow::anyhow!("配置: log_level '{}' 无效: {}", level, e))?;
+        }
+
+        let tls = match (raw.tls_cert, raw.tls_key) {
+            (Some(cert), Some(key)) => Some(TlsFiles { cert, key }),
+            (None, None) => None,
+            _ => anyhow::bail!("配置: tls_cert 与 tls_key 必须同时配置"),
+        };
+        for origin in &raw.cors_origins {
+            validate_origin(origin, raw.cors_origins.len())?;
+        }
+        for name in &raw.cors_allow_headers {
+            HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                anyhow::anyhow!(
+                    "配置: cors_allow_headers 里 '{}' 不是合法的 header 名",
+                    name
+                )
+            })?;
+        }
+
+        let rate_limits = RateLimits {
+            reads: RateSpec::parse("rate_limit_reads", &raw.rate_limit_reads)?,
+            writes: RateSpec::parse("rate_limit_writes", &raw.rate_limit_writes)?,
+            uploads: RateSpec::parse("rate_limit_uploads", &raw.rate_limit_uploads)?,
+            sync: RateSpec::parse("rate_limit_sync", &raw.rate_limit_sync)?,
+        };
+        if raw.auth_max_failures > 0 && raw.auth_lockout_secs == 0 {
+            anyhow::bail!(
+                "配置: auth_lockout_secs 必须 > 0（不想封禁请把 auth_max_failures 设为 0）"
+            );
         }
 
         let tz: Tz = raw.timezone.parse().map_err(|_| {
             anyhow::anyhow!(
-                "config.toml: timezone '{}' 不是合法的 IANA 时区名（例如 Asia/Shanghai / UTC）",
+                "配置: timezone '{}' 不是合法的 IANA 时区名（例如 Asia/Shanghai / UTC）",
                 raw.timezone
             )
         })?;
-        let timezone_offset = crate::time::offset_for_tz_now(tz);
 
         Ok(Config {
             webdav_url: raw.webdav_url.trim_end_matches('/').to_string(),
@@ -107,32 +352,495 @@ impl Config {
             webdav_password: raw.webdav_password,
             api_key: raw.api_key,
             bind: raw.bind,
+            metrics_bind: raw.metrics_bind,
             timezone: tz,
-            timezone_offset,
             pull_interval_secs: raw.pull_interval,
             data_dir: raw.data_dir,
             images_dir: raw.images_dir,
+            log_level: raw.log_level,
+            tls,
+            cors_origins: raw.cors_origins,
+            cors_allow_headers: raw.cors_allow_headers,
+            rate_limits,
+            auth_max_failures: raw.auth_max_failures,
+            auth_lockout_secs: raw.auth_lockout_secs,
+            trust_forwarded_for: raw.trust_forwarded_for,
+            audit_retention_days: raw.audit_retention_days,
+            idempotency_window_secs: raw.idempotency_window_secs,
         })
     }
 
+    /// SQLite 文件位置：`data_dir/data.db`。
+    pub fn db_path(&self) -> PathBuf {
+        self.data_dir.join("data.db")
+    }
+
+    /// 开着无效 key 封禁、监听在回环地址、却没打开 `trust_forwarded_for`：
+    /// 多半是在反代后面，所有客户端都会被算成同一个 IP（反代自己），封禁和
+    /// 按 IP 的限流桶都变成全员共用。启动时据此打警告。
+    pub fn lockout_sees_only_proxy(&self) -> bool {
+        let host = self
+            .bind
+            .rsplit_once(':')
+            .map_or(self.bind.as_str(), |(h, _)| h)
+            .trim_matches(|c| c == '[' || c == ']');
+        let loopback = host == "localhost"
+            || host
+                .parse::<std::net::IpAddr>()
+                .is_ok_and(|ip| ip.is_loopback());
+        self.auth_max_failures > 0 && !self.trust_forwarded_for && loopback
+    }
+
     /// 测试用构造器：跳过 `config.toml` 读盘，直接拼一个最小可用的 `Config`。
     /// `images_dir` / `data_dir` 由调用方传入（通常是 `tempfile::TempDir`），
-    /// 时区固定 `Asia/Shanghai`、`pull_interval` 60s。
+    /// 时区固定 `Asia/Shanghai`、`pull_interval` 60s；不限流、不封禁（各测试
+    /// 自己打开）。
     #[cfg(test)]
     pub fn for_tests(api_key: &str, data_dir: PathBuf, images_dir: PathBuf) -> Self {
         let tz: Tz = "Asia/Shanghai".parse().unwrap();
-        let timezone_offset = crate::time::offset_for_tz_now(tz);
         Config {
             webdav_url: "http://127.0.0.1:0/dav".to_string(),
             webdav_username: "u".to_string(),
             webdav_password: "p".to_string(),
             api_key: api_key.to_string(),
             bind: "127.0.0.1:0".to_string(),
+            metrics_bind: None,
             timezone: tz,
-            timezone_offset,
             pull_interval_secs: 60,
             data_dir,
             images_dir,
+            log_level: None,
+            tls: None,
+            cors_origins: Vec::new(),
+            cors_allow_headers: Vec::new(),
+            rate_limits: RateLimits {
+                reads: None,
+                writes: None,
+                uploads: None,
+                sync: None,
+            },
+            auth_max_failures: 0,
+            auth_lockout_secs: 900,
+            trust_forwarded_for: false,
+            audit_retention_days: 90,
+            idempotency_window_secs: 86_400,
         }
     }
 }
+
+/// Origin 必须是 `scheme://host[:port]`，与浏览器发的 `Origin` 头逐字比较，
+/// 所以不能带路径或结尾的 `/`；`*` 只能单独出现。
+fn validate_origin(origin: &str, count: usize) -> anyhow::Result<()> {
+    if origin == "*" {
+        if count > 1 {
+            anyhow::bail!("配置: cors_origins 里有 \"*\" 时不能再列其他 Origin");
+        }
+        return Ok(());
+    }
+    let rest = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"));
+    match rest {
+        Some(host) if !host.is_empty() && !host.contains(['/', ' ']) => Ok(()),
+        _ => anyhow::bail!(
+            "配置: cors_origins 里 '{}' 不是合法的 Origin（形如 https://todo.example.com，不带路径和结尾 /）",
+            origin
+        ),
+    }
+}
+
+/// 把 `config.toml` 正文与环境变量叠成一张表，并把 `*_file` 全部展开成值。
+///
+/// 每个字段按"环境变量层 → TOML 层"找第一个有值的层；同一层里 `<field>` 与
+/// `<field>_file` 同时出现视为配置错误，而不是悄悄选一个。
+fn merge_sources(
+    body: Option<&str>,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> anyhow::Result<toml::Table> {
+    let mut table: toml::Table = match body {
+        Some(b) => b
+            .parse()
+            .map_err(|e| anyhow::anyhow!("解析 TOML 失败: {}", e))?,
+        None => toml::Table::new(),
+    };
+
+    for field in FIELDS {
+        let file_key = format!("{}_file", field);
+        let var = format!("MINITODO_{}", field.to_uppercase());
+        let file_var = format!("{}_FILE", var);
+
+        let env_value = env(&var);
+        let env_file = env(&file_var);
+        if env_value.is_some() && env_file.is_some() {
+            anyhow::bail!("{} 与 {} 只能设置一个", var, file_var);
+        }
+        if env_value.is_some() || env_file.is_some() {
+            // 环境变量层整体覆盖 TOML 层
+            table.remove(field);
+            table.remove(&file_key);
+        }
+        let file = match env_file {
+            Some(path) => Some((path, file_var)),
+            None => match table.remove(&file_key) {
+                Some(toml::Value::String(path)) => Some((path, file_key.clone())),
+                Some(_) => anyhow::bail!("{} 必须是字符串路径", file_key),
+                None => None,
+            },
+        };
+
+        let value = match (env_value, file) {
+            (Some(v), _) => Some((v, var)),
+            (None, Some((path, source))) => {
+                if table.contains_key(field) {
+                    anyhow::bail!("{} 与 {} 只能设置一个", field, file_key);
+                }
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|e| anyhow::anyhow!("{}: 无法读取 {}: {}", source, path, e))?;
+                Some((content.trim_end_matches(['\r', '\n']).to_string(), source))
+            }
+            (None, None) => None,
+        };
+        if let Some((v, source)) = value {
+            table.insert(field.to_string(), typed(field, v, &source)?);
+        }
+    }
+    Ok(table)
+}
+
+/// 环境变量 / 文件里读到的都是字符串，按字段类型转成 TOML 值。
+fn typed(field: &str, value: String, source: &str) -> anyhow::Result<toml::Value> {
+    if BOOL_FIELDS.contains(&field) {
+        let b: bool = value
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("{}: '{}' 不是 true / false", source, value))?;
+        return Ok(toml::Value::Boolean(b));
+    }
+    if INT_FIELDS.contains(&field) {
+        let n: i64 = value
+            .trim()
+            .parse()
+            .map_err(|_| anyhow::anyhow!("{}: '{}' 不是整数", source, value))?;
+        return Ok(toml::Value::Integer(n));
+    }
+    if LIST_FIELDS.contains(&field) {
+        let items = value
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| toml::Value::String(s.to_string()))
+            .collect();
+        return Ok(toml::Value::Array(items));
+    }
+    Ok(toml::Value::String(value))
+}
+
+/// 运行时可整体替换的配置句柄。
+///
+/// 读方在每个请求 / 每个 sync tick 开头 `get()` 一次快照，同一次处理里看到的
+/// 配置保持一致；SIGHUP 时 [`LiveConfig::reload`] 换上新快照，下一次 `get()`
+/// 即生效。
+#[derive(Clone)]
+pub struct LiveConfig(Arc<RwLock<Arc<Config>>>);
+
+impl LiveConfig {
+    pub fn new(cfg: Config) -> Self {
+        LiveConfig(Arc::new(RwLock::new(Arc::new(cfg))))
+    }
+
+    pub fn get(&self) -> Arc<Config> {
+        self.0
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// 换上新配置。
+    ///
+    /// api_key、WebDAV 地址与凭据、pull_interval、log_level、限流参数、审计保留期、
+    /// 幂等窗口期会生效；监听
+    /// 地址、TLS / CORS、数据目录、时区这些"结构性"字段沿用旧值（listener 与
+    /// 路由已建好、SQLite 已打开，时区还必须与 PC 端一致），返回被忽略的字段名
+    /// 供调用方告警。证书续期不需要 SIGHUP，文件变了会自己重新加载。
+    pub fn reload(&self, new: Config) -> Vec<&'static str> {
+        let mut guard = self
+            .0
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let old = guard.clone();
+
+        let mut ignored = Vec::new();
+        if new.bind != old.bind {
+            ignored.push("bind");
+        }
+        if new.metrics_bind != old.metrics_bind {
+            ignored.push("metrics_bind");
+        }
+        if new.timezone != old.timezone {
+            ignored.push("timezone");
+        }
+        if new.data_dir != old.data_dir {
+            ignored.push("data_dir");
+        }
+        if new.images_dir != old.images_dir {
+            ignored.push("images_dir");
+        }
+        if new.tls != old.tls {
+            ignored.push("tls_cert / tls_key");
+        }
+        if new.cors_origins != old.cors_origins || new.cors_allow_headers != old.cors_allow_headers
+        {
+            ignored.push("cors_*");
+        }
+
+        *guard = Arc::new(Config {
+            bind: old.bind.clone(),
+            metrics_bind: old.metrics_bind.clone(),
+            timezone: old.timezone,
+            data_dir: old.data_dir.clone(),
+            images_dir: old.images_dir.clone(),
+            tls: old.tls.clone(),
+            cors_origins: old.cors_origins.clone(),
+            cors_allow_headers: old.cors_allow_headers.clone(),
+            ..new
+        });
+        ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use tempfile::TempDir;
+
+    const BASE: &str = r#"
+webdav_url = "https://dav.example.com/dav/"
+webdav_username = "u"
+webdav_password = "from-toml"
+api_key = "toml-api-key-1234567890"
+"#;
+
+    fn load_with(body: Option<&str>, vars: &[(&str, &str)]) -> anyhow::Result<Config> {
+        let env: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        Config::from_table(merge_sources(body, &|k| env.get(k).cloned())?)
+    }
+
+    #[test]
+    fn env_overrides_toml_and_defaults_apply() {
+        let cfg = load_with(
+            Some(BASE),
+            &[
+                ("MINITODO_WEBDAV_PASSWORD", "from-env"),
+                ("MINITODO_PULL_INTERVAL", "15"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(cfg.webdav_url, "https://dav.example.com/dav");
+        assert_eq!(cfg.webdav_password, "from-env");
+        assert_eq!(cfg.pull_interval_secs, 15);
+        assert_eq!(cfg.bind, "127.0.0.1:8787");
+        assert_eq!(cfg.log_level, None);
+    }
+
+    #[test]
+    fn env_only_without_config_file() {
+        let cfg = load_with(
+            None,
+            &[
+                ("MINITODO_WEBDAV_URL", "http://dav"),
+                ("MINITODO_WEBDAV_USERNAME", "u"),
+                ("MINITODO_WEBDAV_PASSWORD", "p"),
+                ("MINITODO_API_KEY", "env-api-key-1234567890"),
+                ("MINITODO_TIMEZONE", "UTC"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(cfg.timezone, chrono_tz::UTC);
+
+        let err = load_with(None, &[("MINITODO_WEBDAV_URL", "http://dav")]).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("webdav_username"),
+            "{:#}",
+            err
+        );
+    }
+
+    #[test]
+    fn file_variants_read_secrets_and_trim_newline() {
+        let tmp = TempDir::new().unwrap();
+        let secret = tmp.path().join("api_key");
+        std::fs::write(&secret, "file-api-key-1234567890\n").unwrap();
+        let pass = tmp.path().join("pass");
+        std::fs::write(&pass, "p@ss word\r\n").unwrap();
+
+        let body = format!(
+            "webdav_url = \"http://dav\"\nwebdav_username = \"u\"\nwebdav_password_file = {:?}\napi_key = \"toml-api-key-1234567890\"\n",
+            pass.to_str().unwrap()
+        );
+        let cfg = load_with(
+            Some(&body),
+            &[("MINITODO_API_KEY_FILE", secret.to_str().unwrap())],
+        )
+        .unwrap();
+        assert_eq!(cfg.webdav_password, "p@ss word");
+        assert_eq!(cfg.api_key, "file-api-key-1234567890");
+    }
+
+    #[test]
+    fn conflicting_or_invalid_sources_are_rejected() {
+        let both_toml = format!("{}webdav_password_file = \"/run/secrets/x\"\n", BASE);
+        assert!(load_with(Some(&both_toml), &[]).is_err());
+        assert!(load_with(
+            Some(BASE),
+            &[
+                ("MINITODO_API_KEY", "env-api-key-1234567890"),
+                ("MINITODO_API_KEY_FILE", "/run/secrets/api_key"),
+            ],
+        )
+        .is_err());
+        assert!(load_with(Some(BASE), &[("MINITODO_API_KEY_FILE", "/nonexistent/key")]).is_err());
+        assert!(load_with(Some(BASE), &[("MINITODO_PULL_INTERVAL", "soon")]).is_err());
+        assert!(load_with(Some(BASE), &[("MINITODO_PULL_INTERVAL", "0")]).is_err());
+        assert!(load_with(Some(BASE), &[("MINITODO_API_KEY", "short")]).is_err());
+        assert!(load_with(Some(BASE), &[("MINITODO_LOG_LEVEL", "info,[")]).is_err());
+    }
+
+    #[test]
+    fn tls_and_cors_fields() {
+        let body = format!(
+            "{}tls_cert = \"/etc/minitodo/cert.pem\"\ntls_key = \"/etc/minitodo/key.pem\"\ncors_origins = [\"https://todo.example.com\"]\n",
+            BASE
+        );
+        let cfg = load_with(Some(&body), &[]).unwrap();
+        assert_eq!(
+            cfg.tls,
+            Some(TlsFiles {
+                cert: "/etc/minitodo/cert.pem".into(),
+                key: "/etc/minitodo/key.pem".into(),
+            })
+        );
+        assert_eq!(cfg.cors_origins, vec!["https://todo.example.com"]);
+        assert!(cfg.cors_allow_headers.is_empty());
+
+        // 环境变量里的列表写成逗号分隔
+        let cfg = load_with(
+            Some(BASE),
+            &[
+                (
+                    "MINITODO_CORS_ORIGINS",
+                    "https://a.example.com, http://localhost:5173",
+                ),
+                ("MINITODO_CORS_ALLOW_HEADERS", "X-Client-Version"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            cfg.cors_origins,
+            vec!["https://a.example.com", "http://localhost:5173"]
+        );
+        assert_eq!(cfg.cors_allow_headers, vec!["X-Client-Version"]);
+        assert_eq!(cfg.tls, None);
+
+        for bad in [
+            ("MINITODO_TLS_CERT", "/etc/minitodo/cert.pem"),
+            ("MINITODO_CORS_ORIGINS", "*,https://a.example.com"),
+            ("MINITODO_CORS_ORIGINS", "https://a.example.com/"),
+            ("MINITODO_CORS_ORIGINS", "a.example.com"),
+            ("MINITODO_CORS_ALLOW_HEADERS", "bad header"),
+        ] {
+            assert!(load_with(Some(BASE), &[bad]).is_err(), "{:?}", bad);
+        }
+        assert!(load_with(Some(BASE), &[("MINITODO_CORS_ORIGINS", "*")]).is_ok());
+    }
+
+    #[test]
+    fn rate_limit_fields() {
+        let cfg = load_with(Some(BASE), &[]).unwrap();
+        assert_eq!(
+            cfg.rate_limits.reads,
+            Some(RateSpec {
+                limit: 600,
+                window: Duration::from_secs(60),
+            })
+        );
+        assert_eq!(cfg.auth_max_failures, 10);
+        assert!(!cfg.trust_forwarded_for);
+        assert!(cfg.lockout_sees_only_proxy());
+        assert_eq!(cfg.audit_retention_days, 90);
+        assert_eq!(cfg.idempotency_window_secs, 86_400);
+
+        let cfg = load_with(
+            Some(BASE),
+            &[
+                ("MINITODO_RATE_LIMIT_UPLOADS", "5/h"),
+                ("MINITODO_RATE_LIMIT_SYNC", "off"),
+                ("MINITODO_AUTH_MAX_FAILURES", "0"),
+                ("MINITODO_TRUST_FORWARDED_FOR", "true"),
+                ("MINITODO_AUDIT_RETENTION_DAYS", "0"),
+                ("MINITODO_IDEMPOTENCY_WINDOW_SECS", "3600"),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            cfg.rate_limits.uploads,
+            Some(RateSpec {
+                limit: 5,
+                window: Duration::from_secs(3600),
+            })
+        );
+        assert_eq!(cfg.rate_limits.sync, None);
+        assert_eq!(cfg.auth_max_failures, 0);
+        assert!(cfg.trust_forwarded_for);
+        assert!(!cfg.lockout_sees_only_proxy());
+        assert_eq!(cfg.audit_retention_days, 0);
+        assert_eq!(cfg.idempotency_window_secs, 3600);
+        for (bind, expected) in [
+            ("127.0.0.1:8787", true),
+            ("[::1]:8787", true),
+            ("localhost:8787", true),
+            ("0.0.0.0:8787", false),
+        ] {
+            let cfg = load_with(Some(BASE), &[("MINITODO_BIND", bind)]).unwrap();
+            assert_eq!(cfg.lockout_sees_only_proxy(), expected, "{}", bind);
+        }
+        let cfg = load_with(Some(BASE), &[("MINITODO_TRUST_FORWARDED_FOR", "true")]).unwrap();
+        assert!(!cfg.lockout_sees_only_proxy());
+
+        for bad in [
+            ("MINITODO_RATE_LIMIT_READS", "600"),
+            ("MINITODO_RATE_LIMIT_READS", "0/min"),
+            ("MINITODO_RATE_LIMIT_READS", "10/day"),
+            ("MINITODO_AUTH_LOCKOUT_SECS", "0"),
+            ("MINITODO_TRUST_FORWARDED_FOR", "yes"),
+            ("MINITODO_AUDIT_RETENTION_DAYS", "-1"),
+        ] {
+            assert!(load_with(Some(BASE), &[bad]).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn reload_applies_keys_and_keeps_structural_fields() {
+        let live = LiveConfig::new(load_with(Some(BASE), &[]).unwrap());
+        let next = load_with(
+            Some(BASE),
+            &[
+                ("MINITODO_API_KEY", "rotated-api-key-1234567890"),
+                ("MINITODO_PULL_INTERVAL", "5"),
+                ("MINITODO_BIND", "0.0.0.0:9000"),
+                ("MINITODO_LOG_LEVEL", "debug"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(live.reload(next), vec!["bind"]);
+        let cfg = live.get();
+        assert_eq!(cfg.api_key, "rotated-api-key-1234567890");
+        assert_eq!(cfg.pull_interval_secs, 5);
+        assert_eq!(cfg.log_level.as_deref(), Some("debug"));
+        assert_eq!(cfg.bind, "127.0.0.1:8787");
+    }
+}
@@ -0,0 +1,185 @@
+//! `audit_log` 表：记录每个写请求（谁、何时、改了什么）。
+//!
+//! 只追加、cloud 自家持有，不参与 WebDAV 同步。`at` 是 config 时区的本地
+//! 时间字符串（与其余时间戳一致）；DST 回拨会让它短暂倒退，排序一律按自增
+//! `id`。过期行由 `api::audit::spawn_retention` 按 `audit_retention_days` 清理。
+
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use serde_json::Value;
+
+/// 一条审计记录。`before` / `after` 是摘要（见 `api::audit::AuditNote`），
+/// 不是整条 JSON。
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEntry {
+    pub id: i64,
+    pub at: String,
+    pub request_id: String,
+    pub key_name: String,
+    pub client_ip: Option<String>,
+    pub user_agent: Option<String>,
+    pub method: String,
+    pub route: String,
+    pub path: String,
+    pub target: Option<String>,
+    pub status: u16,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// `GET /audit` 的过滤条件，全部可选。
+#[derive(Debug, Default, Clone)]
+pub struct AuditFilter {
+    /// `at >= since`（本地时间字符串，可只写日期）。
+    pub since: Option<String>,
+    pub key_name: Option<String>,
+    pub target: Option<String>,
+    /// 只取 `id < before_id`，翻页用。
+    pub before_id: Option<i64>,
+    pub limit: i64,
+}
+
+/// 追加一条记录，`entry.id` 被忽略。
+pub fn insert(conn: &Connection, entry: &AuditEntry) -> rusqlite::Result<i64> {
+    conn.execute(
+        "INSERT INTO audit_log (at, request_id, key_name, client_ip, user_agent, method, route, \
+         path, target, status, before_json, after_json) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            entry.at,
+            entry.request_id,
+            entry.key_name,
+            entry.client_ip,
+            entry.user_agent,
+            entry.method,
+            entry.route,
+            entry.path,
+            entry.target,
+            entry.status,
+            entry.before.as_ref().map(Value::to_string),
+            entry.after.as_ref().map(Value::to_string),
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// 按 `id` 倒序（新的在前）查询。
+pub fn query(conn: &Connection, filter: &AuditFilter) -> rusqlite::Result<Vec<AuditEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, at, request_id, key_name, client_ip, user_agent, method, route, path, \
+         target, status, before_json, after_json FROM audit_log \
+         WHERE (?1 IS NULL OR at >= ?1) \
+           AND (?2 IS NULL OR key_name = ?2) \
+           AND (?3 IS NULL OR target = ?3) \
+           AND (?4 IS NULL OR id < ?4) \
+         ORDER BY id DESC LIMIT ?5",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            filter.since,
+            filter.key_name,
+            filter.target,
+            filter.before_id,
+            filter.limit
+        ],
+        from_row,
+    )?;
+    rows.collect()
+}
+
+fn from_row(row: &Row<'_>) -> rusqlite::Result<AuditEntry> {
+    let json = |s: Option<String>| s.and_then(|s| serde_json::from_str(&s).ok());
+    Ok(AuditEntry {
+        id: row.get(0)?,
+        at: row.get(1)?,
+        request_id: row.get(2)?,
+        key_name: row.get(3)?,
+        client_ip: row.get(4)?,
+        user_agent: row.get(5)?,
+        method: row.get(6)?,
+        route: row.get(7)?,
+        path: row.get(8)?,
+        target: row.get(9)?,
+        status: row.get(10)?,
+        before: json(row.get(11)?),
+        after: json(row.get(12)?),
+    })
+}
+
+/// 删除 `at` 早于 `cutoff_local` 的记录，返回删除条数。
+pub fn purge_before(conn: &Connection, cutoff_local: &str) -> rusqlite::Result<usize> {
+    conn.execute("DELETE FROM audit_log WHERE at < ?1", [cutoff_local])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn entry(at: &str, target: &str) -> AuditEntry {
+        AuditEntry {
+            id: 0,
+            at: at.to_string(),
+            request_id: "r".to_string(),
+            key_name: "default".to_string(),
+            client_ip: None,
+            user_agent: None,
+            method: "PATCH".to_string(),
+            route: "/todos/:id".to_string(),
+            path: format!("/todos/{}", target),
+            target: Some(target.to_string()),
+            status: 200,
+            before: Some(json!({"title": "a"})),
+            after: Some(json!({"title": "b"})),
+        }
+    }
+
+    #[test]
+    fn insert_query_and_purge() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::schema::init(&conn).unwrap();
+        insert(&conn, &entry("2026-05-01 10:00:00", "1")).unwrap();
+        insert(&conn, &entry("2026-05-02 10:00:00", "2")).unwrap();
+        let last = insert(&conn, &entry("2026-05-03 10:00:00", "1")).unwrap();
+
+        let all = query(
+            &conn,
+            &AuditFilter {
+                limit: 10,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].id, last);
+        assert_eq!(all[0].after, Some(json!({"title": "b"})));
+
+        let hits = query(
+            &conn,
+            &AuditFilter {
+                since: Some("2026-05-02".to_string()),
+                target: Some("1".to_string()),
+                limit: 10,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].at, "2026-05-03 10:00:00");
+
+        let page = query(
+            &conn,
+            &AuditFilter {
+                before_id: Some(last),
+                limit: 1,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(page[0].target.as_deref(), Some("2"));
+
+        assert_eq!(purge_before(&conn, "2026-05-02 10:00:00").unwrap(), 1);
+        assert_eq!(purge_before(&conn, "2026-05-02 10:00:00").unwrap(), 0);
+    }
+}
@@ -0,0 +1,1197 @@
+//! `GET /todos?filter=` 表达式：词法 → 语法树 → 参数化 SQL。
+//!
+//! 语法（关键字大小写不敏感，优先级 `not` > `and` > `or`）：
+//!
+//! ```text
+//! expr    := and ("or" and)*
+//! and     := unary ("and" unary)*
+//! unary   := "not" unary | primary
+//! primary := "(" expr ")"
+//!          | "has" field                        -- 字段非空 / 计数 > 0
+//!          | field                              -- 同上；布尔字段即 = true
+//!          | field op value                     -- op ∈ = != < <= > >= ~
+//!          | field ["not"] "in" "(" value ("," value)* ")"
+//! value   := 整数 | "字符串" | 'string' | true | false | null
+//!          | 2026-05-13 | 2026-05-13T09:00[:00]
+//!          | today | tomorrow | yesterday | now  后接任意个 [+-]N(d|w|h|m)
+//!          | 裸标识符（当字符串，如 priority = high）
+//! ```
+//!
+//! 例：`quadrant in (1,2) and not completed and due < today+3d and text ~ "report"`。
+//!
+//! 相对日期在**解析时**按调用方给的 [`Clock`] 求值，所以语法树里只剩字面量，
+//! 编译成 SQL 是纯函数。`today` 系列取请求方时区（`X-Timezone`）的日历日；
+//! `now±Nh/m` 按真实时长加减（跨 DST 切换不会多 / 少一小时），`now±Nd/w` 按
+//! 墙钟日历加减，最后都换算成存储时区的墙钟时间再与库里的字符串比较。所有值都走 `?`
+//! 绑定，字段名只来自白名单，不存在注入面。
+//!
+//! 出错时返回 `FilterError { column, message }`，column 从 1 开始按字符计，
+//! handler 原样拼进 400 的 detail，客户端能直接定位到出错的 token。
+
+use std::fmt;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use minitodo_model as model;
+use rusqlite::types::Value as SqlValue;
+
+use crate::time::{resolve_local, Clock};
+
+/// 表达式最大长度与嵌套深度：防止恶意输入把递归下降打爆栈。
+const MAX_LEN: usize = 2000;
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterError {
+    /// 出错 token 的起始列（1-based，按字符计）。
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at column {})", self.message, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    /// `~`：大小写不敏感的子串匹配
+    Like,
+}
+
+impl CmpOp {
+    fn sql(self) -> &'static str {
+        match self {
+            CmpOp::Eq => "=",
+            CmpOp::Ne => "!=",
+            CmpOp::Lt => "<",
+            CmpOp::Le => "<=",
+            CmpOp::Gt => ">",
+            CmpOp::Ge => ">=",
+            CmpOp::Like => "LIKE",
+        }
+    }
+
+    fn is_ordering(self) -> bool {
+        matches!(self, CmpOp::Lt | CmpOp::Le | CmpOp::Gt | CmpOp::Ge)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Int(i64),
+    Str(String),
+    Bool(bool),
+    Null,
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Cmp {
+        field: Field,
+        op: CmpOp,
+        value: Literal,
+    },
+    In {
+        field: Field,
+        values: Vec<Literal>,
+        negated: bool,
+    },
+    Truthy(Field),
+}
+
+/// 可过滤字段白名单（API 名 → SQL 表达式）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Description,
+    Notes,
+    /// title / description / notes 任一匹配，只支持 `~`
+    Text,
+    Color,
+    Priority,
+    RepeatType,
+    Quadrant,
+    SortOrder,
+    Seq,
+    Completed,
+    Notified,
+    Repeat,
+    Due,
+    Start,
+    End,
+    Notify,
+    Created,
+    Updated,
+    Subtasks,
+    OpenSubtasks,
+    DoneSubtasks,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Text,
+    Priority,
+    Int,
+    Bool,
+    Time,
+    Count,
+}
+
+const FIELDS: &[(&str, Field)] = &[
+    ("title", Field::Title),
+    ("description", Field::Description),
+    ("notes", Field::Notes),
+    ("text", Field::Text),
+    ("color", Field::Color),
+    ("priority", Field::Priority),
+    ("repeatType", Field::RepeatType),
+    ("quadrant", Field::Quadrant),
+    ("sortOrder", Field::SortOrder),
+    ("seq", Field::Seq),
+    ("completed", Field::Completed),
+    ("notified", Field::Notified),
+    ("repeat", Field::Repeat),
+    ("due", Field::Due),
+    ("start", Field::Start),
+    ("end", Field::End),
+    ("notify", Field::Notify),
+    ("created", Field::Created),
+    ("updated", Field::Updated),
+    ("subtasks", Field::Subtasks),
+    ("openSubtasks", Field::OpenSubtasks),
+    ("doneSubtasks", Field::DoneSubtasks),
+];
+
+impl Field {
+    fn lookup(name: &str) -> Option<Field> {
+        FIELDS
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, f)| *f)
+    }
+
+    fn kind(self) -> Kind {
+        match self {
+            Field::Title
+            | Field::Description
+            | Field::Notes
+            | Field::Text
+            | Field::Color
+            | Field::RepeatType => Kind::Text,
+            Field::Priority => Kind::Priority,
+            Field::Quadrant | Field::SortOrder | Field::Seq => Kind::Int,
+            Field::Completed | Field::Notified | Field::Repeat => Kind::Bool,
+            Field::Due
+            | Field::Start
+            | Field::End
+            | Field::Notify
+            | Field::Created
+            | Field::Updated => Kind::Time,
+            Field::Subtasks | Field::OpenSubtasks | Field::DoneSubtasks => Kind::Count,
+        }
+    }
+
+    /// 原始取值表达式（可能为 NULL）。
+    fn raw_sql(self) -> &'static str {
+        match self {
+            Field::Title => "json_extract(data_json, '$.title')",
+            Field::Description => "json_extract(data_json, '$.description')",
+            Field::Notes => "json_extract(data_json, '$.notes')",
+            Field::Text => "",
+            Field::Color => "json_extract(data_json, '$.color')",
+            Field::Priority => "json_extract(data_json, '$.priority')",
+            Field::RepeatType => "json_extract(data_json, '$.repeatType')",
+            Field::Quadrant => "json_extract(data_json, '$.quadrant')",
+            Field::SortOrder => "json_extract(data_json, '$.sortOrder')",
+            Field::Seq => "(SELECT seq FROM todo_seq WHERE todo_seq.todo_id = todos.id)",
+            Field::Completed => "json_extract(data_json, '$.completed')",
+            Field::Notified => "json_extract(data_json, '$.notified')",
+            Field::Repeat => "json_extract(data_json, '$.repeatEnabled')",
+            // 与 dueDateBefore/After 一致：空串当 NULL，dueDate 缺省取 endTime
+            Field::Due => "COALESCE(NULLIF(json_extract(data_json, '$.dueDate'), ''), NULLIF(json_extract(data_json, '$.endTime'), ''))",
+            Field::Start => "COALESCE(NULLIF(json_extract(data_json, '$.startTime'), ''), NULLIF(json_extract(data_json, '$.startDate'), ''))",
+            Field::End => "NULLIF(json_extract(data_json, '$.endTime'), '')",
+            Field::Notify => "NULLIF(json_extract(data_json, '$.notifyAt'), '')",
+            Field::Created => "NULLIF(json_extract(data_json, '$.createdAt'), '')",
+            Field::Updated => "updated_at",
+            Field::Subtasks => "(SELECT COUNT(*) FROM subtasks s WHERE s.todo_id = todos.id)",
+            Field::OpenSubtasks => "(SELECT COUNT(*) FROM subtasks s WHERE s.todo_id = todos.id \
+                 AND CAST(IFNULL(json_extract(s.data_json, '$.completed'), 0) AS INTEGER) = 0)",
+            Field::DoneSubtasks => "(SELECT COUNT(*) FROM subtasks s WHERE s.todo_id = todos.id \
+                 AND CAST(IFNULL(json_extract(s.data_json, '$.completed'), 0) AS INTEGER) = 1)",
+        }
+    }
+}
+
+fn field_names() -> String {
+    FIELDS
+        .iter()
+        .map(|(n, _)| *n)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// =============================================================================
+// 词法
+// =============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Lit(Literal),
+    Op(CmpOp),
+    LParen,
+    RParen,
+    Comma,
+    And,
+    Or,
+    Not,
+    In,
+    Has,
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    column: usize,
+    text: String,
+}
+
+fn err(column: usize, message: impl Into<String>) -> FilterError {
+    FilterError {
+        column,
+        message: message.into(),
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | ':' | '.' | '+' | '-')
+}
+
+fn lex(input: &str, clock: &Clock) -> Result<Vec<Token>, FilterError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let single = match c {
+            '(' => Some(Tok::LParen),
+            ')' => Some(Tok::RParen),
+            ',' => Some(Tok::Comma),
+            '~' => Some(Tok::Op(CmpOp::Like)),
+            _ => None,
+        };
+        if let Some(tok) = single {
+            out.push(Token {
+                tok,
+                column,
+                text: c.to_string(),
+            });
+            i += 1;
+            continue;
+        }
+        if matches!(c, '=' | '!' | '<' | '>') {
+            let next = chars.get(i + 1).copied();
+            let (op, len) = match (c, next) {
+                ('=', Some('=')) => (CmpOp::Eq, 2),
+                ('=', _) => (CmpOp::Eq, 1),
+                ('!', Some('=')) => (CmpOp::Ne, 2),
+                ('<', Some('>')) => (CmpOp::Ne, 2),
+                ('<', Some('=')) => (CmpOp::Le, 2),
+                ('<', _) => (CmpOp::Lt, 1),
+                ('>', Some('=')) => (CmpOp::Ge, 2),
+                ('>', _) => (CmpOp::Gt, 1),
+                _ => return Err(err(column, "unexpected '!'; did you mean '!=' or 'not'?")),
+            };
+            out.push(Token {
+                tok: Tok::Op(op),
+                column,
+                text: chars[i..i + len].iter().collect(),
+            });
+            i += len;
+            continue;
+        }
+        if c == '"' || c == '\'' {
+            let mut s = String::new();
+            let mut j = i + 1;
+            loop {
+                match chars.get(j) {
+                    None => return Err(err(column, "unterminated string")),
+                    Some('\\') => {
+                        match chars.get(j + 1) {
+                            Some(&e) => s.push(e),
+                            None => return Err(err(column, "unterminated string")),
+                        }
+                        j += 2;
+                    }
+                    Some(&q) if q == c => break,
+                    Some(&other) => {
+                        s.push(other);
+                        j += 1;
+                    }
+                }
+            }
+            out.push(Token {
+                tok: Tok::Lit(Literal::Str(s)),
+                column,
+                text: chars[i..=j].iter().collect(),
+            });
+            i = j + 1;
+            continue;
+        }
+        let starts_word = c.is_alphanumeric()
+            || c == '_'
+            || (c == '-' && chars.get(i + 1).is_some_and(|d| d.is_ascii_digit()));
+        if !starts_word {
+            return Err(err(column, format!("unexpected character '{}'", c)));
+        }
+        let mut j = i + 1;
+        while j < chars.len() && is_word_char(chars[j]) {
+            j += 1;
+        }
+        let text: String = chars[i..j].iter().collect();
+        let tok = classify_word(&text, column, clock)?;
+        out.push(Token { tok, column, text });
+        i = j;
+    }
+    out.push(Token {
+        tok: Tok::Eof,
+        column: chars.len() + 1,
+        text: String::new(),
+    });
+    Ok(out)
+}
+
+fn classify_word(word: &str, column: usize, clock: &Clock) -> Result<Tok, FilterError> {
+    let lower = word.to_ascii_lowercase();
+    let kw = match lower.as_str() {
+        "and" => Some(Tok::And),
+        "or" => Some(Tok::Or),
+        "not" => Some(Tok::Not),
+        "in" => Some(Tok::In),
+        "has" => Some(Tok::Has),
+        "true" => Some(Tok::Lit(Literal::Bool(true))),
+        "false" => Some(Tok::Lit(Literal::Bool(false))),
+        "null" => Some(Tok::Lit(Literal::Null)),
+        _ => None,
+    };
+    if let Some(t) = kw {
+        return Ok(t);
+    }
+    for base in ["today", "tomorrow", "yesterday", "now"] {
+        if let Some(rest) = lower.strip_prefix(base) {
+            if rest.is_empty() || rest.starts_with(['+', '-']) {
+                return relative(base, rest, column, clock).map(Tok::Lit);
+            }
+        }
+    }
+    let first = word.chars().next().unwrap_or(' ');
+    if first.is_ascii_digit() || first == '-' {
+        if let Ok(n) = word.parse::<i64>() {
+            return Ok(Tok::Lit(Literal::Int(n)));
+        }
+        if let Some(lit) = parse_time_literal(word) {
+            return Ok(Tok::Lit(lit));
+        }
+        return Err(err(
+            column,
+            format!(
+                "invalid literal '{}' (expected integer, YYYY-MM-DD or YYYY-MM-DDTHH:MM[:SS])",
+                word
+            ),
+        ));
+    }
+    if word.contains(['+', '-', ':', '.']) {
+        return Err(err(column, format!("invalid identifier '{}'", word)));
+    }
+    Ok(Tok::Ident(word.to_string()))
+}
+
+fn parse_time_literal(s: &str) -> Option<Literal> {
+    if let Ok(d) = NaiveDate::parse_from_str(s, model::DATE_FORMAT) {
+        return Some(Literal::Date(d));
+    }
+    model::parse_datetime(s).map(|(dt, _)| Literal::DateTime(dt))
+}
+
+/// `today+3d` / `now-2h` / `tomorrow+1w-1d`：基准 + 若干 `[+-]N(d|w|h|m)`。
+/// 日期基准（today/tomorrow/yesterday）只能加减 d / w；结果须落在 1~9999 年。
+fn relative(
+    base: &str,
+    mut rest: &str,
+    column: usize,
+    clock: &Clock,
+) -> Result<Literal, FilterError> {
+    let offsets = rest;
+    let out_of_range = || {
+        err(
+            column,
+            format!("relative date '{}{}' is out of range", base, offsets),
+        )
+    };
+    let is_date = base != "now";
+    let mut day = match base {
+        "tomorrow" => clock.today() + Duration::days(1),
+        "yesterday" => clock.today() - Duration::days(1),
+        _ => clock.today(),
+    };
+    let mut at = clock.client_now();
+    while let Some(sign_char) = rest.chars().next() {
+        let sign = match sign_char {
+            '+' => 1,
+            '-' => -1,
+            _ => {
+                return Err(err(
+                    column,
+                    format!("invalid relative offset '{}' (expected e.g. +3d)", rest),
+                ))
+            }
+        };
+        let body = &rest[sign_char.len_utf8()..];
+        let digits = body.chars().take_while(|c| c.is_ascii_digit()).count();
+        let unit_start = &body[digits..];
+        let unit_len = unit_start
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .count();
+        let n: i64 = body[..digits].parse().map_err(|_| {
+            err(
+                column,
+                format!("invalid relative offset '{}' (expected e.g. +3d)", rest),
+            )
+        })?;
+        // n 非负，乘 ±1 不会溢出
+        let n = n * sign;
+        let unit = &unit_start[..unit_len];
+        let calendar = match unit {
+            "d" => Some(Duration::try_days(n)),
+            "w" => Some(Duration::try_weeks(n)),
+            _ => None,
+        };
+        let elapsed = match unit {
+            "d" | "w" => None,
+            "h" | "m" if is_date => {
+                return Err(err(
+                    column,
+                    format!("'{}' offsets need `now`, not `{}`", unit, base),
+                ))
+            }
+            "h" => Some(Duration::try_hours(n)),
+            "m" => Some(Duration::try_minutes(n)),
+            _ => {
+                return Err(err(
+                    column,
+                    format!("unknown unit '{}' (expected d, w, h or m)", unit),
+                ))
+            }
+        };
+        if let Some(delta) = calendar {
+            let delta = delta.ok_or_else(out_of_range)?;
+            day = day
+                .checked_add_signed(delta)
+                .filter(|d| in_year_range(*d))
+                .ok_or_else(out_of_range)?;
+            let naive = at
+                .naive_local()
+                .checked_add_signed(delta)
+                .filter(|dt| in_year_range(dt.date()))
+                .ok_or_else(out_of_range)?;
+            at = resolve_local(naive, clock.client);
+        }
+        if let Some(delta) = elapsed {
+            at = delta
+                .and_then(|delta| at.checked_add_signed(delta))
+                .filter(|at| in_year_range(at.date_naive()))
+                .ok_or_else(out_of_range)?;
+        }
+        rest = &unit_start[unit_len..];
+    }
+    Ok(if is_date {
+        Literal::Date(day)
+    } else {
+        Literal::DateTime(clock.in_storage(at))
+    })
+}
+
+/// 相对日期的合法范围：1~9999 年，存储与比较都按四位年份的字符串
+fn in_year_range(d: NaiveDate) -> bool {
+    (1..=9999).contains(&d.year())
+}
+
+// =============================================================================
+// 语法
+// =============================================================================
+
+/// 解析 filter 表达式。空串（或全空白）返回 `Ok(None)`。`clock` 用于相对日期
+/// 求值。
+pub fn parse(input: &str, clock: &Clock) -> Result<Option<Expr>, FilterError> {
+    if input.chars().count() > MAX_LEN {
+        return Err(err(
+            MAX_LEN + 1,
+            format!("filter longer than {} chars", MAX_LEN),
+        ));
+    }
+    let tokens = lex(input, clock)?;
+    if tokens.len() == 1 {
+        return Ok(None);
+    }
+    let mut p = Parser { tokens, pos: 0 };
+    let expr = p.expr(0)?;
+    let t = p.peek();
+    if t.tok != Tok::Eof {
+        return Err(unexpected(t, "'and', 'or' or end of filter"));
+    }
+    Ok(Some(expr))
+}
+
+fn unexpected(t: &Token, expected: &str) -> FilterError {
+    let found = if t.tok == Tok::Eof {
+        "end of filter".to_string()
+    } else {
+        format!("'{}'", t.text)
+    };
+    err(t.column, format!("expected {}, found {}", expected, found))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Token {
+        let t = self.tokens[self.pos].clone();
+        if t.tok != Tok::Eof {
+            self.pos += 1;
+        }
+        t
+    }
+
+    fn eat(&mut self, tok: &Tok) -> bool {
+        if &self.peek().tok == tok {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expr(&mut self, depth: usize) -> Result<Expr, FilterError> {
+        if depth > MAX_DEPTH {
+            return Err(err(self.peek().column, "filter nested too deeply"));
+        }
+        let mut left = self.and(depth)?;
+        while self.eat(&Tok::Or) {
+            let right = self.and(depth)?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self, depth: usize) -> Result<Expr, FilterError> {
+        let mut left = self.unary(depth)?;
+        while self.eat(&Tok::And) {
+            let right = self.unary(depth)?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self, depth: usize) -> Result<Expr, FilterError> {
+        if self.eat(&Tok::Not) {
+            if depth > MAX_DEPTH {
+                return Err(err(self.peek().column, "filter nested too deeply"));
+            }
+            return Ok(Expr::Not(Box::new(self.unary(depth + 1)?)));
+        }
+        self.primary(depth)
+    }
+
+    fn primary(&mut self, depth: usize) -> Result<Expr, FilterError> {
+        let t = self.next();
+        match t.tok {
+            Tok::LParen => {
+                let e = self.expr(depth + 1)?;
+                let close = self.next();
+                if close.tok != Tok::RParen {
+                    return Err(unexpected(&close, "')'"));
+                }
+                Ok(e)
+            }
+            Tok::Has => {
+                let f = self.next();
+                let field = self.field(&f)?;
+                Ok(Expr::Truthy(field))
+            }
+            Tok::Ident(_) => {
+                let field = self.field(&t)?;
+                self.predicate(field, &t)
+            }
+            _ => Err(unexpected(&t, "a field name, 'not', 'has' or '('")),
+        }
+    }
+
+    fn field(&self, t: &Token) -> Result<Field, FilterError> {
+        match &t.tok {
+            Tok::Ident(name) => Field::lookup(name).ok_or_else(|| {
+                err(
+                    t.column,
+                    format!(
+                        "unknown field '{}'; expected one of {}",
+                        name,
+                        field_names()
+                    ),
+                )
+            }),
+            _ => Err(unexpected(t, "a field name")),
+        }
+    }
+
+    fn predicate(&mut self, field: Field, field_tok: &Token) -> Result<Expr, FilterError> {
+        let t = self.peek().clone();
+        match t.tok {
+            Tok::Op(op) => {
+                self.pos += 1;
+                let (value, vt) = self.value()?;
+                check(field, op, &value, &vt)?;
+                Ok(Expr::Cmp { field, op, value })
+            }
+            Tok::In => {
+                self.pos += 1;
+                self.in_list(field, false)
+            }
+            Tok::Not if matches!(self.tokens.get(self.pos + 1).map(|t| &t.tok), Some(Tok::In)) => {
+                self.pos += 2;
+                self.in_list(field, true)
+            }
+            _ => {
+                if field == Field::Text {
+                    return Err(err(field_tok.column, "'text' only supports '~'"));
+                }
+                Ok(Expr::Truthy(field))
+            }
+        }
+    }
+
+    fn in_list(&mut self, field: Field, negated: bool) -> Result<Expr, FilterError> {
+        let open = self.next();
+        if open.tok != Tok::LParen {
+            return Err(unexpected(&open, "'(' after 'in'"));
+        }
+        let mut values = Vec::new();
+        loop {
+            let (value, vt) = self.value()?;
+            check(field, CmpOp::Eq, &value, &vt)?;
+            values.push(value);
+            let sep = self.next();
+            match sep.tok {
+                Tok::Comma => continue,
+                Tok::RParen => break,
+                _ => return Err(unexpected(&sep, "',' or ')'")),
+            }
+        }
+        Ok(Expr::In {
+            field,
+            values,
+            negated,
+        })
+    }
+
+    fn value(&mut self) -> Result<(Literal, Token), FilterError> {
+        let t = self.next();
+        let v = match &t.tok {
+            Tok::Lit(l) => l.clone(),
+            // 裸标识符作字符串值：priority = high
+            Tok::Ident(s) => Literal::Str(s.clone()),
+            _ => return Err(unexpected(&t, "a value")),
+        };
+        Ok((v, t))
+    }
+}
+
+/// 字段类型 × 运算符 × 值类型 的合法性检查，错误指向值 token。
+fn check(field: Field, op: CmpOp, value: &Literal, t: &Token) -> Result<(), FilterError> {
+    let bad = |msg: String| Err(err(t.column, msg));
+    let name = FIELDS
+        .iter()
+        .find(|(_, f)| *f == field)
+        .map(|(n, _)| *n)
+        .unwrap_or("?");
+    let kind = field.kind();
+
+    if field == Field::Text && op != CmpOp::Like {
+        return bad("'text' only supports '~'".into());
+    }
+    if op == CmpOp::Like && !matches!(kind, Kind::Text | Kind::Priority) {
+        return bad(format!("'~' is only valid on text fields, not '{}'", name));
+    }
+    if matches!(value, Literal::Null) {
+        return match op {
+            CmpOp::Eq | CmpOp::Ne if kind != Kind::Count && kind != Kind::Bool => Ok(()),
+            _ => bad(format!(
+                "null can only be compared with = or != on '{}'",
+                name
+            )),
+        };
+    }
+    match kind {
+        Kind::Text => match value {
+            Literal::Str(_) if !op.is_ordering() => Ok(()),
+            Literal::Str(_) => bad(format!("'{}' only supports =, != and ~", name)),
+            _ => bad(format!("'{}' expects a string", name)),
+        },
+        Kind::Priority => match value {
+            Literal::Str(s) if op.is_ordering() && priority_rank(s).is_none() => bad(format!(
+                "ordering on priority needs high, medium or low, not '{}'",
+                s
+            )),
+            Literal::Str(_) => Ok(()),
+            _ => bad("'priority' expects high, medium or low".into()),
+        },
+        Kind::Int | Kind::Count => match value {
+            Literal::Int(_) => Ok(()),
+            _ => bad(format!("'{}' expects an integer", name)),
+        },
+        Kind::Bool => match value {
+            Literal::Bool(_) if matches!(op, CmpOp::Eq | CmpOp::Ne) => Ok(()),
+            Literal::Bool(_) => bad(format!("'{}' only supports = and !=", name)),
+            _ => bad(format!("'{}' expects true or false", name)),
+        },
+        Kind::Time => match value {
+            Literal::Date(_) | Literal::DateTime(_) => Ok(()),
+            Literal::Str(s) if parse_time_literal(s).is_some() => Ok(()),
+            _ => bad(format!(
+                "'{}' expects a date (YYYY-MM-DD, today+3d, now-2h ...)",
+                name
+            )),
+        },
+    }
+}
+
+fn priority_rank(s: &str) -> Option<i64> {
+    match s.to_ascii_lowercase().as_str() {
+        "high" => Some(3),
+        "medium" => Some(2),
+        "low" => Some(1),
+        _ => None,
+    }
+}
+
+const PRIORITY_RANK_SQL: &str = "CASE IFNULL(json_extract(data_json, '$.priority'), '') \
+     WHEN 'high' THEN 3 WHEN 'medium' THEN 2 WHEN 'low' THEN 1 ELSE 0 END";
+
+// =============================================================================
+// 编译
+// =============================================================================
+
+/// 把语法树编译成 SQL 片段（可直接 `AND (...)` 拼进 WHERE），值追加到 `args`。
+pub fn to_sql(expr: &Expr, args: &mut Vec<SqlValue>) -> String {
+    match expr {
+        Expr::And(a, b) => format!("({} AND {})", to_sql(a, args), to_sql(b, args)),
+        Expr::Or(a, b) => format!("({} OR {})", to_sql(a, args), to_sql(b, args)),
+        // NULL 比较结果在 NOT 下仍是 NULL：IFNULL 兜成"不满足"再取反
+        Expr::Not(e) => format!("(NOT IFNULL({}, 0))", to_sql(e, args)),
+        Expr::Cmp { field, op, value } => cmp_sql(*field, *op, value, args),
+        Expr::In {
+            field,
+            values,
+            negated,
+        } => {
+            let parts: Vec<String> = values
+                .iter()
+                .map(|v| cmp_sql(*field, CmpOp::Eq, v, args))
+                .collect();
+            let any = format!("({})", parts.join(" OR "));
+            if *negated {
+                format!("(NOT IFNULL({}, 0))", any)
+            } else {
+                any
+            }
+        }
+        Expr::Truthy(field) => truthy_sql(*field),
+    }
+}
+
+fn truthy_sql(field: Field) -> String {
+    let raw = field.raw_sql();
+    match field.kind() {
+        Kind::Bool => format!("(CAST(IFNULL({}, 0) AS INTEGER) = 1)", raw),
+        Kind::Count => format!("({} > 0)", raw),
+        Kind::Int => format!("(IFNULL({}, 0) != 0)", raw),
+        Kind::Text | Kind::Priority | Kind::Time => format!("(NULLIF({}, '') IS NOT NULL)", raw),
+    }
+}
+
+fn cmp_sql(field: Field, op: CmpOp, value: &Literal, args: &mut Vec<SqlValue>) -> String {
+    let raw = field.raw_sql();
+    if matches!(value, Literal::Null) {
+        let not = if op == CmpOp::Ne { "NOT " } else { "" };
+        return format!("(NULLIF({}, '') IS {}NULL)", raw, not);
+    }
+    if field == Field::Text {
+        let like = like_pattern(value);
+        let cols = [Field::Title, Field::Description, Field::Notes];
+        let parts: Vec<String> = cols
+            .iter()
+            .map(|f| {
+                args.push(SqlValue::Text(like.clone()));
+                format!("IFNULL({}, '') LIKE ? ESCAPE '\\'", f.raw_sql())
+            })
+            .collect();
+        return format!("({})", parts.join(" OR "));
+    }
+    if op == CmpOp::Like {
+        args.push(SqlValue::Text(like_pattern(value)));
+        return format!("(IFNULL({}, '') LIKE ? ESCAPE '\\')", raw);
+    }
+    match field.kind() {
+        Kind::Text => {
+            args.push(SqlValue::Text(str_of(value)));
+            format!("(IFNULL({}, '') {} ?)", raw, op.sql())
+        }
+        Kind::Priority if op.is_ordering() => {
+            args.push(SqlValue::Integer(
+                priority_rank(&str_of(value)).unwrap_or(0),
+            ));
+            format!("({} {} ?)", PRIORITY_RANK_SQL, op.sql())
+        }
+        Kind::Priority => {
+            args.push(SqlValue::Text(str_of(value)));
+            format!("(IFNULL({}, '') {} ?)", raw, op.sql())
+        }
+        Kind::Int | Kind::Count => {
+            let n = if let Literal::Int(n) = value { *n } else { 0 };
+            args.push(SqlValue::Integer(n));
+            if field.kind() == Kind::Count {
+                format!("({} {} ?)", raw, op.sql())
+            } else {
+                format!("(CAST({} AS INTEGER) {} ?)", raw, op.sql())
+            }
+        }
+        Kind::Bool => {
+            let b = matches!(value, Literal::Bool(true));
+            args.push(SqlValue::Integer(b as i64));
+            format!("(CAST(IFNULL({}, 0) AS INTEGER) {} ?)", raw, op.sql())
+        }
+        Kind::Time => {
+            let lit = match value {
+                Literal::Str(s) => parse_time_literal(s).unwrap_or(Literal::Null),
+                other => other.clone(),
+            };
+            match lit {
+                // 按日比较：只取前 10 位，`due = today` 命中当天任意时刻
+                Literal::Date(d) => {
+                    args.push(SqlValue::Text(d.format("%Y-%m-%d").to_string()));
+                    format!("(substr({}, 1, 10) {} ?)", raw, op.sql())
+                }
+                // 按时刻比较：PC 前端写 `T` 分隔、SQLite 写空格，统一成空格
+                Literal::DateTime(dt) => {
+                    args.push(SqlValue::Text(dt.format("%Y-%m-%d %H:%M:%S").to_string()));
+                    format!("(replace({}, 'T', ' ') {} ?)", raw, op.sql())
+                }
+                _ => "(0)".to_string(),
+            }
+        }
+    }
+}
+
+fn str_of(v: &Literal) -> String {
+    match v {
+        Literal::Str(s) => s.clone(),
+        Literal::Int(n) => n.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// `~ "50%"` → `%50\%%`：用户输入里的 LIKE 通配符按字面匹配。
+fn like_pattern(v: &Literal) -> String {
+    let s = str_of(v)
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::America::New_York;
+    use chrono_tz::Asia::Shanghai;
+    use chrono_tz::Tz;
+    use rusqlite::Connection;
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2026-05-13 10:30:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    /// 上海 `now()` 那一刻；请求方与存储同为上海。
+    fn clock() -> Clock {
+        clock_at("2026-05-13 02:30:00", Shanghai, Shanghai)
+    }
+
+    fn clock_at(utc: &str, client: Tz, storage: Tz) -> Clock {
+        Clock {
+            now: NaiveDateTime::parse_from_str(utc, "%Y-%m-%d %H:%M:%S")
+                .unwrap()
+                .and_utc(),
+            client,
+            storage,
+        }
+    }
+
+    fn p(s: &str) -> Expr {
+        parse(s, &clock()).unwrap().unwrap()
+    }
+
+    fn perr(s: &str) -> FilterError {
+        parse(s, &clock()).unwrap_err()
+    }
+
+    #[test]
+    fn empty_filter_is_none() {
+        assert_eq!(parse("   ", &clock()).unwrap(), None);
+    }
+
+    #[test]
+    fn precedence_not_and_or() {
+        let e = p("completed or not notified and quadrant = 1");
+        let Expr::Or(_, right) = e else {
+            panic!("or must be the root");
+        };
+        assert!(matches!(*right, Expr::And(ref l, _) if matches!(**l, Expr::Not(_))));
+    }
+
+    #[test]
+    fn relative_dates_resolve_against_now() {
+        let d = |s: &str| match p(&format!("due < {}", s)) {
+            Expr::Cmp { value, .. } => value,
+            _ => unreachable!(),
+        };
+        let date = |s| Literal::Date(NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap());
+        assert_eq!(d("today"), date("2026-05-13"));
+        assert_eq!(d("today+3d"), date("2026-05-16"));
+        assert_eq!(d("tomorrow-1w"), date("2026-05-07"));
+        assert_eq!(d("yesterday"), date("2026-05-12"));
+        assert_eq!(d("now+2h"), Literal::DateTime(now() + Duration::hours(2)));
+    }
+
+    /// 纽约 2026-03-08 02:00 拨快到 03:00：`now+1h` 按真实时长，`now+1d` 按墙钟；
+    /// 请求方在纽约、存储在上海时，`today` 取纽约的日期，`now` 换算成上海墙钟。
+    #[test]
+    fn relative_dates_across_dst_and_zones() {
+        let at = |s: &str, clock: &Clock| match parse(&format!("due < {}", s), clock)
+            .unwrap()
+            .unwrap()
+        {
+            Expr::Cmp { value, .. } => value,
+            _ => unreachable!(),
+        };
+        let dt = |s: &str| {
+            Literal::DateTime(NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S").unwrap())
+        };
+        let date = |s| Literal::Date(NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap());
+
+        // UTC 06:30 = 纽约 01:30 EST
+        let ny = clock_at("2026-03-08 06:30:00", New_York, New_York);
+        assert_eq!(at("now", &ny), dt("2026-03-08 01:30:00"));
+        assert_eq!(at("now+1h", &ny), dt("2026-03-08 03:30:00"));
+        assert_eq!(at("now+1d", &ny), dt("2026-03-09 01:30:00"));
+        assert_eq!(at("now+1d-1h", &ny), dt("2026-03-09 00:30:00"));
+
+        // UTC 2026-05-13 20:00：纽约 13 日 16:00，上海 14 日 04:00
+        let remote = clock_at("2026-05-13 20:00:00", New_York, Shanghai);
+        assert_eq!(at("today", &remote), date("2026-05-13"));
+        assert_eq!(at("tomorrow", &remote), date("2026-05-14"));
+        assert_eq!(at("now", &remote), dt("2026-05-14 04:00:00"));
+    }
+
+    #[test]
+    fn errors_point_at_bad_token() {
+        let e = perr("quadrant = 1 and bogus = 2");
+        assert_eq!(e.column, 18);
+        assert!(e.message.contains("unknown field 'bogus'"));
+
+        let e = perr("quadrant = \"x\"");
+        assert_eq!(e.column, 12);
+
+        let e = perr("due < today+3x");
+        assert_eq!(e.column, 7);
+        assert!(e.message.contains("unknown unit"));
+
+        let e = perr("(completed");
+        assert_eq!(e.column, 11);
+        assert!(e.message.contains("expected ')'"));
+
+        let e = perr("completed completed");
+        assert_eq!(e.column, 11);
+
+        assert!(perr("5 < due").message.contains("expected a field name"));
+        assert!(perr("due < today+3h").message.contains("need `now`"));
+        assert!(perr("text = \"x\"").message.contains("only supports '~'"));
+        assert!(perr("title ~ 'x").message.contains("unterminated"));
+    }
+
+    /// 相对偏移里的多字节字符、超大 N 都报错而不是 panic。
+    #[test]
+    fn relative_offsets_reject_bad_input_without_panicking() {
+        let e = perr("due < today+3dé");
+        assert_eq!(e.column, 7);
+        assert!(
+            e.message.contains("invalid relative offset"),
+            "{}",
+            e.message
+        );
+
+        for input in [
+            "due < today+1000000000d",
+            "due < today+99999999999999d",
+            "due < now-99999999999999w",
+            "due < now+99999999999999h",
+            "due < now+9223372036854775807m",
+        ] {
+            let e = perr(input);
+            assert_eq!(e.column, 7, "{}", input);
+            assert!(
+                e.message.contains("out of range"),
+                "{}: {}",
+                input,
+                e.message
+            );
+        }
+    }
+
+    #[test]
+    fn nesting_is_bounded() {
+        let deep = format!("{}completed{}", "(".repeat(100), ")".repeat(100));
+        assert!(perr(&deep).message.contains("nested too deeply"));
+    }
+
+    // ---- SQL 端到端：在内存库上跑编译结果 ----
+
+    fn db() -> Connection {
+        let c = Connection::open_in_memory().unwrap();
+        crate::db::schema::init(&c).unwrap();
+        let todos = [
+            (
+                1,
+                r#"{"title":"Weekly report","quadrant":1,"completed":false,"dueDate":"2026-05-14","priority":"high"}"#,
+            ),
+            (
+                2,
+                r#"{"title":"Groceries","quadrant":2,"completed":false,"endTime":"2026-05-20T18:00:00","priority":"low"}"#,
+            ),
+            (
+                3,
+                r#"{"title":"Old report","quadrant":3,"completed":true,"dueDate":"2026-05-01"}"#,
+            ),
+            (
+                4,
+                r#"{"title":"50% off","quadrant":4,"completed":false,"description":"coupon"}"#,
+            ),
+        ];
+        for (id, j) in todos {
+            c.execute(
+                "INSERT INTO todos (id, data_json, updated_at) VALUES (?1, ?2, '2026-05-13 00:00:00')",
+                rusqlite::params![id.to_string(), j],
+            )
+            .unwrap();
+        }
+        c.execute(
+            "INSERT INTO subtasks (id, todo_id, data_json, updated_at) VALUES
+             ('10', '1', '{\"completed\":false}', ''),
+             ('11', '1', '{\"completed\":true}', ''),
+             ('12', '2', '{\"completed\":true}', '')",
+            [],
+        )
+        .unwrap();
+        c
+    }
+
+    fn ids(c: &Connection, filter: &str) -> Vec<String> {
+        let e = p(filter);
+        let mut args = Vec::new();
+        let sql = format!(
+            "SELECT id FROM todos WHERE {} ORDER BY id",
+            to_sql(&e, &mut args)
+        );
+        let mut stmt = c.prepare(&sql).unwrap();
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(args), |r| r.get::<_, String>(0))
+            .unwrap();
+        rows.map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn example_from_spec() {
+        let c = db();
+        assert_eq!(
+            ids(
+                &c,
+                r#"quadrant in (1,2) and not completed and due < today+3d and text ~ "report""#
+            ),
+            vec!["1"]
+        );
+    }
+
+    #[test]
+    fn in_not_in_and_or_groups() {
+        let c = db();
+        assert_eq!(ids(&c, "quadrant not in (1, 2)"), vec!["3", "4"]);
+        assert_eq!(
+            ids(&c, "(quadrant = 1 or quadrant = 4) and not completed"),
+            vec!["1", "4"]
+        );
+    }
+
+    #[test]
+    fn date_vs_datetime_comparison() {
+        let c = db();
+        // endTime 带时间，按日比较也能命中
+        assert_eq!(ids(&c, "due = 2026-05-20"), vec!["2"]);
+        assert_eq!(ids(&c, "due > 2026-05-20T17:00"), vec!["2"]);
+        // 无 due 的 todo 不会被 < / not 误伤
+        assert_eq!(ids(&c, "due < today"), vec!["3"]);
+        assert_eq!(ids(&c, "not has due"), vec!["4"]);
+        assert_eq!(ids(&c, "due = null"), vec!["4"]);
+    }
+
+    #[test]
+    fn subtask_predicates() {
+        let c = db();
+        assert_eq!(ids(&c, "openSubtasks"), vec!["1"]);
+        assert_eq!(ids(&c, "has subtasks and not openSubtasks"), vec!["2"]);
+        assert_eq!(ids(&c, "doneSubtasks >= 1"), vec!["1", "2"]);
+    }
+
+    #[test]
+    fn priority_ordering_and_like_escaping() {
+        let c = db();
+        assert_eq!(ids(&c, "priority >= medium"), vec!["1"]);
+        assert_eq!(ids(&c, "priority = low"), vec!["2"]);
+        assert_eq!(ids(&c, "title ~ '50%'"), vec!["4"]);
+        assert_eq!(ids(&c, "title ~ '5_%'"), Vec::<String>::new());
+        assert_eq!(ids(&c, "text ~ COUPON"), vec!["4"]);
+    }
+}
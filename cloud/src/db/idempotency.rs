@@ -0,0 +1,166 @@
+//! `idempotency_keys` 表：`Idempotency-Key` 请求的指纹与首次响应。
+//!
+//! cloud-only、不同步。一行的生命周期：[`begin`] 插入"处理中"行（`status`
+//! 为 NULL）→ handler 跑完 [`complete`] 写入响应；handler 5xx 或 panic 时
+//! [`abandon`] 删行，让客户端重试时重新执行。`key` 列存的是带 API key 名前缀
+//! 的值（见 `api::idempotency`）。`created_at` 用 unix 秒，只用来算窗口期，
+//! 与本地时间字符串无关。
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+/// 处理中的行超过这个秒数仍未完成，视为进程在处理中途退出，允许接管。
+const STALE_IN_PROGRESS_SECS: i64 = 600;
+
+/// 首次请求的响应。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Begin {
+    /// 首次见到这个 key（或已过期 / 接管了卡住的行），调用方执行请求。
+    Fresh,
+    /// 同一个 key 的请求还在处理。
+    InProgress,
+    /// key 用过，但请求指纹不同。
+    Mismatch,
+    /// 已完成，直接回放。
+    Done(StoredResponse),
+}
+
+/// 查 key 并在需要时占位。先删掉早于 `now - window_secs` 的行，过期的 key
+/// 视为没见过。
+pub fn begin(
+    conn: &mut Connection,
+    key: &str,
+    request_hash: &str,
+    now: i64,
+    window_secs: i64,
+) -> rusqlite::Result<Begin> {
+    let tx = conn.transaction()?;
+    tx.execute(
+        "DELETE FROM idempotency_keys WHERE created_at < ?1",
+        [now - window_secs],
+    )?;
+    let row = tx
+        .query_row(
+            "SELECT request_hash, created_at, status, content_type, body \
+             FROM idempotency_keys WHERE key = ?1",
+            [key],
+            |r| {
+                Ok((
+                    r.get::<_, String>(0)?,
+                    r.get::<_, i64>(1)?,
+                    r.get::<_, Option<u16>>(2)?,
+                    r.get::<_, Option<String>>(3)?,
+                    r.get::<_, Option<Vec<u8>>>(4)?,
+                ))
+            },
+        )
+        .optional()?;
+
+    let outcome = match row {
+        Some((hash, _, _, _, _)) if hash != request_hash => Begin::Mismatch,
+        Some((_, _, Some(status), content_type, body)) => Begin::Done(StoredResponse {
+            status,
+            content_type,
+            body: body.unwrap_or_default(),
+        }),
+        Some((_, created_at, None, _, _)) if now - created_at < STALE_IN_PROGRESS_SECS => {
+            Begin::InProgress
+        }
+        _ => {
+            tx.execute(
+                "INSERT OR REPLACE INTO idempotency_keys (key, request_hash, created_at) \
+                 VALUES (?1, ?2, ?3)",
+                params![key, request_hash, now],
+            )?;
+            Begin::Fresh
+        }
+    };
+    tx.commit()?;
+    Ok(outcome)
+}
+
+/// 写入首次响应。窗口期从 [`begin`] 时算起。
+pub fn complete(conn: &Connection, key: &str, resp: &StoredResponse) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE idempotency_keys SET status = ?2, content_type = ?3, body = ?4 WHERE key = ?1",
+        params![key, resp.status, resp.content_type, resp.body],
+    )?;
+    Ok(())
+}
+
+/// 放弃占位（handler 出错），之后用同一个 key 重试会重新执行。
+pub fn abandon(conn: &Connection, key: &str) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM idempotency_keys WHERE key = ?1", [key])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: i64 = 86_400;
+
+    fn conn() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::schema::init(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn lifecycle() {
+        let mut c = conn();
+        assert_eq!(
+            begin(&mut c, "k", "h1", 1000, WINDOW).unwrap(),
+            Begin::Fresh
+        );
+        assert_eq!(
+            begin(&mut c, "k", "h1", 1001, WINDOW).unwrap(),
+            Begin::InProgress
+        );
+        assert_eq!(
+            begin(&mut c, "k", "h2", 1001, WINDOW).unwrap(),
+            Begin::Mismatch
+        );
+
+        let resp = StoredResponse {
+            status: 201,
+            content_type: Some("application/json".to_string()),
+            body: br#"{"id":1}"#.to_vec(),
+        };
+        complete(&c, "k", &resp).unwrap();
+        assert_eq!(
+            begin(&mut c, "k", "h1", 1002, WINDOW).unwrap(),
+            Begin::Done(resp)
+        );
+        assert_eq!(
+            begin(&mut c, "k", "h2", 1002, WINDOW).unwrap(),
+            Begin::Mismatch
+        );
+
+        // 窗口期过后 key 可以重用
+        assert_eq!(
+            begin(&mut c, "k", "h2", 1000 + WINDOW + 1, WINDOW).unwrap(),
+            Begin::Fresh
+        );
+    }
+
+    #[test]
+    fn abandoned_and_stale_rows_can_be_retried() {
+        let mut c = conn();
+        assert_eq!(begin(&mut c, "k", "h", 1000, WINDOW).unwrap(), Begin::Fresh);
+        abandon(&c, "k").unwrap();
+        assert_eq!(begin(&mut c, "k", "h", 1001, WINDOW).unwrap(), Begin::Fresh);
+
+        // 进程在处理中途退出留下的行，过一段时间后可以接管
+        assert_eq!(
+            begin(&mut c, "k", "h", 1001 + STALE_IN_PROGRESS_SECS, WINDOW).unwrap(),
+            Begin::Fresh
+        );
+    }
+}
@@ -6,13 +6,19 @@
 //! `data_json`，列表/过滤用 SQLite JSON1 `json_extract` 完成。这样 PC 端
 //! 加新字段不影响云端代码。
 
+pub mod audit;
+pub mod filter;
+pub mod idempotency;
 pub mod repo;
 pub mod schema;
+pub mod stats;
 
 use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use rusqlite::Connection;
+use rusqlite::backup::Backup;
+use rusqlite::{Connection, OpenFlags};
 
 /// 线程安全的 SQLite 包装。axum handler + 后台 worker 共用。
 #[derive(Clone)]
@@ -40,6 +46,49 @@ impl Db {
         })
     }
 
+    /// 用 SQLite online backup 把当前库完整拷到 `dest`（WAL 中未 checkpoint 的
+    /// 页也包含在内）。`dest` 已存在时拒绝覆盖。
+    pub fn backup_to(&self, dest: &Path) -> anyhow::Result<()> {
+        if dest.exists() {
+            anyhow::bail!("{} 已存在，拒绝覆盖", dest.display());
+        }
+        self.with_conn(|conn| -> rusqlite::Result<()> {
+            let mut dst = Connection::open(dest)?;
+            let backup = Backup::new(conn, &mut dst)?;
+            backup.run_to_completion(256, Duration::ZERO, None)
+        })
+        .map_err(|e| anyhow::anyhow!("备份到 {} 失败: {}", dest.display(), e))
+    }
+
+    /// 用 `src` 备份整体替换当前库内容，然后补跑迁移并标脏。
+    ///
+    /// 标脏是为了让恢复出来的数据以"本地有未推送改动"的身份参与下一次同步：
+    /// pull 会跳过孤儿清理（否则远端没有的记录会被删掉），push 再按 LWW 合并回远端。
+    pub fn restore_from(&self, src: &Path) -> anyhow::Result<()> {
+        let src_conn = Connection::open_with_flags(src, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(|e| anyhow::anyhow!("打开备份 {} 失败: {}", src.display(), e))?;
+        let has_todos: bool = src_conn
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'todos'",
+                [],
+                |r| r.get(0),
+            )
+            .map_err(|e| anyhow::anyhow!("{} 不是 SQLite 数据库: {}", src.display(), e))?;
+        if !has_todos {
+            anyhow::bail!(
+                "{} 不是 minitodo-cloud 的备份（缺少 todos 表）",
+                src.display()
+            );
+        }
+        self.with_conn(|conn| -> anyhow::Result<()> {
+            Backup::new(&src_conn, conn)?.run_to_completion(256, Duration::ZERO, None)?;
+            schema::init(conn)?;
+            repo::mark_dirty(conn)?;
+            Ok(())
+        })
+        .map_err(|e| anyhow::anyhow!("从 {} 恢复失败: {:#}", src.display(), e))
+    }
+
     /// 在 lock 内同步执行一段逻辑。所有数据库操作都走这里。
     ///
     /// 拿锁时忽略中毒标记：持锁闭包 panic 只影响那一次请求，SQLite 连接本身
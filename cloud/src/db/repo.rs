@@ -16,6 +16,30 @@ pub struct TodoRow {
     pub updated_at: String,
 }
 
+/// 单条 saved view：`data_json` 即 `settings.savedViews[]` 中的对象。
+#[derive(Debug, Clone)]
+pub struct ViewRow {
+    pub name: String,
+    pub data_json: String,
+    pub updated_at: String,
+}
+
+/// 单条 todo 模板：`data_json` 即 sync-data `templates[]` 中的对象。
+#[derive(Debug, Clone)]
+pub struct TemplateRow {
+    pub id: String,
+    pub data_json: String,
+    pub updated_at: String,
+}
+
+/// 单条标签：`data_json` 即 sync-data `tags[]` 中的对象。
+#[derive(Debug, Clone)]
+pub struct TagRow {
+    pub name: String,
+    pub data_json: String,
+    pub updated_at: String,
+}
+
 /// 单条 subtask 在 SQLite 中的快照。同 `TodoRow`。
 #[derive(Debug, Clone)]
 pub struct SubtaskRow {
@@ -50,6 +74,11 @@ pub fn set_meta(conn: &Connection, key: &str, value: &str) -> rusqlite::Result<(
     Ok(())
 }
 
+pub fn delete_meta(conn: &Connection, key: &str) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM meta WHERE key = ?1", [key])?;
+    Ok(())
+}
+
 /// 标脏：置 `dirty=true` 并把 `dirty_generation` 计数 +1。
 ///
 /// 所有写路径（todos / subtasks / images 的增删改）都必须走这里。generation
@@ -69,6 +98,36 @@ pub fn get_dirty_generation(conn: &Connection) -> rusqlite::Result<i64> {
         .unwrap_or(0))
 }
 
+/// 还没推上远端的写入代数：`dirty_generation` 减去最近一次 PUT 成功时快照
+/// 所在的代数（`pushed_generation`，见 `push::clear_dirty_if_unchanged`）。
+/// dirty 未置位时恒为 0。
+pub fn dirty_generation_lag(conn: &Connection) -> rusqlite::Result<i64> {
+    if get_meta(conn, "dirty")?.as_deref() != Some("true") {
+        return Ok(0);
+    }
+    let pushed = get_meta(conn, "pushed_generation")?
+        .and_then(|s| s.parse::<i64>().ok())
+        .unwrap_or(0);
+    Ok((get_dirty_generation(conn)? - pushed).max(0))
+}
+
+/// `meta.dirty_images` 里等待上传的图片数；键不存在或值非法视为 0。
+pub fn dirty_images_len(conn: &Connection) -> rusqlite::Result<usize> {
+    Ok(get_meta(conn, "dirty_images")?
+        .and_then(|s| serde_json::from_str::<Vec<String>>(&s).ok())
+        .map(|v| v.len())
+        .unwrap_or(0))
+}
+
+/// SQLite 主库逻辑大小（`page_count * page_size`，不含 WAL）。
+pub fn db_size_bytes(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        [],
+        |row| row.get(0),
+    )
+}
+
 /// 给 `todo_id` 分配 / 取得 cloud-only 短码 `seq`。
 ///
 /// - 已分配 → 返回现有 seq（幂等，多次调用不重复分配）。
@@ -93,6 +152,33 @@ pub fn assign_seq(conn: &Connection, todo_id: &str) -> rusqlite::Result<i64> {
     Ok(next)
 }
 
+/// 重建 `todo_seq`：按现有 seq（未分配的排最后）→ `createdAt` → id 的顺序
+/// 重新编号为 1..N，顺带丢掉指向已删除 todo 的残留行。返回 todo 数。
+///
+/// 会改变已有短码，只给管理员 `reseq` 子命令用。
+pub fn rebuild_seq(conn: &mut Connection) -> rusqlite::Result<usize> {
+    let tx = conn.transaction()?;
+    let ids: Vec<String> = {
+        let mut stmt = tx.prepare(
+            "SELECT t.id FROM todos t
+             LEFT JOIN todo_seq s ON s.todo_id = t.id
+             ORDER BY s.seq IS NULL, s.seq,
+                      json_extract(t.data_json, '$.createdAt'), CAST(t.id AS INTEGER), t.id",
+        )?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+    tx.execute("DELETE FROM todo_seq", [])?;
+    for (i, id) in ids.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO todo_seq (todo_id, seq) VALUES (?1, ?2)",
+            params![id, i as i64 + 1],
+        )?;
+    }
+    tx.commit()?;
+    Ok(ids.len())
+}
+
 /// 查 `todo_id` 对应的 seq；未分配返回 None。
 pub fn get_seq(conn: &Connection, todo_id: &str) -> rusqlite::Result<Option<i64>> {
     conn.query_row(
@@ -164,6 +250,11 @@ pub fn seq_map_for_todos(
 // settings KV（与 SyncData.settings JSON 字段对应）
 // =============================================================================
 
+/// pull 下来的远端 `settings` 整 JSON。
+pub const SETTING_REMOTE: &str = "all";
+/// `PATCH /settings` 累积的改动（JSON 对象），push 成功后清掉。
+pub const SETTING_PENDING: &str = "pending";
+
 pub fn set_setting(conn: &Connection, key: &str, value: &str) -> rusqlite::Result<()> {
     conn.execute(
         "INSERT INTO settings (key, value) VALUES (?1, ?2)
@@ -173,6 +264,23 @@ pub fn set_setting(conn: &Connection, key: &str, value: &str) -> rusqlite::Resul
     Ok(())
 }
 
+pub fn get_setting(conn: &Connection, key: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row("SELECT value FROM settings WHERE key = ?1", [key], |r| {
+        r.get::<_, Option<String>>(0)
+    })
+    .optional()
+    .map(Option::flatten)
+}
+
+/// 值仍等于 `expected` 时才删除（读取之后没被别人改过），返回是否删除。
+pub fn delete_setting_if(conn: &Connection, key: &str, expected: &str) -> rusqlite::Result<bool> {
+    let n = conn.execute(
+        "DELETE FROM settings WHERE key = ?1 AND value = ?2",
+        params![key, expected],
+    )?;
+    Ok(n > 0)
+}
+
 // =============================================================================
 // todos / subtasks
 // =============================================================================
@@ -202,21 +310,117 @@ pub struct ListTodosFilter {
     pub due_date_after: Option<String>,
     pub start_date: Option<String>,
     pub q: Option<String>,
-    /// `(field, asc)`。例如 `("dueDate", true)` 对应 `+dueDate`，`("priority", false)` 对应 `-priority`。
-    pub sort: Option<(String, bool)>,
+    /// `tag=work`：todo 的 `tags` 必须包含全部这些标签。
+    pub tags_include: Vec<String>,
+    /// `tag=-personal`：todo 的 `tags` 不能包含其中任何一个。
+    pub tags_exclude: Vec<String>,
+    /// `filter=` 表达式编译前的语法树（见 [`super::filter`]），与其它条件 AND。
+    pub expr: Option<super::filter::Expr>,
+    /// 多键排序 `(field, asc)`，按优先级排列。例如 `sort=-priority,+dueDate` 对应
+    /// `[("priority", false), ("dueDate", true)]`；空表示默认 sortOrder asc。
+    pub sort: Vec<(String, bool)>,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    /// keyset 分页：只返回排在该位置**之后**的行。与 `offset` 互斥（上层保证）。
+    pub after: Option<TodoCursor>,
+}
+
+/// keyset 分页位置：排序表达式的取值（与排序键一一对应）+ 兜底 tie-breaker id。
+///
+/// 与 offset 不同，两页之间 pull merge 插入 / 删除的行不会让后一页跳过或重复
+/// 条目——位置由"上一页最后一条的排序值"决定，而不是行号。
+#[derive(Debug, Clone, PartialEq)]
+pub struct TodoCursor {
+    pub keys: Vec<serde_json::Value>,
+    pub id: String,
 }
 
 /// 列表查询。返回原始 `TodoRow`，filter / sort / pagination 都已在 SQL 里完成。
 ///
 /// 排序字段白名单：`dueDate` / `startTime` / `priority` / `quadrant` / `sortOrder`
 /// / `updatedAt` / `createdAt` / `title`；非白名单 fallback 到 sortOrder asc。
+/// 排序之后总以 `id ASC` 兜底，保证顺序稳定（cursor 依赖这一点）。
 pub fn list_todos_filtered(
     conn: &Connection,
     filter: &ListTodosFilter,
 ) -> rusqlite::Result<Vec<TodoRow>> {
-    let mut sql = String::from("SELECT id, data_json, updated_at FROM todos WHERE 1=1");
+    let (where_sql, mut args) = filter_where(filter);
+    let mut sql = format!(
+        "SELECT id, data_json, updated_at FROM todos WHERE {}",
+        where_sql
+    );
+
+    let order = order_keys(filter);
+    if let Some(ref cur) = filter.after {
+        let (after_sql, after_args) = after_clause(&order, cur);
+        sql.push_str(" AND ");
+        sql.push_str(&after_sql);
+        args.extend(after_args);
+    }
+
+    let order_sql: Vec<String> = order
+        .iter()
+        .map(|(expr, asc)| format!("{} {}", expr, if *asc { "ASC" } else { "DESC" }))
+        .collect();
+    sql.push_str(&format!(" ORDER BY {}, id ASC", order_sql.join(", ")));
+
+    if let Some(l) = filter.limit {
+        sql.push_str(" LIMIT ?");
+        args.push(Box::new(l));
+        if let Some(o) = filter.offset {
+            sql.push_str(" OFFSET ?");
+            args.push(Box::new(o));
+        }
+    } else if let Some(o) = filter.offset {
+        sql.push_str(" LIMIT -1 OFFSET ?");
+        args.push(Box::new(o));
+    }
+
+    let mut stmt = conn.prepare(&sql)?;
+    let params_refs: Vec<&dyn rusqlite::ToSql> = args.iter().map(|b| b.as_ref()).collect();
+    let rows = stmt.query_map(rusqlite::params_from_iter(params_refs), |row| {
+        Ok(TodoRow {
+            id: row.get(0)?,
+            data_json: row.get(1)?,
+            updated_at: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// 满足 filter 的总条数（忽略 limit / offset / after），用于 `X-Total-Count`。
+pub fn count_todos_filtered(conn: &Connection, filter: &ListTodosFilter) -> rusqlite::Result<i64> {
+    let (where_sql, args) = filter_where(filter);
+    let sql = format!("SELECT COUNT(*) FROM todos WHERE {}", where_sql);
+    let params_refs: Vec<&dyn rusqlite::ToSql> = args.iter().map(|b| b.as_ref()).collect();
+    conn.query_row(&sql, rusqlite::params_from_iter(params_refs), |r| r.get(0))
+}
+
+/// 取某条 todo 在当前排序下的 cursor（上一页最后一条 → 下一页起点）。
+pub fn cursor_for_todo(
+    conn: &Connection,
+    filter: &ListTodosFilter,
+    id: &str,
+) -> rusqlite::Result<Option<TodoCursor>> {
+    let order = order_keys(filter);
+    let exprs: Vec<&str> = order.iter().map(|(e, _)| e.as_str()).collect();
+    let sql = format!("SELECT {} FROM todos WHERE id = ?1", exprs.join(", "));
+    conn.query_row(&sql, [id], |row| {
+        let mut keys = Vec::with_capacity(exprs.len());
+        for i in 0..exprs.len() {
+            keys.push(sql_to_json(row.get::<_, rusqlite::types::Value>(i)?));
+        }
+        Ok(TodoCursor {
+            keys,
+            id: id.to_string(),
+        })
+    })
+    .optional()
+}
+
+/// WHERE 子句（不含 `WHERE` 关键字）与绑定参数；list 与 count 共用。
+fn filter_where(filter: &ListTodosFilter) -> (String, Vec<Box<dyn rusqlite::ToSql>>) {
+    let mut sql = String::from("1=1");
     let mut args: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
 
     if let Some(c) = filter.completed {
@@ -261,49 +465,99 @@ pub fn list_todos_filtered(
         args.push(Box::new(like.clone()));
         args.push(Box::new(like));
     }
+    for tag in &filter.tags_include {
+        sql.push_str(" AND EXISTS (SELECT 1 FROM json_each(data_json, '$.tags') WHERE value = ?)");
+        args.push(Box::new(tag.clone()));
+    }
+    for tag in &filter.tags_exclude {
+        sql.push_str(
+            " AND NOT EXISTS (SELECT 1 FROM json_each(data_json, '$.tags') WHERE value = ?)",
+        );
+        args.push(Box::new(tag.clone()));
+    }
+    if let Some(ref expr) = filter.expr {
+        let mut vals = Vec::new();
+        sql.push_str(&format!(" AND {}", super::filter::to_sql(expr, &mut vals)));
+        args.extend(
+            vals.into_iter()
+                .map(|v| Box::new(v) as Box<dyn rusqlite::ToSql>),
+        );
+    }
+    (sql, args)
+}
 
-    let (sort_field_sql, asc) = match &filter.sort {
-        Some((field, asc)) => (sort_expr(field.as_str()), *asc),
-        None => (
+/// 当前 filter 的排序键：`(SQL 表达式, asc)` 列表，不含 id tie-breaker。
+fn order_keys(filter: &ListTodosFilter) -> Vec<(String, bool)> {
+    if filter.sort.is_empty() {
+        return vec![(
             "CAST(IFNULL(json_extract(data_json, '$.sortOrder'), 0) AS INTEGER)".to_string(),
             true,
-        ),
-    };
-    sql.push_str(&format!(
-        " ORDER BY {} {}, id ASC",
-        sort_field_sql,
-        if asc { "ASC" } else { "DESC" }
-    ));
+        )];
+    }
+    filter
+        .sort
+        .iter()
+        .map(|(field, asc)| (sort_expr(field.as_str()), *asc))
+        .collect()
+}
 
-    if let Some(l) = filter.limit {
-        sql.push_str(" LIMIT ?");
-        args.push(Box::new(l));
-        if let Some(o) = filter.offset {
-            sql.push_str(" OFFSET ?");
-            args.push(Box::new(o));
+/// "排在 cursor 之后" 的条件。多键时按字典序展开：
+/// `k1 > v1 OR (k1 = v1 AND k2 > v2) OR ... OR (k1 = v1 AND ... AND id > cid)`，
+/// DESC 键把 `>` 换成 `<`；id 永远 ASC。
+fn after_clause(
+    order: &[(String, bool)],
+    cur: &TodoCursor,
+) -> (String, Vec<Box<dyn rusqlite::ToSql>>) {
+    let mut ors = Vec::new();
+    let mut args: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+    for i in 0..=order.len() {
+        let mut ands = Vec::new();
+        for (j, (expr, _)) in order.iter().enumerate().take(i) {
+            ands.push(format!("{} = ?", expr));
+            args.push(Box::new(json_to_sql(cur.keys.get(j))));
         }
-    } else if let Some(o) = filter.offset {
-        sql.push_str(" LIMIT -1 OFFSET ?");
-        args.push(Box::new(o));
+        if let Some((expr, asc)) = order.get(i) {
+            ands.push(format!("{} {} ?", expr, if *asc { ">" } else { "<" }));
+            args.push(Box::new(json_to_sql(cur.keys.get(i))));
+        } else {
+            ands.push("id > ?".to_string());
+            args.push(Box::new(cur.id.clone()));
+        }
+        ors.push(format!("({})", ands.join(" AND ")));
     }
+    (format!("({})", ors.join(" OR ")), args)
+}
 
-    let mut stmt = conn.prepare(&sql)?;
-    let params_refs: Vec<&dyn rusqlite::ToSql> = args.iter().map(|b| b.as_ref()).collect();
-    let rows = stmt.query_map(rusqlite::params_from_iter(params_refs), |row| {
-        Ok(TodoRow {
-            id: row.get(0)?,
-            data_json: row.get(1)?,
-            updated_at: row.get(2)?,
-        })
-    })?;
-    rows.collect()
+fn sql_to_json(v: rusqlite::types::Value) -> serde_json::Value {
+    use rusqlite::types::Value as Sql;
+    match v {
+        Sql::Null => serde_json::Value::Null,
+        Sql::Integer(i) => serde_json::json!(i),
+        Sql::Real(f) => serde_json::json!(f),
+        Sql::Text(s) => serde_json::Value::String(s),
+        Sql::Blob(_) => serde_json::Value::Null,
+    }
+}
+
+fn json_to_sql(v: Option<&serde_json::Value>) -> rusqlite::types::Value {
+    use rusqlite::types::Value as Sql;
+    match v {
+        Some(serde_json::Value::Number(n)) => match n.as_i64() {
+            Some(i) => Sql::Integer(i),
+            None => Sql::Real(n.as_f64().unwrap_or(0.0)),
+        },
+        Some(serde_json::Value::String(s)) => Sql::Text(s.clone()),
+        Some(serde_json::Value::Bool(b)) => Sql::Integer(*b as i64),
+        _ => Sql::Null,
+    }
 }
 
 fn sort_expr(field: &str) -> String {
     match field {
         "dueDate" | "endTime" => {
+            // 与 dueDateBefore/After 一致：空串当 NULL，dueDate 缺省取 endTime。
             // COALESCE 支持 3 参，IFNULL 不支持
-            "COALESCE(json_extract(data_json, '$.dueDate'), json_extract(data_json, '$.endTime'), '')".to_string()
+            "COALESCE(NULLIF(json_extract(data_json, '$.dueDate'), ''), NULLIF(json_extract(data_json, '$.endTime'), ''), '')".to_string()
         }
         "startTime" | "startDate" => {
             "COALESCE(json_extract(data_json, '$.startTime'), json_extract(data_json, '$.startDate'), '')".to_string()
@@ -532,6 +786,270 @@ pub fn delete_subtasks_not_in(
     Ok(n)
 }
 
+// =============================================================================
+// saved views
+// =============================================================================
+
+fn view_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<ViewRow> {
+    Ok(ViewRow {
+        name: row.get(0)?,
+        data_json: row.get(1)?,
+        updated_at: row.get(2)?,
+    })
+}
+
+pub fn list_views(conn: &Connection) -> rusqlite::Result<Vec<ViewRow>> {
+    let mut stmt =
+        conn.prepare("SELECT name, data_json, updated_at FROM saved_views ORDER BY name ASC")?;
+    let rows = stmt.query_map([], view_from_row)?;
+    rows.collect()
+}
+
+pub fn get_view(conn: &Connection, name: &str) -> rusqlite::Result<Option<ViewRow>> {
+    conn.query_row(
+        "SELECT name, data_json, updated_at FROM saved_views WHERE name = ?1",
+        [name],
+        view_from_row,
+    )
+    .optional()
+}
+
+pub fn upsert_view(
+    conn: &Connection,
+    name: &str,
+    data_json: &str,
+    updated_at: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO saved_views (name, data_json, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(name) DO UPDATE SET data_json = excluded.data_json, updated_at = excluded.updated_at",
+        params![name, data_json, updated_at],
+    )?;
+    Ok(())
+}
+
+/// LWW：远端 `updated_at` ≥ 本地才写。pull merge 用。
+pub fn upsert_view_if_newer(
+    conn: &Connection,
+    name: &str,
+    data_json: &str,
+    updated_at: &str,
+) -> rusqlite::Result<bool> {
+    if let Some(row) = get_view(conn, name)? {
+        if updated_at < row.updated_at.as_str() {
+            return Ok(false);
+        }
+    }
+    upsert_view(conn, name, data_json, updated_at)?;
+    Ok(true)
+}
+
+pub fn delete_view(conn: &Connection, name: &str) -> rusqlite::Result<bool> {
+    let n = conn.execute("DELETE FROM saved_views WHERE name = ?1", [name])?;
+    Ok(n > 0)
+}
+
+/// 删除 name 不在 `keep` 集合内的 views（pull 孤儿清理）。
+pub fn delete_views_not_in(
+    conn: &Connection,
+    keep: &std::collections::HashSet<String>,
+) -> rusqlite::Result<usize> {
+    let mut n = 0usize;
+    for row in list_views(conn)? {
+        if !keep.contains(&row.name) {
+            n += delete_view(conn, &row.name)? as usize;
+        }
+    }
+    Ok(n)
+}
+
+// =============================================================================
+// templates
+// =============================================================================
+
+fn template_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<TemplateRow> {
+    Ok(TemplateRow {
+        id: row.get(0)?,
+        data_json: row.get(1)?,
+        updated_at: row.get(2)?,
+    })
+}
+
+/// 按模板名排序（名字相同按 id）。
+pub fn list_templates(conn: &Connection) -> rusqlite::Result<Vec<TemplateRow>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, data_json, updated_at FROM templates
+         ORDER BY json_extract(data_json, '$.name') ASC, id ASC",
+    )?;
+    let rows = stmt.query_map([], template_from_row)?;
+    rows.collect()
+}
+
+pub fn get_template(conn: &Connection, id: &str) -> rusqlite::Result<Option<TemplateRow>> {
+    conn.query_row(
+        "SELECT id, data_json, updated_at FROM templates WHERE id = ?1",
+        [id],
+        template_from_row,
+    )
+    .optional()
+}
+
+pub fn upsert_template(
+    conn: &Connection,
+    id: &str,
+    data_json: &str,
+    updated_at: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO templates (id, data_json, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(id) DO UPDATE SET data_json = excluded.data_json, updated_at = excluded.updated_at",
+        params![id, data_json, updated_at],
+    )?;
+    Ok(())
+}
+
+/// LWW：远端 `updated_at` ≥ 本地才写。pull merge 用。
+pub fn upsert_template_if_newer(
+    conn: &Connection,
+    id: &str,
+    data_json: &str,
+    updated_at: &str,
+) -> rusqlite::Result<bool> {
+    if let Some(row) = get_template(conn, id)? {
+        if updated_at < row.updated_at.as_str() {
+            return Ok(false);
+        }
+    }
+    upsert_template(conn, id, data_json, updated_at)?;
+    Ok(true)
+}
+
+pub fn delete_template(conn: &Connection, id: &str) -> rusqlite::Result<bool> {
+    let n = conn.execute("DELETE FROM templates WHERE id = ?1", [id])?;
+    Ok(n > 0)
+}
+
+/// 删除 id 不在 `keep` 集合内的模板（pull 孤儿清理）。
+pub fn delete_templates_not_in(
+    conn: &Connection,
+    keep: &std::collections::HashSet<String>,
+) -> rusqlite::Result<usize> {
+    let mut n = 0usize;
+    for row in list_templates(conn)? {
+        if !keep.contains(&row.id) {
+            n += delete_template(conn, &row.id)? as usize;
+        }
+    }
+    Ok(n)
+}
+
+// =============================================================================
+// tags
+// =============================================================================
+
+fn tag_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<TagRow> {
+    Ok(TagRow {
+        name: row.get(0)?,
+        data_json: row.get(1)?,
+        updated_at: row.get(2)?,
+    })
+}
+
+pub fn list_tags(conn: &Connection) -> rusqlite::Result<Vec<TagRow>> {
+    let mut stmt =
+        conn.prepare("SELECT name, data_json, updated_at FROM tags ORDER BY name ASC")?;
+    let rows = stmt.query_map([], tag_from_row)?;
+    rows.collect()
+}
+
+pub fn get_tag(conn: &Connection, name: &str) -> rusqlite::Result<Option<TagRow>> {
+    conn.query_row(
+        "SELECT name, data_json, updated_at FROM tags WHERE name = ?1",
+        [name],
+        tag_from_row,
+    )
+    .optional()
+}
+
+pub fn upsert_tag(
+    conn: &Connection,
+    name: &str,
+    data_json: &str,
+    updated_at: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO tags (name, data_json, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(name) DO UPDATE SET data_json = excluded.data_json, updated_at = excluded.updated_at",
+        params![name, data_json, updated_at],
+    )?;
+    Ok(())
+}
+
+/// LWW：远端 `updated_at` ≥ 本地才写。pull merge 用。
+pub fn upsert_tag_if_newer(
+    conn: &Connection,
+    name: &str,
+    data_json: &str,
+    updated_at: &str,
+) -> rusqlite::Result<bool> {
+    if let Some(row) = get_tag(conn, name)? {
+        if updated_at < row.updated_at.as_str() {
+            return Ok(false);
+        }
+    }
+    upsert_tag(conn, name, data_json, updated_at)?;
+    Ok(true)
+}
+
+pub fn delete_tag(conn: &Connection, name: &str) -> rusqlite::Result<bool> {
+    let n = conn.execute("DELETE FROM tags WHERE name = ?1", [name])?;
+    Ok(n > 0)
+}
+
+/// 删除 name 不在 `keep` 集合内的标签（pull 孤儿清理）。
+pub fn delete_tags_not_in(
+    conn: &Connection,
+    keep: &std::collections::HashSet<String>,
+) -> rusqlite::Result<usize> {
+    let mut n = 0usize;
+    for row in list_tags(conn)? {
+        if !keep.contains(&row.name) {
+            n += delete_tag(conn, &row.name)? as usize;
+        }
+    }
+    Ok(n)
+}
+
+/// 所有 todo 的 `tags` 里出现过的标签名 → 引用它的 todo 数，按名字排序。
+pub fn tag_usage(conn: &Connection) -> rusqlite::Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.value, COUNT(DISTINCT todos.id)
+         FROM todos, json_each(todos.data_json, '$.tags') AS t
+         WHERE t.type = 'text'
+         GROUP BY t.value
+         ORDER BY t.value ASC",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
+/// `tags` 里含 `name` 的 todo（改名 / 删除标签时要逐条改写）。
+pub fn todos_with_tag(conn: &Connection, name: &str) -> rusqlite::Result<Vec<TodoRow>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, data_json, updated_at FROM todos
+         WHERE EXISTS (SELECT 1 FROM json_each(data_json, '$.tags') WHERE value = ?1)
+         ORDER BY id ASC",
+    )?;
+    let rows = stmt.query_map([name], |row| {
+        Ok(TodoRow {
+            id: row.get(0)?,
+            data_json: row.get(1)?,
+            updated_at: row.get(2)?,
+        })
+    })?;
+    rows.collect()
+}
+
 // =============================================================================
 // Tombstones（软删除标记，push worker merge 用）
 // =============================================================================
@@ -550,6 +1068,20 @@ pub fn add_tombstone(
     Ok(())
 }
 
+/// 按 name 作主键的实体（tag）被重新创建时清掉旧墓碑，否则 push merge 会把
+/// 新记录当成已删除剔除。
+pub fn remove_tombstone(
+    conn: &Connection,
+    entity_type: &str,
+    entity_id: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM tombstones WHERE entity_type = ?1 AND entity_id = ?2",
+        params![entity_type, entity_id],
+    )?;
+    Ok(())
+}
+
 /// 返回所有 tombstones 的 `(entity_type, entity_id, deleted_at)` 列表。
 pub fn list_tombstones(conn: &Connection) -> rusqlite::Result<Vec<(String, String, String)>> {
     let mut stmt = conn.prepare("SELECT entity_type, entity_id, deleted_at FROM tombstones")?;
@@ -557,8 +1089,7 @@ pub fn list_tombstones(conn: &Connection) -> rusqlite::Result<Vec<(String, Strin
     rows.collect()
 }
 
-/// 一次查询：墓碑是否存在。供 push merge 路径以外（如调试 / 未来路由）使用。
-#[allow(dead_code)]
+/// 一次查询：墓碑是否存在。pull merge saved views 时用来挡住本地已删的 view。
 pub fn has_tombstone(
     conn: &Connection,
     entity_type: &str,
@@ -572,6 +1103,15 @@ pub fn has_tombstone(
     Ok(count > 0)
 }
 
+/// 按 `entity_type` 统计 tombstone 数。
+pub fn count_tombstones_by_type(conn: &Connection) -> rusqlite::Result<Vec<(String, i64)>> {
+    let mut stmt = conn.prepare(
+        "SELECT entity_type, COUNT(*) FROM tombstones GROUP BY entity_type ORDER BY entity_type",
+    )?;
+    let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    rows.collect()
+}
+
 /// 清理早于 `cutoff_local` 的 tombstones。push worker 每次 PUT 成功后调用。
 pub fn purge_tombstones_before(conn: &Connection, cutoff_local: &str) -> rusqlite::Result<usize> {
     let n = conn.execute(
@@ -764,7 +1304,7 @@ mod tests {
         let rows = list_todos_filtered(
             &c,
             &ListTodosFilter {
-                sort: Some(("dueDate".to_string(), false)),
+                sort: vec![("dueDate".to_string(), false)],
                 ..Default::default()
             },
         )
@@ -774,6 +1314,204 @@ mod tests {
         assert_eq!(rows[1].id, "1");
     }
 
+    /// 排序与 dueDateBefore/After 一致：dueDate 为空串时取 endTime。
+    #[test]
+    fn sort_by_due_date_treats_empty_due_date_as_missing() {
+        let c = fresh();
+        insert_todo(
+            &c,
+            "1",
+            r#"{"id":1,"title":"a","dueDate":"2026-05-13"}"#,
+            "2026-05-13 10:00:00",
+        );
+        insert_todo(
+            &c,
+            "2",
+            r#"{"id":2,"title":"b","dueDate":"","endTime":"2026-05-12T18:00:00"}"#,
+            "2026-05-13 10:00:00",
+        );
+        let rows = list_todos_filtered(
+            &c,
+            &ListTodosFilter {
+                sort: vec![("dueDate".to_string(), true)],
+                ..Default::default()
+            },
+        )
+        .expect("query must succeed");
+        assert_eq!(rows[0].id, "2");
+        assert_eq!(rows[1].id, "1");
+    }
+
+    #[test]
+    fn cursor_after_skips_up_to_position_and_breaks_ties_by_id() {
+        let c = fresh();
+        // 1 与 2 同 quadrant，靠 id 兜底排序
+        insert_todo(&c, "1", r#"{"id":1,"quadrant":1}"#, "2026-05-13 10:00:00");
+        insert_todo(&c, "2", r#"{"id":2,"quadrant":1}"#, "2026-05-13 10:00:00");
+        insert_todo(&c, "3", r#"{"id":3,"quadrant":2}"#, "2026-05-13 10:00:00");
+        let mut filter = ListTodosFilter {
+            sort: vec![("quadrant".to_string(), true)],
+            ..Default::default()
+        };
+        let cur = cursor_for_todo(&c, &filter, "1").unwrap().unwrap();
+        assert_eq!(cur.keys, vec![serde_json::json!(1)]);
+        filter.after = Some(cur);
+        let rows = list_todos_filtered(&c, &filter).unwrap();
+        let ids: Vec<&str> = rows.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["2", "3"]);
+        // count 忽略 cursor
+        assert_eq!(count_todos_filtered(&c, &filter).unwrap(), 3);
+    }
+
+    #[test]
+    fn view_upsert_if_newer_and_orphan_cleanup() {
+        let c = fresh();
+        upsert_view(&c, "q1", r#"{"name":"q1"}"#, "2026-05-13 10:00:00").unwrap();
+        upsert_view(&c, "old", r#"{"name":"old"}"#, "2026-05-13 10:00:00").unwrap();
+        // 远端更旧 → 不覆盖
+        assert!(
+            !upsert_view_if_newer(&c, "q1", r#"{"name":"q1","v":0}"#, "2026-05-13 09:00:00")
+                .unwrap()
+        );
+        assert!(
+            upsert_view_if_newer(&c, "q1", r#"{"name":"q1","v":2}"#, "2026-05-13 11:00:00")
+                .unwrap()
+        );
+        assert!(get_view(&c, "q1")
+            .unwrap()
+            .unwrap()
+            .data_json
+            .contains("\"v\":2"));
+
+        let keep: std::collections::HashSet<String> = ["q1".to_string()].into_iter().collect();
+        assert_eq!(delete_views_not_in(&c, &keep).unwrap(), 1);
+        let names: Vec<String> = list_views(&c)
+            .unwrap()
+            .into_iter()
+            .map(|v| v.name)
+            .collect();
+        assert_eq!(names, vec!["q1"]);
+    }
+
+    #[test]
+    fn templates_list_by_name_and_orphan_cleanup() {
+        let c = fresh();
+        upsert_template(&c, "1", r#"{"id":1,"name":"周报"}"#, "2026-05-13 10:00:00").unwrap();
+        upsert_template(&c, "2", r#"{"id":2,"name":"发版"}"#, "2026-05-13 10:00:00").unwrap();
+        upsert_template(&c, "3", r#"{"id":3,"name":"入职"}"#, "2026-05-13 10:00:00").unwrap();
+        assert!(!upsert_template_if_newer(
+            &c,
+            "1",
+            r#"{"id":1,"name":"x"}"#,
+            "2026-05-13 09:00:00"
+        )
+        .unwrap());
+
+        let keep: std::collections::HashSet<String> =
+            ["1".to_string(), "3".to_string()].into_iter().collect();
+        assert_eq!(delete_templates_not_in(&c, &keep).unwrap(), 1);
+        let rows = list_templates(&c).unwrap();
+        let ids: Vec<&str> = rows.iter().map(|r| r.id.as_str()).collect();
+        // 按 name 排序：入职 < 周报（Unicode 码点）
+        assert_eq!(ids, vec!["3", "1"]);
+        assert!(rows[1].data_json.contains("周报"));
+    }
+
+    #[test]
+    fn filter_by_tags_and_usage() {
+        let c = fresh();
+        insert_todo(&c, "1", r#"{"id":1,"tags":["work","urgent"]}"#, "x");
+        insert_todo(&c, "2", r#"{"id":2,"tags":["work","personal"]}"#, "x");
+        insert_todo(&c, "3", r#"{"id":3,"tags":"work"}"#, "x");
+        insert_todo(&c, "4", r#"{"id":4}"#, "x");
+
+        let ids = |f: &ListTodosFilter| -> Vec<String> {
+            list_todos_filtered(&c, f)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.id)
+                .collect()
+        };
+        let f = ListTodosFilter {
+            tags_include: vec!["work".into()],
+            tags_exclude: vec!["personal".into()],
+            ..Default::default()
+        };
+        // 非数组的 tags（3）json_each 按单值处理，仍然命中
+        assert_eq!(ids(&f), vec!["1", "3"]);
+        assert_eq!(count_todos_filtered(&c, &f).unwrap(), 2);
+        let f = ListTodosFilter {
+            tags_exclude: vec!["work".into()],
+            ..Default::default()
+        };
+        assert_eq!(ids(&f), vec!["4"]);
+
+        let usage = tag_usage(&c).unwrap();
+        assert_eq!(
+            usage,
+            vec![
+                ("personal".to_string(), 1),
+                ("urgent".to_string(), 1),
+                ("work".to_string(), 3)
+            ]
+        );
+        let with: Vec<String> = todos_with_tag(&c, "urgent")
+            .unwrap()
+            .into_iter()
+            .map(|r| r.id)
+            .collect();
+        assert_eq!(with, vec!["1"]);
+    }
+
+    #[test]
+    fn rebuild_seq_compacts_and_keeps_order() {
+        let mut c = fresh();
+        insert_todo(&c, "10", r#"{"createdAt":"2026-05-01 00:00:00"}"#, "x");
+        insert_todo(&c, "20", r#"{"createdAt":"2026-05-02 00:00:00"}"#, "x");
+        insert_todo(&c, "30", r#"{"createdAt":"2026-04-01 00:00:00"}"#, "x");
+        assign_seq(&c, "20").unwrap(); // 1
+        assign_seq(&c, "10").unwrap(); // 2
+        c.execute("INSERT INTO todo_seq (todo_id, seq) VALUES ('gone', 7)", [])
+            .unwrap();
+
+        assert_eq!(rebuild_seq(&mut c).unwrap(), 3);
+        assert_eq!(get_seq(&c, "20").unwrap(), Some(1));
+        assert_eq!(get_seq(&c, "10").unwrap(), Some(2));
+        // 没有 seq 的排在最后
+        assert_eq!(get_seq(&c, "30").unwrap(), Some(3));
+        assert_eq!(get_seq(&c, "gone").unwrap(), None);
+    }
+
+    #[test]
+    fn dirty_generation_lag_counts_unpushed_writes() {
+        let c = fresh();
+        assert_eq!(dirty_generation_lag(&c).unwrap(), 0);
+        mark_dirty(&c).unwrap();
+        mark_dirty(&c).unwrap();
+        assert_eq!(dirty_generation_lag(&c).unwrap(), 2);
+        set_meta(&c, "pushed_generation", "1").unwrap();
+        assert_eq!(dirty_generation_lag(&c).unwrap(), 1);
+        set_meta(&c, "dirty", "false").unwrap();
+        assert_eq!(dirty_generation_lag(&c).unwrap(), 0);
+    }
+
+    #[test]
+    fn gauges_for_images_tombstones_and_size() {
+        let c = fresh();
+        assert_eq!(dirty_images_len(&c).unwrap(), 0);
+        set_meta(&c, "dirty_images", r#"["a.png","b.jpg"]"#).unwrap();
+        assert_eq!(dirty_images_len(&c).unwrap(), 2);
+
+        add_tombstone(&c, "todo", "1", "2026-05-13 10:00:00").unwrap();
+        add_tombstone(&c, "todo", "2", "2026-05-13 10:00:00").unwrap();
+        add_tombstone(&c, "view", "q1", "2026-05-13 10:00:00").unwrap();
+        assert_eq!(
+            count_tombstones_by_type(&c).unwrap(),
+            vec![("todo".to_string(), 2), ("view".to_string(), 1)]
+        );
+        assert!(db_size_bytes(&c).unwrap() > 0);
+    }
+
     #[test]
     fn tombstone_insert_list_purge() {
         let c = fresh();
@@ -1,4 +1,6 @@
-//! 启动时建表。Schema 设计参考 prd：4 张表 + tombstones 表。
+//! 启动时建表。Schema 设计参考 prd：4 张表 + tombstones 表；另有随 sync-data
+//! 同步的 saved_views / templates / tags，以及 cloud-only 的 todo_seq / audit_log /
+//! idempotency_keys。
 
 use rusqlite::Connection;
 
@@ -34,8 +36,8 @@ pub fn init(conn: &Connection) -> anyhow::Result<()> {
         -- merge 时使用——本地有 tombstone 而远端有 record → 删除（防止远端
         -- 陈旧数据复活已删除的本地 record）。
         --
-        -- entity_type ∈ {'todo', 'subtask'}；deleted_at 用 PC 风格的本地时间
-        -- 字符串。过期清理在 push 完成后做（>7 天移除）。
+        -- entity_type ∈ {'todo', 'subtask', 'view', 'template', 'tag'}；deleted_at 用
+        -- PC 风格的本地时间字符串。过期清理在 push 完成后做（>7 天移除）。
         CREATE TABLE IF NOT EXISTS tombstones (
             entity_type TEXT NOT NULL,
             entity_id   TEXT NOT NULL,
@@ -57,6 +59,71 @@ pub fn init(conn: &Connection) -> anyhow::Result<()> {
             todo_id TEXT PRIMARY KEY,
             seq     INTEGER NOT NULL UNIQUE
         );
+
+        -- 具名 saved view：filter 表达式 + sort + 投影字段。按 name 主键，
+        -- data_json 即 `settings.savedViews[]` 里的一项，随 sync-data 与 PC
+        -- 双向同步（per-view LWW，删除走 entity_type = 'view' 的 tombstone）。
+        CREATE TABLE IF NOT EXISTS saved_views (
+            name        TEXT PRIMARY KEY,
+            data_json   TEXT NOT NULL,
+            updated_at  TEXT NOT NULL
+        );
+
+        -- todo 模板：todo 字段 + 子任务清单，标题 / 描述里可带 `{{date}}` 等
+        -- 占位符，实例化时展开。data_json 即 sync-data 顶层 `templates[]` 里的
+        -- 一项（PC `todo_templates` 表的一行），per-template LWW，删除走
+        -- entity_type = 'template' 的 tombstone。
+        CREATE TABLE IF NOT EXISTS templates (
+            id          TEXT PRIMARY KEY,
+            data_json   TEXT NOT NULL,
+            updated_at  TEXT NOT NULL
+        );
+
+        -- 标签注册表：按 name 主键，data_json 即 sync-data 顶层 `tags[]` 里的一项
+        -- （`{name, color, createdAt, updatedAt}`），per-tag LWW，删除走
+        -- entity_type = 'tag' 的 tombstone。todo 挂哪些标签记在 todo 自己的
+        -- `tags: [name]` 里，这里只管颜色；没登记的标签名按默认色显示。
+        CREATE TABLE IF NOT EXISTS tags (
+            name        TEXT PRIMARY KEY,
+            data_json   TEXT NOT NULL,
+            updated_at  TEXT NOT NULL
+        );
+
+        -- 写请求审计（cloud-only，不同步）：每个 POST / PATCH / DELETE 一行。
+        -- before_json / after_json 是变更摘要而不是整条记录；按 id 排序，
+        -- at 为 config 时区本地时间，供 `since` 过滤与保留期清理。
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id          INTEGER PRIMARY KEY AUTOINCREMENT,
+            at          TEXT NOT NULL,
+            request_id  TEXT NOT NULL,
+            key_name    TEXT NOT NULL,
+            client_ip   TEXT,
+            user_agent  TEXT,
+            method      TEXT NOT NULL,
+            route       TEXT NOT NULL,
+            path        TEXT NOT NULL,
+            target      TEXT,
+            status      INTEGER NOT NULL,
+            before_json TEXT,
+            after_json  TEXT
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_audit_log_at ON audit_log(at);
+        CREATE INDEX IF NOT EXISTS idx_audit_log_target ON audit_log(target);
+
+        -- Idempotency-Key（cloud-only，不同步）：请求指纹 + 首次响应。status 为
+        -- NULL 表示还在处理；created_at 是 unix 秒，超出窗口期的行在下次查询时删除。
+        CREATE TABLE IF NOT EXISTS idempotency_keys (
+            key          TEXT PRIMARY KEY,
+            request_hash TEXT NOT NULL,
+            created_at   INTEGER NOT NULL,
+            status       INTEGER,
+            content_type TEXT,
+            body         BLOB
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_idempotency_keys_created_at
+            ON idempotency_keys(created_at);
         "#,
     )
     .map_err(|e| anyhow::anyhow!("初始化 schema 失败: {}", e))?;
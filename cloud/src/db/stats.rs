@@ -0,0 +1,362 @@
+//! `GET /stats` 报表：全部用 SQLite JSON1 从 `data_json` 现算，不落任何汇总表。
+//!
+//! 口径：
+//! - "完成"以 `completedAt` 落在 `[from, to]`（按本地日期，含两端）为准。
+//!   `completedAt` 是 PATCH / PC `update_todo` 在 `completed` 翻转时写入的；
+//!   更早完成、没有 `completedAt` 的历史数据不计入完成数 / 平均耗时。
+//! - 截止时间沿用 `due` 的定义：`dueDate` 优先，其次 `endTime`。只有日期的
+//!   `dueDate` 视为当天 23:59:59 截止。
+//! - `overdue.open` 与 `byQuadrant[].open / overdue` 是"现在"的快照，与区间无关。
+//! - `subtasks` 统计全部仍挂在 todo 下的子任务。
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+
+/// 归一化后的 todo 视图，所有统计查询都以它开头。
+const BASE: &str = "WITH t AS (
+    SELECT COALESCE(json_extract(data_json, '$.completed'), 0) AS done,
+           json_extract(data_json, '$.completedAt') AS completed_at,
+           json_extract(data_json, '$.createdAt') AS created_at,
+           COALESCE(json_extract(data_json, '$.quadrant'), 4) AS quadrant,
+           CASE WHEN length(due) = 10 THEN due || ' 23:59:59' ELSE due END AS due
+    FROM (
+        SELECT data_json,
+               COALESCE(NULLIF(json_extract(data_json, '$.dueDate'), ''), NULLIF(json_extract(data_json, '$.endTime'), '')) AS due
+        FROM todos
+    )
+)";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub completed: CompletedStats,
+    /// 区间内创建的 todo 数（按 `createdAt`）。
+    pub created: i64,
+    /// 区间内完成的 todo 从 `createdAt` 到 `completedAt` 的平均小时数；无样本为 null。
+    pub avg_completion_hours: Option<f64>,
+    pub overdue: OverdueStats,
+    pub by_quadrant: Vec<QuadrantStats>,
+    pub subtasks: SubtaskStats,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletedStats {
+    pub total: i64,
+    /// 区间内每一天都有一项（没有完成的日子为 0），便于直接画图。
+    pub per_day: Vec<DayCount>,
+    /// 按周一起始的自然周聚合，首尾两周可能不完整。
+    pub per_week: Vec<WeekCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DayCount {
+    pub date: NaiveDate,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeekCount {
+    pub week_start: NaiveDate,
+    pub count: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OverdueStats {
+    /// 当前未完成且已过截止时间。
+    pub open: i64,
+    /// 区间内完成、但完成时已过截止时间。
+    pub completed_late: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuadrantStats {
+    pub quadrant: i64,
+    pub open: i64,
+    pub completed: i64,
+    pub overdue: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubtaskStats {
+    pub total: i64,
+    pub completed: i64,
+    /// `completed / total`；没有子任务时为 null。
+    pub ratio: Option<f64>,
+}
+
+/// 计算 `[from, to]` 区间的统计。`now` 是本地墙钟时间，用于判断逾期。
+pub fn compute(
+    conn: &Connection,
+    from: NaiveDate,
+    to: NaiveDate,
+    now: NaiveDateTime,
+) -> rusqlite::Result<Stats> {
+    let from_s = from.format("%Y-%m-%d").to_string();
+    let to_s = to.format("%Y-%m-%d").to_string();
+    let now_s = now.format("%Y-%m-%d %H:%M:%S").to_string();
+
+    let mut stmt = conn.prepare(&format!(
+        "{BASE} SELECT substr(completed_at, 1, 10) AS d, COUNT(*) FROM t
+         WHERE done = 1 AND d BETWEEN ?1 AND ?2 GROUP BY d"
+    ))?;
+    let done_by_day: Vec<(String, i64)> = stmt
+        .query_map(params![from_s, to_s], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    let completed = bucket_completed(from, to, &done_by_day);
+
+    let created: i64 = conn.query_row(
+        &format!("{BASE} SELECT COUNT(*) FROM t WHERE substr(created_at, 1, 10) BETWEEN ?1 AND ?2"),
+        params![from_s, to_s],
+        |r| r.get(0),
+    )?;
+
+    let avg_completion_hours: Option<f64> = conn.query_row(
+        &format!(
+            "{BASE} SELECT AVG((julianday(completed_at) - julianday(created_at)) * 24) FROM t
+             WHERE done = 1 AND substr(completed_at, 1, 10) BETWEEN ?1 AND ?2
+               AND julianday(completed_at) >= julianday(created_at)"
+        ),
+        params![from_s, to_s],
+        |r| r.get(0),
+    )?;
+
+    let overdue_open: i64 = conn.query_row(
+        &format!("{BASE} SELECT COUNT(*) FROM t WHERE done = 0 AND julianday(due) < julianday(?1)"),
+        params![now_s],
+        |r| r.get(0),
+    )?;
+    let completed_late: i64 = conn.query_row(
+        &format!(
+            "{BASE} SELECT COUNT(*) FROM t
+             WHERE done = 1 AND substr(completed_at, 1, 10) BETWEEN ?1 AND ?2
+               AND julianday(completed_at) > julianday(due)"
+        ),
+        params![from_s, to_s],
+        |r| r.get(0),
+    )?;
+
+    let mut stmt = conn.prepare(&format!(
+        "{BASE} SELECT quadrant,
+                COUNT(CASE WHEN done = 0 THEN 1 END),
+                COUNT(CASE WHEN done = 1 AND substr(completed_at, 1, 10) BETWEEN ?1 AND ?2 THEN 1 END),
+                COUNT(CASE WHEN done = 0 AND julianday(due) < julianday(?3) THEN 1 END)
+         FROM t GROUP BY quadrant ORDER BY quadrant"
+    ))?;
+    let by_quadrant = stmt
+        .query_map(params![from_s, to_s, now_s], |r| {
+            Ok(QuadrantStats {
+                quadrant: r.get(0)?,
+                open: r.get(1)?,
+                completed: r.get(2)?,
+                overdue: r.get(3)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let (sub_total, sub_done): (i64, i64) = conn.query_row(
+        "SELECT COUNT(*), COUNT(CASE WHEN json_extract(data_json, '$.completed') = 1 THEN 1 END)
+         FROM subtasks WHERE todo_id IN (SELECT id FROM todos)",
+        [],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    )?;
+
+    Ok(Stats {
+        from,
+        to,
+        completed,
+        created,
+        avg_completion_hours,
+        overdue: OverdueStats {
+            open: overdue_open,
+            completed_late,
+        },
+        by_quadrant,
+        subtasks: SubtaskStats {
+            total: sub_total,
+            completed: sub_done,
+            ratio: (sub_total > 0).then(|| sub_done as f64 / sub_total as f64),
+        },
+    })
+}
+
+/// 把 SQL 按日分组的结果补齐成连续的日 / 周序列。
+fn bucket_completed(from: NaiveDate, to: NaiveDate, rows: &[(String, i64)]) -> CompletedStats {
+    let mut per_day = Vec::new();
+    let mut per_week: Vec<WeekCount> = Vec::new();
+    let mut total = 0;
+    let mut d = from;
+    while d <= to {
+        let key = d.format("%Y-%m-%d").to_string();
+        let count = rows
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, n)| *n)
+            .unwrap_or(0);
+        total += count;
+        per_day.push(DayCount { date: d, count });
+
+        let week_start = d - Duration::days(d.weekday().num_days_from_monday() as i64);
+        match per_week.last_mut() {
+            Some(w) if w.week_start == week_start => w.count += count,
+            _ => per_week.push(WeekCount { week_start, count }),
+        }
+        d += Duration::days(1);
+    }
+    CompletedStats {
+        total,
+        per_day,
+        per_week,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::repo::{upsert_subtask, upsert_todo};
+
+    fn fresh() -> Connection {
+        let c = Connection::open_in_memory().unwrap();
+        crate::db::schema::init(&c).unwrap();
+        c
+    }
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()
+    }
+
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2026-05-20 12:00:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn seed(c: &Connection) {
+        let todos = [
+            // 5/13（周三）完成，耗时 2 小时
+            r#"{"id":1,"quadrant":1,"completed":true,"createdAt":"2026-05-13 08:00:00","completedAt":"2026-05-13 10:00:00"}"#,
+            // 5/18（周一）完成，晚于 date-only 截止（5/17 当天结束）
+            r#"{"id":2,"quadrant":1,"completed":true,"createdAt":"2026-05-17T10:00:00","completedAt":"2026-05-18T12:00:00","dueDate":"2026-05-17"}"#,
+            // 未完成且已逾期（endTime 早于 now）
+            r#"{"id":3,"quadrant":2,"completed":false,"createdAt":"2026-05-14 09:00:00","endTime":"2026-05-19 18:00:00"}"#,
+            // 未完成，date-only 截止就是今天：还没逾期
+            r#"{"id":4,"quadrant":2,"completed":false,"createdAt":"2026-04-01 09:00:00","dueDate":"2026-05-20"}"#,
+            // 区间外完成
+            r#"{"id":5,"quadrant":3,"completed":true,"createdAt":"2026-04-01 09:00:00","completedAt":"2026-04-02 09:00:00"}"#,
+            // 历史数据：completed 但没有 completedAt
+            r#"{"id":6,"quadrant":4,"completed":true,"createdAt":"2026-05-15 09:00:00"}"#,
+        ];
+        for (i, t) in todos.iter().enumerate() {
+            upsert_todo(c, &(i + 1).to_string(), t, "2026-05-20 00:00:00").unwrap();
+        }
+        upsert_subtask(c, "11", "1", r#"{"completed":true}"#, "2026-05-20 00:00:00").unwrap();
+        upsert_subtask(
+            c,
+            "12",
+            "1",
+            r#"{"completed":false}"#,
+            "2026-05-20 00:00:00",
+        )
+        .unwrap();
+        upsert_subtask(c, "13", "3", r#"{"completed":true}"#, "2026-05-20 00:00:00").unwrap();
+        upsert_subtask(
+            c,
+            "14",
+            "4",
+            r#"{"completed":false}"#,
+            "2026-05-20 00:00:00",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn completion_counts_per_day_and_week() {
+        let c = fresh();
+        seed(&c);
+        let s = compute(&c, date("2026-05-11"), date("2026-05-20"), now()).unwrap();
+        assert_eq!(s.completed.total, 2);
+        assert_eq!(s.completed.per_day.len(), 10);
+        assert_eq!(s.completed.per_day[2].date, date("2026-05-13"));
+        assert_eq!(s.completed.per_day[2].count, 1);
+        assert_eq!(s.completed.per_day[7].count, 1);
+        assert_eq!(
+            s.completed.per_week,
+            vec![
+                WeekCount {
+                    week_start: date("2026-05-11"),
+                    count: 1
+                },
+                WeekCount {
+                    week_start: date("2026-05-18"),
+                    count: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn created_and_average_completion_time() {
+        let c = fresh();
+        seed(&c);
+        let s = compute(&c, date("2026-05-11"), date("2026-05-20"), now()).unwrap();
+        // id 1 / 2 / 3 / 6 创建于区间内
+        assert_eq!(s.created, 4);
+        // (2h + 26h) / 2，'T' 与空格两种时间格式都要能算
+        assert_eq!(s.avg_completion_hours.map(|h| h.round()), Some(14.0));
+
+        let empty = compute(&c, date("2026-01-01"), date("2026-01-31"), now()).unwrap();
+        assert_eq!(empty.avg_completion_hours, None);
+        assert_eq!(empty.completed.total, 0);
+    }
+
+    #[test]
+    fn overdue_treats_date_only_due_as_end_of_day() {
+        let c = fresh();
+        seed(&c);
+        let s = compute(&c, date("2026-05-11"), date("2026-05-20"), now()).unwrap();
+        assert_eq!(s.overdue.open, 1);
+        assert_eq!(s.overdue.completed_late, 1);
+    }
+
+    #[test]
+    fn quadrant_breakdown_and_subtask_ratio() {
+        let c = fresh();
+        seed(&c);
+        let s = compute(&c, date("2026-05-11"), date("2026-05-20"), now()).unwrap();
+        assert_eq!(
+            s.by_quadrant,
+            vec![
+                QuadrantStats {
+                    quadrant: 1,
+                    open: 0,
+                    completed: 2,
+                    overdue: 0
+                },
+                QuadrantStats {
+                    quadrant: 2,
+                    open: 2,
+                    completed: 0,
+                    overdue: 1
+                },
+                QuadrantStats {
+                    quadrant: 3,
+                    open: 0,
+                    completed: 0,
+                    overdue: 0
+                },
+                QuadrantStats {
+                    quadrant: 4,
+                    open: 0,
+                    completed: 0,
+                    overdue: 0
+                },
+            ]
+        );
+        assert_eq!(s.subtasks.total, 4);
+        assert_eq!(s.subtasks.completed, 2);
+        assert_eq!(s.subtasks.ratio, Some(0.5));
+    }
+}
@@ -1,101 +1,327 @@
 //! minitodo-cloud：mini-todo 的云端 HTTP API。
 //!
-//! 启动顺序：
+//! 命令行见 [`cli::USAGE`]：不带子命令（或 `serve`）启动服务，其余子命令是
+//! 一次性的运维操作（pull / push / backup / restore / reseq / verify / export…）。
+//!
+//! `serve` 启动顺序：
 //! 1. 加载 `config.toml`（缺字段直接 panic 出来）
 //! 2. 打开 SQLite + 建表
 //! 3. 启动时同步执行一次 `pull_once`，把 WebDAV 上现有数据灌进本地
 //! 4. spawn 后台 `start_pull_loop`（60s 轮询） + `start_push_loop`（1s 检查
 //!    dirty 并条件 PUT 回 WebDAV） + `spawn_bootstrap`（一次性图片镜像）
-//! 5. 启动 axum，监听 `config.bind`
+//! 5. 启动 axum，监听 `config.bind`（配置了 `tls_cert` / `tls_key` 时走
+//!    HTTPS，见 [`tls`]）；配置了 `metrics_bind` 时另起一个只有 `/metrics` 的
+//!    plain HTTP listener
+//! 6. SIGTERM / SIGINT：停止接收请求、最后推送一次再退出（见 [`shutdown`]）；
+//!    SIGHUP：重新加载配置（见 [`config::LiveConfig::reload`]）
 
 use std::env;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::process::ExitCode;
 
-use tracing::{info, warn};
-use tracing_subscriber::EnvFilter;
+use tracing::{error, info, warn};
+use tracing_subscriber::layer::SubscriberExt as _;
+use tracing_subscriber::util::SubscriberInitExt as _;
+use tracing_subscriber::{reload, EnvFilter, Registry};
 
 mod api;
+mod cli;
 mod config;
 mod db;
+mod metrics;
+mod shutdown;
 mod sync;
 mod time;
+mod tls;
 mod util;
 
 use crate::api::AppState;
-use crate::config::Config;
+use crate::config::{Config, LiveConfig};
 use crate::db::Db;
+use crate::shutdown::Shutdown;
+use crate::sync::push::FlushOutcome;
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    init_tracing();
+fn main() -> anyhow::Result<ExitCode> {
+    let cli = match cli::parse(env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, cli::USAGE);
+            return Ok(ExitCode::from(64));
+        }
+    };
+    if cli.command == cli::Command::Help {
+        print!("{}", cli::USAGE);
+        return Ok(ExitCode::SUCCESS);
+    }
+    let serving = cli.command == cli::Command::Serve;
+    let log = init_tracing(serving);
 
-    let cfg_path = resolve_config_path();
-    info!(target: "minitodo_cloud", "loading config from {}", cfg_path.display());
-    let cfg = Arc::new(Config::load(&cfg_path)?);
+    let cfg_path = resolve_config_path(cli.config);
+    match &cfg_path {
+        Some(p) => info!(target: "minitodo_cloud", "loading config from {}", p.display()),
+        None => info!(target: "minitodo_cloud", "no config file, using MINITODO_* env only"),
+    }
+    let cfg = Config::load(cfg_path.as_deref())?;
+
+    if !serving {
+        return cli::run(cli.command, &cfg);
+    }
+    apply_log_level(&log, cfg.log_level.as_deref(), serving);
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| anyhow::anyhow!("创建 tokio runtime 失败: {}", e))?;
+    let res = rt.block_on(serve(LiveConfig::new(cfg), cfg_path, log));
+    // 后台 worker 可能正卡在 blocking 的 WebDAV 请求上；直接 drop runtime 会
+    // 一直等它返回。数据已经落盘（dirty 也在），不必等。
+    rt.shutdown_timeout(std::time::Duration::from_secs(1));
+    res
+}
+
+/// 跑到收到退出信号为止。返回值区分"最后一批改动是否已推上 WebDAV"。
+async fn serve(
+    live: LiveConfig,
+    cfg_path: Option<PathBuf>,
+    log: LogHandle,
+) -> anyhow::Result<ExitCode> {
+    // 结构性字段（bind / data_dir / images_dir / timezone）不随 SIGHUP 变，
+    // 启动阶段用这份快照即可
+    let cfg = live.get();
+    if cfg.lockout_sees_only_proxy() {
+        warn!(
+            target: "minitodo_cloud",
+            "bind {} 是回环地址但 trust_forwarded_for 未打开：反代后面所有客户端都算同一个 IP，\
+             限流桶共用、无效 key 封禁会挡住所有不带正确 key 的请求",
+            cfg.bind
+        );
+    }
 
     // 准备 data_dir / images_dir
     std::fs::create_dir_all(&cfg.data_dir)
         .map_err(|e| anyhow::anyhow!("创建 data_dir {} 失败: {}", cfg.data_dir.display(), e))?;
-    let db_path: PathBuf = cfg.data_dir.join("data.db");
-    let db = Db::open(&db_path)?;
+    let db = Db::open(&cfg.db_path())?;
 
-    // 启动时同步拉一次。失败不阻断启动（远端可能暂时不可用），但记日志。
-    match sync::pull::pull_once(&cfg, &db) {
-        Ok(()) => info!(target: "minitodo_cloud", "initial pull ok"),
+    // 启动时同步拉一次。失败不阻断启动（远端可能暂时不可用），但记日志；
+    // 此时 /health/ready 返回 503，直到 pull worker 某次 tick 成功。
+    let ready = sync::new_readiness();
+    // reqwest blocking 客户端不能在 async 上下文里直接用（drop 内部 runtime 会 panic）
+    let (cfg_ref, db_ref) = (cfg.clone(), db.clone());
+    let initial = tokio::task::spawn_blocking(move || sync::pull::pull_once(&cfg_ref, &db_ref))
+        .await
+        .map_err(|e| anyhow::anyhow!("initial pull panicked: {}", e))?;
+    match initial {
+        Ok(()) => {
+            info!(target: "minitodo_cloud", "initial pull ok");
+            sync::mark_ready(&ready);
+        }
         Err(e) => warn!(target: "minitodo_cloud", "initial pull failed: {:#}", e),
     }
 
     // 同步互斥锁：pull / push / POST /sync 系列共享一把，全进程只建一次。
     // （启动时那次 pull_once 在 worker spawn 之前跑，不存在并发，无需加锁）
     let sync_lock = sync::new_sync_lock();
+    let shutdown = Shutdown::listen();
+    spawn_reload_on_sighup(live.clone(), cfg_path, log);
 
     // 后台 worker
-    sync::pull::start_pull_loop(cfg.clone(), db.clone(), sync_lock.clone());
-    sync::push::start_push_loop(cfg.clone(), db.clone(), sync_lock.clone());
+    sync::pull::start_pull_loop(live.clone(), db.clone(), sync_lock.clone(), ready.clone());
+    sync::push::start_push_loop(live.clone(), db.clone(), sync_lock.clone());
     sync::images::spawn_bootstrap(cfg.clone());
+    api::audit::spawn_retention(live.clone(), db.clone());
 
     // axum
     let state = AppState {
-        config: cfg.clone(),
+        config: live.clone(),
         db: db.clone(),
-        sync_lock,
+        sync_lock: sync_lock.clone(),
+        ready,
+        limiter: Default::default(),
     };
-    let router = api::build_router(state);
+    let router = api::build_router(state.clone());
+
+    if let Some(addr) = cfg.metrics_bind.clone() {
+        let listener = tokio::net::TcpListener::bind(&addr)
+            .await
+            .map_err(|e| anyhow::anyhow!("无法绑定 metrics_bind {}: {}", addr, e))?;
+        info!(target: "minitodo_cloud", "metrics on http://{}/metrics", addr);
+        let metrics_router = api::build_metrics_router(state);
+        let stop = shutdown.clone().wait();
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, metrics_router)
+                .with_graceful_shutdown(stop)
+                .await
+            {
+                warn!(target: "minitodo_cloud", "metrics listener 退出: {}", e);
+            }
+        });
+    }
 
+    // 证书有问题要在绑定端口之前报出来
+    let tls = match &cfg.tls {
+        Some(files) => {
+            let reloader = tls::CertReloader::load(&files.cert, &files.key)?;
+            let acceptor = reloader.acceptor()?;
+            reloader.spawn_watcher();
+            Some(acceptor)
+        }
+        None => None,
+    };
     let listener = tokio::net::TcpListener::bind(&cfg.bind)
         .await
         .map_err(|e| anyhow::anyhow!("无法绑定 {}: {}", cfg.bind, e))?;
-    info!(target: "minitodo_cloud", "listening on http://{}", cfg.bind);
-    axum::serve(listener, router)
-        .await
-        .map_err(|e| anyhow::anyhow!("axum serve 失败: {}", e))?;
-    Ok(())
+    let mut server = match tls {
+        Some(acceptor) => {
+            info!(target: "minitodo_cloud", "listening on https://{}", cfg.bind);
+            let stop = shutdown.clone();
+            tokio::spawn(async move {
+                tls::serve(listener, router, acceptor, stop).await;
+                Ok(())
+            })
+        }
+        None => {
+            info!(target: "minitodo_cloud", "listening on http://{}", cfg.bind);
+            // 限流按对端 IP 计，需要 ConnectInfo
+            tokio::spawn(
+                axum::serve(
+                    listener,
+                    router.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .with_graceful_shutdown(shutdown.clone().wait())
+                .into_future(),
+            )
+        }
+    };
+
+    tokio::select! {
+        // 信号先查：收到信号后 server 会很快正常结束，两个分支可能同时就绪，
+        // 不能把这种正常结束误判成"意外退出"
+        biased;
+        _ = shutdown.wait() => {}
+        res = &mut server => {
+            // 没收到信号 listener 就退出了，只可能是出错
+            res.map_err(|e| anyhow::anyhow!("HTTP 服务异常退出: {}", e))?
+                .map_err(|e| anyhow::anyhow!("HTTP 服务失败: {}", e))?;
+            anyhow::bail!("HTTP 服务意外退出");
+        }
+    }
+
+    // 已停止 accept；等在途请求写完 SQLite（它们标的 dirty 要一起推上去）
+    match tokio::time::timeout(shutdown::DRAIN_TIMEOUT, &mut server).await {
+        Ok(_) => info!(target: "minitodo_cloud", "在途请求已处理完"),
+        Err(_) => {
+            warn!(
+                target: "minitodo_cloud",
+                "等待在途请求超过 {}s，强制断开",
+                shutdown::DRAIN_TIMEOUT.as_secs()
+            );
+            server.abort();
+        }
+    }
+
+    let outcome = sync::push::final_flush(live.get(), db, sync_lock, shutdown::FLUSH_TIMEOUT).await;
+    match outcome {
+        FlushOutcome::Clean => info!(target: "minitodo_cloud", "无待推送改动，退出"),
+        FlushOutcome::Flushed => info!(target: "minitodo_cloud", "最后一批改动已推送，退出"),
+        FlushOutcome::Pending => {
+            warn!(target: "minitodo_cloud", "最后一次推送失败，改动留待下次启动推送")
+        }
+        FlushOutcome::TimedOut => warn!(
+            target: "minitodo_cloud",
+            "最后一次推送超过 {}s 未完成，改动留待下次启动推送",
+            shutdown::FLUSH_TIMEOUT.as_secs()
+        ),
+    }
+    Ok(if outcome.is_flushed() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(shutdown::EXIT_UNFLUSHED)
+    })
+}
+
+type LogHandle = reload::Handle<EnvFilter, Registry>;
+
+/// 日志写 stderr。过滤器可热替换：读到配置后、以及每次 SIGHUP 时按
+/// `log_level` 重设。
+fn init_tracing(serving: bool) -> LogHandle {
+    let (filter, handle) = reload::Layer::new(default_filter(serving));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr))
+        .init();
+    handle
 }
 
-fn init_tracing() {
-    let filter = EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| EnvFilter::new("info,minitodo_cloud=debug"));
-    tracing_subscriber::fmt().with_env_filter(filter).init();
+/// 未配置 `log_level` 时用 `RUST_LOG`，再没有就用内置默认。一次性子命令默认
+/// 只到 warn，避免干扰 `export` 等命令的输出。
+fn default_filter(serving: bool) -> EnvFilter {
+    let default = if serving {
+        "info,minitodo_cloud=debug"
+    } else {
+        "warn"
+    };
+    EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default))
+}
+
+fn apply_log_level(log: &LogHandle, level: Option<&str>, serving: bool) {
+    // log_level 已在 Config 校验时确认可解析
+    let filter = level.map_or_else(|| default_filter(serving), EnvFilter::new);
+    if let Err(e) = log.reload(filter) {
+        warn!(target: "minitodo_cloud", "更新日志级别失败: {}", e);
+    }
 }
 
-fn resolve_config_path() -> PathBuf {
-    let mut args = env::args().skip(1);
-    while let Some(a) = args.next() {
-        match a.as_str() {
-            "--config" | "-c" => {
-                if let Some(v) = args.next() {
-                    return PathBuf::from(v);
+/// SIGHUP：重新读配置文件 + 环境变量（`*_file` 指向的 secret 也会重读），
+/// 校验通过才替换；失败沿用旧配置。
+#[cfg(unix)]
+fn spawn_reload_on_sighup(live: LiveConfig, cfg_path: Option<PathBuf>, log: LogHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hup = match signal(SignalKind::hangup()) {
+        Ok(s) => s,
+        Err(e) => {
+            warn!(target: "minitodo_cloud", "无法注册 SIGHUP 处理，配置热更新不可用: {}", e);
+            return;
+        }
+    };
+    tokio::spawn(async move {
+        while hup.recv().await.is_some() {
+            match Config::load(cfg_path.as_deref()) {
+                Ok(new) => {
+                    let level = new.log_level.clone();
+                    let ignored = live.reload(new);
+                    apply_log_level(&log, level.as_deref(), true);
+                    info!(target: "minitodo_cloud", "SIGHUP: 配置已重新加载");
+                    if !ignored.is_empty() {
+                        warn!(
+                            target: "minitodo_cloud",
+                            "SIGHUP: {} 需要重启才能生效，本次忽略",
+                            ignored.join(", ")
+                        );
+                    }
                 }
+                Err(e) => error!(
+                    target: "minitodo_cloud",
+                    "SIGHUP: 重新加载配置失败，继续使用旧配置: {:#}", e
+                ),
             }
-            other if other.starts_with("--config=") => {
-                return PathBuf::from(&other["--config=".len()..]);
-            }
-            _ => {}
         }
+    });
+}
+
+#[cfg(not(unix))]
+fn spawn_reload_on_sighup(_live: LiveConfig, _cfg_path: Option<PathBuf>, _log: LogHandle) {}
+
+/// 配置文件位置：`--config` / `MINITODO_CONFIG` 显式指定时必须存在；否则用
+/// 当前目录的 `config.toml`，不存在就只用环境变量。
+fn resolve_config_path(from_args: Option<PathBuf>) -> Option<PathBuf> {
+    if let Some(p) = from_args {
+        return Some(p);
     }
     if let Ok(p) = env::var("MINITODO_CONFIG") {
-        return PathBuf::from(p);
+        return Some(PathBuf::from(p));
     }
-    PathBuf::from("config.toml")
+    let default = PathBuf::from("config.toml");
+    default.exists().then_some(default)
 }
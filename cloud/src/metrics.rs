@@ -0,0 +1,347 @@
+//! 进程内 Prometheus 指标，`GET /metrics` 以 text exposition format 输出。
+//!
+//! 不引入 prometheus crate：需要的只有计数器 + 固定桶直方图，手写几十行即可，
+//! 输出格式也完全可控。注册表是进程级单例（`global()`）——HTTP 中间件、
+//! pull / push worker、`POST /sync` 都要往里记，逐层传 `Arc` 得改一串签名。
+//!
+//! 计数类指标在内存里累加，进程重启归零（Prometheus 的 `rate()` 会处理）；
+//! 队列长度 / tombstone 数 / DB 大小等 gauge 在抓取时现查 SQLite。
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::db::{repo, Db};
+
+/// 直方图桶上界（秒）。覆盖本地 SQLite 请求（毫秒级）到慢速 WebDAV 同步（数秒）。
+const BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+static REGISTRY: LazyLock<Registry> = LazyLock::new(Registry::default);
+
+/// 进程级指标注册表。
+pub fn global() -> &'static Registry {
+    &REGISTRY
+}
+
+/// 同步操作种类，作为 `op` 标签。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SyncOp {
+    Pull,
+    Push,
+}
+
+impl SyncOp {
+    fn label(self) -> &'static str {
+        match self {
+            SyncOp::Pull => "pull",
+            SyncOp::Push => "push",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    /// 与 `BUCKETS` 一一对应的非累积计数；输出时再累加成 `le` 语义。
+    buckets: [u64; BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, secs: f64) {
+        if let Some(i) = BUCKETS.iter().position(|b| secs <= *b) {
+            self.buckets[i] += 1;
+        }
+        self.sum += secs;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let sep = if labels.is_empty() { "" } else { "," };
+        let mut acc = 0;
+        for (b, n) in BUCKETS.iter().zip(self.buckets) {
+            acc += n;
+            let _ = writeln!(out, "{name}_bucket{{{labels}{sep}le=\"{b}\"}} {acc}");
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{{labels}{sep}le=\"+Inf\"}} {}",
+            self.count
+        );
+        let _ = writeln!(out, "{name}_sum{{{labels}}} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{{{labels}}} {}", self.count);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    /// `(method, route, status)` → 请求数
+    http_requests: BTreeMap<(String, String, u16), u64>,
+    /// `(method, route)` → 耗时
+    http_duration: BTreeMap<(String, String), Histogram>,
+    /// `(op, ok)` → tick 数
+    sync_ticks: BTreeMap<(SyncOp, bool), u64>,
+    sync_duration: BTreeMap<SyncOp, Histogram>,
+    /// 最近一次成功的 unix 秒
+    sync_last_success: BTreeMap<SyncOp, u64>,
+    push_412: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Registry {
+    inner: Mutex<Inner>,
+}
+
+impl Registry {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        // 指标不值得为锁中毒 panic：记录过程中 panic 最多丢一个样本
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 记一次 HTTP 请求。`route` 是路由模板（如 `/todos/:id`），不是实际路径，
+    /// 避免 id 进标签导致时序数爆炸。
+    pub fn observe_http(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let mut g = self.lock();
+        *g.http_requests
+            .entry((method.to_string(), route.to_string(), status))
+            .or_default() += 1;
+        g.http_duration
+            .entry((method.to_string(), route.to_string()))
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// 记一次 pull / push tick 的耗时与结果。
+    pub fn observe_sync(&self, op: SyncOp, ok: bool, elapsed: Duration) {
+        let mut g = self.lock();
+        *g.sync_ticks.entry((op, ok)).or_default() += 1;
+        g.sync_duration
+            .entry(op)
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+        if ok {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            g.sync_last_success.insert(op, now);
+        }
+    }
+
+    /// push 条件 PUT 收到 412（远端被别人改过，下一轮重试）。
+    pub fn inc_push_412(&self) {
+        self.lock().push_412 += 1;
+    }
+
+    /// 渲染全部指标。gauge 类现查 `db`。
+    pub fn render(&self, db: &Db) -> rusqlite::Result<String> {
+        let gauges = db.with_conn(|conn| -> rusqlite::Result<_> {
+            Ok((
+                repo::dirty_generation_lag(conn)?,
+                repo::dirty_images_len(conn)?,
+                repo::count_tombstones_by_type(conn)?,
+                repo::db_size_bytes(conn)?,
+            ))
+        })?;
+        let (lag, dirty_images, tombstones, db_size) = gauges;
+
+        let g = self.lock();
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "minitodo_http_requests_total",
+            "counter",
+            "HTTP 请求数",
+        );
+        for ((method, route, status), n) in &g.http_requests {
+            let _ = writeln!(
+                out,
+                "minitodo_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                escape(method),
+                escape(route),
+                status,
+                n
+            );
+        }
+
+        header(
+            &mut out,
+            "minitodo_http_request_duration_seconds",
+            "histogram",
+            "HTTP 请求耗时",
+        );
+        for ((method, route), h) in &g.http_duration {
+            let labels = format!("method=\"{}\",route=\"{}\"", escape(method), escape(route));
+            h.render(&mut out, "minitodo_http_request_duration_seconds", &labels);
+        }
+
+        header(
+            &mut out,
+            "minitodo_sync_ticks_total",
+            "counter",
+            "pull / push tick 次数（按结果）",
+        );
+        for ((op, ok), n) in &g.sync_ticks {
+            let outcome = if *ok { "ok" } else { "error" };
+            let _ = writeln!(
+                out,
+                "minitodo_sync_ticks_total{{op=\"{}\",outcome=\"{}\"}} {}",
+                op.label(),
+                outcome,
+                n
+            );
+        }
+
+        header(
+            &mut out,
+            "minitodo_sync_tick_duration_seconds",
+            "histogram",
+            "pull / push tick 耗时",
+        );
+        for (op, h) in &g.sync_duration {
+            let labels = format!("op=\"{}\"", op.label());
+            h.render(&mut out, "minitodo_sync_tick_duration_seconds", &labels);
+        }
+
+        header(
+            &mut out,
+            "minitodo_sync_last_success_timestamp_seconds",
+            "gauge",
+            "最近一次成功 tick 的 unix 时间",
+        );
+        for (op, ts) in &g.sync_last_success {
+            let _ = writeln!(
+                out,
+                "minitodo_sync_last_success_timestamp_seconds{{op=\"{}\"}} {}",
+                op.label(),
+                ts
+            );
+        }
+
+        header(
+            &mut out,
+            "minitodo_sync_push_412_total",
+            "counter",
+            "push 条件 PUT 收到 412 后放弃本轮的次数",
+        );
+        let _ = writeln!(out, "minitodo_sync_push_412_total {}", g.push_412);
+
+        header(
+            &mut out,
+            "minitodo_sync_dirty_generation_lag",
+            "gauge",
+            "尚未推上远端的写入代数",
+        );
+        let _ = writeln!(out, "minitodo_sync_dirty_generation_lag {}", lag);
+
+        header(
+            &mut out,
+            "minitodo_sync_dirty_images",
+            "gauge",
+            "meta.dirty_images 中等待上传的图片数",
+        );
+        let _ = writeln!(out, "minitodo_sync_dirty_images {}", dirty_images);
+
+        header(
+            &mut out,
+            "minitodo_tombstones",
+            "gauge",
+            "tombstone 数（按类型）",
+        );
+        for (kind, n) in &tombstones {
+            let _ = writeln!(
+                out,
+                "minitodo_tombstones{{type=\"{}\"}} {}",
+                escape(kind),
+                n
+            );
+        }
+
+        header(
+            &mut out,
+            "minitodo_db_size_bytes",
+            "gauge",
+            "SQLite 主库大小（不含 WAL）",
+        );
+        let _ = writeln!(out, "minitodo_db_size_bytes {}", db_size);
+
+        Ok(out)
+    }
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// label 值转义：`\` / `"` / 换行。
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn fresh_db() -> (Db, TempDir) {
+        let tmp = TempDir::new().expect("tempdir");
+        let db = Db::open(&tmp.path().join("data.db")).expect("open db");
+        (db, tmp)
+    }
+
+    #[test]
+    fn histogram_buckets_are_cumulative() {
+        let mut h = Histogram::default();
+        h.observe(0.003);
+        h.observe(0.2);
+        h.observe(30.0);
+        let mut out = String::new();
+        h.render(&mut out, "x", "op=\"pull\"");
+        assert!(out.contains("x_bucket{op=\"pull\",le=\"0.001\"} 0\n"));
+        assert!(out.contains("x_bucket{op=\"pull\",le=\"0.005\"} 1\n"));
+        assert!(out.contains("x_bucket{op=\"pull\",le=\"0.25\"} 2\n"));
+        assert!(out.contains("x_bucket{op=\"pull\",le=\"10\"} 2\n"));
+        assert!(out.contains("x_bucket{op=\"pull\",le=\"+Inf\"} 3\n"));
+        assert!(out.contains("x_count{op=\"pull\"} 3\n"));
+    }
+
+    #[test]
+    fn render_includes_counters_and_db_gauges() {
+        let (db, _tmp) = fresh_db();
+        db.with_conn(|conn| -> rusqlite::Result<()> {
+            repo::mark_dirty(conn)?;
+            repo::set_meta(conn, "dirty_images", r#"["a.png"]"#)?;
+            repo::add_tombstone(conn, "todo", "1", "2026-05-13 10:00:00")
+        })
+        .unwrap();
+
+        let r = Registry::default();
+        r.observe_http("GET", "/todos/:id", 200, Duration::from_millis(3));
+        r.observe_sync(SyncOp::Push, false, Duration::from_secs(2));
+        r.inc_push_412();
+        let out = r.render(&db).unwrap();
+
+        assert!(out.contains(
+            "minitodo_http_requests_total{method=\"GET\",route=\"/todos/:id\",status=\"200\"} 1\n"
+        ));
+        assert!(out.contains("minitodo_sync_ticks_total{op=\"push\",outcome=\"error\"} 1\n"));
+        assert!(!out.contains("minitodo_sync_last_success_timestamp_seconds{"));
+        assert!(out.contains("minitodo_sync_push_412_total 1\n"));
+        assert!(out.contains("minitodo_sync_dirty_generation_lag 1\n"));
+        assert!(out.contains("minitodo_sync_dirty_images 1\n"));
+        assert!(out.contains("minitodo_tombstones{type=\"todo\"} 1\n"));
+        assert!(out.contains("# TYPE minitodo_db_size_bytes gauge\n"));
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
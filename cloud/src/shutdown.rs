@@ -0,0 +1,86 @@
+//! 优雅退出。
+//!
+//! SIGTERM（systemd / Docker stop）或 SIGINT（Ctrl-C）到达后：
+//! 1. HTTP listener 停止 accept，等在途请求处理完（最多 [`DRAIN_TIMEOUT`]）
+//! 2. 在 `SyncLock` 下补跑一次 `push_tick`（最多 [`FLUSH_TIMEOUT`]），把最后
+//!    那批写入推上 WebDAV——否则它们要等下次启动才会被推送
+//! 3. 全部推完退出码 0；仍有未推送数据时退出码 [`EXIT_UNFLUSHED`]
+//!
+//! 两段超时之和（30s）小于 systemd 默认的 `TimeoutStopSec=90s`。
+
+use std::time::Duration;
+
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+/// 收到信号后等待在途 HTTP 请求完成的上限。
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 最后一次 push 的上限（含等 `SyncLock`）。
+pub const FLUSH_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// 退出时仍有数据没推上去（sysexits `EX_TEMPFAIL`）。数据本身留在 SQLite 的
+/// `meta.dirty` 里，下次启动会续推；退出码只是让编排系统 / 运维看得到。
+pub const EXIT_UNFLUSHED: u8 = 75;
+
+/// 退出信号的订阅端，可随意 clone 给各个 listener。
+#[derive(Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    /// 安装信号处理并返回订阅端。只应在 `serve` 里调用一次。
+    pub fn listen() -> Self {
+        let (tx, rx) = watch::channel(false);
+        tokio::spawn(async move {
+            let name = wait_for_signal().await;
+            info!(target: "minitodo_cloud", "收到 {}，开始优雅退出", name);
+            let _ = tx.send(true);
+        });
+        Shutdown(rx)
+    }
+
+    /// 等到退出信号为止。
+    pub async fn wait(mut self) {
+        // 发送端只在发出 true 之后才 drop，Err 分支不会先于信号出现
+        let _ = self.0.wait_for(|fired| *fired).await;
+    }
+}
+
+/// 测试里用 channel 手动触发退出。
+#[cfg(test)]
+impl From<watch::Receiver<bool>> for Shutdown {
+    fn from(rx: watch::Receiver<bool>) -> Self {
+        Shutdown(rx)
+    }
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut term = match signal(SignalKind::terminate()) {
+        Ok(s) => s,
+        Err(e) => {
+            warn!(target: "minitodo_cloud", "无法注册 SIGTERM 处理: {}", e);
+            return ctrl_c().await;
+        }
+    };
+    tokio::select! {
+        _ = term.recv() => "SIGTERM",
+        name = ctrl_c() => name,
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> &'static str {
+    ctrl_c().await
+}
+
+async fn ctrl_c() -> &'static str {
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        // 注册失败就永远不触发，而不是立刻退出
+        warn!(target: "minitodo_cloud", "无法注册 Ctrl-C 处理: {}", e);
+        std::future::pending::<()>().await;
+    }
+    "SIGINT"
+}
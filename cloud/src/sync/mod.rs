@@ -10,8 +10,18 @@ pub mod pull;
 pub mod push;
 pub mod webdav;
 
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
+use tracing::warn;
+
+use crate::config::Config;
+use crate::db::{repo, Db};
+use crate::time::now_local_string;
+
+/// WebDAV 上 mini-todo 的根目录（`sync-data.json.gz` 与 `images/` 都在其下）。
+pub const REMOTE_DIR: &str = "/mini-todo";
+
 /// 同步操作互斥锁：pull tick / push tick / `POST /sync`（含 `/sync/pull`、
 /// `/sync/push`）共享同一把，保证任一时刻只有一个同步操作在跑。
 ///
@@ -28,3 +38,46 @@ pub type SyncLock = Arc<tokio::sync::Mutex<()>>;
 pub fn new_sync_lock() -> SyncLock {
     Arc::new(tokio::sync::Mutex::new(()))
 }
+
+/// 就绪标志：进程内第一次 pull 成功后置 true，此后不再回落（之后的 pull 失败
+/// 由 `X-Sync-Status` / `/health/details` 反映，不让负载均衡把实例摘掉）。
+/// 与 `SyncLock` 一样在 `main` 里创建一次，交给 pull worker 与 `AppState`。
+pub type Readiness = Arc<AtomicBool>;
+
+pub fn new_readiness() -> Readiness {
+    Arc::new(AtomicBool::new(false))
+}
+
+pub fn mark_ready(ready: &Readiness) {
+    ready.store(true, Ordering::Relaxed);
+}
+
+pub fn is_ready(ready: &Readiness) -> bool {
+    ready.load(Ordering::Relaxed)
+}
+
+/// 把一次 pull / push 的结果记进 meta，供 `/health/details` 展示：失败写
+/// `last_{op}_error` + `last_{op}_error_at`，成功时删除。push tick 每秒一次，
+/// 所以成功路径先读再删，没有旧错误就不产生写入。记录失败只打日志。
+pub(crate) fn record_outcome(cfg: &Config, db: &Db, op: &str, res: &anyhow::Result<()>) {
+    let error_key = format!("last_{}_error", op);
+    let error_at_key = format!("last_{}_error_at", op);
+    let written = db.with_conn(|conn| -> rusqlite::Result<()> {
+        match res {
+            Ok(()) => {
+                if repo::get_meta(conn, &error_key)?.is_some() {
+                    repo::delete_meta(conn, &error_key)?;
+                    repo::delete_meta(conn, &error_at_key)?;
+                }
+            }
+            Err(e) => {
+                repo::set_meta(conn, &error_key, &format!("{:#}", e))?;
+                repo::set_meta(conn, &error_at_key, &now_local_string(cfg.timezone))?;
+            }
+        }
+        Ok(())
+    });
+    if let Err(e) = written {
+        warn!(target: "minitodo_cloud::sync", "记录 {} 结果失败: {}", op, e);
+    }
+}
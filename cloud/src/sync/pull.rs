@@ -8,21 +8,20 @@
 //! - 推送在 `push.rs` 的 push worker 负责
 
 use std::io::Read as _;
-use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use flate2::read::GzDecoder;
 use serde::Deserialize;
 use tracing::{error, info, warn};
 
-use crate::config::Config;
+use crate::config::{Config, LiveConfig};
 use crate::db::{repo, Db};
+use crate::metrics::{self, SyncOp};
 use crate::sync::webdav::WebDavClient;
-use crate::sync::SyncLock;
+use crate::sync::{Readiness, SyncLock, REMOTE_DIR};
 use crate::time::now_local_string;
 
 /// 远端 `/mini-todo` 同步目录路径。
-const REMOTE_DIR: &str = "/mini-todo";
 const SYNC_DATA_FILE: &str = "/mini-todo/sync-data.json.gz";
 
 /// 与 `pc::commands::sync_cmd::SyncData` 对齐的反序列化结构。
@@ -30,6 +29,7 @@ const SYNC_DATA_FILE: &str = "/mini-todo/sync-data.json.gz";
 /// - 字段 camelCase（与 PC 端 `#[serde(rename_all = "camelCase")]` 一致）
 /// - todos 中含嵌套 `subtasks`（PC 端导出时也把 subtask 嵌进去）
 /// - settings / 未知字段全部以 `serde_json::Value` 透传，保持 schema 漂移宽容
+/// - `templates` / `tags` 用 `Option` 区分"远端没有这个键"（旧版 PC 写的）与空数组
 /// - `serde` 默认忽略未知字段，因此 v3.0 旧数据也能解析
 #[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -47,6 +47,10 @@ pub struct SyncData {
     pub settings: serde_json::Value,
     #[serde(default)]
     pub images: Vec<String>,
+    #[serde(default)]
+    pub templates: Option<Vec<serde_json::Value>>,
+    #[serde(default)]
+    pub tags: Option<Vec<serde_json::Value>>,
 }
 
 /// 主入口：拉一次 + 合并；返回是否成功拿到远端数据。
@@ -54,6 +58,14 @@ pub struct SyncData {
 /// 304 → 视为成功但跳过解码；调用方读 `meta.last_pull_at` 已被更新即可。
 /// 404 → 远端还没有 sync-data，返回成功但 `data` 为空；进程继续工作。
 pub fn pull_once(cfg: &Config, db: &Db) -> anyhow::Result<()> {
+    let started = Instant::now();
+    let res = pull_and_backfill(cfg, db);
+    metrics::global().observe_sync(SyncOp::Pull, res.is_ok(), started.elapsed());
+    super::record_outcome(cfg, db, "pull", &res);
+    res
+}
+
+fn pull_and_backfill(cfg: &Config, db: &Db) -> anyhow::Result<()> {
     pull_once_inner(cfg, db)?;
     // 不管远端是否变化，本地都可能有从 PC 端同步来的、还没分配 cloud 短码
     // `seq` 的 todo。每次 pull tick 末尾扫一遍 `todo_seq` LEFT JOIN 缺失行，
@@ -74,7 +86,7 @@ fn pull_once_inner(cfg: &Config, db: &Db) -> anyhow::Result<()> {
         .map_err(|e| anyhow::anyhow!("读 meta.last_etag 失败: {}", e))?;
     let res = client.get(SYNC_DATA_FILE, last_etag.as_deref())?;
 
-    let now = now_local_string(cfg.timezone_offset);
+    let now = now_local_string(cfg.timezone);
 
     match res.status_code {
         304 => {
@@ -117,9 +129,10 @@ fn pull_once_inner(cfg: &Config, db: &Db) -> anyhow::Result<()> {
         if let Some(lm) = res.last_modified.as_deref() {
             repo::set_meta(conn, "last_modified", lm)?;
         }
-        // settings 整 JSON 存一行。当前只写不读（push 合并时用的是远端 GET 回来的
-        // settings），保留作调试快照 / 未来 /settings 端点的数据源。
-        repo::set_setting(conn, "all", &settings_str)?;
+        // settings 整 JSON 存一行，是 `GET /settings` 的数据源（push 合并时用的
+        // 仍是远端 GET 回来的 settings）。还没推上去的 PATCH 改动另存在
+        // `pending`，这里不动它。
+        repo::set_setting(conn, repo::SETTING_REMOTE, &settings_str)?;
         Ok(())
     })
     .map_err(|e| anyhow::anyhow!("写 meta/settings 失败: {}", e))?;
@@ -144,20 +157,36 @@ pub(crate) fn backfill_missing_seq(db: &Db) -> rusqlite::Result<usize> {
     })
 }
 
+/// 无条件拉取远端 `sync-data.json.gz` 并解析，不写本地。远端还没有时返回
+/// `None`。给管理命令（`verify`）用。
+pub fn fetch_remote(cfg: &Config) -> anyhow::Result<Option<SyncData>> {
+    let client = WebDavClient::new(&cfg.webdav_url, &cfg.webdav_username, &cfg.webdav_password)?;
+    let res = client.get(SYNC_DATA_FILE, None)?;
+    if res.status_code == 404 {
+        return Ok(None);
+    }
+    let json = gunzip(&res.bytes.unwrap_or_default())?;
+    let data: SyncData =
+        serde_json::from_str(&json).map_err(|e| anyhow::anyhow!("解析 sync-data 失败: {}", e))?;
+    Ok(Some(data))
+}
+
 /// 后台 spawn 的轮询循环。
-pub fn start_pull_loop(cfg: Arc<Config>, db: Db, sync_lock: SyncLock) {
-    let interval = Duration::from_secs(cfg.pull_interval_secs);
+///
+/// 任一 tick 成功即置 `ready`（启动时那次 pull 失败的话，由这里补上就绪）。
+/// 每轮重新读 `cfg`，SIGHUP 改了 `pull_interval` / WebDAV 凭据时下一轮生效。
+pub fn start_pull_loop(cfg: LiveConfig, db: Db, sync_lock: SyncLock, ready: Readiness) {
     tokio::spawn(async move {
         loop {
-            tokio::time::sleep(interval).await;
-            let cfg_ref = cfg.clone();
+            tokio::time::sleep(Duration::from_secs(cfg.get().pull_interval_secs)).await;
+            let cfg_ref = cfg.get();
             let db_ref = db.clone();
             // 在 async 层拿锁、持有到 blocking 段结束（不能在 blocking 线程里
             // 做 async 锁操作），与 push tick / POST /sync 串行。
             let _guard = sync_lock.lock().await;
             let res = tokio::task::spawn_blocking(move || pull_once(&cfg_ref, &db_ref)).await;
             match res {
-                Ok(Ok(())) => {}
+                Ok(Ok(())) => super::mark_ready(&ready),
                 Ok(Err(e)) => error!(target: "minitodo_cloud::pull", "pull tick failed: {:#}", e),
                 Err(join_err) => {
                     error!(target: "minitodo_cloud::pull", "pull task panicked: {}", join_err)
@@ -172,7 +201,7 @@ pub fn start_pull_loop(cfg: Arc<Config>, db: Db, sync_lock: SyncLock) {
 /// 1. 远端 record.updated_at ≥ 本地 → upsert；反之保留本地
 /// 2. merge 完毕后，删除"本地有但远端没有"的 todos/subtasks（孤儿清理）
 ///    — 当 `meta.dirty == "true"` 时跳过清理，保护 cloud API 本地新建还没 push 的记录
-fn merge_into_sqlite(db: &Db, data: &SyncData) -> anyhow::Result<(usize, usize)> {
+pub(crate) fn merge_into_sqlite(db: &Db, data: &SyncData) -> anyhow::Result<(usize, usize)> {
     db.with_conn(|conn| -> rusqlite::Result<(usize, usize)> {
         let tx = conn.transaction()?;
 
@@ -243,12 +272,103 @@ fn merge_into_sqlite(db: &Db, data: &SyncData) -> anyhow::Result<(usize, usize)>
             repo::delete_subtasks_not_in(&tx, &remote_subtask_ids)?;
         }
 
+        merge_views(&tx, &data.settings, skip_cleanup)?;
+        merge_templates(&tx, data.templates.as_deref(), skip_cleanup)?;
+        merge_tags(&tx, data.tags.as_deref(), skip_cleanup)?;
+
         tx.commit()?;
         Ok((todo_n, sub_n))
     })
     .map_err(|e| anyhow::anyhow!("merge_into_sqlite 失败: {}", e))
 }
 
+/// `settings.savedViews[]` → `saved_views` 表：per-view LWW，本地有 tombstone
+/// 的 view 不复活。孤儿清理与 todos 同样受 dirty 保护；另外远端 settings 里
+/// **没有** `savedViews` 键时（旧版 PC 写的 sync-data 会丢掉未知字段）不清理，
+/// 否则一次旧客户端的 push 就会抹掉云端全部 view。
+fn merge_views(
+    conn: &rusqlite::Connection,
+    settings: &serde_json::Value,
+    skip_cleanup: bool,
+) -> rusqlite::Result<()> {
+    let Some(views) = settings.get("savedViews").and_then(|v| v.as_array()) else {
+        return Ok(());
+    };
+    let mut remote_names = std::collections::HashSet::new();
+    for v in views {
+        let Some(name) = v.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        remote_names.insert(name.to_string());
+        if repo::has_tombstone(conn, "view", name)? {
+            continue;
+        }
+        let updated_at = v.get("updatedAt").and_then(|u| u.as_str()).unwrap_or("");
+        repo::upsert_view_if_newer(conn, name, &v.to_string(), updated_at)?;
+    }
+    if !skip_cleanup {
+        repo::delete_views_not_in(conn, &remote_names)?;
+    }
+    Ok(())
+}
+
+/// 顶层 `templates[]` → `templates` 表：per-template LWW，规则同
+/// [`merge_views`]——本地 tombstone 挡住复活，dirty 时不清理，远端没有
+/// `templates` 键（旧版 PC）时也不清理。
+fn merge_templates(
+    conn: &rusqlite::Connection,
+    templates: Option<&[serde_json::Value]>,
+    skip_cleanup: bool,
+) -> rusqlite::Result<()> {
+    let Some(templates) = templates else {
+        return Ok(());
+    };
+    let mut remote_ids = std::collections::HashSet::new();
+    for t in templates {
+        let Some(id) = extract_id(t) else {
+            continue;
+        };
+        remote_ids.insert(id.clone());
+        if repo::has_tombstone(conn, "template", &id)? {
+            continue;
+        }
+        let updated_at = t.get("updatedAt").and_then(|u| u.as_str()).unwrap_or("");
+        repo::upsert_template_if_newer(conn, &id, &t.to_string(), updated_at)?;
+    }
+    if !skip_cleanup {
+        repo::delete_templates_not_in(conn, &remote_ids)?;
+    }
+    Ok(())
+}
+
+/// 顶层 `tags[]` → `tags` 表：按 name per-tag LWW，规则同 [`merge_templates`]。
+/// todo 挂哪些标签跟着 todo 本身的 `tags` 字段走，这里只同步颜色登记。
+fn merge_tags(
+    conn: &rusqlite::Connection,
+    tags: Option<&[serde_json::Value]>,
+    skip_cleanup: bool,
+) -> rusqlite::Result<()> {
+    let Some(tags) = tags else {
+        return Ok(());
+    };
+    let mut remote_names = std::collections::HashSet::new();
+    for t in tags {
+        let Some(name) = t.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        remote_names.insert(name.to_string());
+        if repo::has_tombstone(conn, "tag", name)? {
+            continue;
+        }
+        let updated_at = t.get("updatedAt").and_then(|u| u.as_str()).unwrap_or("");
+        repo::upsert_tag_if_newer(conn, name, &t.to_string(), updated_at)?;
+    }
+    if !skip_cleanup {
+        repo::delete_tags_not_in(conn, &remote_names)?;
+    }
+    Ok(())
+}
+
 /// PC 端 todo / subtask 的 `id` 是 i64；这里统一转字符串便于 PK 处理。
 /// 复用 `crate::util::id_string`（同一份逻辑也在 push / api 用）。
 use crate::util::id_string as extract_id;
@@ -298,6 +418,8 @@ mod tests {
             todos,
             settings: serde_json::Value::Null,
             images: Vec::new(),
+            templates: None,
+            tags: None,
         }
     }
 
@@ -447,4 +569,121 @@ mod tests {
 
         assert_eq!(todo_title(&db, "1").as_deref(), Some("新"));
     }
+
+    fn view_names(db: &Db) -> Vec<String> {
+        db.with_conn(|conn| repo::list_views(conn))
+            .unwrap()
+            .into_iter()
+            .map(|v| v.name)
+            .collect()
+    }
+
+    #[test]
+    fn merge_saved_views_from_settings() {
+        let (db, _tmp) = fresh_db();
+        db.with_conn(|conn| {
+            repo::upsert_view(conn, "local", r#"{"name":"local"}"#, "2026-01-01 10:00:00")?;
+            repo::upsert_view(
+                conn,
+                "deleted",
+                r#"{"name":"deleted"}"#,
+                "2026-01-01 10:00:00",
+            )?;
+            repo::delete_view(conn, "deleted")?;
+            repo::add_tombstone(conn, "view", "deleted", "2026-01-02 10:00:00")
+        })
+        .unwrap();
+
+        // 远端 settings 无 savedViews 键（旧版 PC）→ 不动本地 view
+        merge_into_sqlite(&db, &sync_data(vec![])).unwrap();
+        assert_eq!(view_names(&db), vec!["local"]);
+
+        let mut data = sync_data(vec![]);
+        data.settings = serde_json::json!({
+            "isFixed": false,
+            "savedViews": [
+                {"name": "pc", "filter": "completed", "updatedAt": "2026-01-03 10:00:00"},
+                {"name": "deleted", "updatedAt": "2026-01-01 10:00:00"},
+            ],
+        });
+        merge_into_sqlite(&db, &data).unwrap();
+        // local 不在远端且不 dirty → 清理；deleted 有 tombstone → 不复活
+        assert_eq!(view_names(&db), vec!["pc"]);
+    }
+
+    #[test]
+    fn merge_templates_lww_tombstone_and_missing_key() {
+        let (db, _tmp) = fresh_db();
+        db.with_conn(|conn| {
+            repo::upsert_template(
+                conn,
+                "1",
+                r#"{"id":1,"name":"本地"}"#,
+                "2026-01-05 10:00:00",
+            )?;
+            repo::add_tombstone(conn, "template", "3", "2026-01-02 10:00:00")
+        })
+        .unwrap();
+
+        // 远端没有 templates 键（旧版 PC）→ 不动本地模板
+        merge_into_sqlite(&db, &sync_data(vec![])).unwrap();
+        let ids = |db: &Db| -> Vec<String> {
+            db.with_conn(|conn| repo::list_templates(conn))
+                .unwrap()
+                .into_iter()
+                .map(|r| r.id)
+                .collect()
+        };
+        assert_eq!(ids(&db), vec!["1"]);
+
+        let mut data = sync_data(vec![]);
+        data.templates = Some(vec![
+            serde_json::json!({"id": 1, "name": "远端较旧", "updatedAt": "2026-01-01 10:00:00"}),
+            serde_json::json!({"id": 2, "name": "新模板", "updatedAt": "2026-01-03 10:00:00"}),
+            serde_json::json!({"id": 3, "name": "已删", "updatedAt": "2026-01-03 10:00:00"}),
+        ]);
+        merge_into_sqlite(&db, &data).unwrap();
+        assert_eq!(ids(&db), vec!["2", "1"]);
+        let local = db.with_conn(|conn| repo::get_template(conn, "1")).unwrap();
+        assert!(local.unwrap().data_json.contains("本地"));
+    }
+
+    #[test]
+    fn merge_tags_lww_tombstone_and_missing_key() {
+        let (db, _tmp) = fresh_db();
+        db.with_conn(|conn| {
+            repo::upsert_tag(
+                conn,
+                "work",
+                r##"{"name":"work","color":"#EF4444"}"##,
+                "2026-01-05 10:00:00",
+            )?;
+            repo::upsert_tag(conn, "old", r#"{"name":"old"}"#, "2026-01-01 10:00:00")?;
+            repo::add_tombstone(conn, "tag", "gone", "2026-01-02 10:00:00")
+        })
+        .unwrap();
+        let names = |db: &Db| -> Vec<String> {
+            db.with_conn(|conn| repo::list_tags(conn))
+                .unwrap()
+                .into_iter()
+                .map(|r| r.name)
+                .collect()
+        };
+
+        // 远端没有 tags 键（旧版 PC）→ 不动本地登记
+        merge_into_sqlite(&db, &sync_data(vec![])).unwrap();
+        assert_eq!(names(&db), vec!["old", "work"]);
+
+        let mut data = sync_data(vec![]);
+        data.tags = Some(vec![
+            serde_json::json!({"name": "work", "color": "#000000", "updatedAt": "2026-01-01 10:00:00"}),
+            serde_json::json!({"name": "home", "color": "#10B981", "updatedAt": "2026-01-03 10:00:00"}),
+            serde_json::json!({"name": "gone", "updatedAt": "2026-01-03 10:00:00"}),
+        ]);
+        merge_into_sqlite(&db, &data).unwrap();
+        // old 不在远端 → 清理；gone 有 tombstone → 不复活；work 本地较新 → 保留
+        assert_eq!(names(&db), vec!["home", "work"]);
+        let work = db.with_conn(|conn| repo::get_tag(conn, "work")).unwrap();
+        assert!(work.unwrap().data_json.contains("#EF4444"));
+    }
 }
@@ -7,7 +7,7 @@
 use std::collections::HashSet;
 use std::io::Write as _;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use flate2::write::GzEncoder;
 use flate2::Compression;
@@ -15,13 +15,13 @@ use serde::Serialize;
 use serde_json::{json, Value};
 use tracing::{error, info, warn};
 
-use crate::config::Config;
+use crate::config::{Config, LiveConfig};
 use crate::db::{repo, Db};
+use crate::metrics::{self, SyncOp};
 use crate::sync::webdav::WebDavClient;
-use crate::sync::SyncLock;
+use crate::sync::{SyncLock, REMOTE_DIR};
 use crate::time::now_local_string;
 
-const REMOTE_DIR: &str = "/mini-todo";
 const REMOTE_IMAGES_DIR: &str = "/mini-todo/images";
 const SYNC_DATA_FILE: &str = "/mini-todo/sync-data.json.gz";
 
@@ -34,11 +34,11 @@ enum PushOutcome {
 }
 
 /// 后台 spawn 的 push 循环（1s tick）。
-pub fn start_push_loop(cfg: Arc<Config>, db: Db, sync_lock: SyncLock) {
+pub fn start_push_loop(cfg: LiveConfig, db: Db, sync_lock: SyncLock) {
     tokio::spawn(async move {
         loop {
             tokio::time::sleep(Duration::from_secs(1)).await;
-            let cfg_ref = cfg.clone();
+            let cfg_ref = cfg.get();
             let db_ref = db.clone();
             // 在 async 层拿锁、持有到 blocking 段结束（不能在 blocking 线程里
             // 做 async 锁操作），与 pull tick / POST /sync 串行。
@@ -55,12 +55,86 @@ pub fn start_push_loop(cfg: Arc<Config>, db: Db, sync_lock: SyncLock) {
     });
 }
 
+/// 退出前最后一次推送的结果，决定进程退出码。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushOutcome {
+    /// 本来就没有待推送的改动。
+    Clean,
+    /// 待推送的改动已全部落到远端。
+    Flushed,
+    /// 推送失败（WebDAV 不可达 / 连续 412），dirty 保留在 SQLite 里等下次启动。
+    Pending,
+    /// 超时：拿不到 `SyncLock`（别的同步卡在慢速网络上）或 PUT 本身太慢。
+    TimedOut,
+}
+
+impl FlushOutcome {
+    pub fn is_flushed(self) -> bool {
+        matches!(self, FlushOutcome::Clean | FlushOutcome::Flushed)
+    }
+}
+
+/// 优雅退出时调用：在 `SyncLock` 下补跑 `push_tick`，把 HTTP 停止接收前最后
+/// 那批写入推上去。412 时会重新 GET → merge 再试，最多 3 轮；整体受
+/// `timeout` 限制。无论结果如何 dirty 都持久化在 SQLite 里，下次启动照常续推。
+pub async fn final_flush(
+    cfg: Arc<Config>,
+    db: Db,
+    sync_lock: SyncLock,
+    timeout: Duration,
+) -> FlushOutcome {
+    let work = async {
+        let _guard = sync_lock.lock().await;
+        if !has_pending(&db) {
+            return FlushOutcome::Clean;
+        }
+        for _ in 0..3 {
+            let (cfg_ref, db_ref) = (cfg.clone(), db.clone());
+            match tokio::task::spawn_blocking(move || push_tick(&cfg_ref, &db_ref)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => {
+                    error!(target: "minitodo_cloud::push", "final push failed: {:#}", e);
+                    return FlushOutcome::Pending;
+                }
+                Err(join_err) => {
+                    error!(target: "minitodo_cloud::push", "final push panicked: {}", join_err);
+                    return FlushOutcome::Pending;
+                }
+            }
+            if !has_pending(&db) {
+                return FlushOutcome::Flushed;
+            }
+        }
+        FlushOutcome::Pending
+    };
+    tokio::time::timeout(timeout, work)
+        .await
+        .unwrap_or(FlushOutcome::TimedOut)
+}
+
+/// 是否还有没推上去的 sync-data 或图片。读失败按"有"处理，宁可报未刷完。
+fn has_pending(db: &Db) -> bool {
+    db.with_conn(|conn| -> rusqlite::Result<bool> {
+        Ok(repo::get_meta(conn, "dirty")?.as_deref() == Some("true")
+            || repo::dirty_images_len(conn)? > 0)
+    })
+    .unwrap_or(true)
+}
+
 /// 单次 tick：检查 dirty / dirty_images 并处理。
 ///
 /// dirty 的清除时机：**PUT 成功之后**，且仅当 `dirty_generation` 相比本轮开始
 /// 时没有变化。早期版本在推送前就置 dirty=false，慢速网络窗口期内并发的 pull
 /// 会把本地新建、还没推上去的记录当孤儿删掉（进程在此期间崩溃亦然）。
 pub fn push_tick(cfg: &Config, db: &Db) -> anyhow::Result<()> {
+    let started = Instant::now();
+    let res = push_tick_inner(cfg, db);
+    metrics::global().observe_sync(SyncOp::Push, res.is_ok(), started.elapsed());
+    super::record_outcome(cfg, db, "push", &res);
+    res
+}
+
+fn push_tick_inner(cfg: &Config, db: &Db) -> anyhow::Result<()> {
     // === dirty sync-data ===
     let dirty = db
         .with_conn(|conn| repo::get_meta(conn, "dirty"))
@@ -84,9 +158,11 @@ pub fn push_tick(cfg: &Config, db: &Db) -> anyhow::Result<()> {
 ///
 /// generation 变了说明推送窗口期内又有新写入（这些改动不在刚 PUT 的快照里），
 /// dirty 必须保留给下一轮。读 + 判 + 写在同一个 `with_conn` 闭包内完成，与
-/// 写路径的 `mark_dirty` 互斥（`Db` 的 Mutex 保证）。
+/// 写路径的 `mark_dirty` 互斥（`Db` 的 Mutex 保证）。无论是否清除都记下
+/// `pushed_generation = g0`，`/metrics` 据此算 dirty generation lag。
 fn clear_dirty_if_unchanged(db: &Db, g0: i64) -> rusqlite::Result<bool> {
     db.with_conn(|conn| -> rusqlite::Result<bool> {
+        repo::set_meta(conn, "pushed_generation", &g0.to_string())?;
         if repo::get_dirty_generation(conn)? != g0 {
             return Ok(false);
         }
@@ -137,9 +213,10 @@ fn do_push_sync_data(cfg: &Config, db: &Db) -> anyhow::Result<PushOutcome> {
             let after_get = client.get(SYNC_DATA_FILE, None).ok();
             let new_lm = after_get.as_ref().and_then(|g| g.last_modified.clone());
 
-            let now_local = now_local_string(cfg.timezone_offset);
+            let now_local = now_local_string(cfg.timezone);
             db.with_conn(|conn| -> rusqlite::Result<()> {
                 repo::set_meta(conn, "last_pull_at", &now_local)?;
+                repo::set_meta(conn, "last_push_at", &now_local)?;
                 if let Some(lm) = new_lm.as_deref() {
                     repo::set_meta(conn, "last_modified", lm)?;
                 }
@@ -149,12 +226,18 @@ fn do_push_sync_data(cfg: &Config, db: &Db) -> anyhow::Result<PushOutcome> {
                 // 清理超过 7 天的 tombstone（用 PC 风格本地时间字符串比较；
                 // chrono 算 7 天前的本地时间）
                 let cutoff = chrono::Utc::now()
-                    .with_timezone(&cfg.timezone_offset)
+                    .with_timezone(&cfg.timezone)
                     .checked_sub_signed(chrono::Duration::days(7))
                     .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string());
                 if let Some(c) = cutoff {
                     let _ = repo::purge_tombstones_before(conn, &c);
                 }
+                // 推上去的 settings 就是新的远端；pending 只在推送期间没被
+                // 再次 PATCH 时才清，否则留给下一轮
+                repo::set_setting(conn, repo::SETTING_REMOTE, &merged["settings"].to_string())?;
+                if let Some(raw) = local_snapshot.settings_pending.as_deref() {
+                    repo::delete_setting_if(conn, repo::SETTING_PENDING, raw)?;
+                }
                 Ok(())
             })?;
             info!(target: "minitodo_cloud::push", "push ok");
@@ -163,6 +246,7 @@ fn do_push_sync_data(cfg: &Config, db: &Db) -> anyhow::Result<PushOutcome> {
         412 => {
             // 远端被别人改过：本轮不算推送成功，dirty 保持 true，下轮重试
             warn!(target: "minitodo_cloud::push", "412 precondition failed; will retry");
+            metrics::global().inc_push_412();
             Ok(PushOutcome::Retry)
         }
         other => {
@@ -174,19 +258,36 @@ fn do_push_sync_data(cfg: &Config, db: &Db) -> anyhow::Result<PushOutcome> {
 /// 本地 SQLite 全部 todos + subtasks 序列化成一个简化的 "snapshot" 形式：
 /// 每条 todo 的 `data_json` 反序列化为 Value，并把 subtasks 嵌入。
 #[derive(Debug, Clone, Serialize)]
-struct LocalSnapshot {
+pub(crate) struct LocalSnapshot {
     todos: Vec<Value>,
+    /// saved views（`saved_views.data_json`），merge 时写进 `settings.savedViews`。
+    views: Vec<Value>,
+    /// todo 模板（`templates.data_json`），merge 时写进顶层 `templates`。
+    templates: Vec<Value>,
+    /// 标签颜色登记（`tags.data_json`），merge 时写进顶层 `tags`。
+    tags: Vec<Value>,
     images: Vec<String>,
+    /// `PATCH /settings` 累积的改动（`settings.pending` 原文），merge 时覆盖到
+    /// 远端 settings 上；PUT 成功后按原文比对删除。
+    settings_pending: Option<String>,
 }
 
 type TodoTuple = (String, Value, String);
+/// `(todo, subtask, view, template, tag)` 五类 tombstone id 集合。
+type TombstoneSets = (
+    HashSet<String>,
+    HashSet<String>,
+    HashSet<String>,
+    HashSet<String>,
+    HashSet<String>,
+);
 type SubtaskTuple = (Value, String);
 type SnapshotRaw = (
     Vec<TodoTuple>,
     std::collections::HashMap<String, Vec<SubtaskTuple>>,
 );
 
-fn build_local_snapshot(cfg: &Config, db: &Db) -> anyhow::Result<LocalSnapshot> {
+pub(crate) fn build_local_snapshot(cfg: &Config, db: &Db) -> anyhow::Result<LocalSnapshot> {
     let (todos, subtasks_by_todo) = db.with_conn(|conn| -> rusqlite::Result<SnapshotRaw> {
         let todo_rows = repo::all_todos(conn)?;
         let mut todos_acc: Vec<TodoTuple> = Vec::with_capacity(todo_rows.len());
@@ -237,28 +338,57 @@ fn build_local_snapshot(cfg: &Config, db: &Db) -> anyhow::Result<LocalSnapshot>
     }
     images.sort();
 
+    let views = db
+        .with_conn(|conn| repo::list_views(conn))?
+        .into_iter()
+        .map(|r| serde_json::from_str(&r.data_json).unwrap_or_else(|_| json!({"name": r.name})))
+        .collect();
+    let templates = db
+        .with_conn(|conn| repo::list_templates(conn))?
+        .into_iter()
+        .map(|r| serde_json::from_str(&r.data_json).unwrap_or_else(|_| json!({"id": r.id})))
+        .collect();
+    let tags = db
+        .with_conn(|conn| repo::list_tags(conn))?
+        .into_iter()
+        .map(|r| serde_json::from_str(&r.data_json).unwrap_or_else(|_| json!({"name": r.name})))
+        .collect();
+    let settings_pending = db.with_conn(|conn| repo::get_setting(conn, repo::SETTING_PENDING))?;
+
     Ok(LocalSnapshot {
         todos: out_todos,
+        views,
+        templates,
+        tags,
         images,
+        settings_pending,
     })
 }
 
 /// per-record LWW merge：本地 + 远端 → 合并 SyncData。
 ///
 /// - todos & nested subtasks：updatedAt 大的胜
+/// - subtask 在本地与远端挂在不同 todo 下（`POST /subtasks/:id/move`）：按
+///   updatedAt 定归属，另一边的副本丢弃
 /// - 本地有 tombstone → 把对应 record 从合并结果中剔除
-/// - 远端 settings 总是优先（云端不写 settings）
-fn merge_sync_data(
+/// - settings 以远端为底，`PATCH /settings` 还没推上去的字段覆盖其上；
+///   `settings.savedViews` 按 view name 逐条 LWW，本地 view tombstone 剔除对应条目
+/// - 顶层 `templates` 按模板 id 逐条 LWW，本地 template tombstone 剔除对应条目
+/// - 顶层 `tags` 按标签 name 逐条 LWW，本地 tag tombstone 剔除对应条目
+pub(crate) fn merge_sync_data(
     remote: &Value,
     local: &LocalSnapshot,
     db: &Db,
     cfg: &Config,
 ) -> anyhow::Result<Value> {
     // 收集本地 tombstones
-    let (todo_tombs, subtask_tombs) = db.with_conn(
-        |conn| -> rusqlite::Result<(HashSet<String>, HashSet<String>)> {
+    let (todo_tombs, subtask_tombs, view_tombs, template_tombs, tag_tombs) =
+        db.with_conn(|conn| -> rusqlite::Result<TombstoneSets> {
             let mut t = HashSet::new();
             let mut s = HashSet::new();
+            let mut v = HashSet::new();
+            let mut tpl = HashSet::new();
+            let mut tag = HashSet::new();
             for (typ, id, _ts) in repo::list_tombstones(conn)? {
                 match typ.as_str() {
                     "todo" => {
@@ -267,12 +397,20 @@ fn merge_sync_data(
                     "subtask" => {
                         s.insert(id);
                     }
+                    "view" => {
+                        v.insert(id);
+                    }
+                    "template" => {
+                        tpl.insert(id);
+                    }
+                    "tag" => {
+                        tag.insert(id);
+                    }
                     _ => {}
                 }
             }
-            Ok((t, s))
-        },
-    )?;
+            Ok((t, s, v, tpl, tag))
+        })?;
 
     // 远端 todos & subtasks
     let remote_todos = remote
@@ -290,6 +428,8 @@ fn merge_sync_data(
         }
     }
 
+    let owners = moved_subtask_owners(&remote_todos, &local.todos);
+
     // 输出 todos：以 union(id) 为基准
     let mut all_ids: Vec<String> = local_by_id.keys().cloned().collect();
     for r in &remote_todos {
@@ -315,14 +455,20 @@ fn merge_sync_data(
                 if updated_at(l) >= updated_at(r) {
                     // 本地更新或同时：以本地为主体，但子任务还要 union-merge
                     let mut base = l.clone();
-                    let merged_subs =
-                        merge_subtasks_into(remote_subs(r), local_subs(l), &subtask_tombs);
+                    let merged_subs = merge_subtasks_into(
+                        owned_by(remote_subs(r), id, &owners),
+                        owned_by(local_subs(l), id, &owners),
+                        &subtask_tombs,
+                    );
                     base["subtasks"] = Value::Array(merged_subs);
                     base
                 } else {
                     let mut base = r.clone();
-                    let merged_subs =
-                        merge_subtasks_into(remote_subs(r), local_subs(l), &subtask_tombs);
+                    let merged_subs = merge_subtasks_into(
+                        owned_by(remote_subs(r), id, &owners),
+                        owned_by(local_subs(l), id, &owners),
+                        &subtask_tombs,
+                    );
                     base["subtasks"] = Value::Array(merged_subs);
                     base
                 }
@@ -330,13 +476,21 @@ fn merge_sync_data(
             (Some(r), None) => {
                 // 本地没有 + 远端有：可能本地没 pull 过；保留远端
                 let mut base = r.clone();
-                let merged_subs = merge_subtasks_into(remote_subs(r), Vec::new(), &subtask_tombs);
+                let merged_subs = merge_subtasks_into(
+                    owned_by(remote_subs(r), id, &owners),
+                    Vec::new(),
+                    &subtask_tombs,
+                );
                 base["subtasks"] = Value::Array(merged_subs);
                 base
             }
             (None, Some(l)) => {
                 let mut base = l.clone();
-                let merged_subs = merge_subtasks_into(Vec::new(), local_subs(l), &subtask_tombs);
+                let merged_subs = merge_subtasks_into(
+                    Vec::new(),
+                    owned_by(local_subs(l), id, &owners),
+                    &subtask_tombs,
+                );
                 base["subtasks"] = Value::Array(merged_subs);
                 base
             }
@@ -348,10 +502,33 @@ fn merge_sync_data(
     // settings：远端优先。若远端为空（首次部署，云端 push 比 PC 第一次 PUT 还早
     // 的边角场景），写入一个最小合法的 PC AppSettings——`is_fixed` / `window_position`
     // / `window_size` 在 PC 端不带 `serde(default)`，缺失会导致 PC import 失败。
-    let settings = match remote.get("settings") {
+    let mut settings = match remote.get("settings") {
         Some(v) if !v.is_null() && v.is_object() => v.clone(),
         _ => default_app_settings_value(),
     };
+    let remote_views = settings
+        .get("savedViews")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    settings["savedViews"] = Value::Array(merge_views(remote_views, &local.views, &view_tombs));
+    if let Some(raw) = local.settings_pending.as_deref() {
+        overlay_settings(&mut settings, raw);
+    }
+
+    let remote_templates = remote
+        .get("templates")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let templates = merge_templates(remote_templates, &local.templates, &template_tombs);
+
+    let remote_tags = remote
+        .get("tags")
+        .and_then(|v| v.as_array())
+        .cloned()
+        .unwrap_or_default();
+    let tags = merge_tags(remote_tags, &local.tags, &tag_tombs);
 
     // images：远端 ∪ 本地
     let mut images: HashSet<String> = local.images.iter().cloned().collect();
@@ -367,7 +544,7 @@ fn merge_sync_data(
 
     // 元信息
     let now_iso = chrono::Utc::now()
-        .with_timezone(&cfg.timezone_offset)
+        .with_timezone(&cfg.timezone)
         .format("%Y-%m-%dT%H:%M:%S%:z")
         .to_string();
     let version = remote
@@ -388,9 +565,65 @@ fn merge_sync_data(
         "todos": out_todos,
         "settings": settings,
         "images": images_vec,
+        "templates": templates,
+        "tags": tags,
     }))
 }
 
+/// 把 `settings.pending`（JSON 对象）逐字段覆盖到 settings 上；解析不了就忽略。
+fn overlay_settings(settings: &mut Value, pending_raw: &str) {
+    let Ok(Value::Object(pending)) = serde_json::from_str::<Value>(pending_raw) else {
+        return;
+    };
+    if let Some(obj) = settings.as_object_mut() {
+        obj.extend(pending);
+    }
+}
+
+/// saved views 按 `name` 逐条 LWW（`updatedAt` 大的胜，相同取本地），tombstone
+/// 中的 name 直接剔除；结果按 name 排序，保证 sync-data 内容稳定。
+fn merge_views(remote: Vec<Value>, local: &[Value], tombs: &HashSet<String>) -> Vec<Value> {
+    merge_keyed(remote, local, tombs, |v| {
+        v.get("name").and_then(|n| n.as_str()).map(str::to_string)
+    })
+}
+
+/// 模板按 id 逐条 LWW，规则同 [`merge_views`]；结果按 id 排序。
+fn merge_templates(remote: Vec<Value>, local: &[Value], tombs: &HashSet<String>) -> Vec<Value> {
+    merge_keyed(remote, local, tombs, id_string)
+}
+
+/// 标签登记按 name 逐条 LWW，规则同 [`merge_views`]；结果按 name 排序。
+fn merge_tags(remote: Vec<Value>, local: &[Value], tombs: &HashSet<String>) -> Vec<Value> {
+    merge_keyed(remote, local, tombs, |v| {
+        v.get("name").and_then(|n| n.as_str()).map(str::to_string)
+    })
+}
+
+fn merge_keyed(
+    remote: Vec<Value>,
+    local: &[Value],
+    tombs: &HashSet<String>,
+    key_of: impl Fn(&Value) -> Option<String>,
+) -> Vec<Value> {
+    let mut by_key: std::collections::BTreeMap<String, Value> = std::collections::BTreeMap::new();
+    for v in remote.into_iter().chain(local.iter().cloned()) {
+        let Some(key) = key_of(&v) else {
+            continue;
+        };
+        if tombs.contains(&key) {
+            continue;
+        }
+        match by_key.get(&key) {
+            Some(existing) if updated_at(existing) > updated_at(&v) => {}
+            _ => {
+                by_key.insert(key, v);
+            }
+        }
+    }
+    by_key.into_values().collect()
+}
+
 fn remote_subs(t: &Value) -> Vec<Value> {
     t.get("subtasks")
         .and_then(|v| v.as_array())
@@ -405,6 +638,60 @@ fn local_subs(t: &Value) -> Vec<Value> {
         .unwrap_or_default()
 }
 
+/// 本地与远端父 todo 不一致的 subtask id → 胜出的父 todo id（updatedAt 大的
+/// 一边，相同取本地）。subtask id 全局唯一，不处理的话 union merge 会让挪过的
+/// 子任务在新旧两个 todo 下各出现一次。
+fn moved_subtask_owners(
+    remote_todos: &[Value],
+    local_todos: &[Value],
+) -> std::collections::HashMap<String, String> {
+    let mut remote_parent: std::collections::HashMap<String, (String, String)> =
+        std::collections::HashMap::new();
+    for t in remote_todos {
+        let Some(tid) = id_string(t) else { continue };
+        for s in remote_subs(t) {
+            if let Some(sid) = id_string(&s) {
+                remote_parent.insert(sid, (tid.clone(), updated_at(&s).to_string()));
+            }
+        }
+    }
+    let mut owners = std::collections::HashMap::new();
+    for t in local_todos {
+        let Some(tid) = id_string(t) else { continue };
+        for s in local_subs(t) {
+            let Some(sid) = id_string(&s) else { continue };
+            let Some((rtid, rts)) = remote_parent.get(&sid) else {
+                continue;
+            };
+            if *rtid == tid {
+                continue;
+            }
+            let winner = if updated_at(&s) >= rts.as_str() {
+                tid.clone()
+            } else {
+                rtid.clone()
+            };
+            owners.insert(sid, winner);
+        }
+    }
+    owners
+}
+
+/// 去掉归属（见 [`moved_subtask_owners`]）不是 `todo_id` 的子任务。
+fn owned_by(
+    subs: Vec<Value>,
+    todo_id: &str,
+    owners: &std::collections::HashMap<String, String>,
+) -> Vec<Value> {
+    subs.into_iter()
+        .filter(|s| {
+            id_string(s)
+                .and_then(|sid| owners.get(&sid))
+                .is_none_or(|owner| owner == todo_id)
+        })
+        .collect()
+}
+
 fn merge_subtasks_into(
     remote_subs: Vec<Value>,
     local_subs: Vec<Value>,
@@ -584,6 +871,12 @@ mod tests {
             "generation 变了就不该清 dirty"
         );
         assert_eq!(dirty_flag(&db).as_deref(), Some("true"));
+        // 刚 PUT 的快照之后只剩一次写入没推上去
+        assert_eq!(
+            db.with_conn(|conn| repo::dirty_generation_lag(conn))
+                .unwrap(),
+            1
+        );
     }
 
     /// 推送期间无写入 → PUT 成功后正常清 dirty。
@@ -619,6 +912,38 @@ mod tests {
         assert_eq!(dirty_flag(&db).as_deref(), Some("true"));
     }
 
+    fn unreachable_cfg(tmp: &TempDir) -> Arc<Config> {
+        // Config::for_tests 的 webdav_url 指向 127.0.0.1:0，必然连不上
+        Arc::new(Config::for_tests(
+            "test-api-key-1234567890abcdef",
+            tmp.path().join("data"),
+            tmp.path().join("images"),
+        ))
+    }
+
+    #[tokio::test]
+    async fn final_flush_outcomes() {
+        let (db, tmp) = fresh_db();
+        let cfg = unreachable_cfg(&tmp);
+        let lock = crate::sync::new_sync_lock();
+        let flush = |timeout| final_flush(cfg.clone(), db.clone(), lock.clone(), timeout);
+
+        assert_eq!(flush(Duration::from_secs(5)).await, FlushOutcome::Clean);
+
+        db.with_conn(|conn| repo::mark_dirty(conn)).unwrap();
+        let out = flush(Duration::from_secs(5)).await;
+        assert_eq!(out, FlushOutcome::Pending);
+        assert!(!out.is_flushed());
+        assert_eq!(dirty_flag(&db).as_deref(), Some("true"));
+
+        // 别的同步一直占着锁 → 超时返回，不会卡住退出流程
+        let _held = lock.lock().await;
+        assert_eq!(
+            flush(Duration::from_millis(50)).await,
+            FlushOutcome::TimedOut
+        );
+    }
+
     #[test]
     fn merge_subtasks_lww_keeps_newer() {
         let remote = vec![json!({"id": 1, "title": "old", "updatedAt": "2026-05-13 10:00:00"})];
@@ -663,6 +988,81 @@ mod tests {
         assert!(ids.contains("2"));
     }
 
+    #[test]
+    fn moved_subtask_follows_newer_parent() {
+        let remote = vec![
+            json!({"id": 1, "subtasks": [{"id": 10, "updatedAt": "2026-01-01 08:00:00"}]}),
+            json!({"id": 2, "subtasks": []}),
+        ];
+        let local = vec![
+            json!({"id": 1, "subtasks": []}),
+            json!({"id": 2, "subtasks": [{"id": 10, "updatedAt": "2026-01-02 08:00:00"}]}),
+        ];
+        let owners = moved_subtask_owners(&remote, &local);
+        assert_eq!(owners.get("10").map(String::as_str), Some("2"));
+        assert!(owned_by(remote_subs(&remote[0]), "1", &owners).is_empty());
+        assert_eq!(owned_by(local_subs(&local[1]), "2", &owners).len(), 1);
+
+        // 远端（PC）之后又改过这条：留在远端的父 todo 下
+        let remote =
+            vec![json!({"id": 1, "subtasks": [{"id": 10, "updatedAt": "2026-01-03 08:00:00"}]})];
+        let owners = moved_subtask_owners(&remote, &local);
+        assert_eq!(owners.get("10").map(String::as_str), Some("1"));
+        assert!(owned_by(local_subs(&local[1]), "2", &owners).is_empty());
+    }
+
+    #[test]
+    fn merge_views_lww_by_name_and_tombstones() {
+        let remote = vec![
+            json!({"name": "q1", "filter": "quadrant = 1", "updatedAt": "2026-05-13 12:00:00"}),
+            json!({"name": "gone", "updatedAt": "2026-05-13 12:00:00"}),
+            json!({"name": "pc-only", "updatedAt": "2026-05-13 09:00:00"}),
+        ];
+        let local = vec![
+            json!({"name": "q1", "filter": "quadrant = 2", "updatedAt": "2026-05-13 11:00:00"}),
+            json!({"name": "cloud-only", "updatedAt": "2026-05-13 11:00:00"}),
+        ];
+        let mut tombs = HashSet::new();
+        tombs.insert("gone".to_string());
+        let out = merge_views(remote, &local, &tombs);
+        let names: Vec<&str> = out.iter().filter_map(|v| v["name"].as_str()).collect();
+        assert_eq!(names, vec!["cloud-only", "pc-only", "q1"]);
+        assert_eq!(out[2]["filter"], "quadrant = 1", "远端更新，应保留远端");
+    }
+
+    #[test]
+    fn merge_templates_lww_by_id_and_tombstones() {
+        let remote = vec![
+            json!({"id": 1, "name": "远端", "updatedAt": "2026-05-13 09:00:00"}),
+            json!({"id": 2, "name": "已删", "updatedAt": "2026-05-13 12:00:00"}),
+        ];
+        let local = vec![
+            json!({"id": 1, "name": "本地", "updatedAt": "2026-05-13 10:00:00"}),
+            json!({"id": 3, "name": "新建", "updatedAt": "2026-05-13 10:00:00"}),
+        ];
+        let tombs: HashSet<String> = ["2".to_string()].into_iter().collect();
+        let out = merge_templates(remote, &local, &tombs);
+        let names: Vec<&str> = out.iter().filter_map(|v| v["name"].as_str()).collect();
+        assert_eq!(names, vec!["本地", "新建"]);
+    }
+
+    #[test]
+    fn merge_tags_lww_by_name_and_tombstones() {
+        let remote = vec![
+            json!({"name": "work", "color": "#000000", "updatedAt": "2026-05-13 09:00:00"}),
+            json!({"name": "renamed", "updatedAt": "2026-05-13 12:00:00"}),
+        ];
+        let local = vec![
+            json!({"name": "work", "color": "#EF4444", "updatedAt": "2026-05-13 10:00:00"}),
+            json!({"name": "home", "updatedAt": "2026-05-13 10:00:00"}),
+        ];
+        let tombs: HashSet<String> = ["renamed".to_string()].into_iter().collect();
+        let out = merge_tags(remote, &local, &tombs);
+        let names: Vec<&str> = out.iter().filter_map(|v| v["name"].as_str()).collect();
+        assert_eq!(names, vec!["home", "work"]);
+        assert_eq!(out[1]["color"], "#EF4444");
+    }
+
     #[test]
     fn id_string_handles_numeric_and_string() {
         assert_eq!(id_string(&json!({"id": 42})), Some("42".to_string()));
@@ -694,6 +1094,25 @@ mod tests {
         );
     }
 
+    #[test]
+    fn overlay_settings_applies_pending_fields() {
+        let mut s = json!({"isFixed": true, "viewMode": "list", "savedViews": []});
+        overlay_settings(&mut s, r#"{"viewMode":"quadrant","showCalendar":true}"#);
+        assert_eq!(
+            s,
+            json!({
+                "isFixed": true,
+                "viewMode": "quadrant",
+                "showCalendar": true,
+                "savedViews": [],
+            })
+        );
+
+        // 坏数据不影响推送
+        overlay_settings(&mut s, "not json");
+        assert_eq!(s["viewMode"], "quadrant");
+    }
+
     #[test]
     fn default_settings_has_required_fields() {
         // PC 端 AppSettings 必填字段：isFixed / windowPosition / windowSize
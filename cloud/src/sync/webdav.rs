@@ -77,6 +77,21 @@ impl WebDavClient {
         Ok(())
     }
 
+    /// 连通性探测：对 `remote_path` 发 `PROPFIND Depth: 0`，返回 HTTP 状态码。
+    /// 给 `/health/details` 用，超时比普通请求短，避免健康检查被拖住。
+    pub fn probe(&self, remote_path: &str, timeout: Duration) -> anyhow::Result<u16> {
+        let url = self.full_url(remote_path);
+        let resp = self
+            .client
+            .request(Method::from_bytes(b"PROPFIND").unwrap(), &url)
+            .basic_auth(&self.username, Some(&self.password))
+            .header("Depth", "0")
+            .timeout(timeout)
+            .send()
+            .map_err(|e| anyhow::anyhow!("PROPFIND {} 失败: {}", remote_path, e))?;
+        Ok(resp.status().as_u16())
+    }
+
     /// 条件 GET。`if_none_match` 不为空时附 `If-None-Match` header，远端文件
     /// 未变会返回 304；调用方应据此跳过解码。
     pub fn get(
@@ -3,39 +3,118 @@
 //! **核心契约**：所有 `updated_at` 字符串必须与 PC SQLite
 //! `datetime('now','localtime')` 完全一致，即 `"%Y-%m-%d %H:%M:%S"`、
 //! 不带时区后缀、按"本地时区现在"取墙钟时间。云端没有 OS 级 localtime，
-//! 改用 `chrono::FixedOffset` 模拟用户在 config 里指定的时区即可。
+//! 改用 config 里的 IANA `Tz` 模拟。
+//!
+//! offset 一律在"要格式化的那个时刻"现算，不缓存：DST 时区一年里有两个
+//! offset，缓存启动时的值会让半年的时间戳差一小时，LWW 比较随之错乱。
 
-use chrono::{FixedOffset, Offset, TimeZone, Utc};
+use chrono::{DateTime, Duration, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
 use chrono_tz::Tz;
 
-/// 取出 `tz` 当前时刻相对 UTC 的 `FixedOffset`。
-///
-/// 由于 DST 切换时 offset 会变，理论上每次都应当用 `Tz` 重新算；但 mini-todo
-/// 当前唯一支持时区是 Asia/Shanghai（无 DST），其他用户场景也基本都是无 DST
-/// 时区，所以服务启动时取一次即可。后续如需精确支持 DST，可在取时间时
-/// 直接用 `Tz` 重新计算，而非缓存的 offset。
-pub fn offset_for_tz_now(tz: Tz) -> FixedOffset {
-    let now_utc = Utc::now();
-    tz.from_utc_datetime(&now_utc.naive_utc()).offset().fix()
-}
+/// PC SQLite `datetime('now','localtime')` 的格式。
+pub const LOCAL_FMT: &str = "%Y-%m-%d %H:%M:%S";
 
 /// 返回与 PC SQLite `datetime('now','localtime')` 字符串格式一致的时间戳。
 ///
 /// 示例：`"2026-05-13 12:34:56"`。
-pub fn now_local_string(offset: FixedOffset) -> String {
-    Utc::now()
-        .with_timezone(&offset)
-        .format("%Y-%m-%d %H:%M:%S")
-        .to_string()
+pub fn now_local_string(tz: Tz) -> String {
+    format_local(Utc::now(), tz)
+}
+
+/// 把某个时刻按 `tz` 在**该时刻**的 offset 格式化成 PC 风格字符串。
+pub fn format_local(at: DateTime<Utc>, tz: Tz) -> String {
+    at.with_timezone(&tz).format(LOCAL_FMT).to_string()
+}
+
+/// 把 `tz` 下的墙钟时间解析成确定的时刻。
+///
+/// DST 切换会让墙钟时间不唯一：
+/// - 回拨重叠（如纽约 11 月 01:30 出现两次）取较早的那个；
+/// - 拨快跳过（如纽约 3 月 02:30 不存在）顺延到跳过之后，按切换前的 offset
+///   算——与 PC 端 SQLite / 多数日历软件的处理一致。
+pub fn resolve_local(naive: NaiveDateTime, tz: Tz) -> DateTime<Tz> {
+    match tz.from_local_datetime(&naive) {
+        LocalResult::Single(d) => d,
+        LocalResult::Ambiguous(earliest, _) => earliest,
+        LocalResult::None => {
+            // 间隙最长一小时（个别历史时区两小时）：按间隙之前的 offset 换算到
+            // UTC，再落回 tz，即得切换后对应的墙钟时间
+            let before = tz
+                .offset_from_local_datetime(&(naive - Duration::hours(3)))
+                .earliest()
+                .expect("三小时前不会仍处于 DST 间隙");
+            let utc = naive - Duration::seconds(i64::from(before.fix().local_minus_utc()));
+            Utc.from_utc_datetime(&utc).with_timezone(&tz)
+        }
+    }
+}
+
+/// 把 `from` 时区的墙钟时间换算成 `to` 时区的墙钟时间。
+pub fn convert_local(naive: NaiveDateTime, from: Tz, to: Tz) -> NaiveDateTime {
+    if from == to {
+        return naive;
+    }
+    resolve_local(naive, from).with_timezone(&to).naive_local()
+}
+
+/// 解释相对时间（`today` / `now-2h` / "明天下午3点"）用的时钟。
+///
+/// 两个时区分工不同：`client` 是请求方所在时区（`X-Timezone`，缺省同
+/// `storage`），决定"今天"是哪一天、"下午3点"是谁的下午；`storage` 是库里
+/// 时间字符串所用的时区（`config.timezone`，与 PC 一致），所有落库 / 参与比较
+/// 的墙钟时间最终都换算到它。
+#[derive(Debug, Clone, Copy)]
+pub struct Clock {
+    pub now: DateTime<Utc>,
+    pub client: Tz,
+    pub storage: Tz,
+}
+
+impl Clock {
+    pub fn new(client: Tz, storage: Tz) -> Self {
+        Clock {
+            now: Utc::now(),
+            client,
+            storage,
+        }
+    }
+
+    /// 请求方时区的今天。
+    pub fn today(&self) -> NaiveDate {
+        self.client_now().date_naive()
+    }
+
+    /// 请求方时区的现在。
+    pub fn client_now(&self) -> DateTime<Tz> {
+        self.now.with_timezone(&self.client)
+    }
+
+    /// 某个时刻在存储时区下的墙钟时间。
+    pub fn in_storage(&self, at: DateTime<Tz>) -> NaiveDateTime {
+        at.with_timezone(&self.storage).naive_local()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono_tz::America::New_York;
+    use chrono_tz::Asia::Shanghai;
+    use chrono_tz::Europe::London;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        NaiveDateTime::parse_from_str(s, LOCAL_FMT)
+            .unwrap()
+            .and_utc()
+    }
+
+    fn naive(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, LOCAL_FMT).unwrap()
+    }
 
     #[test]
     fn format_shape_matches_pc_sqlite() {
-        let s = now_local_string(FixedOffset::east_opt(8 * 3600).unwrap());
+        let s = now_local_string(Shanghai);
         // 形如 "2026-05-13 12:34:56"
         assert_eq!(s.len(), 19);
         assert_eq!(&s[4..5], "-");
@@ -44,4 +123,82 @@ mod tests {
         assert_eq!(&s[13..14], ":");
         assert_eq!(&s[16..17], ":");
     }
+
+    /// 同一个进程跨过 DST 切换：每个时刻按自己的 offset 格式化，而不是
+    /// 沿用进程启动时的 offset。纽约 2026-03-08 02:00 EST → 03:00 EDT，
+    /// 2026-11-01 02:00 EDT → 01:00 EST。
+    #[test]
+    fn format_local_follows_dst_per_instant() {
+        assert_eq!(
+            format_local(utc("2026-03-08 06:59:59"), New_York),
+            "2026-03-08 01:59:59"
+        );
+        assert_eq!(
+            format_local(utc("2026-03-08 07:00:00"), New_York),
+            "2026-03-08 03:00:00"
+        );
+        assert_eq!(
+            format_local(utc("2026-11-01 05:30:00"), New_York),
+            "2026-11-01 01:30:00"
+        );
+        assert_eq!(
+            format_local(utc("2026-11-01 06:30:00"), New_York),
+            "2026-11-01 01:30:00"
+        );
+        // 伦敦夏令时：UTC+1
+        assert_eq!(
+            format_local(utc("2026-07-01 12:00:00"), London),
+            "2026-07-01 13:00:00"
+        );
+        assert_eq!(
+            format_local(utc("2026-12-01 12:00:00"), London),
+            "2026-12-01 12:00:00"
+        );
+    }
+
+    #[test]
+    fn resolve_local_handles_gap_and_fold() {
+        // 普通时刻
+        let d = resolve_local(naive("2026-07-01 09:00:00"), New_York);
+        assert_eq!(d.with_timezone(&Utc), utc("2026-07-01 13:00:00"));
+        // 回拨重叠：01:30 出现两次，取较早（EDT）那次
+        let d = resolve_local(naive("2026-11-01 01:30:00"), New_York);
+        assert_eq!(d.with_timezone(&Utc), utc("2026-11-01 05:30:00"));
+        // 拨快跳过：02:30 不存在，顺延为 03:30 EDT
+        let d = resolve_local(naive("2026-03-08 02:30:00"), New_York);
+        assert_eq!(d.with_timezone(&Utc), utc("2026-03-08 07:30:00"));
+        assert_eq!(d.naive_local(), naive("2026-03-08 03:30:00"));
+    }
+
+    #[test]
+    fn convert_local_between_zones_across_dst() {
+        // 纽约冬令时 20:00 = 上海次日 09:00；夏令时 20:00 = 上海次日 08:00
+        assert_eq!(
+            convert_local(naive("2026-01-15 20:00:00"), New_York, Shanghai),
+            naive("2026-01-16 09:00:00")
+        );
+        assert_eq!(
+            convert_local(naive("2026-07-15 20:00:00"), New_York, Shanghai),
+            naive("2026-07-16 08:00:00")
+        );
+        assert_eq!(
+            convert_local(naive("2026-07-15 20:00:00"), Shanghai, Shanghai),
+            naive("2026-07-15 20:00:00")
+        );
+    }
+
+    #[test]
+    fn clock_today_is_per_client_zone() {
+        // UTC 2026-05-13 20:00：上海已是 14 日，纽约还是 13 日
+        let clock = Clock {
+            now: utc("2026-05-13 20:00:00"),
+            client: New_York,
+            storage: Shanghai,
+        };
+        assert_eq!(clock.today(), NaiveDate::from_ymd_opt(2026, 5, 13).unwrap());
+        assert_eq!(
+            clock.in_storage(clock.client_now()),
+            naive("2026-05-14 04:00:00")
+        );
+    }
 }
@@ -0,0 +1,320 @@
+//! 内置 TLS（配置了 `tls_cert` / `tls_key` 时启用）。
+//!
+//! - 证书链 / 私钥都是 PEM；私钥支持 PKCS#8 / PKCS#1 / SEC1，启动时必须能
+//!   加载且与证书匹配，否则直接退出
+//! - 后台每 [`CERT_POLL_INTERVAL`] 看一次两个文件的 mtime，变了就重新加载，
+//!   新握手立即用新证书（certbot / acme.sh 续期后不用重启）；加载失败（比如
+//!   只换了证书、私钥还没写完）沿用旧证书，下次文件再变时重试
+//! - axum 0.7 的 `serve` 只接受 `TcpListener`，TLS 连接由 [`serve`] 自己
+//!   accept → 握手 → 交给 hyper；与 `axum::serve` 一样只开 HTTP/1.1，
+//!   收到退出信号后停止 accept 并等在途连接处理完
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use axum::extract::ConnectInfo;
+use axum::Router;
+use hyper::body::Incoming;
+use hyper::server::conn::http1;
+use hyper_util::rt::TokioIo;
+use hyper_util::server::graceful::GracefulShutdown;
+use hyper_util::service::TowerToHyperService;
+use rustls_pki_types::pem::PemObject;
+use rustls_pki_types::{CertificateDer, PrivateKeyDer};
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt as _;
+use tracing::{debug, error, info, warn};
+
+use crate::shutdown::Shutdown;
+
+/// 检查证书文件是否更新的间隔。
+pub const CERT_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 单个连接完成 TLS 握手的上限，防止半开连接一直占着 task。
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 握手时返回"当前"证书的 resolver；[`CertReloader`] 在后台替换它。
+#[derive(Debug)]
+struct CurrentCert(RwLock<Arc<CertifiedKey>>);
+
+impl ResolvesServerCert for CurrentCert {
+    fn resolve(&self, _hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.0
+                .read()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .clone(),
+        )
+    }
+}
+
+/// 证书 / 私钥文件与当前生效的证书。
+pub struct CertReloader {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    current: Arc<CurrentCert>,
+    seen: (Option<SystemTime>, Option<SystemTime>),
+}
+
+impl CertReloader {
+    /// 加载证书，失败直接返回错误（启动阶段用）。
+    pub fn load(cert_path: &Path, key_path: &Path) -> anyhow::Result<Self> {
+        let seen = mtimes(cert_path, key_path);
+        let key = load_certified_key(cert_path, key_path)?;
+        Ok(CertReloader {
+            cert_path: cert_path.to_path_buf(),
+            key_path: key_path.to_path_buf(),
+            current: Arc::new(CurrentCert(RwLock::new(Arc::new(key)))),
+            seen,
+        })
+    }
+
+    /// 基于当前证书构造 acceptor。之后换证书无需重建。
+    pub fn acceptor(&self) -> anyhow::Result<TlsAcceptor> {
+        let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| anyhow::anyhow!("初始化 TLS 失败: {}", e))?
+            .with_no_client_auth()
+            .with_cert_resolver(self.current.clone());
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+
+    /// 文件 mtime 变了就重新加载。返回是否换上了新证书。
+    pub fn reload_if_changed(&mut self) -> bool {
+        let now = mtimes(&self.cert_path, &self.key_path);
+        if now == self.seen {
+            return false;
+        }
+        // 失败也记下这次的 mtime：证书和私钥往往分两步写，等另一个文件也
+        // 变了再试，而不是每个周期刷一条同样的错误
+        self.seen = now;
+        match load_certified_key(&self.cert_path, &self.key_path) {
+            Ok(key) => {
+                *self
+                    .current
+                    .0
+                    .write()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(key);
+                info!(
+                    target: "minitodo_cloud",
+                    "TLS 证书已重新加载: {}",
+                    self.cert_path.display()
+                );
+                true
+            }
+            Err(e) => {
+                error!(
+                    target: "minitodo_cloud",
+                    "TLS 证书文件有变化但加载失败，继续使用旧证书: {:#}", e
+                );
+                false
+            }
+        }
+    }
+
+    /// 后台轮询，直到进程退出。
+    pub fn spawn_watcher(mut self) {
+        tokio::spawn(async move {
+            let mut tick = tokio::time::interval(CERT_POLL_INTERVAL);
+            tick.tick().await;
+            loop {
+                tick.tick().await;
+                self.reload_if_changed();
+            }
+        });
+    }
+}
+
+fn mtimes(cert: &Path, key: &Path) -> (Option<SystemTime>, Option<SystemTime>) {
+    let mtime = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    (mtime(cert), mtime(key))
+}
+
+fn load_certified_key(cert_path: &Path, key_path: &Path) -> anyhow::Result<CertifiedKey> {
+    let chain = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|it| it.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| anyhow::anyhow!("读取证书 {} 失败: {}", cert_path.display(), e))?;
+    if chain.is_empty() {
+        anyhow::bail!("证书 {} 里没有 CERTIFICATE 段", cert_path.display());
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| anyhow::anyhow!("读取私钥 {} 失败: {}", key_path.display(), e))?;
+    CertifiedKey::from_der(chain, key, &ring::default_provider()).map_err(|e| {
+        anyhow::anyhow!(
+            "证书 {} 与私钥 {} 无法配对: {}",
+            cert_path.display(),
+            key_path.display(),
+            e
+        )
+    })
+}
+
+/// TLS 版的 `axum::serve(..).with_graceful_shutdown(..)`。
+///
+/// 收到退出信号后立刻停止 accept（drop listener），然后等已建立的连接把
+/// 在途请求处理完才返回；调用方自己决定最多等多久。
+pub async fn serve(listener: TcpListener, router: Router, acceptor: TlsAcceptor, stop: Shutdown) {
+    let graceful = GracefulShutdown::new();
+    let stop = stop.wait();
+    tokio::pin!(stop);
+
+    loop {
+        let (tcp, peer) = tokio::select! {
+            res = listener.accept() => match res {
+                Ok(conn) => conn,
+                Err(e) => {
+                    // EMFILE 之类的瞬时错误：稍等再 accept，别空转
+                    warn!(target: "minitodo_cloud", "accept 失败: {}", e);
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+            },
+            _ = &mut stop => break,
+        };
+
+        let acceptor = acceptor.clone();
+        // 与 plain HTTP 的 into_make_service_with_connect_info 一致，限流要用
+        let service = TowerToHyperService::new(router.clone().map_request(
+            move |mut req: axum::extract::Request<Incoming>| {
+                req.extensions_mut().insert(ConnectInfo(peer));
+                req
+            },
+        ));
+        let watcher = graceful.watcher();
+        tokio::spawn(async move {
+            let tls = match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(tcp)).await {
+                Ok(Ok(tls)) => tls,
+                Ok(Err(e)) => {
+                    debug!(target: "minitodo_cloud", "TLS 握手失败 ({}): {}", peer, e);
+                    return;
+                }
+                Err(_) => {
+                    debug!(target: "minitodo_cloud", "TLS 握手超时 ({})", peer);
+                    return;
+                }
+            };
+            let conn = http1::Builder::new().serve_connection(TokioIo::new(tls), service);
+            if let Err(e) = watcher.watch(conn).await {
+                debug!(target: "minitodo_cloud", "连接 {} 异常结束: {}", peer, e);
+            }
+        });
+    }
+
+    drop(listener);
+    graceful.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    //! 证书在 `testdata/tls/`：一张测试 CA 签的两张 `localhost` / `127.0.0.1`
+    //! 服务端证书（a、b），有效期 100 年。
+
+    use super::*;
+    use axum::routing::get;
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+    use tokio_rustls::TlsConnector;
+
+    const CA: &str = include_str!("../testdata/tls/ca.pem");
+    const CERT_A: &str = include_str!("../testdata/tls/a.crt.pem");
+    const KEY_A: &str = include_str!("../testdata/tls/a.key.pem");
+    const CERT_B: &str = include_str!("../testdata/tls/b.crt.pem");
+    const KEY_B: &str = include_str!("../testdata/tls/b.key.pem");
+
+    fn write_pair(dir: &Path, cert: &str, key: &str) -> (PathBuf, PathBuf) {
+        let (c, k) = (dir.join("cert.pem"), dir.join("key.pem"));
+        std::fs::write(&c, cert).unwrap();
+        std::fs::write(&k, key).unwrap();
+        (c, k)
+    }
+
+    /// 用测试 CA 建 TLS 连接，发一个 GET，返回服务端证书与响应文本。
+    async fn fetch(addr: std::net::SocketAddr) -> (Vec<u8>, String) {
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(CertificateDer::from_pem_slice(CA.as_bytes()).unwrap())
+            .unwrap();
+        let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let tcp = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut tls = TlsConnector::from(Arc::new(config))
+            .connect("localhost".try_into().unwrap(), tcp)
+            .await
+            .unwrap();
+        let peer = tls.get_ref().1.peer_certificates().unwrap()[0].to_vec();
+        tls.write_all(b"GET /ping HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        let mut body = String::new();
+        tls.read_to_string(&mut body).await.unwrap();
+        (peer, body)
+    }
+
+    fn der(pem: &str) -> Vec<u8> {
+        CertificateDer::from_pem_slice(pem.as_bytes())
+            .unwrap()
+            .to_vec()
+    }
+
+    #[test]
+    fn load_rejects_mismatched_or_missing_files() {
+        let tmp = TempDir::new().unwrap();
+        let (c, k) = write_pair(tmp.path(), CERT_A, KEY_B);
+        let err = CertReloader::load(&c, &k).err().unwrap();
+        assert!(format!("{:#}", err).contains("无法配对"), "{:#}", err);
+
+        let (c, k) = write_pair(tmp.path(), CERT_A, KEY_A);
+        assert!(CertReloader::load(&c, &k).is_ok());
+        assert!(CertReloader::load(&c, &tmp.path().join("missing.pem")).is_err());
+        assert!(CertReloader::load(&k, &k).is_err());
+    }
+
+    #[tokio::test]
+    async fn serves_https_and_picks_up_rotated_cert() {
+        let tmp = TempDir::new().unwrap();
+        let (c, k) = write_pair(tmp.path(), CERT_A, KEY_A);
+        let mut reloader = CertReloader::load(&c, &k).unwrap();
+        let acceptor = reloader.acceptor().unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = Router::new().route("/ping", get(|| async { "pong" }));
+        let (tx, rx) = tokio::sync::watch::channel(false);
+        let server = tokio::spawn(serve(listener, router, acceptor, Shutdown::from(rx)));
+
+        let (peer, body) = fetch(addr).await;
+        assert_eq!(peer, der(CERT_A));
+        assert!(body.starts_with("HTTP/1.1 200"), "{}", body);
+        assert!(body.ends_with("pong"), "{}", body);
+
+        // 半截轮换（只换了证书）：沿用旧证书
+        std::fs::write(&c, CERT_B).unwrap();
+        reloader.seen = (None, None);
+        assert!(!reloader.reload_if_changed());
+        assert_eq!(fetch(addr).await.0, der(CERT_A));
+
+        // 私钥也到位后换上新证书，无需重启
+        std::fs::write(&k, KEY_B).unwrap();
+        reloader.seen = (None, None);
+        assert!(reloader.reload_if_changed());
+        assert!(!reloader.reload_if_changed());
+        assert_eq!(fetch(addr).await.0, der(CERT_B));
+
+        tx.send(true).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("收到退出信号后应停止")
+            .unwrap();
+    }
+}
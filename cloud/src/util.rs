@@ -1,8 +1,11 @@
 //! 跨模块共享的小工具。
 //!
-//! 当前只有 `id_string`：从 `serde_json::Value` 的 `"id"` 字段提取字符串形式的
-//! id。PC 端 todo / subtask 的 `id` 列是 SQLite `INTEGER PRIMARY KEY AUTOINCREMENT`
-//! (即 i64)；云端把它统一转字符串作为 KV-style PK 使用。
+//! - `id_string`：从 `serde_json::Value` 的 `"id"` 字段提取字符串形式的 id。
+//!   PC 端 todo / subtask 的 `id` 列是 SQLite `INTEGER PRIMARY KEY AUTOINCREMENT`
+//!   (即 i64)；云端把它统一转字符串作为 KV-style PK 使用。
+//! - `disk_free_bytes`：目录所在文件系统对非 root 用户可用的字节数。
+
+use std::path::Path;
 
 use serde_json::Value;
 
@@ -24,6 +27,28 @@ pub fn id_string(v: &Value) -> Option<String> {
     None
 }
 
+/// `path` 所在文件系统的可用空间（`statvfs` 的 `f_bavail * f_frsize`）。
+/// 路径不存在或非 unix 平台返回 None。
+#[cfg(unix)]
+pub fn disk_free_bytes(path: &Path) -> Option<u64> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut st: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path 是以 NUL 结尾的合法 C 字符串，st 是可写的 statvfs 结构体
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut st) } != 0 {
+        return None;
+    }
+    #[allow(clippy::unnecessary_cast)] // 各平台 statvfs 字段宽度不同
+    Some(st.f_bavail as u64 * st.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn disk_free_bytes(_path: &Path) -> Option<u64> {
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,139 @@
+//! mini-todo 数据模型的字段规则，PC 与 cloud 共用：两边都按这里判断合不合法，
+//! 错误提示各自给（PC 中文、cloud 英文）。改规则只改这里，两边不会再各写一份。
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+/// PC 数据库里的日期时间格式：前端写 `YYYY-MM-DDTHH:MM:SS`，SQLite
+/// `datetime('now','localtime')` 与 cloud 写的是空格分隔。
+pub const DATETIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+];
+
+/// 纯日期格式（`dueDate`）。
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// `priority` 的取值，过滤 / 排序里 high > medium > low。
+pub const PRIORITIES: [&str; 3] = ["high", "medium", "low"];
+
+/// 标签名最长字符数：标签名要进 URL path 与 `?tag=`，长度做基本限制。
+pub const MAX_TAG_LEN: usize = 32;
+
+/// 标签名不合规的原因，见 [`check_tag_name`]。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagNameError {
+    /// 空串或全空白
+    Blank,
+    /// 首尾有空白
+    Padded,
+    /// 超过 [`MAX_TAG_LEN`] 个字符
+    TooLong,
+    /// 以 `-` 开头
+    LeadingDash,
+    /// 含 `/`、`,` 或控制字符
+    ForbiddenChar,
+}
+
+/// 标签名：非空、首尾无空白、不超过 [`MAX_TAG_LEN`] 个字符；不能以 `-` 开头
+/// （cloud 的 `?tag=-x` 表示排除），不能含 `/`（进不了 URL path）、`,`
+/// （`?tag=a,b`）和控制字符。
+pub fn check_tag_name(name: &str) -> Result<(), TagNameError> {
+    if name.trim().is_empty() {
+        return Err(TagNameError::Blank);
+    }
+    if name.trim() != name {
+        return Err(TagNameError::Padded);
+    }
+    if name.chars().count() > MAX_TAG_LEN {
+        return Err(TagNameError::TooLong);
+    }
+    if name.starts_with('-') {
+        return Err(TagNameError::LeadingDash);
+    }
+    if name.chars().any(|c| c == '/' || c == ',' || c.is_control()) {
+        return Err(TagNameError::ForbiddenChar);
+    }
+    Ok(())
+}
+
+pub fn is_priority(s: &str) -> bool {
+    PRIORITIES.contains(&s)
+}
+
+/// 按 [`DATETIME_FORMATS`] 任一格式解析，返回命中的格式。
+pub fn parse_datetime(s: &str) -> Option<(NaiveDateTime, &'static str)> {
+    DATETIME_FORMATS.iter().find_map(|fmt| {
+        NaiveDateTime::parse_from_str(s, fmt)
+            .ok()
+            .map(|dt| (dt, *fmt))
+    })
+}
+
+/// `dueDate`：`YYYY-MM-DD`，也接受带时间的写法；空串视同未设置（截止取
+/// `endTime`）。
+pub fn is_due_date(s: &str) -> bool {
+    s.is_empty() || NaiveDate::parse_from_str(s, DATE_FORMAT).is_ok() || parse_datetime(s).is_some()
+}
+
+/// 日期时间（[`DATETIME_FORMATS`]）或纯日期字符串平移 `days` 天，保持原格式。
+/// `days` 为 0、空串、认不出的格式或超出日期范围时返回 `None`，调用方原样保留。
+pub fn shift_days(s: &str, days: i64) -> Option<String> {
+    if days == 0 {
+        return None;
+    }
+    let delta = Duration::try_days(days)?;
+    if let Some((dt, fmt)) = parse_datetime(s) {
+        return dt
+            .checked_add_signed(delta)
+            .map(|dt| dt.format(fmt).to_string());
+    }
+    NaiveDate::parse_from_str(s, DATE_FORMAT)
+        .ok()?
+        .checked_add_signed(delta)
+        .map(|d| d.format(DATE_FORMAT).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_name_rules() {
+        assert_eq!(check_tag_name("工作"), Ok(()));
+        assert_eq!(check_tag_name(&"x".repeat(MAX_TAG_LEN)), Ok(()));
+        assert_eq!(check_tag_name(" "), Err(TagNameError::Blank));
+        assert_eq!(check_tag_name(" work"), Err(TagNameError::Padded));
+        assert_eq!(
+            check_tag_name(&"x".repeat(MAX_TAG_LEN + 1)),
+            Err(TagNameError::TooLong)
+        );
+        assert_eq!(check_tag_name("-work"), Err(TagNameError::LeadingDash));
+        assert_eq!(check_tag_name("a,b"), Err(TagNameError::ForbiddenChar));
+        assert_eq!(check_tag_name("a\tb"), Err(TagNameError::ForbiddenChar));
+    }
+
+    #[test]
+    fn due_date_and_priority() {
+        assert!(is_due_date(""));
+        assert!(is_due_date("2026-05-20"));
+        assert!(is_due_date("2026-05-20 18:00"));
+        assert!(!is_due_date("2026/05/20"));
+        assert!(is_priority("high"));
+        assert!(!is_priority("urgent"));
+    }
+
+    #[test]
+    fn shift_days_keeps_format() {
+        assert_eq!(shift_days("2026-05-20", 3).as_deref(), Some("2026-05-23"));
+        assert_eq!(
+            shift_days("2026-05-31T09:00", 1).as_deref(),
+            Some("2026-06-01T09:00")
+        );
+        assert_eq!(shift_days("2026-05-20", 0), None);
+        assert_eq!(shift_days("", 1), None);
+        assert_eq!(shift_days("2026-05-20", i64::MAX), None);
+        assert_eq!(shift_days("2026-05-20", 999_999_999), None);
+    }
+}
@@ -1,6 +1,8 @@
+use super::tag::write_todo_tags;
 use crate::db::{
-    subtask_from_row, todo_from_row, AppSettings, Database, ExportData, Todo, WindowPosition,
-    WindowSize, DEFAULT_WINDOW_BG_ALPHA, DEFAULT_WINDOW_BG_COLOR, SUBTASK_COLUMNS, TODO_COLUMNS,
+    subtask_from_row, tag_from_row, template_from_row, todo_from_row, AppSettings, Database,
+    ExportData, Tag, Todo, TodoTemplate, WindowPosition, WindowSize, DEFAULT_WINDOW_BG_ALPHA,
+    DEFAULT_WINDOW_BG_COLOR, SUBTASK_COLUMNS, TAG_COLUMNS, TEMPLATE_COLUMNS, TODO_COLUMNS,
 };
 use chrono::Local;
 use rusqlite::params;
@@ -56,6 +58,8 @@ fn read_app_settings(conn: &rusqlite::Connection) -> AppSettings {
     let show_calendar = get_setting_bool(conn, "show_calendar", false);
     let view_mode = get_setting_string(conn, "view_mode", "list");
     let notification_type = get_setting_string(conn, "notification_type", "system");
+    let saved_views =
+        serde_json::from_str(&get_setting_string(conn, "saved_views", "[]")).unwrap_or_default();
 
     AppSettings {
         is_fixed,
@@ -69,6 +73,7 @@ fn read_app_settings(conn: &rusqlite::Connection) -> AppSettings {
         show_calendar,
         view_mode,
         notification_type,
+        saved_views,
     }
 }
 
@@ -123,6 +128,12 @@ pub(crate) fn write_app_settings(conn: &rusqlite::Connection, settings: &AppSett
         "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES ('notification_type', ?1, datetime('now', 'localtime'))",
         [&settings.notification_type],
     )?;
+    let views_json =
+        serde_json::to_string(&settings.saved_views).unwrap_or_else(|_| "[]".to_string());
+    conn.execute(
+        "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES ('saved_views', ?1, datetime('now', 'localtime'))",
+        [&views_json],
+    )?;
     Ok(())
 }
 
@@ -147,16 +158,35 @@ pub fn export_data_internal(db: &Database) -> Result<String, String> {
 
         let settings = read_app_settings(conn);
 
-        Ok((todos, settings))
+        let template_sql = format!(
+            "SELECT {} FROM todo_templates ORDER BY id ASC",
+            TEMPLATE_COLUMNS
+        );
+        let mut template_stmt = conn.prepare(&template_sql)?;
+        let templates: Vec<TodoTemplate> = template_stmt
+            .query_map([], template_from_row)?
+            .filter_map(|t| t.ok())
+            .collect();
+
+        let tag_sql = format!("SELECT {} FROM tags ORDER BY name ASC", TAG_COLUMNS);
+        let mut tag_stmt = conn.prepare(&tag_sql)?;
+        let tags: Vec<Tag> = tag_stmt
+            .query_map([], tag_from_row)?
+            .filter_map(|t| t.ok())
+            .collect();
+
+        Ok((todos, settings, templates, tags))
     });
 
     match result {
-        Ok((todos, settings)) => {
+        Ok((todos, settings, templates, tags)) => {
             let export_data = ExportData {
                 version: "4.0".to_string(),
                 exported_at: Local::now().format("%Y-%m-%dT%H:%M:%S%:z").to_string(),
                 todos,
                 settings,
+                templates,
+                tags,
             };
             serde_json::to_string_pretty(&export_data).map_err(|e| e.to_string())
         }
@@ -170,6 +200,8 @@ pub fn export_data_internal(db: &Database) -> Result<String, String> {
 /// task_dependencies / prompt_templates / agent_executions 字段以及 todo / subtask
 /// 上的 agent / 调度 / 工作流字段，会被 serde 在反序列化阶段静默忽略
 /// （`ExportData` / `Todo` / `SubTask` 在 v2.0 后不再声明这些字段）。
+/// todo 模板同样整表替换；没有 `templates` 字段的旧备份导入后模板为空。
+/// 标签登记表也整表替换，再按每条待办的 `tags` 重建关联（没登记的按默认颜色补上）。
 ///
 /// 整个导入体（清空 + 逐条 INSERT + 写设置）包在单个 `BEGIN IMMEDIATE` 事务里：
 /// 中途任一语句失败时旧数据整体回滚，不会出现"旧数据已删、新数据只写了半截"
@@ -189,6 +221,16 @@ pub fn import_data_raw(db: &Database, json_data: &str) -> Result<(), String> {
 
         tx.execute("DELETE FROM subtasks", [])?;
         tx.execute("DELETE FROM todos", [])?;
+        tx.execute("DELETE FROM todo_templates", [])?;
+        tx.execute("DELETE FROM tags", [])?;
+
+        for tag in &import.tags {
+            tx.execute(
+                "INSERT OR IGNORE INTO tags (name, color, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![tag.name, tag.color, tag.created_at, tag.updated_at],
+            )?;
+        }
 
         for todo in &import.todos {
             let notified_i = if todo.notified { 1i32 } else { 0 };
@@ -198,9 +240,10 @@ pub fn import_data_raw(db: &Database, json_data: &str) -> Result<(), String> {
             tx.execute(
                 "INSERT INTO todos (title, description, color, quadrant, notify_at, notify_before,
                                     notified, completed, sort_order, start_time, end_time, created_at, updated_at,
-                                    repeat_enabled, repeat_type, repeat_interval, repeat_weekdays, repeat_month_day)
+                                    repeat_enabled, repeat_type, repeat_interval, repeat_weekdays, repeat_month_day,
+                                    completed_at, priority, due_date)
                  VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13,
-                         ?14, ?15, ?16, ?17, ?18)",
+                         ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21)",
                 params![
                     todo.title, todo.description, todo.color, todo.quadrant,
                     todo.notify_at, todo.notify_before,
@@ -209,10 +252,12 @@ pub fn import_data_raw(db: &Database, json_data: &str) -> Result<(), String> {
                     todo.created_at, todo.updated_at,
                     repeat_enabled_i, todo.repeat_type, todo.repeat_interval,
                     todo.repeat_weekdays, todo.repeat_month_day,
+                    todo.completed_at, todo.priority, todo.due_date,
                 ],
             )?;
 
             let new_todo_id = tx.last_insert_rowid();
+            write_todo_tags(&tx, new_todo_id, &todo.tags)?;
 
             for subtask in &todo.subtasks {
                 let sub_completed_i = if subtask.completed { 1i32 } else { 0 };
@@ -228,6 +273,20 @@ pub fn import_data_raw(db: &Database, json_data: &str) -> Result<(), String> {
             }
         }
 
+        for template in &import.templates {
+            let subtasks =
+                serde_json::to_string(&template.subtasks).unwrap_or_else(|_| "[]".to_string());
+            tx.execute(
+                "INSERT INTO todo_templates (name, title, description, color, quadrant, subtasks, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    template.name, template.title, template.description,
+                    template.color, template.quadrant, subtasks,
+                    template.created_at, template.updated_at,
+                ],
+            )?;
+        }
+
         write_app_settings(&tx, &import.settings)?;
 
         tx.commit()?;
@@ -323,6 +382,10 @@ mod tests {
             repeat_interval: 1,
             repeat_weekdays: None,
             repeat_month_day: None,
+            completed_at: None,
+            priority: None,
+            due_date: None,
+            tags: Vec::new(),
             subtasks: Vec::new(),
         }
     }
@@ -357,7 +420,10 @@ mod tests {
                 show_calendar: false,
                 view_mode: "list".to_string(),
                 notification_type: "system".to_string(),
+                saved_views: Vec::new(),
             },
+            templates: Vec::new(),
+            tags: Vec::new(),
         };
         serde_json::to_string(&data).expect("序列化导出数据失败")
     }
@@ -435,4 +501,43 @@ mod tests {
         assert_eq!(titles(&db), vec!["已有待办".to_string()]);
         assert_eq!(count(&db, "subtasks"), 1);
     }
+
+    #[test]
+    fn import_rebuilds_tags_and_keeps_colors() {
+        let db = test_db();
+        db.with_connection(|conn| {
+            conn.execute("INSERT INTO tags (name) VALUES ('旧标签')", [])?;
+            Ok(())
+        })
+        .expect("写入初始标签失败");
+
+        let mut t = make_todo(100, "导入待办");
+        t.tags = vec!["work".to_string(), "home".to_string()];
+        let mut data: ExportData =
+            serde_json::from_str(&export_json(vec![t])).expect("反序列化失败");
+        data.tags.push(Tag {
+            id: 7,
+            name: "work".to_string(),
+            color: "#EF4444".to_string(),
+            created_at: "2026-01-01 00:00:00".to_string(),
+            updated_at: "2026-01-01 00:00:00".to_string(),
+        });
+        import_data_raw(&db, &serde_json::to_string(&data).unwrap()).expect("导入应当成功");
+
+        let exported: ExportData =
+            serde_json::from_str(&export_data_internal(&db).unwrap()).expect("导出失败");
+        assert_eq!(exported.todos[0].tags, vec!["work", "home"]);
+        let tags: Vec<(String, String)> = exported
+            .tags
+            .into_iter()
+            .map(|t| (t.name, t.color))
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                ("home".to_string(), crate::db::DEFAULT_TAG_COLOR.to_string()),
+                ("work".to_string(), "#EF4444".to_string()),
+            ]
+        );
+    }
 }
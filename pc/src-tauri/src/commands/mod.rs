@@ -3,6 +3,8 @@ pub mod holiday;
 pub mod notification_cmd;
 pub mod settings_cmd;
 pub mod sync_cmd;
+pub mod tag;
+pub mod template;
 pub mod todo;
 pub mod window;
 
@@ -11,5 +13,7 @@ pub use holiday::*;
 pub use notification_cmd::*;
 pub use settings_cmd::*;
 pub use sync_cmd::*;
+pub use tag::*;
+pub use template::*;
 pub use todo::*;
 pub use window::*;
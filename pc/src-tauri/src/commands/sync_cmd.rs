@@ -7,11 +7,12 @@
 //!   远端，做 per-record LWW merge 到本地 SQLite，再重新 PUT，最多重试 3 次
 //!
 //! sync 下载路径（`webdav_apply_remote`、`webdav_auto_sync`）使用 per-record merge +
-//! 孤儿清理：先 LWW 合并远端 todos/subtasks，再删除"本地有但远端没有"的记录，
-//! 最后写入远端 settings。`import_data_raw` 仅供手动文件导入使用。
+//! 孤儿清理：先 LWW 合并远端 tags/todos/subtasks/templates，再删除"本地有但远端没有"的
+//! 记录，最后写入远端 settings。`import_data_raw` 仅供手动文件导入使用。
 
 use super::data::{export_data_internal, write_app_settings};
-use crate::db::{Database, SubTask, Todo};
+use super::tag::write_todo_tags;
+use crate::db::{Database, SubTask, Tag, Todo, TodoTemplate};
 use crate::services::webdav::{UploadOutcome, WebDavClient};
 use flate2::read::GzDecoder;
 use flate2::write::GzEncoder;
@@ -139,6 +140,14 @@ pub struct SyncData {
     pub todos: Vec<serde_json::Value>,
     pub settings: serde_json::Value,
     pub images: Vec<String>,
+    /// todo 模板（格式同 `TodoTemplate`）。旧版本写出的 sync-data 没有这个 key，
+    /// 反序列化为 `None`，此时不做模板的孤儿清理，免得把本地模板当孤儿删掉。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub templates: Option<Vec<serde_json::Value>>,
+    /// 标签登记（格式同 `Tag`，按 name 对齐）。同 `templates`，旧数据没有这个 key
+    /// 时不做标签的孤儿清理。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<serde_json::Value>>,
 }
 
 #[tauri::command]
@@ -201,6 +210,20 @@ pub fn webdav_upload_sync(db: State<Database>) -> Result<String, String> {
                 .cloned()
                 .unwrap_or(serde_json::Value::Null),
             images: image_files.clone(),
+            templates: Some(
+                export_data
+                    .get("templates")
+                    .and_then(|v| v.as_array())
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
+            tags: Some(
+                export_data
+                    .get("tags")
+                    .and_then(|v| v.as_array())
+                    .cloned()
+                    .unwrap_or_default(),
+            ),
         };
 
         let sync_json = serde_json::to_string(&sync_data).map_err(|e| e.to_string())?;
@@ -510,7 +533,21 @@ fn check_local_changes(db: &State<Database>, settings: &SyncSettings) -> Result<
                 |row| row.get(0),
             )
             .unwrap_or(0);
-        Ok(todo_count > 0 || subtask_count > 0)
+        let template_count: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM todo_templates WHERE updated_at > ?1",
+                [last_sync],
+                |row| row.get(0),
+            )
+            .unwrap_or(0);
+        let tag_count: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM tags WHERE updated_at > ?1",
+                [last_sync],
+                |row| row.get(0),
+            )
+            .unwrap_or(0);
+        Ok(todo_count > 0 || subtask_count > 0 || template_count > 0 || tag_count > 0)
     })
     .map_err(|e| e.to_string())
 }
@@ -549,12 +586,19 @@ pub struct MergeStats {
     pub subtasks_updated: u32,
     pub subtasks_inserted: u32,
     pub subtasks_deleted: u32,
+    pub templates_updated: u32,
+    pub templates_inserted: u32,
+    pub templates_deleted: u32,
+    pub tags_updated: u32,
+    pub tags_inserted: u32,
+    pub tags_deleted: u32,
 }
 
 /// per-record LWW merge：把远端 `SyncData` 合并进本地 SQLite。
 ///
 /// 语义：
-/// - 远端 todo / subtask 的 `updatedAt` ≥ 本地 → upsert 远端字段
+/// - 远端 todo / subtask / template / tag 的 `updatedAt` ≥ 本地 → upsert 远端字段
+///   （tag 按 name 对齐，其余按 id）
 /// - 本地 `updatedAt` > 远端 → 保留本地（不动）
 /// - 远端有、本地无 → 直接 INSERT（用远端 id）
 /// - 本地有、远端无 → **保留本地**（不删；412 冲突路径需保留本地新增）
@@ -563,6 +607,9 @@ pub struct MergeStats {
 /// 孤儿清理和 settings 写入由上层 `sync_apply_remote` 负责（sync 下载路径），
 /// 412 冲突路径直接调本函数不做清理。
 ///
+/// 标签先于 todos 合并：远端 todo 挂了本地没登记的标签时会按默认颜色自动登记，
+/// `updated_at` 取当前时间，之后再合远端登记的颜色就 LWW 不过了。
+///
 /// 整个 merge 包在单事务里；中间任何一步失败回滚。
 pub fn merge_remote_into_local(db: &Database, remote: &SyncData) -> Result<MergeStats, String> {
     // 把远端 Vec<serde_json::Value> 反序列化为 Vec<Todo>；Todo 自带 subtasks 嵌套
@@ -571,14 +618,36 @@ pub fn merge_remote_into_local(db: &Database, remote: &SyncData) -> Result<Merge
         .iter()
         .filter_map(|v| serde_json::from_value::<Todo>(v.clone()).ok())
         .collect();
+    let remote_templates: Vec<TodoTemplate> = remote
+        .templates
+        .iter()
+        .flatten()
+        .filter_map(|v| serde_json::from_value::<TodoTemplate>(v.clone()).ok())
+        .collect();
+    let remote_tags: Vec<Tag> = remote
+        .tags
+        .iter()
+        .flatten()
+        .filter_map(|v| serde_json::from_value::<Tag>(v.clone()).ok())
+        .collect();
 
     let stats = db
         .with_connection(|conn| {
             // 单事务封装：savepoint 在 with_connection 内不暴露，直接用 immediate transaction
             conn.execute("BEGIN IMMEDIATE", [])?;
-            let result = merge_todos_inner(conn, &remote_todos);
+            let mut stats = MergeStats::default();
+            let result = remote_tags
+                .iter()
+                .try_for_each(|remote_tag| merge_tag(conn, remote_tag, &mut stats))
+                .and_then(|()| merge_todos_inner(conn, &remote_todos, &mut stats))
+                .and_then(|()| {
+                    for remote_template in &remote_templates {
+                        merge_template(conn, remote_template, &mut stats)?;
+                    }
+                    Ok(())
+                });
             match result {
-                Ok(stats) => {
+                Ok(()) => {
                     conn.execute("COMMIT", [])?;
                     Ok(stats)
                 }
@@ -597,9 +666,8 @@ pub fn merge_remote_into_local(db: &Database, remote: &SyncData) -> Result<Merge
 fn merge_todos_inner(
     conn: &rusqlite::Connection,
     remote_todos: &[Todo],
-) -> rusqlite::Result<MergeStats> {
-    let mut stats = MergeStats::default();
-
+    stats: &mut MergeStats,
+) -> rusqlite::Result<()> {
     for remote_todo in remote_todos {
         let todo_id = remote_todo.id;
         let local_updated_at: Option<String> = conn
@@ -628,8 +696,9 @@ fn merge_todos_inner(
                         completed = ?8, sort_order = ?9, start_time = ?10, end_time = ?11,
                         created_at = ?12, updated_at = ?13,
                         repeat_enabled = ?14, repeat_type = ?15, repeat_interval = ?16,
-                        repeat_weekdays = ?17, repeat_month_day = ?18
-                     WHERE id = ?19",
+                        repeat_weekdays = ?17, repeat_month_day = ?18, completed_at = ?19,
+                        priority = ?20, due_date = ?21
+                     WHERE id = ?22",
                     params![
                         remote_todo.title,
                         remote_todo.description,
@@ -649,6 +718,9 @@ fn merge_todos_inner(
                         remote_todo.repeat_interval,
                         remote_todo.repeat_weekdays,
                         remote_todo.repeat_month_day,
+                        remote_todo.completed_at,
+                        remote_todo.priority,
+                        remote_todo.due_date,
                         todo_id,
                     ],
                 )?;
@@ -659,9 +731,10 @@ fn merge_todos_inner(
                                         notify_at, notify_before, notified, completed,
                                         sort_order, start_time, end_time, created_at, updated_at,
                                         repeat_enabled, repeat_type, repeat_interval,
-                                        repeat_weekdays, repeat_month_day)
+                                        repeat_weekdays, repeat_month_day, completed_at,
+                                        priority, due_date)
                      VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14,
-                             ?15, ?16, ?17, ?18, ?19)",
+                             ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
                     params![
                         todo_id,
                         remote_todo.title,
@@ -682,19 +755,23 @@ fn merge_todos_inner(
                         remote_todo.repeat_interval,
                         remote_todo.repeat_weekdays,
                         remote_todo.repeat_month_day,
+                        remote_todo.completed_at,
+                        remote_todo.priority,
+                        remote_todo.due_date,
                     ],
                 )?;
                 stats.todos_inserted += 1;
             }
+            write_todo_tags(conn, todo_id, &remote_todo.tags)?;
         }
 
         // subtasks 同样 per-record LWW
         for remote_sub in &remote_todo.subtasks {
-            merge_subtask(conn, remote_sub, &mut stats)?;
+            merge_subtask(conn, remote_sub, stats)?;
         }
     }
 
-    Ok(stats)
+    Ok(())
 }
 
 fn merge_subtask(
@@ -761,6 +838,123 @@ fn merge_subtask(
     Ok(())
 }
 
+/// 模板 per-record LWW，语义同 `merge_subtask`。子任务清单是 JSON 文本列，整体覆盖。
+fn merge_template(
+    conn: &rusqlite::Connection,
+    remote: &TodoTemplate,
+    stats: &mut MergeStats,
+) -> rusqlite::Result<()> {
+    let local_updated_at: Option<String> = conn
+        .query_row(
+            "SELECT updated_at FROM todo_templates WHERE id = ?1",
+            [remote.id],
+            |row| row.get::<_, String>(0),
+        )
+        .ok();
+
+    let should_apply = match &local_updated_at {
+        Some(local) => remote.updated_at.as_str() >= local.as_str(),
+        None => true,
+    };
+
+    if !should_apply {
+        return Ok(());
+    }
+
+    let subtasks = serde_json::to_string(&remote.subtasks).unwrap_or_else(|_| "[]".to_string());
+
+    if local_updated_at.is_some() {
+        conn.execute(
+            "UPDATE todo_templates SET
+                name = ?1, title = ?2, description = ?3, color = ?4, quadrant = ?5,
+                subtasks = ?6, created_at = ?7, updated_at = ?8
+             WHERE id = ?9",
+            params![
+                remote.name,
+                remote.title,
+                remote.description,
+                remote.color,
+                remote.quadrant,
+                subtasks,
+                remote.created_at,
+                remote.updated_at,
+                remote.id,
+            ],
+        )?;
+        stats.templates_updated += 1;
+    } else {
+        conn.execute(
+            "INSERT INTO todo_templates (id, name, title, description, color, quadrant,
+                                         subtasks, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                remote.id,
+                remote.name,
+                remote.title,
+                remote.description,
+                remote.color,
+                remote.quadrant,
+                subtasks,
+                remote.created_at,
+                remote.updated_at,
+            ],
+        )?;
+        stats.templates_inserted += 1;
+    }
+
+    Ok(())
+}
+
+/// 标签 per-record LWW，按 name 对齐（`id` 只在本机有意义，不用远端的）。
+fn merge_tag(
+    conn: &rusqlite::Connection,
+    remote: &Tag,
+    stats: &mut MergeStats,
+) -> rusqlite::Result<()> {
+    let local_updated_at: Option<String> = conn
+        .query_row(
+            "SELECT updated_at FROM tags WHERE name = ?1",
+            [&remote.name],
+            |row| row.get::<_, String>(0),
+        )
+        .ok();
+
+    let should_apply = match &local_updated_at {
+        Some(local) => remote.updated_at.as_str() >= local.as_str(),
+        None => true,
+    };
+
+    if !should_apply {
+        return Ok(());
+    }
+
+    if local_updated_at.is_some() {
+        conn.execute(
+            "UPDATE tags SET color = ?1, created_at = ?2, updated_at = ?3 WHERE name = ?4",
+            params![
+                remote.color,
+                remote.created_at,
+                remote.updated_at,
+                remote.name,
+            ],
+        )?;
+        stats.tags_updated += 1;
+    } else {
+        conn.execute(
+            "INSERT INTO tags (name, color, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                remote.name,
+                remote.color,
+                remote.created_at,
+                remote.updated_at,
+            ],
+        )?;
+        stats.tags_inserted += 1;
+    }
+
+    Ok(())
+}
+
 /// sync 下载统一入口：per-record merge + 孤儿清理 + settings 写入。
 ///
 /// 供 `webdav_apply_remote` 和 `webdav_auto_sync` 共用。与 `merge_remote_into_local`
@@ -771,14 +965,26 @@ fn sync_apply_remote(db: &Database, remote: &SyncData) -> Result<(), String> {
     let (todos_deleted, subtasks_deleted) = delete_orphan_todos(db, remote)?;
     stats.todos_deleted = todos_deleted;
     stats.subtasks_deleted = subtasks_deleted;
+    if let Some(templates) = &remote.templates {
+        stats.templates_deleted = delete_orphan_templates(db, templates)?;
+    }
+    if let Some(tags) = &remote.tags {
+        stats.tags_deleted = delete_orphan_tags(db, tags)?;
+    }
     eprintln!(
-        "[sync] 应用远端完成: todos 新增 {} / 更新 {} / 删除 {}, subtasks 新增 {} / 更新 {} / 删除 {}",
+        "[sync] 应用远端完成: todos 新增 {} / 更新 {} / 删除 {}, subtasks 新增 {} / 更新 {} / 删除 {}, templates 新增 {} / 更新 {} / 删除 {}, tags 新增 {} / 更新 {} / 删除 {}",
         stats.todos_inserted,
         stats.todos_updated,
         stats.todos_deleted,
         stats.subtasks_inserted,
         stats.subtasks_updated,
-        stats.subtasks_deleted
+        stats.subtasks_deleted,
+        stats.templates_inserted,
+        stats.templates_updated,
+        stats.templates_deleted,
+        stats.tags_inserted,
+        stats.tags_updated,
+        stats.tags_deleted
     );
 
     // settings：从远端 SyncData 解析并写入
@@ -859,6 +1065,63 @@ fn delete_orphan_todos(db: &Database, remote: &SyncData) -> Result<(u32, u32), S
     .map_err(|e| e.to_string())
 }
 
+/// 删除"本地有但远端没有"的模板，返回删除条数。与 `delete_orphan_todos` 一样
+/// 直接从 `serde_json::Value` 取 id，格式异常的远端模板也不会让本地记录被误删。
+fn delete_orphan_templates(
+    db: &Database,
+    remote_templates: &[serde_json::Value],
+) -> Result<u32, String> {
+    let remote_ids: std::collections::HashSet<i64> = remote_templates
+        .iter()
+        .filter_map(|v| v.get("id")?.as_i64())
+        .collect();
+
+    db.with_connection(|conn| {
+        let mut stmt = conn.prepare("SELECT id FROM todo_templates")?;
+        let local_ids: Vec<i64> = stmt
+            .query_map([], |row| row.get::<_, i64>(0))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let mut deleted = 0u32;
+        for id in local_ids {
+            if !remote_ids.contains(&id) {
+                deleted +=
+                    conn.execute("DELETE FROM todo_templates WHERE id = ?1", [id])? as u32;
+            }
+        }
+        Ok(deleted)
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// 删除"本地有但远端没有"、且没有待办挂着的标签，返回删除条数。待办上的标签
+/// 以待办为准（远端删标签时会连带改写并刷新那些待办），这里只清理登记表。
+fn delete_orphan_tags(db: &Database, remote_tags: &[serde_json::Value]) -> Result<u32, String> {
+    let remote_names: std::collections::HashSet<&str> = remote_tags
+        .iter()
+        .filter_map(|v| v.get("name")?.as_str())
+        .collect();
+
+    db.with_connection(|conn| {
+        let mut stmt = conn
+            .prepare("SELECT id, name FROM tags WHERE id NOT IN (SELECT tag_id FROM todo_tags)")?;
+        let unused: Vec<(i64, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .filter_map(|r| r.ok())
+            .collect();
+
+        let mut deleted = 0u32;
+        for (id, name) in unused {
+            if !remote_names.contains(name.as_str()) {
+                deleted += conn.execute("DELETE FROM tags WHERE id = ?1", [id])? as u32;
+            }
+        }
+        Ok(deleted)
+    })
+    .map_err(|e| e.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -889,6 +1152,10 @@ mod tests {
             repeat_interval: 1,
             repeat_weekdays: None,
             repeat_month_day: None,
+            completed_at: None,
+            priority: None,
+            due_date: None,
+            tags: Vec::new(),
             subtasks: Vec::new(),
         }
     }
@@ -917,6 +1184,8 @@ mod tests {
                 .collect(),
             settings: serde_json::Value::Null,
             images: Vec::new(),
+            templates: None,
+            tags: None,
         }
     }
 
@@ -1078,4 +1347,158 @@ mod tests {
 
         assert_eq!(count_subtasks(&db), 1);
     }
+
+    fn template_json(id: i64, name: &str, updated_at: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id,
+            "name": name,
+            "title": "{{week}} 周报",
+            "description": null,
+            "color": "#10B981",
+            "quadrant": 4,
+            "subtasks": [{"title": "汇总"}],
+            "createdAt": "2026-01-01 00:00:00",
+            "updatedAt": updated_at,
+        })
+    }
+
+    fn template_names(db: &Database) -> Vec<String> {
+        db.with_connection(|conn| {
+            let mut stmt = conn.prepare("SELECT name FROM todo_templates ORDER BY id")?;
+            let rows = stmt.query_map([], |r| r.get::<_, String>(0))?;
+            Ok(rows.filter_map(|r| r.ok()).collect())
+        })
+        .unwrap()
+    }
+
+    /// 模板按 id LWW 合并；远端带 `templates` 时删除本地孤儿，
+    /// 旧版本写出的 sync-data（没有 `templates`）不动本地模板。
+    #[test]
+    fn templates_merge_lww_and_orphan_cleanup() {
+        let db = test_db();
+        let mut data = sync_data(vec![]);
+        data.templates = Some(vec![
+            template_json(1, "周报", "2026-01-05 10:00:00"),
+            template_json(2, "发版", "2026-01-01 10:00:00"),
+        ]);
+        let stats = merge_remote_into_local(&db, &data).unwrap();
+        assert_eq!(stats.templates_inserted, 2);
+
+        data.templates = Some(vec![template_json(1, "远端较旧", "2026-01-02 10:00:00")]);
+        let stats = merge_remote_into_local(&db, &data).unwrap();
+        assert_eq!(stats.templates_updated, 0);
+        assert_eq!(template_names(&db), vec!["周报", "发版"]);
+
+        sync_apply_remote(&db, &sync_data(vec![])).unwrap();
+        assert_eq!(template_names(&db).len(), 2);
+
+        sync_apply_remote(&db, &data).unwrap();
+        assert_eq!(template_names(&db), vec!["周报"]);
+    }
+
+    fn tag_json(name: &str, color: &str, updated_at: &str) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "color": color,
+            "createdAt": "2026-01-01 00:00:00",
+            "updatedAt": updated_at,
+        })
+    }
+
+    fn tag_colors(db: &Database) -> Vec<(String, String)> {
+        db.with_connection(|conn| {
+            let mut stmt = conn.prepare("SELECT name, color FROM tags ORDER BY name")?;
+            let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+            Ok(rows.filter_map(|r| r.ok()).collect())
+        })
+        .unwrap()
+    }
+
+    /// 标签按 name LWW；远端 todo 上的标签先于自动登记拿到远端颜色；
+    /// 孤儿清理只删没有待办挂着的标签。
+    #[test]
+    fn tags_merge_by_name_and_orphan_cleanup() {
+        let db = test_db();
+        let mut t = make_todo(1, "远端", "2026-01-05 10:00:00");
+        t.tags = vec!["work".to_string(), "home".to_string()];
+        let mut data = sync_data(vec![t]);
+        data.tags = Some(vec![
+            tag_json("work", "#EF4444", "2026-01-05 10:00:00"),
+            tag_json("old", "#3B82F6", "2026-01-01 10:00:00"),
+        ]);
+        let stats = merge_remote_into_local(&db, &data).unwrap();
+        assert_eq!(stats.tags_inserted, 2);
+        assert_eq!(
+            tag_colors(&db),
+            vec![
+                ("home".to_string(), crate::db::DEFAULT_TAG_COLOR.to_string()),
+                ("old".to_string(), "#3B82F6".to_string()),
+                ("work".to_string(), "#EF4444".to_string()),
+            ]
+        );
+        let tags = db
+            .with_connection(|conn| {
+                let sql = format!("SELECT {} FROM todos WHERE id = 1", crate::db::TODO_COLUMNS);
+                conn.query_row(&sql, [], crate::db::todo_from_row)
+            })
+            .unwrap()
+            .tags;
+        assert_eq!(tags, vec!["work", "home"]);
+
+        // 远端不再登记 old / home：old 没人用被删，home 还挂在待办上保留
+        data.tags = Some(vec![tag_json("work", "#EF4444", "2026-01-05 10:00:00")]);
+        sync_apply_remote(&db, &data).unwrap();
+        let names: Vec<String> = tag_colors(&db).into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, vec!["home", "work"]);
+    }
+
+    /// cloud 写入的 `priority` / `dueDate` 合并到本地后原样保留，下次上传不会丢。
+    #[test]
+    fn priority_and_due_date_survive_merge() {
+        let db = test_db();
+        let mut t = make_todo(1, "交报告", "2026-01-01 10:00:00");
+        t.priority = Some("high".to_string());
+        t.due_date = Some("2026-05-20".to_string());
+        merge_remote_into_local(&db, &sync_data(vec![t])).unwrap();
+
+        let todo = db
+            .with_connection(|conn| {
+                let sql = format!("SELECT {} FROM todos WHERE id = 1", crate::db::TODO_COLUMNS);
+                conn.query_row(&sql, [], crate::db::todo_from_row)
+            })
+            .unwrap();
+        assert_eq!(todo.priority.as_deref(), Some("high"));
+        assert_eq!(todo.due_date.as_deref(), Some("2026-05-20"));
+
+        let json = serde_json::to_value(&todo).unwrap();
+        assert_eq!(json["priority"], "high");
+        assert_eq!(json["dueDate"], "2026-05-20");
+    }
+
+    /// cloud 早期原样透传的非法 `priority` / `dueDate`（数字、不认识的取值）按
+    /// 未设置处理，整条待办照常合并。
+    #[test]
+    fn legacy_invalid_priority_and_due_date_are_dropped() {
+        let db = test_db();
+        let mut data = sync_data(vec![]);
+        for (id, priority) in [(1, serde_json::json!(3)), (2, serde_json::json!("urgent"))] {
+            let mut todo =
+                serde_json::to_value(make_todo(id, "旧数据", "2026-01-01 10:00:00")).unwrap();
+            todo["priority"] = priority;
+            todo["dueDate"] = serde_json::json!(20260520);
+            data.todos.push(todo);
+        }
+        let stats = merge_remote_into_local(&db, &data).unwrap();
+        assert_eq!(stats.todos_inserted, 2);
+
+        let (priority, due_date): (Option<String>, Option<String>) = db
+            .with_connection(|conn| {
+                conn.query_row("SELECT MAX(priority), MAX(due_date) FROM todos", [], |r| {
+                    Ok((r.get(0)?, r.get(1)?))
+                })
+            })
+            .unwrap();
+        assert_eq!(priority, None);
+        assert_eq!(due_date, None);
+    }
 }
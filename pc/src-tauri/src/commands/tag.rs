@@ -0,0 +1,265 @@
+//! 标签：增删改查 + 待办挂标签。
+//!
+//! 标签按 name 唯一，`tags` 表只登记颜色，待办挂哪些标签存在 `todo_tags`。
+//! 给待办挂一个没登记过的标签名时按默认颜色自动登记。改名 / 删除会刷新受影响
+//! 待办的 `updated_at`，否则同步时 LWW 会被远端旧版本盖回去。标签名规则与
+//! cloud 共用 `minitodo_model`。
+
+use super::todo::in_transaction;
+use crate::db::{tag_from_row, CreateTagRequest, Database, Tag, UpdateTagRequest, TAG_COLUMNS};
+use minitodo_model::{self as model, TagNameError};
+use rusqlite::OptionalExtension;
+use tauri::State;
+
+#[tauri::command]
+pub fn get_tags(db: State<Database>) -> Result<Vec<Tag>, String> {
+    db.with_connection(|conn| {
+        let sql = format!("SELECT {} FROM tags ORDER BY name ASC", TAG_COLUMNS);
+        let mut stmt = conn.prepare(&sql)?;
+        let tags = stmt
+            .query_map([], tag_from_row)?
+            .filter_map(|t| t.ok())
+            .collect();
+        Ok(tags)
+    })
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_tag(db: State<Database>, data: CreateTagRequest) -> Result<Tag, String> {
+    validate_tag_name(&data.name)?;
+
+    db.with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO tags (name, color) VALUES (?1, ?2)",
+            (&data.name, &data.color),
+        )?;
+
+        let sql = format!("SELECT {} FROM tags WHERE id = ?", TAG_COLUMNS);
+        conn.query_row(&sql, [conn.last_insert_rowid()], tag_from_row)
+    })
+    .map_err(|e| match e {
+        rusqlite::Error::SqliteFailure(err, _)
+            if err.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            format!("标签 {} 已存在", data.name)
+        }
+        e => e.to_string(),
+    })
+}
+
+#[tauri::command]
+pub fn update_tag(db: State<Database>, id: i64, data: UpdateTagRequest) -> Result<Tag, String> {
+    update_tag_inner(&db, id, data)
+}
+
+fn update_tag_inner(db: &Database, id: i64, data: UpdateTagRequest) -> Result<Tag, String> {
+    if let Some(ref name) = data.name {
+        validate_tag_name(name)?;
+    }
+
+    db.with_connection(|conn| {
+        in_transaction(conn, |conn| {
+            let sql = format!("SELECT {} FROM tags WHERE id = ?", TAG_COLUMNS);
+            let before = conn.query_row(&sql, [id], tag_from_row)?;
+
+            if let Some(ref name) = data.name {
+                if *name != before.name {
+                    let taken: Option<i64> = conn
+                        .query_row("SELECT id FROM tags WHERE name = ?", [name], |row| {
+                            row.get(0)
+                        })
+                        .optional()?;
+                    if taken.is_some() {
+                        return Err(rusqlite::Error::InvalidParameterName(format!(
+                            "标签 {} 已存在",
+                            name
+                        )));
+                    }
+                    conn.execute("UPDATE tags SET name = ?1 WHERE id = ?2", (name, id))?;
+                    touch_tagged_todos(conn, id)?;
+                }
+            }
+            if let Some(ref color) = data.color {
+                conn.execute("UPDATE tags SET color = ?1 WHERE id = ?2", (color, id))?;
+            }
+            conn.execute(
+                "UPDATE tags SET updated_at = datetime('now', 'localtime') WHERE id = ?",
+                [id],
+            )?;
+
+            conn.query_row(&sql, [id], tag_from_row)
+        })
+    })
+    .map_err(|e| match e {
+        rusqlite::Error::InvalidParameterName(msg) => msg,
+        e => e.to_string(),
+    })
+}
+
+/// 删除标签：所有待办上的该标签一并摘掉（`todo_tags` 级联删除）。
+#[tauri::command]
+pub fn delete_tag(db: State<Database>, id: i64) -> Result<(), String> {
+    delete_tag_inner(&db, id)
+}
+
+fn delete_tag_inner(db: &Database, id: i64) -> Result<(), String> {
+    db.with_connection(|conn| {
+        in_transaction(conn, |conn| {
+            touch_tagged_todos(conn, id)?;
+            conn.execute("DELETE FROM tags WHERE id = ?", [id])?;
+            Ok(())
+        })
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// 整体替换待办的标签，按 `names` 的顺序挂上（重复的只挂一次）；没登记过的
+/// 标签名按默认颜色自动登记。调用方负责开事务。
+pub(crate) fn write_todo_tags(
+    conn: &rusqlite::Connection,
+    todo_id: i64,
+    names: &[String],
+) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM todo_tags WHERE todo_id = ?", [todo_id])?;
+    for name in names {
+        conn.execute("INSERT OR IGNORE INTO tags (name) VALUES (?)", [name])?;
+        conn.execute(
+            "INSERT OR IGNORE INTO todo_tags (todo_id, tag_id)
+             SELECT ?1, id FROM tags WHERE name = ?2",
+            (todo_id, name),
+        )?;
+    }
+    Ok(())
+}
+
+/// 校验一组待办标签名，供创建 / 更新待办时使用。
+pub(crate) fn validate_tag_names(names: &[String]) -> Result<(), String> {
+    names.iter().try_for_each(|n| validate_tag_name(n))
+}
+
+/// 刷新挂着该标签的待办的 `updated_at`
+fn touch_tagged_todos(conn: &rusqlite::Connection, tag_id: i64) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE todos SET updated_at = datetime('now', 'localtime')
+         WHERE id IN (SELECT todo_id FROM todo_tags WHERE tag_id = ?)",
+        [tag_id],
+    )?;
+    Ok(())
+}
+
+/// 规则见 `minitodo_model::check_tag_name`，这里只负责中文提示。
+fn validate_tag_name(name: &str) -> Result<(), String> {
+    model::check_tag_name(name).map_err(|e| match e {
+        TagNameError::Blank => "标签名不能为空".to_string(),
+        TagNameError::Padded => "标签名首尾不能有空格".to_string(),
+        TagNameError::TooLong => format!("标签名不能超过 {} 个字符", model::MAX_TAG_LEN),
+        TagNameError::LeadingDash => "标签名不能以 - 开头".to_string(),
+        TagNameError::ForbiddenChar => "标签名不能包含 /、, 或控制字符".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{todo_from_row, TODO_COLUMNS};
+
+    fn test_db() -> Database {
+        Database::new_in_memory().expect("打开内存库失败")
+    }
+
+    fn insert_todo(db: &Database, title: &str, tags: &[&str]) -> i64 {
+        let names: Vec<String> = tags.iter().map(|s| s.to_string()).collect();
+        db.with_connection(|conn| {
+            conn.execute("INSERT INTO todos (title) VALUES (?)", [title])?;
+            let id = conn.last_insert_rowid();
+            write_todo_tags(conn, id, &names)?;
+            Ok(id)
+        })
+        .expect("写入待办失败")
+    }
+
+    fn todo_tags(db: &Database, id: i64) -> Vec<String> {
+        db.with_connection(|conn| {
+            let sql = format!("SELECT {} FROM todos WHERE id = ?", TODO_COLUMNS);
+            conn.query_row(&sql, [id], todo_from_row)
+        })
+        .expect("读取待办失败")
+        .tags
+    }
+
+    fn tag_id(db: &Database, name: &str) -> i64 {
+        db.with_connection(|conn| {
+            conn.query_row("SELECT id FROM tags WHERE name = ?", [name], |row| {
+                row.get(0)
+            })
+        })
+        .expect("标签不存在")
+    }
+
+    #[test]
+    fn write_todo_tags_keeps_order_and_auto_registers() {
+        let db = test_db();
+        let id = insert_todo(&db, "a", &["work", "urgent", "work"]);
+        assert_eq!(todo_tags(&db, id), vec!["work", "urgent"]);
+
+        let color: String = db
+            .with_connection(|conn| {
+                conn.query_row("SELECT color FROM tags WHERE name = 'urgent'", [], |row| {
+                    row.get(0)
+                })
+            })
+            .unwrap();
+        assert_eq!(color, crate::db::DEFAULT_TAG_COLOR);
+
+        let bare = insert_todo(&db, "b", &[]);
+        assert!(todo_tags(&db, bare).is_empty());
+    }
+
+    #[test]
+    fn rename_and_delete_follow_todos() {
+        let db = test_db();
+        let a = insert_todo(&db, "a", &["work", "home"]);
+        let b = insert_todo(&db, "b", &["home"]);
+        let work = tag_id(&db, "work");
+        let home = tag_id(&db, "home");
+
+        let err = update_tag_inner(
+            &db,
+            work,
+            UpdateTagRequest {
+                name: Some("home".to_string()),
+                color: None,
+            },
+        )
+        .expect_err("改成已有的名字应当报错");
+        assert!(err.contains("已存在"), "{}", err);
+
+        let tag = update_tag_inner(
+            &db,
+            work,
+            UpdateTagRequest {
+                name: Some("office".to_string()),
+                color: Some("#EF4444".to_string()),
+            },
+        )
+        .expect("改名失败");
+        assert_eq!(tag.name, "office");
+        assert_eq!(tag.color, "#EF4444");
+        assert_eq!(todo_tags(&db, a), vec!["office", "home"]);
+
+        delete_tag_inner(&db, home).expect("删除失败");
+        assert_eq!(todo_tags(&db, a), vec!["office"]);
+        assert!(todo_tags(&db, b).is_empty());
+    }
+
+    #[test]
+    fn tag_name_rules() {
+        assert!(validate_tag_name("工作").is_ok());
+        assert!(validate_tag_name("").is_err());
+        assert!(validate_tag_name(" work").is_err());
+        assert!(validate_tag_name("-work").is_err());
+        assert!(validate_tag_name("a/b").is_err());
+        assert!(validate_tag_name(&"x".repeat(model::MAX_TAG_LEN + 1)).is_err());
+    }
+}
@@ -0,0 +1,350 @@
+//! todo 模板：增删改查 + 实例化。
+//!
+//! 模板的标题、描述和子任务文本里可以写 `{{date}}` / `{{week}}` / `{{name}}`
+//! 等占位符，`instantiate_template` 时展开后生成一条待办及其子任务。展开规则与
+//! cloud `api/templates.rs` 一致，两边改动要同步。
+
+use super::todo::in_transaction;
+use crate::db::{
+    subtask_from_row, template_from_row, todo_from_row, CreateTemplateRequest, Database, Todo,
+    TodoTemplate, UpdateTemplateRequest, SUBTASK_COLUMNS, TEMPLATE_COLUMNS, TODO_COLUMNS,
+};
+use chrono::{Datelike, NaiveDate};
+use std::collections::{BTreeSet, HashMap};
+use tauri::State;
+
+#[tauri::command]
+pub fn get_templates(db: State<Database>) -> Result<Vec<TodoTemplate>, String> {
+    db.with_connection(|conn| {
+        let sql = format!(
+            "SELECT {} FROM todo_templates ORDER BY name ASC, id ASC",
+            TEMPLATE_COLUMNS
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let templates = stmt
+            .query_map([], template_from_row)?
+            .filter_map(|t| t.ok())
+            .collect();
+        Ok(templates)
+    })
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_template(
+    db: State<Database>,
+    data: CreateTemplateRequest,
+) -> Result<TodoTemplate, String> {
+    if data.name.trim().is_empty() || data.title.trim().is_empty() {
+        return Err("模板名和标题不能为空".to_string());
+    }
+    let subtasks = serde_json::to_string(&data.subtasks).map_err(|e| e.to_string())?;
+
+    db.with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO todo_templates (name, title, description, color, quadrant, subtasks)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            (
+                &data.name,
+                &data.title,
+                &data.description,
+                &data.color,
+                data.quadrant,
+                &subtasks,
+            ),
+        )?;
+
+        let id = conn.last_insert_rowid();
+
+        let sql = format!(
+            "SELECT {} FROM todo_templates WHERE id = ?",
+            TEMPLATE_COLUMNS
+        );
+        conn.query_row(&sql, [id], template_from_row)
+    })
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_template(
+    db: State<Database>,
+    id: i64,
+    data: UpdateTemplateRequest,
+) -> Result<TodoTemplate, String> {
+    db.with_connection(|conn| {
+        let mut updates = Vec::new();
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+        if let Some(ref name) = data.name {
+            updates.push("name = ?");
+            params.push(Box::new(name.clone()));
+        }
+        if let Some(ref title) = data.title {
+            updates.push("title = ?");
+            params.push(Box::new(title.clone()));
+        }
+        if let Some(ref desc) = data.description {
+            updates.push("description = ?");
+            params.push(Box::new(desc.clone()));
+        }
+        if let Some(ref color) = data.color {
+            updates.push("color = ?");
+            params.push(Box::new(color.clone()));
+        }
+        if let Some(quadrant) = data.quadrant {
+            updates.push("quadrant = ?");
+            params.push(Box::new(quadrant));
+        }
+        if let Some(ref subtasks) = data.subtasks {
+            updates.push("subtasks = ?");
+            params.push(Box::new(
+                serde_json::to_string(subtasks).unwrap_or_else(|_| "[]".to_string()),
+            ));
+        }
+
+        if updates.is_empty() {
+            return Err(rusqlite::Error::InvalidParameterName(
+                "No fields to update".to_string(),
+            ));
+        }
+
+        updates.push("updated_at = datetime('now', 'localtime')");
+
+        let sql = format!(
+            "UPDATE todo_templates SET {} WHERE id = ?",
+            updates.join(", ")
+        );
+        params.push(Box::new(id));
+
+        let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
+        conn.execute(&sql, params_refs.as_slice())?;
+
+        let sql = format!(
+            "SELECT {} FROM todo_templates WHERE id = ?",
+            TEMPLATE_COLUMNS
+        );
+        conn.query_row(&sql, [id], template_from_row)
+    })
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_template(db: State<Database>, id: i64) -> Result<(), String> {
+    db.with_connection(|conn| {
+        conn.execute("DELETE FROM todo_templates WHERE id = ?", [id])?;
+        Ok(())
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// 按模板新建一条待办及其子任务。`params` 提供占位符的值，也可以覆盖内置的
+/// `date` / `week`（默认取本机今天）。有占位符没给值时报错并列出缺的 key，
+/// 不落库。
+#[tauri::command]
+pub fn instantiate_template(
+    db: State<Database>,
+    id: i64,
+    params: HashMap<String, String>,
+) -> Result<Todo, String> {
+    instantiate_template_inner(&db, id, params, chrono::Local::now().date_naive())
+}
+
+fn instantiate_template_inner(
+    db: &Database,
+    id: i64,
+    params: HashMap<String, String>,
+    today: NaiveDate,
+) -> Result<Todo, String> {
+    let template = db
+        .with_connection(|conn| {
+            let sql = format!(
+                "SELECT {} FROM todo_templates WHERE id = ?",
+                TEMPLATE_COLUMNS
+            );
+            conn.query_row(&sql, [id], template_from_row)
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut vars = builtin_vars(today);
+    vars.extend(params);
+
+    let mut missing = BTreeSet::new();
+    let title = expand(&template.title, &vars, &mut missing);
+    let description = template
+        .description
+        .as_deref()
+        .map(|d| expand(d, &vars, &mut missing));
+    let subtasks: Vec<(String, Option<String>)> = template
+        .subtasks
+        .iter()
+        .map(|s| {
+            let title = expand(&s.title, &vars, &mut missing);
+            let content = s.content.as_deref().map(|c| expand(c, &vars, &mut missing));
+            (title, content)
+        })
+        .collect();
+
+    if !missing.is_empty() {
+        let keys: Vec<String> = missing.into_iter().collect();
+        return Err(format!("缺少模板参数: {}", keys.join(", ")));
+    }
+    if title.trim().is_empty() || subtasks.iter().any(|(t, _)| t.trim().is_empty()) {
+        return Err("展开占位符后标题为空".to_string());
+    }
+
+    db.with_connection(|conn| {
+        in_transaction(conn, |conn| {
+            let max_order: i32 = conn
+                .query_row(
+                    "SELECT COALESCE(MAX(sort_order), -1) FROM todos WHERE completed = 0",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap_or(-1);
+
+            conn.execute(
+                "INSERT INTO todos (title, description, color, quadrant, sort_order)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                (
+                    &title,
+                    &description,
+                    &template.color,
+                    template.quadrant,
+                    max_order + 1,
+                ),
+            )?;
+            let todo_id = conn.last_insert_rowid();
+
+            for (index, (sub_title, content)) in subtasks.iter().enumerate() {
+                conn.execute(
+                    "INSERT INTO subtasks (parent_id, title, content, sort_order) VALUES (?1, ?2, ?3, ?4)",
+                    (todo_id, sub_title, content, index as i32),
+                )?;
+            }
+
+            let sql = format!("SELECT {} FROM todos WHERE id = ?", TODO_COLUMNS);
+            let mut todo = conn.query_row(&sql, [todo_id], todo_from_row)?;
+
+            let subtask_sql = format!(
+                "SELECT {} FROM subtasks WHERE parent_id = ? ORDER BY sort_order ASC",
+                SUBTASK_COLUMNS
+            );
+            let mut subtask_stmt = conn.prepare(&subtask_sql)?;
+            let subtask_iter = subtask_stmt.query_map([todo_id], subtask_from_row)?;
+            todo.subtasks = subtask_iter.filter_map(|s| s.ok()).collect();
+
+            Ok(todo)
+        })
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// 内置变量：`date` 为 `YYYY-MM-DD`，`week` 为 ISO 周（如 `2026-W07`）。
+fn builtin_vars(today: NaiveDate) -> HashMap<String, String> {
+    let week = today.iso_week();
+    HashMap::from([
+        ("date".to_string(), today.format("%Y-%m-%d").to_string()),
+        (
+            "week".to_string(),
+            format!("{}-W{:02}", week.year(), week.week()),
+        ),
+    ])
+}
+
+/// 展开 `{{key}}`，key 两侧允许空格，只认字母 / 数字 / `_`。`vars` 里没有的
+/// key 记进 `missing`、原文保留；不像占位符的 `{{` 原样输出。
+fn expand(text: &str, vars: &HashMap<String, String>, missing: &mut BTreeSet<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let key = after.find("}}").map(|end| (end, after[..end].trim()));
+        match key {
+            Some((end, key))
+                if !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_') =>
+            {
+                match vars.get(key) {
+                    Some(v) => out.push_str(v),
+                    None => {
+                        missing.insert(key.to_string());
+                        out.push_str(&rest[start..start + 2 + end + 2]);
+                    }
+                }
+                rest = &after[end + 2..];
+            }
+            _ => {
+                out.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::TemplateSubtask;
+
+    fn test_db() -> Database {
+        Database::new_in_memory().expect("打开内存库失败")
+    }
+
+    fn seed_template(db: &Database) -> i64 {
+        let subtasks = vec![
+            TemplateSubtask {
+                title: "给 {{name}} 开账号".to_string(),
+                content: None,
+            },
+            TemplateSubtask {
+                title: "领电脑".to_string(),
+                content: Some("{{date}} 前".to_string()),
+            },
+        ];
+        db.with_connection(|conn| {
+            conn.execute(
+                "INSERT INTO todo_templates (name, title, description, subtasks)
+                 VALUES ('入职', '{{name}} 入职', '第 {{week}} 周', ?1)",
+                [serde_json::to_string(&subtasks).unwrap()],
+            )?;
+            Ok(conn.last_insert_rowid())
+        })
+        .expect("写入模板失败")
+    }
+
+    #[test]
+    fn expand_replaces_known_and_reports_missing() {
+        let vars = builtin_vars(NaiveDate::from_ymd_opt(2026, 1, 1).unwrap());
+        let mut missing = BTreeSet::new();
+        let out = expand(
+            "{{ date }} / {{week}} / {{name}} / {{ not a key }}",
+            &vars,
+            &mut missing,
+        );
+        assert_eq!(out, "2026-01-01 / 2026-W01 / {{name}} / {{ not a key }}");
+        assert_eq!(missing.into_iter().collect::<Vec<_>>(), vec!["name"]);
+    }
+
+    #[test]
+    fn instantiate_creates_todo_with_expanded_subtasks() {
+        let db = test_db();
+        let id = seed_template(&db);
+        let today = NaiveDate::from_ymd_opt(2026, 2, 13).unwrap();
+
+        let err = instantiate_template_inner(&db, id, HashMap::new(), today)
+            .expect_err("缺 name 应当报错");
+        assert!(err.contains("name"), "{}", err);
+
+        let params = HashMap::from([("name".to_string(), "小王".to_string())]);
+        let todo = instantiate_template_inner(&db, id, params, today).expect("实例化失败");
+
+        assert_eq!(todo.title, "小王 入职");
+        assert_eq!(todo.description.as_deref(), Some("第 2026-W07 周"));
+        assert_eq!(todo.color, "#10B981");
+        assert_eq!(todo.subtasks.len(), 2);
+        assert_eq!(todo.subtasks[0].title, "给 小王 开账号");
+        assert_eq!(todo.subtasks[1].content.as_deref(), Some("2026-02-13 前"));
+    }
+}
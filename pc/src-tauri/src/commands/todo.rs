@@ -1,8 +1,11 @@
+use super::tag::{validate_tag_names, write_todo_tags};
 use crate::db::{
     subtask_from_row, todo_from_row, CreateSubTaskRequest, CreateTodoRequest, Database, SubTask,
     Todo, UpdateSubTaskRequest, UpdateTodoRequest, SUBTASK_COLUMNS, TODO_COLUMNS,
 };
 use base64::{engine::general_purpose, Engine};
+use minitodo_model as model;
+use minitodo_quickadd::QuickAdd;
 use std::path::{Path, PathBuf};
 use tauri::State;
 
@@ -40,42 +43,115 @@ pub fn get_todos(db: State<Database>) -> Result<Vec<Todo>, String> {
 
 #[tauri::command]
 pub fn create_todo(db: State<Database>, data: CreateTodoRequest) -> Result<Todo, String> {
+    validate_tag_names(&data.tags)?;
+    validate_priority(data.priority.as_deref())?;
+    validate_due_date(data.due_date.as_deref())?;
+
     db.with_connection(|conn| {
-        // 获取最大排序值
-        let max_order: i32 = conn
-            .query_row(
-                "SELECT COALESCE(MAX(sort_order), -1) FROM todos WHERE completed = 0",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(-1);
+        in_transaction(conn, |conn| {
+            // 获取最大排序值
+            let max_order: i32 = conn
+                .query_row(
+                    "SELECT COALESCE(MAX(sort_order), -1) FROM todos WHERE completed = 0",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap_or(-1);
 
-        conn.execute(
-            "INSERT INTO todos (title, description, color, quadrant, notify_at, notify_before, start_time, end_time, sort_order)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-            (
-                &data.title,
-                &data.description,
-                &data.color,
-                data.quadrant,
-                &data.notify_at,
-                data.notify_before.unwrap_or(0),
-                &data.start_time,
-                &data.end_time,
-                max_order + 1,
-            ),
-        )?;
+            conn.execute(
+                "INSERT INTO todos (title, description, color, quadrant, notify_at, notify_before, start_time, end_time, sort_order,
+                                    priority, due_date)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                (
+                    &data.title,
+                    &data.description,
+                    &data.color,
+                    data.quadrant,
+                    &data.notify_at,
+                    data.notify_before.unwrap_or(0),
+                    &data.start_time,
+                    &data.end_time,
+                    max_order + 1,
+                    &data.priority,
+                    &data.due_date,
+                ),
+            )?;
 
-        let id = conn.last_insert_rowid();
+            let id = conn.last_insert_rowid();
+            write_todo_tags(conn, id, &data.tags)?;
+
+            let sql = format!("SELECT {} FROM todos WHERE id = ?", TODO_COLUMNS);
+            conn.query_row(&sql, [id], todo_from_row)
+        })
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// 快速添加预览：只解析不落库，托盘快速添加输入框据此实时展示识别结果。
+/// 解析规则与 cloud `POST /todos/quick` 共用 `minitodo_quickadd`。
+#[tauri::command]
+pub fn parse_quick_add(text: String) -> QuickAdd {
+    minitodo_quickadd::parse(&text, chrono::Local::now().naive_local())
+}
+
+/// 快速添加：解析一行文字并直接创建待办（含重复规则）。
+/// `color` 由前端按解析出的象限给出，与编辑器新建时一致。
+#[tauri::command]
+pub fn quick_add_todo(db: State<Database>, text: String, color: String) -> Result<Todo, String> {
+    let parsed = minitodo_quickadd::parse(&text, chrono::Local::now().naive_local());
+    if parsed.title.is_empty() {
+        return Err("未能从输入中解析出标题".to_string());
+    }
+    validate_tag_names(&parsed.tags)?;
+
+    db.with_connection(|conn| {
+        in_transaction(conn, |conn| {
+            let max_order: i32 = conn
+                .query_row(
+                    "SELECT COALESCE(MAX(sort_order), -1) FROM todos WHERE completed = 0",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap_or(-1);
+
+            conn.execute(
+                "INSERT INTO todos (title, color, quadrant, notify_at, notify_before, start_time, end_time, sort_order,
+                                    repeat_enabled, repeat_type, repeat_interval, repeat_weekdays, repeat_month_day)
+                 VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                rusqlite::params![
+                    parsed.title,
+                    color,
+                    parsed.quadrant.unwrap_or(4),
+                    parsed.notify_at,
+                    parsed.start_time,
+                    parsed.end_time,
+                    max_order + 1,
+                    parsed.repeat_enabled as i32,
+                    parsed.repeat_type,
+                    parsed.repeat_interval.unwrap_or(1),
+                    parsed.repeat_weekdays,
+                    parsed.repeat_month_day,
+                ],
+            )?;
+
+            let id = conn.last_insert_rowid();
+            write_todo_tags(conn, id, &parsed.tags)?;
 
-        let sql = format!("SELECT {} FROM todos WHERE id = ?", TODO_COLUMNS);
-        conn.query_row(&sql, [id], todo_from_row)
+            let sql = format!("SELECT {} FROM todos WHERE id = ?", TODO_COLUMNS);
+            conn.query_row(&sql, [id], todo_from_row)
+        })
     })
     .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn update_todo(db: State<Database>, id: i64, data: UpdateTodoRequest) -> Result<Todo, String> {
+    if let Some(ref tags) = data.tags {
+        validate_tag_names(tags)?;
+    }
+    validate_priority(data.priority.as_deref())?;
+    validate_due_date(data.due_date.as_deref())?;
+
     db.with_connection(|conn| {
         let mut updates = Vec::new();
         let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
@@ -113,6 +189,14 @@ pub fn update_todo(db: State<Database>, id: i64, data: UpdateTodoRequest) -> Res
         if let Some(completed) = data.completed {
             updates.push("completed = ?");
             params.push(Box::new(if completed { 1 } else { 0 }));
+            // SET 右侧读到的是更新前的行：只在 0 → 1 时记完成时间，重复标记完成不覆盖
+            if completed {
+                updates.push(
+                    "completed_at = CASE WHEN completed = 1 THEN completed_at ELSE datetime('now', 'localtime') END",
+                );
+            } else {
+                updates.push("completed_at = NULL");
+            }
         }
         if let Some(sort_order) = data.sort_order {
             updates.push("sort_order = ?");
@@ -132,6 +216,20 @@ pub fn update_todo(db: State<Database>, id: i64, data: UpdateTodoRequest) -> Res
             updates.push("end_time = ?");
             params.push(Box::new(end_time.clone()));
         }
+        // 优先级
+        if data.clear_priority {
+            updates.push("priority = NULL");
+        } else if let Some(ref priority) = data.priority {
+            updates.push("priority = ?");
+            params.push(Box::new(priority.clone()));
+        }
+        // 截止日期
+        if data.clear_due_date {
+            updates.push("due_date = NULL");
+        } else if let Some(ref due_date) = data.due_date {
+            updates.push("due_date = ?");
+            params.push(Box::new(due_date.clone()));
+        }
         // 重复提醒
         if data.clear_repeat {
             updates.push("repeat_enabled = 0");
@@ -166,7 +264,7 @@ pub fn update_todo(db: State<Database>, id: i64, data: UpdateTodoRequest) -> Res
             }
         }
 
-        if updates.is_empty() {
+        if updates.is_empty() && data.tags.is_none() {
             return Err(rusqlite::Error::InvalidParameterName(
                 "No fields to update".to_string(),
             ));
@@ -178,7 +276,14 @@ pub fn update_todo(db: State<Database>, id: i64, data: UpdateTodoRequest) -> Res
         params.push(Box::new(id));
 
         let params_refs: Vec<&dyn rusqlite::ToSql> = params.iter().map(|p| p.as_ref()).collect();
-        conn.execute(&sql, params_refs.as_slice())?;
+        in_transaction(conn, |conn| {
+            conn.execute(&sql, params_refs.as_slice())?;
+            // 标签整体替换，只改标签也会刷新 updated_at
+            if let Some(ref tags) = data.tags {
+                write_todo_tags(conn, id, tags)?;
+            }
+            Ok(())
+        })?;
 
         let todo_sql = format!("SELECT {} FROM todos WHERE id = ?", TODO_COLUMNS);
         let mut todo = conn.query_row(&todo_sql, [id], todo_from_row)?;
@@ -326,6 +431,237 @@ pub fn delete_subtask(db: State<Database>, id: i64) -> Result<(), String> {
     .map_err(|e| e.to_string())
 }
 
+/// 子任务升级为独立待办
+///
+/// 标题、内容（→ `description`）、完成状态原样带过去；内容里的图片按文件名引用，
+/// 文件不用动。颜色 / 象限沿用原父待办，排到未完成列表末尾。原子任务删除，
+/// 与 `delete_subtask` 一样，下次上传后远端随之消失。
+#[tauri::command]
+pub fn promote_subtask(db: State<Database>, id: i64) -> Result<Todo, String> {
+    db.with_connection(|conn| {
+        in_transaction(conn, |conn| {
+            let sql = format!("SELECT {} FROM subtasks WHERE id = ?", SUBTASK_COLUMNS);
+            let subtask = conn.query_row(&sql, [id], subtask_from_row)?;
+            let (color, quadrant): (String, i32) = conn.query_row(
+                "SELECT color, quadrant FROM todos WHERE id = ?",
+                [subtask.parent_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )?;
+            let max_order: i32 = conn
+                .query_row(
+                    "SELECT COALESCE(MAX(sort_order), -1) FROM todos WHERE completed = 0",
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap_or(-1);
+
+            conn.execute(
+                "INSERT INTO todos (title, description, color, quadrant, completed, completed_at, sort_order)
+                 VALUES (?1, ?2, ?3, ?4, ?5, CASE WHEN ?5 = 1 THEN datetime('now', 'localtime') END, ?6)",
+                rusqlite::params![
+                    subtask.title,
+                    subtask.content,
+                    color,
+                    quadrant,
+                    subtask.completed as i32,
+                    max_order + 1,
+                ],
+            )?;
+            let new_id = conn.last_insert_rowid();
+            conn.execute("DELETE FROM subtasks WHERE id = ?", [id])?;
+
+            let sql = format!("SELECT {} FROM todos WHERE id = ?", TODO_COLUMNS);
+            conn.query_row(&sql, [new_id], todo_from_row)
+        })
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// 待办降级为另一个待办（`into_id`）的子任务
+///
+/// 标题、描述（→ `content`）、完成状态带过去，排到目标子任务末尾。子任务只有一层，
+/// 它自带的子任务按原顺序接在后面，一起挪到目标下（保留 id）。日期、提醒、重复
+/// 规则等子任务没有的字段丢弃。原待办删除。返回目标待办（含子任务）。
+#[tauri::command]
+pub fn demote_todo(db: State<Database>, id: i64, into_id: i64) -> Result<Todo, String> {
+    if id == into_id {
+        return Err("不能降级为自己的子任务".to_string());
+    }
+    db.with_connection(|conn| {
+        in_transaction(conn, |conn| {
+            let sql = format!("SELECT {} FROM todos WHERE id = ?", TODO_COLUMNS);
+            let todo = conn.query_row(&sql, [id], todo_from_row)?;
+            // 目标不存在 → QueryReturnedNoRows
+            conn.query_row("SELECT id FROM todos WHERE id = ?", [into_id], |row| {
+                row.get::<_, i64>(0)
+            })?;
+            let mut next_order: i32 = conn.query_row(
+                "SELECT COALESCE(MAX(sort_order), -1) + 1 FROM subtasks WHERE parent_id = ?",
+                [into_id],
+                |row| row.get(0),
+            )?;
+
+            conn.execute(
+                "INSERT INTO subtasks (parent_id, title, content, completed, sort_order)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![
+                    into_id,
+                    todo.title,
+                    todo.description,
+                    todo.completed as i32,
+                    next_order,
+                ],
+            )?;
+
+            let child_ids: Vec<i64> = conn
+                .prepare("SELECT id FROM subtasks WHERE parent_id = ? ORDER BY sort_order ASC")?
+                .query_map([id], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            for child_id in child_ids {
+                next_order += 1;
+                conn.execute(
+                    "UPDATE subtasks SET parent_id = ?1, sort_order = ?2,
+                        updated_at = datetime('now', 'localtime')
+                     WHERE id = ?3",
+                    rusqlite::params![into_id, next_order, child_id],
+                )?;
+            }
+            conn.execute("DELETE FROM todos WHERE id = ?", [id])?;
+
+            let sql = format!("SELECT {} FROM todos WHERE id = ?", TODO_COLUMNS);
+            let mut target = conn.query_row(&sql, [into_id], todo_from_row)?;
+            let subtask_sql = format!(
+                "SELECT {} FROM subtasks WHERE parent_id = ? ORDER BY sort_order ASC",
+                SUBTASK_COLUMNS
+            );
+            target.subtasks = conn
+                .prepare(&subtask_sql)?
+                .query_map([into_id], subtask_from_row)?
+                .filter_map(|s| s.ok())
+                .collect();
+            Ok(target)
+        })
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// 复制待办及其子任务
+///
+/// 新 id、创建 / 更新时间取当前时间，`notified` 与完成状态（`completed` / `completed_at`）
+/// 重置，其余字段（含排序、标签、优先级）原样保留，副本与原待办排在一起。`shift_days` 非空时
+/// 把开始 / 截止 / 提醒时间和截止日期整体平移若干天（可为负）。子任务内容里的图片按文件名引用，图片只增不删，
+/// 副本直接沿用同一文件。与 cloud `POST /todos/:id/duplicate` 语义一致。
+#[tauri::command]
+pub fn duplicate_todo(
+    db: State<Database>,
+    id: i64,
+    shift_days: Option<i64>,
+) -> Result<Todo, String> {
+    let shift = shift_days.unwrap_or(0);
+    db.with_connection(|conn| {
+        in_transaction(conn, |conn| {
+            let sql = format!("SELECT {} FROM todos WHERE id = ?", TODO_COLUMNS);
+            let src = conn.query_row(&sql, [id], todo_from_row)?;
+            let shifted = |s: &Option<String>| {
+                s.as_ref()
+                    .map(|v| model::shift_days(v, shift).unwrap_or_else(|| v.clone()))
+            };
+
+            conn.execute(
+                "INSERT INTO todos (title, description, color, quadrant, notify_at, notify_before,
+                                    notified, completed, sort_order, start_time, end_time,
+                                    repeat_enabled, repeat_type, repeat_interval, repeat_weekdays,
+                                    repeat_month_day, completed_at, priority, due_date)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0, 0, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, NULL,
+                         ?15, ?16)",
+                rusqlite::params![
+                    src.title,
+                    src.description,
+                    src.color,
+                    src.quadrant,
+                    shifted(&src.notify_at),
+                    src.notify_before,
+                    src.sort_order,
+                    shifted(&src.start_time),
+                    shifted(&src.end_time),
+                    src.repeat_enabled as i32,
+                    src.repeat_type,
+                    src.repeat_interval,
+                    src.repeat_weekdays,
+                    src.repeat_month_day,
+                    src.priority,
+                    shifted(&src.due_date),
+                ],
+            )?;
+            let new_id = conn.last_insert_rowid();
+            conn.execute(
+                "INSERT INTO subtasks (parent_id, title, content, completed, sort_order)
+                 SELECT ?1, title, content, completed, sort_order FROM subtasks WHERE parent_id = ?2",
+                [new_id, id],
+            )?;
+            conn.execute(
+                "INSERT INTO todo_tags (todo_id, tag_id)
+                 SELECT ?1, tag_id FROM todo_tags WHERE todo_id = ?2 ORDER BY rowid",
+                [new_id, id],
+            )?;
+
+            let mut todo = conn.query_row(&sql, [new_id], todo_from_row)?;
+            let subtask_sql = format!(
+                "SELECT {} FROM subtasks WHERE parent_id = ? ORDER BY sort_order ASC",
+                SUBTASK_COLUMNS
+            );
+            todo.subtasks = conn
+                .prepare(&subtask_sql)?
+                .query_map([new_id], subtask_from_row)?
+                .filter_map(|s| s.ok())
+                .collect();
+            Ok(todo)
+        })
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// 优先级取值与 cloud 共用 `minitodo_model::PRIORITIES`。
+fn validate_priority(priority: Option<&str>) -> Result<(), String> {
+    match priority {
+        Some(p) if !model::is_priority(p) => Err(format!(
+            "优先级 {} 无效，应为 {}",
+            p,
+            model::PRIORITIES.join(" / ")
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// 截止日期规则与 cloud 共用 `minitodo_model::is_due_date`。
+fn validate_due_date(due_date: Option<&str>) -> Result<(), String> {
+    match due_date {
+        Some(s) if !model::is_due_date(s) => Err(format!(
+            "截止日期 {} 无效，应为 YYYY-MM-DD 或 YYYY-MM-DDTHH:MM:SS",
+            s
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// `BEGIN IMMEDIATE` … `COMMIT`，闭包出错则回滚（同 `merge_remote_into_local`）。
+pub(crate) fn in_transaction<R>(
+    conn: &rusqlite::Connection,
+    f: impl FnOnce(&rusqlite::Connection) -> rusqlite::Result<R>,
+) -> rusqlite::Result<R> {
+    conn.execute("BEGIN IMMEDIATE", [])?;
+    match f(conn) {
+        Ok(r) => {
+            conn.execute("COMMIT", [])?;
+            Ok(r)
+        }
+        Err(e) => {
+            let _ = conn.execute("ROLLBACK", []);
+            Err(e)
+        }
+    }
+}
+
 fn get_images_dir_path() -> PathBuf {
     dirs::data_local_dir()
         .unwrap_or_else(|| PathBuf::from("."))
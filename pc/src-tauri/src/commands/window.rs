@@ -707,6 +707,17 @@ pub fn get_settings(db: State<Database>) -> Result<AppSettings, String> {
             )
             .unwrap_or_else(|_| "system".to_string());
 
+        let saved_views = conn
+            .query_row(
+                "SELECT value FROM settings WHERE key = 'saved_views'",
+                [],
+                |row| {
+                    let val: String = row.get(0)?;
+                    Ok(serde_json::from_str(&val).unwrap_or_default())
+                },
+            )
+            .unwrap_or_default();
+
         Ok(AppSettings {
             is_fixed,
             window_position,
@@ -719,6 +730,7 @@ pub fn get_settings(db: State<Database>) -> Result<AppSettings, String> {
             show_calendar,
             view_mode,
             notification_type,
+            saved_views,
         })
     })
     .map_err(|e| e.to_string())
@@ -781,6 +793,8 @@ pub fn save_settings(db: State<Database>, settings: AppSettings) -> Result<(), S
             [&settings.text_theme],
         )?;
 
+        // saved_views 不在前端提交的字段里，只由同步 / 导入写入（write_app_settings）
+
         Ok(())
     })
     .map_err(|e| e.to_string())
@@ -141,6 +141,129 @@ pub fn run_migrations(conn: &Connection) -> Result<()> {
         apply_migration(conn, 26, migration_v26)?;
     }
 
+    if current_version < 27 {
+        apply_migration(conn, 27, migration_v27)?;
+    }
+
+    if current_version < 28 {
+        apply_migration(conn, 28, migration_v28)?;
+    }
+
+    if current_version < 29 {
+        apply_migration(conn, 29, migration_v29)?;
+    }
+
+    if current_version < 30 {
+        apply_migration(conn, 30, migration_v30)?;
+    }
+
+    Ok(())
+}
+
+/// 迁移 v30：todos 新增 `priority` / `due_date`，对应 cloud 的 `priority` / `dueDate`。
+///
+/// v1 建表时带了一列 `priority TEXT NOT NULL DEFAULT 'medium' CHECK(...)`，v4 把它
+/// 折算成 color 后再没用过，各行都是陈旧值；cloud 的 priority 可以为空，这里删掉
+/// 旧列重新加一列可空的，旧值不保留。`due_date` 存 `YYYY-MM-DD`（也接受带时间），
+/// 为空时截止时间取 `end_time`，与 cloud 的过滤 / 排序规则一致。
+///
+/// 按列的现状判断要不要改，重跑不会报错。
+fn migration_v30(conn: &Connection) -> Result<()> {
+    let legacy_priority: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('todos') WHERE name = 'priority' AND \"notnull\" = 1",
+        [],
+        |row| row.get(0),
+    )?;
+    if legacy_priority {
+        conn.execute("ALTER TABLE todos DROP COLUMN priority", [])?;
+    }
+    if !has_column(conn, "todos", "priority")? {
+        conn.execute("ALTER TABLE todos ADD COLUMN priority TEXT", [])?;
+    }
+    if !has_column(conn, "todos", "due_date")? {
+        conn.execute("ALTER TABLE todos ADD COLUMN due_date TEXT", [])?;
+    }
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_todos_due_date ON todos(due_date)",
+        [],
+    )?;
+    Ok(())
+}
+
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get(0),
+    )
+}
+
+/// 迁移 v29：新增 `tags` / `todo_tags` 两张表（标签，多对多）。
+///
+/// `tags` 按 name 唯一，记标签颜色；`todo_tags` 是待办与标签的关联，两头都
+/// `ON DELETE CASCADE`——删待办或删标签时关联行随之消失。同步 / 导出时待办带
+/// `tags: [name]`，标签颜色另放顶层 `tags[]`，与 cloud 一致（见 `commands/tag.rs`）。
+fn migration_v29(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE,
+            color TEXT NOT NULL DEFAULT '#6B7280',
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS todo_tags (
+            todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+            tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+            PRIMARY KEY (todo_id, tag_id)
+        )",
+        [],
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_todo_tags_tag_id ON todo_tags(tag_id)",
+        [],
+    )?;
+    Ok(())
+}
+
+/// 迁移 v28：新增 `todo_templates` 表（todo 模板）。
+///
+/// 一条模板 = 待办的标题 / 描述 / 颜色 / 象限 + 一份子任务清单，文本里可写
+/// `{{date}}` / `{{week}}` / `{{name}}` 等占位符，实例化时展开（见
+/// `commands/template.rs`）。子任务清单只在实例化时整体读取，直接存 JSON 文本
+/// `[{"title": ..., "content": ...}]`，不单独建表。
+fn migration_v28(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS todo_templates (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            title TEXT NOT NULL,
+            description TEXT,
+            color TEXT NOT NULL DEFAULT '#10B981',
+            quadrant INTEGER NOT NULL DEFAULT 4,
+            subtasks TEXT NOT NULL DEFAULT '[]',
+            created_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime')),
+            updated_at TEXT NOT NULL DEFAULT (datetime('now', 'localtime'))
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+/// 迁移 v27：todos 新增 `completed_at`，记录最近一次被标记完成的时间。
+///
+/// 供 cloud 端 `GET /stats` 统计"每天完成数 / 从创建到完成的平均耗时"，
+/// `update_todo` 在 `completed` 由 0 翻到 1 时写入、翻回 0 时清空。
+/// 升级前已完成的待办没有真实完成时间，用 `updated_at` 近似回填。
+fn migration_v27(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE todos ADD COLUMN completed_at TEXT", [])?;
+    conn.execute(
+        "UPDATE todos SET completed_at = updated_at WHERE completed = 1",
+        [],
+    )?;
     Ok(())
 }
 
@@ -782,17 +905,21 @@ mod tests {
         assert_eq!(max_version(&conn), 99);
     }
 
-    /// 26 个迁移逐个包事务后，全新库仍能一次性迁到最新版本。
+    /// 30 个迁移逐个包事务后，全新库仍能一次性迁到最新版本。
     #[test]
     fn fresh_database_migrates_to_latest_version() {
         let conn = fresh_conn();
         run_migrations(&conn).expect("全新库迁移失败");
 
-        assert_eq!(max_version(&conn), 26);
+        assert_eq!(max_version(&conn), 30);
         assert!(table_exists(&conn, "todos"));
         assert!(table_exists(&conn, "subtasks"));
         assert!(table_exists(&conn, "settings"));
         assert!(table_exists(&conn, "screen_configs"));
+        assert!(table_exists(&conn, "todo_templates"));
+        assert!(table_exists(&conn, "tags"));
+        assert!(table_exists(&conn, "todo_tags"));
+        assert!(has_column(&conn, "todos", "due_date").unwrap());
         // v23 已删除的 Agent 相关表不应残留
         assert!(!table_exists(&conn, "agent_configs"));
     }
@@ -804,6 +931,72 @@ mod tests {
         run_migrations(&conn).expect("首次迁移失败");
         run_migrations(&conn).expect("二次迁移失败");
 
-        assert_eq!(max_version(&conn), 26);
+        assert_eq!(max_version(&conn), 30);
+    }
+
+    /// v27 给已完成的老数据用 updated_at 回填 completed_at，未完成的保持 NULL。
+    #[test]
+    fn v27_backfills_completed_at_from_updated_at() {
+        let conn = fresh_conn();
+        run_migrations(&conn).expect("首次迁移失败");
+        // 版本号取 MAX(version)：v27 之后的版本号也要一起删，后续迁移都可重跑
+        conn.execute("DELETE FROM migrations WHERE version >= 27", [])
+            .expect("回退版本号失败");
+        conn.execute("ALTER TABLE todos DROP COLUMN completed_at", [])
+            .expect("删除列失败");
+        conn.execute(
+            "INSERT INTO todos (title, completed, updated_at) VALUES ('done', 1, '2026-05-10 08:00:00')",
+            [],
+        )
+        .expect("插入已完成待办失败");
+        conn.execute("INSERT INTO todos (title, completed) VALUES ('open', 0)", [])
+            .expect("插入未完成待办失败");
+
+        run_migrations(&conn).expect("v27 迁移失败");
+
+        let done: Option<String> = conn
+            .query_row("SELECT completed_at FROM todos WHERE title = 'done'", [], |r| r.get(0))
+            .unwrap();
+        let open: Option<String> = conn
+            .query_row("SELECT completed_at FROM todos WHERE title = 'open'", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(done.as_deref(), Some("2026-05-10 08:00:00"));
+        assert_eq!(open, None);
+    }
+
+    /// v30 换掉 v1 遗留的 `priority NOT NULL DEFAULT 'medium'`：新列可空、旧值不保留。
+    #[test]
+    fn v30_replaces_legacy_priority_with_nullable_column() {
+        let conn = fresh_conn();
+        run_migrations(&conn).expect("迁移失败");
+
+        conn.execute("INSERT INTO todos (title) VALUES ('a')", [])
+            .expect("插入待办失败");
+        let (priority, due_date): (Option<String>, Option<String>) = conn
+            .query_row("SELECT priority, due_date FROM todos", [], |r| {
+                Ok((r.get(0)?, r.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(priority, None);
+        assert_eq!(due_date, None);
+
+        let (notnull, default): (bool, Option<String>) = conn
+            .query_row(
+                "SELECT \"notnull\", dflt_value FROM pragma_table_info('todos')
+                 WHERE name = 'priority'",
+                [],
+                |r| Ok((r.get(0)?, r.get(1)?)),
+            )
+            .unwrap();
+        assert!(!notnull);
+        assert_eq!(default, None);
+
+        // 旧列的 CHECK 只放行 high / medium / low，新列不再带约束
+        conn.execute("UPDATE todos SET priority = 'urgent'", [])
+            .expect("旧 CHECK 约束应已随旧列删除");
+        let priority: Option<String> = conn
+            .query_row("SELECT priority FROM todos", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(priority.as_deref(), Some("urgent"));
     }
 }
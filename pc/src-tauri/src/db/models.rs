@@ -6,7 +6,19 @@ pub const SUBTASK_COLUMNS: &str =
 
 pub const TODO_COLUMNS: &str = "id, title, description, color, quadrant, notify_at, notify_before,
      notified, completed, sort_order, start_time, end_time, created_at, updated_at,
-     repeat_enabled, repeat_type, repeat_interval, repeat_weekdays, repeat_month_day";
+     repeat_enabled, repeat_type, repeat_interval, repeat_weekdays, repeat_month_day, completed_at,
+     priority, due_date,
+     (SELECT json_group_array(tags.name ORDER BY todo_tags.rowid)
+        FROM todo_tags JOIN tags ON tags.id = todo_tags.tag_id
+       WHERE todo_tags.todo_id = todos.id)";
+
+pub const TEMPLATE_COLUMNS: &str =
+    "id, name, title, description, color, quadrant, subtasks, created_at, updated_at";
+
+pub const TAG_COLUMNS: &str = "id, name, color, created_at, updated_at";
+
+/// 标签默认颜色；与 cloud `DEFAULT_TAG_COLOR` 一致，没登记颜色的标签也按它显示
+pub const DEFAULT_TAG_COLOR: &str = "#6B7280";
 
 pub fn subtask_from_row(row: &Row) -> rusqlite::Result<SubTask> {
     Ok(SubTask {
@@ -42,10 +54,45 @@ pub fn todo_from_row(row: &Row) -> rusqlite::Result<Todo> {
         repeat_interval: row.get(16).unwrap_or(1),
         repeat_weekdays: row.get(17).unwrap_or(None),
         repeat_month_day: row.get(18).unwrap_or(None),
+        completed_at: row.get(19).unwrap_or(None),
+        priority: row.get(20).unwrap_or(None),
+        due_date: row.get(21).unwrap_or(None),
+        // 第 22 列是 json_group_array 拼出的标签名数组
+        tags: row
+            .get::<_, String>(22)
+            .ok()
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default(),
         subtasks: Vec::new(),
     })
 }
 
+pub fn tag_from_row(row: &Row) -> rusqlite::Result<Tag> {
+    Ok(Tag {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        color: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+pub fn template_from_row(row: &Row) -> rusqlite::Result<TodoTemplate> {
+    let subtasks: String = row.get(6)?;
+    Ok(TodoTemplate {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        title: row.get(2)?,
+        description: row.get(3)?,
+        color: row.get(4)?,
+        quadrant: row.get(5)?,
+        // 列里是本程序写入的 JSON，解析失败只可能是手改了库，按空清单处理
+        subtasks: serde_json::from_str(&subtasks).unwrap_or_default(),
+        created_at: row.get(7)?,
+        updated_at: row.get(8)?,
+    })
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Todo {
@@ -77,6 +124,18 @@ pub struct Todo {
     pub repeat_weekdays: Option<String>,
     #[serde(default)]
     pub repeat_month_day: Option<i32>,
+    /// 最近一次标记完成的时间（未完成为空）
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    /// 优先级：high / medium / low（可为空）
+    #[serde(default, deserialize_with = "lenient_priority")]
+    pub priority: Option<String>,
+    /// 截止日期（`YYYY-MM-DD`，也可带时间；为空时截止按 `end_time` 算，与 cloud 一致）
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub due_date: Option<String>,
+    /// 标签名（多对多，存在 `todo_tags`）
+    #[serde(default)]
+    pub tags: Vec<String>,
     #[serde(default)]
     pub subtasks: Vec<SubTask>,
 }
@@ -85,6 +144,27 @@ fn default_repeat_interval() -> i32 {
     1
 }
 
+/// 只收字符串，其余类型按未设置处理。cloud 早期对 `priority` / `dueDate` 原样
+/// 透传，可能存着 `"priority": 3` 这种值，不能让它把整条待办的反序列化带崩。
+fn lenient_string<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<serde_json::Value>::deserialize(deserializer)?;
+    Ok(match value {
+        Some(serde_json::Value::String(s)) => Some(s),
+        _ => None,
+    })
+}
+
+/// 同 [`lenient_string`]，取值不在 high / medium / low 里的也按未设置处理。
+fn lenient_priority<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(lenient_string(deserializer)?.filter(|p| minitodo_model::is_priority(p)))
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SubTask {
@@ -114,6 +194,13 @@ pub struct CreateTodoRequest {
     pub start_time: Option<String>,
     /// 截止时间（可为空）
     pub end_time: Option<String>,
+    /// 优先级：high / medium / low（可为空）
+    pub priority: Option<String>,
+    /// 截止日期（可为空）
+    pub due_date: Option<String>,
+    /// 标签名；没登记过的标签按默认颜色自动创建
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 fn default_quadrant() -> i32 {
@@ -146,6 +233,16 @@ pub struct UpdateTodoRequest {
     /// 是否明确清除截止时间
     #[serde(default)]
     pub clear_end_time: bool,
+    /// 优先级：high / medium / low
+    pub priority: Option<String>,
+    /// 截止日期
+    pub due_date: Option<String>,
+    /// 是否明确清除优先级
+    #[serde(default)]
+    pub clear_priority: bool,
+    /// 是否明确清除截止日期
+    #[serde(default)]
+    pub clear_due_date: bool,
     /// 是否启用重复提醒
     pub repeat_enabled: Option<bool>,
     /// 重复类型：daily / weekly / monthly
@@ -159,6 +256,8 @@ pub struct UpdateTodoRequest {
     /// 是否明确清除重复提醒
     #[serde(default)]
     pub clear_repeat: bool,
+    /// 整体替换标签（`Some(vec![])` 清空）
+    pub tags: Option<Vec<String>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -178,6 +277,99 @@ pub struct UpdateSubTaskRequest {
     pub sort_order: Option<i32>,
 }
 
+/// todo 模板：实例化时生成一条待办及其子任务，文本支持 `{{date}}` / `{{week}}` /
+/// `{{name}}` 等占位符（见 `commands/template.rs`）。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TodoTemplate {
+    pub id: i64,
+    /// 模板名（列表里显示用，如"发版清单"）
+    pub name: String,
+    pub title: String,
+    pub description: Option<String>,
+    /// 颜色（HEX 格式，如 #EF4444）
+    pub color: String,
+    /// 四象限：1=重要紧急, 2=重要不紧急, 3=紧急不重要, 4=不紧急不重要
+    pub quadrant: i32,
+    #[serde(default)]
+    pub subtasks: Vec<TemplateSubtask>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateSubtask {
+    pub title: String,
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTemplateRequest {
+    pub name: String,
+    pub title: String,
+    pub description: Option<String>,
+    #[serde(default = "default_template_color")]
+    pub color: String,
+    #[serde(default = "default_quadrant")]
+    pub quadrant: i32,
+    #[serde(default)]
+    pub subtasks: Vec<TemplateSubtask>,
+}
+
+fn default_template_color() -> String {
+    "#10B981".to_string()
+}
+
+/// 标签：按 name 唯一，只记颜色；待办挂哪些标签见 `Todo::tags`。
+/// 跨设备同步按 name 对齐，`id` 只在本机有意义。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tag {
+    #[serde(default)]
+    pub id: i64,
+    pub name: String,
+    /// 颜色（HEX 格式，如 #EF4444）
+    #[serde(default = "default_tag_color")]
+    pub color: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateTagRequest {
+    pub name: String,
+    #[serde(default = "default_tag_color")]
+    pub color: String,
+}
+
+fn default_tag_color() -> String {
+    DEFAULT_TAG_COLOR.to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateTagRequest {
+    /// 改名：所有挂着该标签的待办跟着变
+    pub name: Option<String>,
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateTemplateRequest {
+    pub name: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub color: Option<String>,
+    pub quadrant: Option<i32>,
+    /// 整体替换子任务清单
+    pub subtasks: Option<Vec<TemplateSubtask>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct WindowPosition {
@@ -222,6 +414,31 @@ pub struct AppSettings {
     /// 通知类型：system 或 app
     #[serde(default = "default_notification_type")]
     pub notification_type: String,
+    /// 云端 `/views` 维护的具名视图，经 sync-data 的 settings 同步过来。
+    /// 前端 `save_settings` 不带此字段，故只由同步 / 导入写入。
+    #[serde(default)]
+    pub saved_views: Vec<SavedView>,
+}
+
+/// 具名 saved view：filter 表达式 + 排序 + 投影字段，语法见 cloud
+/// `GET /todos?filter=`。PC 端目前只保存与透传，保证 push 回去时不丢。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedView {
+    pub name: String,
+    #[serde(default)]
+    pub filter: Option<String>,
+    #[serde(default)]
+    pub sort: Option<String>,
+    #[serde(default)]
+    pub fields: Option<Vec<String>>,
+    #[serde(default)]
+    pub with_subtasks: bool,
+    #[serde(default)]
+    pub created_at: Option<String>,
+    /// LWW 依据：云端 push merge 按它逐条比较
+    #[serde(default)]
+    pub updated_at: Option<String>,
 }
 
 fn default_text_theme() -> String {
@@ -272,6 +489,12 @@ pub struct ExportData {
     pub exported_at: String,
     pub todos: Vec<Todo>,
     pub settings: AppSettings,
+    /// todo 模板；旧备份没有此字段，反序列化为空
+    #[serde(default)]
+    pub templates: Vec<TodoTemplate>,
+    /// 标签颜色登记；待办挂的标签在 `Todo::tags` 里，旧备份没有此字段
+    #[serde(default)]
+    pub tags: Vec<Tag>,
 }
 
 /// 屏幕配置记录，用于存储不同屏幕组合下的窗口状态
@@ -21,8 +21,11 @@ use commands::{
     get_screen_config, get_settings, get_show_calendar, get_subtask, get_sync_settings,
     get_system_fonts, get_text_theme, get_todo_font_family, get_todo_font_size, get_todos,
     get_window_background, get_window_persist_state, import_data, import_data_from_file,
-    import_subtasks_from_paths,
-    is_fixed_mode, list_screen_configs, reorder_subtasks, reorder_todos, reset_window,
+    import_subtasks_from_paths, demote_todo, duplicate_todo, promote_subtask,
+    get_templates, create_template, update_template, delete_template, instantiate_template,
+    get_tags, create_tag, update_tag, delete_tag,
+    is_fixed_mode, list_screen_configs, parse_quick_add, quick_add_todo, reorder_subtasks,
+    reorder_todos, reset_window,
     save_screen_config,
     save_settings, save_subtask_image, save_sync_settings, set_auto_hide_cursor_inside,
     set_auto_hide_enabled, set_notification_type, set_show_calendar, set_text_theme, set_top_on_wake,
@@ -279,14 +282,30 @@ pub fn run() {
             get_todos,
             create_todo,
             update_todo,
+            parse_quick_add,
+            quick_add_todo,
             delete_todo,
             reorder_todos,
             reorder_subtasks,
+            promote_subtask,
+            demote_todo,
+            duplicate_todo,
             // 子任务命令
             create_subtask,
             update_subtask,
             delete_subtask,
             import_subtasks_from_paths,
+            // 模板命令
+            get_templates,
+            create_template,
+            update_template,
+            delete_template,
+            instantiate_template,
+            // 标签命令
+            get_tags,
+            create_tag,
+            update_tag,
+            delete_tag,
             // 图片命令
             get_images_dir,
             get_subtask,
@@ -1,2 +1,4 @@
 export { useTodoStore } from './todoStore'
+export { useTemplateStore } from './templateStore'
+export { useTagStore } from './tagStore'
 export { useAppStore, APP_VERSION } from './appStore'
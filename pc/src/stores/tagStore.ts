@@ -0,0 +1,86 @@
+import { defineStore } from 'pinia'
+import { ref } from 'vue'
+import { invoke } from '@tauri-apps/api/core'
+import type { Tag, CreateTagRequest, UpdateTagRequest } from '@/types'
+import { DEFAULT_TAG_COLOR } from '@/types'
+import { useTodoStore } from './todoStore'
+
+export const useTagStore = defineStore('tag', () => {
+  // 状态
+  const tags = ref<Tag[]>([])
+  const error = ref<string | null>(null)
+
+  // 标签颜色；待办上挂着但还没拉到的标签按默认颜色显示
+  function colorOf(name: string): string {
+    return tags.value.find(t => t.name === name)?.color ?? DEFAULT_TAG_COLOR
+  }
+
+  // 操作方法
+  async function fetchTags() {
+    error.value = null
+    try {
+      tags.value = await invoke<Tag[]>('get_tags')
+    } catch (e) {
+      error.value = String(e)
+      console.error('Failed to fetch tags:', e)
+    }
+  }
+
+  async function addTag(data: CreateTagRequest): Promise<Tag | null> {
+    try {
+      const tag = await invoke<Tag>('create_tag', { data })
+      tags.value.push(tag)
+      tags.value.sort((a, b) => a.name.localeCompare(b.name))
+      return tag
+    } catch (e) {
+      error.value = String(e)
+      console.error('Failed to add tag:', e)
+      return null
+    }
+  }
+
+  // 改名会改写所有挂着该标签的待办，成功后重新拉一次待办
+  async function updateTag(id: number, data: UpdateTagRequest): Promise<boolean> {
+    try {
+      const updated = await invoke<Tag>('update_tag', { id, data })
+      const index = tags.value.findIndex(t => t.id === id)
+      if (index !== -1) {
+        tags.value[index] = updated
+      }
+      if (data.name !== undefined) {
+        await useTodoStore().fetchTodos()
+      }
+      return true
+    } catch (e) {
+      error.value = String(e)
+      console.error('Failed to update tag:', e)
+      return false
+    }
+  }
+
+  // 删除标签会从所有待办上摘掉它
+  async function deleteTag(id: number): Promise<boolean> {
+    try {
+      await invoke('delete_tag', { id })
+      tags.value = tags.value.filter(t => t.id !== id)
+      await useTodoStore().fetchTodos()
+      return true
+    } catch (e) {
+      error.value = String(e)
+      console.error('Failed to delete tag:', e)
+      return false
+    }
+  }
+
+  return {
+    // 状态
+    tags,
+    error,
+    // 方法
+    colorOf,
+    fetchTags,
+    addTag,
+    updateTag,
+    deleteTag
+  }
+})
@@ -0,0 +1,86 @@
+import { defineStore } from 'pinia'
+import { ref } from 'vue'
+import { invoke } from '@tauri-apps/api/core'
+import type { Todo, TodoTemplate, CreateTemplateRequest, UpdateTemplateRequest } from '@/types'
+import { useTodoStore } from './todoStore'
+
+export const useTemplateStore = defineStore('template', () => {
+  // 状态
+  const templates = ref<TodoTemplate[]>([])
+  const error = ref<string | null>(null)
+
+  // 操作方法
+  async function fetchTemplates() {
+    error.value = null
+    try {
+      templates.value = await invoke<TodoTemplate[]>('get_templates')
+    } catch (e) {
+      error.value = String(e)
+      console.error('Failed to fetch templates:', e)
+    }
+  }
+
+  async function addTemplate(data: CreateTemplateRequest): Promise<TodoTemplate | null> {
+    try {
+      const template = await invoke<TodoTemplate>('create_template', { data })
+      templates.value.push(template)
+      return template
+    } catch (e) {
+      error.value = String(e)
+      console.error('Failed to add template:', e)
+      return null
+    }
+  }
+
+  async function updateTemplate(id: number, data: UpdateTemplateRequest): Promise<boolean> {
+    try {
+      const updated = await invoke<TodoTemplate>('update_template', { id, data })
+      const index = templates.value.findIndex(t => t.id === id)
+      if (index !== -1) {
+        templates.value[index] = updated
+      }
+      return true
+    } catch (e) {
+      error.value = String(e)
+      console.error('Failed to update template:', e)
+      return false
+    }
+  }
+
+  async function deleteTemplate(id: number): Promise<boolean> {
+    try {
+      await invoke('delete_template', { id })
+      templates.value = templates.value.filter(t => t.id !== id)
+      return true
+    } catch (e) {
+      error.value = String(e)
+      console.error('Failed to delete template:', e)
+      return false
+    }
+  }
+
+  // 按模板新建待办；params 填占位符的值（缺参数时后端报错并列出缺的 key）
+  async function instantiateTemplate(id: number, params: Record<string, string> = {}): Promise<Todo | null> {
+    try {
+      const todo = await invoke<Todo>('instantiate_template', { id, params })
+      useTodoStore().todos.push(todo)
+      return todo
+    } catch (e) {
+      error.value = String(e)
+      console.error('Failed to instantiate template:', e)
+      return null
+    }
+  }
+
+  return {
+    // 状态
+    templates,
+    error,
+    // 方法
+    fetchTemplates,
+    addTemplate,
+    updateTemplate,
+    deleteTemplate,
+    instantiateTemplate
+  }
+})
@@ -222,6 +222,56 @@ export const useTodoStore = defineStore('todo', () => {
     return false
   }
 
+  // 子任务升级为独立待办
+  async function promoteSubTask(id: number): Promise<Todo | null> {
+    try {
+      const newTodo = await invoke<Todo>('promote_subtask', { id })
+      for (const todo of todos.value) {
+        const index = todo.subtasks.findIndex(s => s.id === id)
+        if (index !== -1) {
+          todo.subtasks.splice(index, 1)
+          break
+        }
+      }
+      todos.value.push(newTodo)
+      return newTodo
+    } catch (e) {
+      error.value = String(e)
+      console.error('Failed to promote subtask:', e)
+      return null
+    }
+  }
+
+  // 待办降级为另一个待办的子任务（自带的子任务一并挪过去）
+  async function demoteTodo(id: number, intoId: number): Promise<boolean> {
+    try {
+      const target = await invoke<Todo>('demote_todo', { id, intoId })
+      todos.value = todos.value.filter(t => t.id !== id)
+      const index = todos.value.findIndex(t => t.id === intoId)
+      if (index !== -1) {
+        todos.value[index] = target
+      }
+      return true
+    } catch (e) {
+      error.value = String(e)
+      console.error('Failed to demote todo:', e)
+      return false
+    }
+  }
+
+  // 复制待办（含子任务）；shiftDays 把开始 / 截止 / 提醒时间平移若干天
+  async function duplicateTodo(id: number, shiftDays?: number): Promise<Todo | null> {
+    try {
+      const copy = await invoke<Todo>('duplicate_todo', { id, shiftDays })
+      todos.value.push(copy)
+      return copy
+    } catch (e) {
+      error.value = String(e)
+      console.error('Failed to duplicate todo:', e)
+      return null
+    }
+  }
+
   return {
     // 状态
     todos,
@@ -247,6 +297,9 @@ export const useTodoStore = defineStore('todo', () => {
     addSubTask,
     updateSubTask,
     deleteSubTask,
-    toggleSubTaskComplete
+    toggleSubTaskComplete,
+    promoteSubTask,
+    demoteTodo,
+    duplicateTodo
   }
 })
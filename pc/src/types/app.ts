@@ -45,6 +45,19 @@ export interface AppSettings {
   windowBgAlpha: number
   /** 文本主题：light（浅色文字，适配深色背景）或 dark（深色文字，适配浅色背景）*/
   textTheme: TextTheme
+  /** 云端 saved views（经 sync-data settings 同步，只读） */
+  savedViews?: SavedView[]
+}
+
+/** 云端 `/views` 定义的具名视图：filter 表达式 + 排序 + 投影 */
+export interface SavedView {
+  name: string
+  filter: string | null
+  sort: string | null
+  fields: string[] | null
+  withSubtasks: boolean
+  createdAt?: string
+  updatedAt?: string
 }
 
 // 窗口模式
@@ -37,6 +37,9 @@ export const DEFAULT_QUADRANT = QUADRANTS.NOT_URGENT_NOT_IMPORTANT
 // 视图模式
 export type ViewMode = 'list' | 'quadrant'
 
+// 优先级（与 cloud priority 一致）
+export type TodoPriority = 'high' | 'medium' | 'low'
+
 // 子任务接口
 export interface SubTask {
   id: number
@@ -79,6 +82,14 @@ export interface Todo {
   repeatWeekdays?: string | null
   /** 月重复的日期（1~31） */
   repeatMonthDay?: number | null
+  /** 最近一次标记完成的时间（未完成为空） */
+  completedAt?: string | null
+  /** 优先级（可为空） */
+  priority?: TodoPriority | null
+  /** 截止日期（YYYY-MM-DD，也可带时间；为空时截止按 endTime 算，与 cloud 一致） */
+  dueDate?: string | null
+  /** 标签名 */
+  tags: string[]
   subtasks: SubTask[]
 }
 
@@ -96,6 +107,12 @@ export interface CreateTodoRequest {
   startTime?: string
   /** 截止时间 */
   endTime?: string
+  /** 优先级 */
+  priority?: TodoPriority
+  /** 截止日期 */
+  dueDate?: string
+  /** 标签名（没登记过的按默认颜色自动创建） */
+  tags?: string[]
 }
 
 // 更新待办请求
@@ -120,6 +137,14 @@ export interface UpdateTodoRequest {
   clearStartTime?: boolean
   /** 是否明确清除截止时间 */
   clearEndTime?: boolean
+  /** 优先级 */
+  priority?: TodoPriority
+  /** 截止日期 */
+  dueDate?: string
+  /** 是否明确清除优先级 */
+  clearPriority?: boolean
+  /** 是否明确清除截止日期 */
+  clearDueDate?: boolean
   /** 是否启用重复提醒 */
   repeatEnabled?: boolean
   /** 重复类型 */
@@ -132,6 +157,31 @@ export interface UpdateTodoRequest {
   repeatMonthDay?: number | null
   /** 是否明确清除重复提醒 */
   clearRepeat?: boolean
+  /** 整体替换标签（[] 清空） */
+  tags?: string[]
+}
+
+// 快速添加识别出的片段
+export interface QuickAddToken {
+  kind: 'tag' | 'quadrant' | 'repeat' | 'date' | 'time'
+  /** 原文 */
+  text: string
+}
+
+// 快速添加解析结果（parse_quick_add，与 cloud POST /todos/quick 的 parsed 一致）
+export interface QuickAddResult {
+  title: string
+  startTime: string | null
+  endTime: string | null
+  notifyAt: string | null
+  quadrant: QuadrantType | null
+  tags: string[]
+  repeatEnabled: boolean
+  repeatType: string | null
+  repeatInterval: number | null
+  repeatWeekdays: string | null
+  repeatMonthDay: number | null
+  tokens: QuickAddToken[]
 }
 
 // 创建子任务请求
@@ -149,10 +199,75 @@ export interface UpdateSubTaskRequest {
   sortOrder?: number
 }
 
+// 模板子任务
+export interface TemplateSubtask {
+  title: string
+  content?: string | null
+}
+
+// todo 模板：文本中可写 {{date}} / {{week}} / {{name}} 等占位符，实例化时展开
+export interface TodoTemplate {
+  id: number
+  name: string
+  title: string
+  description: string | null
+  color: string
+  quadrant: number
+  subtasks: TemplateSubtask[]
+  createdAt: string
+  updatedAt: string
+}
+
+// 创建模板请求
+export interface CreateTemplateRequest {
+  name: string
+  title: string
+  description?: string
+  color?: string
+  quadrant?: number
+  subtasks?: TemplateSubtask[]
+}
+
+// 更新模板请求（subtasks 整体替换）
+export interface UpdateTemplateRequest {
+  name?: string
+  title?: string
+  description?: string
+  color?: string
+  quadrant?: number
+  subtasks?: TemplateSubtask[]
+}
+
+// 标签默认颜色（与后端 DEFAULT_TAG_COLOR 一致）
+export const DEFAULT_TAG_COLOR = '#6B7280'
+
+// 标签：按 name 唯一，只登记颜色
+export interface Tag {
+  id: number
+  name: string
+  color: string
+  createdAt: string
+  updatedAt: string
+}
+
+// 创建标签请求
+export interface CreateTagRequest {
+  name: string
+  color?: string
+}
+
+// 更新标签请求（改名时所有挂着该标签的待办跟着变）
+export interface UpdateTagRequest {
+  name?: string
+  color?: string
+}
+
 // 导出数据格式
 export interface ExportData {
   version: string
   exportedAt: string
   todos: Todo[]
   settings: Record<string, unknown>
+  templates?: TodoTemplate[]
+  tags?: Tag[]
 }
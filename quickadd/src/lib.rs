@@ -0,0 +1,474 @@
+//! mini-todo 快速添加解析器：PC 托盘快速添加与 cloud `POST /todos/quick` 共用。
+//!
+//! 输入一行文字，例如：
+//!
+//! - `明天下午3点 提交周报 #work !1 每周五提醒`
+//! - `submit report tomorrow 3pm every Friday`
+//!
+//! 解析出标题、时间（`startTime` / `endTime` / `notifyAt`）、象限、标签和
+//! 重复规则。认不出的文字全部留在标题里。解析器不会报错，最坏的情况是整行
+//! 都成了标题。
+//!
+//! 时间的落点：
+//! - 单个时刻：`endTime` 和 `notifyAt` 都是这个时刻；
+//! - 时间段（`下午3点到5点` / `3pm-5pm`）：写入 `startTime` / `endTime`，`notifyAt` 取开始；
+//! - 只有日期：`endTime` 为当天 23:59:00，与 PC 编辑器只选日期时一致；
+//! - 有时刻没日期：取今天，时刻已过就顺延到明天；
+//! - 有重复规则没日期：取今天起第一个符合规则的日子。没写时刻时提醒默认
+//!   09:00，因为 PC 的重复是靠 `notifyAt` 往后推的，没有 `notifyAt` 就不会重复；
+//! - 日期和重复规则都有、但日期不合规则（`明天` + `每周五`）：顺延到该日期起
+//!   第一个符合规则的日子。
+//!
+//! 所有时间都按调用方传入的 `now` 的时区理解（cloud 用 `config.timezone`，
+//! PC 用本机时区），输出格式 `YYYY-MM-DDTHH:MM:SS`，与 PC 数据库一致。
+
+mod scan;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::Serialize;
+
+use scan::{Piece, RepeatKind};
+
+/// 与 PC `todos` 表的 datetime 文本格式一致。
+const DATETIME_FMT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// 有重复规则但没写时刻时的默认提醒时刻。
+const DEFAULT_REMIND: (u32, u32) = (9, 0);
+
+/// 解析结果，字段名与 todo JSON 对齐，可以直接合并进创建请求。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickAdd {
+    pub title: String,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub notify_at: Option<String>,
+    pub quadrant: Option<i32>,
+    pub tags: Vec<String>,
+    pub repeat_enabled: bool,
+    pub repeat_type: Option<String>,
+    pub repeat_interval: Option<i32>,
+    pub repeat_weekdays: Option<String>,
+    pub repeat_month_day: Option<i32>,
+    /// 识别出的片段（按出现顺序），给 UI 高亮和排查用
+    pub tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// 原文（已去掉首尾空白）
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    Tag,
+    Quadrant,
+    Repeat,
+    Date,
+    Time,
+}
+
+/// 解析一行快速添加文本。`now` 是调用方时区下的本地时间。
+///
+/// 同类片段出现多次时取第一个（标签除外，标签全部收集并去重）。
+pub fn parse(input: &str, now: NaiveDateTime) -> QuickAdd {
+    let today = now.date();
+    let scanned = scan::scan(input, today);
+
+    let mut out = QuickAdd::default();
+    let mut date = None;
+    let mut time = None;
+    let mut repeat = None;
+    for (piece, text) in scanned.pieces {
+        let kind = match piece {
+            Piece::Tag(t) => {
+                if !out.tags.contains(&t) {
+                    out.tags.push(t);
+                }
+                TokenKind::Tag
+            }
+            Piece::Quadrant(q) => {
+                out.quadrant.get_or_insert(q);
+                TokenKind::Quadrant
+            }
+            Piece::Repeat(r) => {
+                repeat.get_or_insert(r);
+                TokenKind::Repeat
+            }
+            Piece::Date(d, _) => {
+                date.get_or_insert(d);
+                TokenKind::Date
+            }
+            Piece::Time(start, end) => {
+                time.get_or_insert((start, end));
+                TokenKind::Time
+            }
+        };
+        out.tokens.push(Token { kind, text });
+    }
+    out.title = clean_title(&scanned.rest);
+
+    let remind = time.map(|(start, _)| start).or_else(|| {
+        repeat
+            .as_ref()
+            .and_then(|_| NaiveTime::from_hms_opt(DEFAULT_REMIND.0, DEFAULT_REMIND.1, 0))
+    });
+    let date = match date {
+        // 写了日期又写了重复：日期不合规则时顺延到它之后第一个符合的日子，
+        // 否则首次提醒落在周四、重复却是每周五
+        Some(d) => match &repeat {
+            Some(r) => first_date(d, |x| r.matches(x)),
+            None => Some(d),
+        },
+        None if time.is_none() && repeat.is_none() => None,
+        None => first_date(today, |d| {
+            repeat.as_ref().is_none_or(|r| r.matches(d))
+                && remind.is_none_or(|t| d.and_time(t) > now)
+        }),
+    };
+
+    if let Some(d) = date {
+        match time {
+            Some((start, Some(end))) => {
+                // 结束早于开始视为跨午夜
+                let end_date = if end <= start {
+                    d + Duration::days(1)
+                } else {
+                    d
+                };
+                out.start_time = Some(fmt(d.and_time(start)));
+                out.end_time = Some(fmt(end_date.and_time(end)));
+                out.notify_at = out.start_time.clone();
+            }
+            Some((at, None)) => {
+                out.end_time = Some(fmt(d.and_time(at)));
+                out.notify_at = out.end_time.clone();
+            }
+            None => {
+                out.end_time = d.and_hms_opt(23, 59, 0).map(fmt);
+                out.notify_at = remind.map(|t| fmt(d.and_time(t)));
+            }
+        }
+    }
+
+    if let Some(r) = repeat {
+        out.repeat_enabled = true;
+        out.repeat_type = Some(r.kind.as_str().to_string());
+        out.repeat_interval = Some(r.interval as i32);
+        match r.kind {
+            RepeatKind::Weekly => {
+                // PC 把 weekly + 空 weekdays 当成每天，所以没写星期时用首次那天的星期
+                let weekdays = if r.weekdays.is_empty() {
+                    date.map(|d| vec![d.weekday().number_from_monday()])
+                } else {
+                    Some(r.weekdays)
+                };
+                out.repeat_weekdays = weekdays.map(|w| {
+                    w.iter()
+                        .map(|d| d.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                });
+            }
+            RepeatKind::Monthly => {
+                out.repeat_month_day = r.month_day.or(date.map(|d| d.day())).map(|d| d as i32);
+            }
+            RepeatKind::Daily => {}
+        }
+    }
+
+    out
+}
+
+fn fmt(dt: NaiveDateTime) -> String {
+    dt.format(DATETIME_FMT).to_string()
+}
+
+/// 从 `from` 起一年内第一个满足条件的日子。
+fn first_date(from: NaiveDate, pred: impl Fn(NaiveDate) -> bool) -> Option<NaiveDate> {
+    (0..=366)
+        .map(|k| from + Duration::days(k))
+        .find(|&d| pred(d))
+}
+
+/// 片段被挖掉后剩下的文字：合并空白，去掉首尾残留的标点。
+fn clean_title(rest: &str) -> String {
+    let joined = rest.split_whitespace().collect::<Vec<_>>().join(" ");
+    joined
+        .trim_matches(|c: char| c.is_whitespace() || ",，、;；:：".contains(c))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2026-05-13 是周三
+    fn now() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2026-05-13 10:30:00", "%Y-%m-%d %H:%M:%S").unwrap()
+    }
+
+    fn p(s: &str) -> QuickAdd {
+        parse(s, now())
+    }
+
+    #[test]
+    fn chinese_full_example() {
+        let q = p("明天下午3点 提交周报 #work !1 每周五提醒");
+        assert_eq!(q.title, "提交周报");
+        // 明天是周四，与每周五冲突 → 顺延到周五
+        assert_eq!(q.end_time.as_deref(), Some("2026-05-15T15:00:00"));
+        assert_eq!(q.notify_at.as_deref(), Some("2026-05-15T15:00:00"));
+        assert_eq!(q.start_time, None);
+        assert_eq!(q.quadrant, Some(1));
+        assert_eq!(q.tags, vec!["work"]);
+        assert!(q.repeat_enabled);
+        assert_eq!(q.repeat_type.as_deref(), Some("weekly"));
+        assert_eq!(q.repeat_interval, Some(1));
+        assert_eq!(q.repeat_weekdays.as_deref(), Some("5"));
+        let kinds: Vec<_> = q.tokens.iter().map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::Date,
+                TokenKind::Time,
+                TokenKind::Tag,
+                TokenKind::Quadrant,
+                TokenKind::Repeat
+            ]
+        );
+        assert_eq!(q.tokens[4].text, "每周五提醒");
+    }
+
+    #[test]
+    fn english_full_example() {
+        let q = p("submit report tomorrow 3pm every Friday");
+        assert_eq!(q.title, "submit report");
+        assert_eq!(q.end_time.as_deref(), Some("2026-05-15T15:00:00"));
+        assert_eq!(q.repeat_type.as_deref(), Some("weekly"));
+        assert_eq!(q.repeat_weekdays.as_deref(), Some("5"));
+    }
+
+    #[test]
+    fn plain_text_is_all_title() {
+        let q = p("buy 2 apples");
+        assert_eq!(q.title, "buy 2 apples");
+        assert!(q.tokens.is_empty());
+        assert_eq!(q.end_time, None);
+        assert!(!q.repeat_enabled);
+    }
+
+    #[test]
+    fn relative_dates() {
+        assert_eq!(
+            p("后天 交房租").end_time.as_deref(),
+            Some("2026-05-15T23:59:00")
+        );
+        assert_eq!(
+            p("3天后 复查").end_time.as_deref(),
+            Some("2026-05-16T23:59:00")
+        );
+        assert_eq!(
+            p("in 2 weeks review").end_time.as_deref(),
+            Some("2026-05-27T23:59:00")
+        );
+        assert_eq!(
+            p("day after tomorrow x").end_time.as_deref(),
+            Some("2026-05-15T23:59:00")
+        );
+        // 日期没有时刻就不提醒
+        assert_eq!(p("后天 交房租").notify_at, None);
+    }
+
+    #[test]
+    fn weekday_dates() {
+        // 今天周三：周五 = 本周五，周三 = 今天，下周一 = 5/18，这周一 = 已过去的 5/11
+        assert_eq!(p("周五 x").end_time.as_deref(), Some("2026-05-15T23:59:00"));
+        assert_eq!(
+            p("星期三 x").end_time.as_deref(),
+            Some("2026-05-13T23:59:00")
+        );
+        assert_eq!(
+            p("下周一 x").end_time.as_deref(),
+            Some("2026-05-18T23:59:00")
+        );
+        assert_eq!(
+            p("这周一 x").end_time.as_deref(),
+            Some("2026-05-11T23:59:00")
+        );
+        assert_eq!(
+            p("x on tue").end_time.as_deref(),
+            Some("2026-05-19T23:59:00")
+        );
+        assert_eq!(
+            p("x next friday").end_time.as_deref(),
+            Some("2026-05-22T23:59:00")
+        );
+        // 裸缩写不当星期：sun 是普通单词
+        assert_eq!(p("buy sun cream").title, "buy sun cream");
+    }
+
+    #[test]
+    fn absolute_dates() {
+        assert_eq!(
+            p("5月20日 体检").end_time.as_deref(),
+            Some("2026-05-20T23:59:00")
+        );
+        // 已过去的月日落到明年
+        assert_eq!(
+            p("3月1号 x").end_time.as_deref(),
+            Some("2027-03-01T23:59:00")
+        );
+        assert_eq!(
+            p("2026年6月1日 x").end_time.as_deref(),
+            Some("2026-06-01T23:59:00")
+        );
+        assert_eq!(
+            p("x 2026-07-04").end_time.as_deref(),
+            Some("2026-07-04T23:59:00")
+        );
+        assert_eq!(
+            p("x May 20th").end_time.as_deref(),
+            Some("2026-05-20T23:59:00")
+        );
+        assert_eq!(
+            p("x 1st of june").end_time.as_deref(),
+            Some("2026-06-01T23:59:00")
+        );
+    }
+
+    #[test]
+    fn times() {
+        let at = |s: &str| p(s).end_time.unwrap();
+        assert_eq!(at("明天上午9点半 x"), "2026-05-14T09:30:00");
+        assert_eq!(at("明天三点一刻 x"), "2026-05-14T03:15:00");
+        assert_eq!(at("明天中午1点 x"), "2026-05-14T13:00:00");
+        assert_eq!(at("明天晚上8点20分 x"), "2026-05-14T20:20:00");
+        assert_eq!(at("明晚8点 x"), "2026-05-14T20:00:00");
+        assert_eq!(at("x tomorrow at 3:30 p.m."), "2026-05-14T15:30:00");
+        assert_eq!(at("x tomorrow 12am"), "2026-05-14T00:00:00");
+        assert_eq!(at("x tomorrow noon"), "2026-05-14T12:00:00");
+        assert_eq!(at("x tomorrow 18:45"), "2026-05-14T18:45:00");
+    }
+
+    #[test]
+    fn time_without_date_rolls_over_when_past() {
+        // now = 10:30：下午3点还没到 → 今天；9点已过 → 明天
+        assert_eq!(
+            p("下午3点 开会").end_time.as_deref(),
+            Some("2026-05-13T15:00:00")
+        );
+        assert_eq!(
+            p("9:00 standup").end_time.as_deref(),
+            Some("2026-05-14T09:00:00")
+        );
+    }
+
+    #[test]
+    fn time_ranges() {
+        let q = p("明天下午3点到5点 评审");
+        assert_eq!(q.title, "评审");
+        assert_eq!(q.start_time.as_deref(), Some("2026-05-14T15:00:00"));
+        assert_eq!(q.end_time.as_deref(), Some("2026-05-14T17:00:00"));
+        assert_eq!(q.notify_at, q.start_time);
+
+        let q = p("review tomorrow from 9:00 to 10:30");
+        assert_eq!(q.title, "review");
+        assert_eq!(q.start_time.as_deref(), Some("2026-05-14T09:00:00"));
+        assert_eq!(q.end_time.as_deref(), Some("2026-05-14T10:30:00"));
+
+        // 结束早于开始 → 跨到第二天
+        let q = p("明天 22:00-01:00 上线");
+        assert_eq!(q.end_time.as_deref(), Some("2026-05-15T01:00:00"));
+    }
+
+    #[test]
+    fn chinese_repeats() {
+        let q = p("每天早上8点 吃药");
+        assert_eq!(q.title, "吃药");
+        assert_eq!(q.repeat_type.as_deref(), Some("daily"));
+        // 今天 8 点已过 → 首次是明天
+        assert_eq!(q.notify_at.as_deref(), Some("2026-05-14T08:00:00"));
+
+        let q = p("每周一三五 健身");
+        assert_eq!(q.repeat_weekdays.as_deref(), Some("1,3,5"));
+        // 没写时刻：默认 09:00 提醒，今天周三 9 点已过 → 周五
+        assert_eq!(q.notify_at.as_deref(), Some("2026-05-15T09:00:00"));
+
+        let q = p("每个工作日 写日报");
+        assert_eq!(q.repeat_weekdays.as_deref(), Some("1,2,3,4,5"));
+
+        let q = p("每2周 复盘");
+        assert_eq!(q.repeat_interval, Some(2));
+        assert_eq!(q.repeat_weekdays.as_deref(), Some("4"));
+
+        let q = p("每月15号 还信用卡");
+        assert_eq!(q.repeat_type.as_deref(), Some("monthly"));
+        assert_eq!(q.repeat_month_day, Some(15));
+        assert_eq!(q.end_time.as_deref(), Some("2026-05-15T23:59:00"));
+    }
+
+    #[test]
+    fn explicit_date_snaps_to_repeat_rule() {
+        // 周五本身符合每周五，不动
+        let q = p("周五 下午3点 周报 每周五");
+        assert_eq!(q.end_time.as_deref(), Some("2026-05-15T15:00:00"));
+
+        // 周四 + 每周一三五 → 下一个符合的周五
+        let q = p("明天 健身 每周一三五");
+        assert_eq!(q.end_time.as_deref(), Some("2026-05-15T23:59:00"));
+        assert_eq!(q.notify_at.as_deref(), Some("2026-05-15T09:00:00"));
+        assert_eq!(q.repeat_weekdays.as_deref(), Some("1,3,5"));
+
+        // 月度同理：5 月 20 日之后第一个 15 号是 6 月 15 日
+        let q = p("5月20日 还信用卡 每月15号");
+        assert_eq!(q.end_time.as_deref(), Some("2026-06-15T23:59:00"));
+        assert_eq!(q.repeat_month_day, Some(15));
+    }
+
+    #[test]
+    fn english_repeats() {
+        let q = p("standup every weekday at 9:30am");
+        assert_eq!(q.title, "standup");
+        assert_eq!(q.repeat_weekdays.as_deref(), Some("1,2,3,4,5"));
+        assert_eq!(q.notify_at.as_deref(), Some("2026-05-14T09:30:00"));
+
+        let q = p("gym every mon, wed and fri");
+        assert_eq!(q.repeat_weekdays.as_deref(), Some("1,3,5"));
+
+        let q = p("water plants every other day");
+        assert_eq!(q.repeat_type.as_deref(), Some("daily"));
+        assert_eq!(q.repeat_interval, Some(2));
+
+        let q = p("pay rent monthly on the 1st");
+        assert_eq!(q.title, "pay rent");
+        assert_eq!(q.repeat_month_day, Some(1));
+        assert_eq!(q.end_time.as_deref(), Some("2026-06-01T23:59:00"));
+    }
+
+    #[test]
+    fn tags_and_quadrant_need_word_boundaries() {
+        let q = p("fix issue#12 !5 #bug #bug ！2");
+        assert_eq!(q.title, "fix issue#12 !5");
+        assert_eq!(q.tags, vec!["bug"]);
+        assert_eq!(q.quadrant, Some(2));
+    }
+
+    #[test]
+    fn workday_word_inside_title_is_kept() {
+        let q = p("整理工作日报");
+        assert_eq!(q.title, "整理工作日报");
+        assert!(!q.repeat_enabled);
+    }
+
+    #[test]
+    fn serializes_camel_case() {
+        let v = serde_json::to_value(p("明天 x #a")).unwrap();
+        assert_eq!(v["endTime"], "2026-05-14T23:59:00");
+        assert_eq!(v["repeatEnabled"], false);
+        assert_eq!(v["tokens"][0]["kind"], "date");
+    }
+}
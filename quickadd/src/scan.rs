@@ -0,0 +1,850 @@
+//! 逐字符扫描：在每个位置依次尝试各类片段 matcher，命中就整段吃掉，
+//! 都不命中则该字符原样留给标题。
+//!
+//! 中文不分词，所以不先切 token；英文 matcher 自己检查 ASCII 词边界，
+//! 避免把 `Saturn` 里的 `Sat` 当成星期六。
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveTime};
+
+/// 时段修饰：上午 / 中午 / 下午（含 am / pm、今晚等日期词带出的时段）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Period {
+    Am,
+    Noon,
+    Pm,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum RepeatKind {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl RepeatKind {
+    /// 与 PC `todos.repeat_type` 取值一致。
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            RepeatKind::Daily => "daily",
+            RepeatKind::Weekly => "weekly",
+            RepeatKind::Monthly => "monthly",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Repeat {
+    pub kind: RepeatKind,
+    pub interval: u32,
+    /// 1 = 周一 … 7 = 周日，升序去重；空 = 未指定
+    pub weekdays: Vec<u32>,
+    pub month_day: Option<u32>,
+}
+
+impl Repeat {
+    fn new(kind: RepeatKind, interval: u32) -> Self {
+        Repeat {
+            kind,
+            interval: interval.max(1),
+            weekdays: Vec::new(),
+            month_day: None,
+        }
+    }
+
+    fn workdays() -> Self {
+        Repeat {
+            weekdays: vec![1, 2, 3, 4, 5],
+            ..Repeat::new(RepeatKind::Weekly, 1)
+        }
+    }
+
+    /// `d` 是否是该规则的一次发生（不看 interval，只用来找第一次）。
+    pub(crate) fn matches(&self, d: NaiveDate) -> bool {
+        match self.kind {
+            RepeatKind::Daily => true,
+            RepeatKind::Weekly => {
+                self.weekdays.is_empty()
+                    || self.weekdays.contains(&d.weekday().number_from_monday())
+            }
+            RepeatKind::Monthly => match self.month_day {
+                // 31 号在小月落到月末，与 PC next_monthly 的 clamp 一致
+                Some(md) => d.day() == md.min(last_day_of_month(d)),
+                None => true,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Piece {
+    Tag(String),
+    Quadrant(i32),
+    Repeat(Repeat),
+    /// 日期 + 日期词带出的时段（`今晚` → Pm），供紧随其后的时刻使用
+    Date(NaiveDate, Option<Period>),
+    /// 开始时刻 + 可选结束时刻（时间段）
+    Time(NaiveTime, Option<NaiveTime>),
+}
+
+pub(crate) struct Scanned {
+    /// 识别出的片段与其原文
+    pub pieces: Vec<(Piece, String)>,
+    /// 剩下的文字（片段处替换成空格）
+    pub rest: String,
+}
+
+pub(crate) fn scan(input: &str, today: NaiveDate) -> Scanned {
+    let c: Vec<char> = input.chars().collect();
+    let mut pieces = Vec::new();
+    let mut rest = String::new();
+    let mut period = None;
+    let mut i = 0;
+    while i < c.len() {
+        match match_at(&c, i, today, period) {
+            Some((end, piece)) => {
+                if let Piece::Date(_, p) = &piece {
+                    period = *p;
+                }
+                let text: String = c[i..end].iter().collect();
+                pieces.push((piece, text.trim().to_string()));
+                rest.push(' ');
+                i = end;
+            }
+            None => {
+                rest.push(c[i]);
+                i += 1;
+            }
+        }
+    }
+    Scanned { pieces, rest }
+}
+
+/// matcher 的顺序就是优先级：重复规则先于日期（`每周五` 不能被当成 `周五`），
+/// 日期先于时刻（`2026-05-20` 里的数字不能被时刻吃掉）。
+fn match_at(
+    c: &[char],
+    i: usize,
+    today: NaiveDate,
+    period: Option<Period>,
+) -> Option<(usize, Piece)> {
+    tag(c, i)
+        .or_else(|| quadrant(c, i))
+        .or_else(|| repeat_zh(c, i))
+        .or_else(|| repeat_en(c, i))
+        .or_else(|| date_iso(c, i))
+        .or_else(|| date_zh(c, i, today))
+        .or_else(|| date_en(c, i, today))
+        .or_else(|| time_range(c, i, period))
+}
+
+// =============================================================================
+// 标签 / 象限
+// =============================================================================
+
+/// `#work`：前面必须是行首或空白，`#` 之后到空白为止。
+fn tag(c: &[char], i: usize) -> Option<(usize, Piece)> {
+    if !matches!(c[i], '#' | '＃') || !after_space(c, i) {
+        return None;
+    }
+    let mut e = i + 1;
+    while e < c.len() && !c[e].is_whitespace() && !matches!(c[e], '#' | '＃') {
+        e += 1;
+    }
+    if e == i + 1 {
+        return None;
+    }
+    Some((e, Piece::Tag(c[i + 1..e].iter().collect())))
+}
+
+/// `!1` ~ `!4`（也接受全角 `！`），必须独立成词。
+fn quadrant(c: &[char], i: usize) -> Option<(usize, Piece)> {
+    if !matches!(c[i], '!' | '！') || !after_space(c, i) {
+        return None;
+    }
+    let q = c.get(i + 1)?.to_digit(10).filter(|q| (1..=4).contains(q))?;
+    let e = i + 2;
+    if e < c.len() && !c[e].is_whitespace() {
+        return None;
+    }
+    Some((e, Piece::Quadrant(q as i32)))
+}
+
+// =============================================================================
+// 重复规则
+// =============================================================================
+
+/// 每天 / 每N天 / 每周一三五 / 每2周 / 每个工作日 / 每月15号 / 每3个月，
+/// 可带尾随的 `提醒`。
+fn repeat_zh(c: &[char], i: usize) -> Option<(usize, Piece)> {
+    let (rep, e) = if let Some(e) = lit(c, i, "工作日") {
+        // 不带 每 的 工作日 必须独立出现，免得吃掉 `工作日报`
+        if !(e >= c.len() || c[e].is_whitespace() || lit(c, e, "提醒").is_some()) {
+            return None;
+        }
+        (Repeat::workdays(), e)
+    } else {
+        let p = lit(c, i, "每")?;
+        if let Some((_, e)) = first_lit(c, p, &["个工作日", "工作日"]) {
+            (Repeat::workdays(), e)
+        } else {
+            let (n, p) = number(c, p).unwrap_or((1, p));
+            let p = lit(c, p, "个").unwrap_or(p);
+            if let Some((_, e)) = first_lit(c, p, &["天", "日"]) {
+                (Repeat::new(RepeatKind::Daily, n), e)
+            } else if let Some((_, mut e)) = first_lit(c, p, &["周", "星期", "礼拜"]) {
+                let mut rep = Repeat::new(RepeatKind::Weekly, n);
+                rep.weekdays = zh_weekday_list(c, &mut e);
+                (rep, e)
+            } else if let Some(mut e) = lit(c, p, "月") {
+                let mut rep = Repeat::new(RepeatKind::Monthly, n);
+                if let Some((d, e2)) = number(c, e) {
+                    if let Some((_, e3)) = first_lit(c, e2, &["号", "日"]) {
+                        if (1..=31).contains(&d) {
+                            rep.month_day = Some(d);
+                            e = e3;
+                        }
+                    }
+                }
+                (rep, e)
+            } else {
+                return None;
+            }
+        }
+    };
+    // `每周五提醒` 里的 提醒 只是修饰，一并吃掉
+    let e = lit(c, e, "提醒").unwrap_or(e);
+    Some((e, Piece::Repeat(rep)))
+}
+
+/// `一三五` / `一、三和五` / `一和周三`，吃不到就返回空。
+fn zh_weekday_list(c: &[char], p: &mut usize) -> Vec<u32> {
+    let mut out = Vec::new();
+    let mut q = *p;
+    loop {
+        let mut r = q;
+        if !out.is_empty() {
+            if let Some((_, e)) = first_lit(c, r, &["、", ",", "，", "和", "及", "与", "/"]) {
+                r = e;
+            }
+            if let Some((_, e)) = first_lit(c, r, &["周", "星期", "礼拜"]) {
+                r = e;
+            }
+        }
+        match c.get(r).and_then(|&ch| zh_weekday(ch)) {
+            Some(wd) => {
+                if !out.contains(&wd) {
+                    out.push(wd);
+                }
+                q = r + 1;
+            }
+            None => break,
+        }
+    }
+    *p = q;
+    out.sort_unstable();
+    out
+}
+
+/// daily / weekly [on mon] / monthly [on the 15th] / weekdays /
+/// every day / every 2 weeks / every other day / every friday / every mon and thu
+fn repeat_en(c: &[char], i: usize) -> Option<(usize, Piece)> {
+    if !at_boundary(c, i) {
+        return None;
+    }
+    if let Some(e) = word(c, i, "daily") {
+        return Some((e, Piece::Repeat(Repeat::new(RepeatKind::Daily, 1))));
+    }
+    if let Some((_, e)) = first_word(c, i, &["weekdays", "workdays"]) {
+        return Some((e, Piece::Repeat(Repeat::workdays())));
+    }
+    if let Some(e) = word(c, i, "weekly") {
+        return Some(weekly_on(c, e, 1));
+    }
+    if let Some(e) = word(c, i, "monthly") {
+        return Some(monthly_on(c, e, 1));
+    }
+
+    let p = ws1(c, word(c, i, "every")?)?;
+    let (n, p) = if let Some(e) = word(c, p, "other") {
+        (2, ws1(c, e)?)
+    } else if let Some((n, e)) = digits(c, p, 3) {
+        (n, ws1(c, e)?)
+    } else {
+        (1, p)
+    };
+    if let Some((_, e)) = first_word(c, p, &["days", "day"]) {
+        return Some((e, Piece::Repeat(Repeat::new(RepeatKind::Daily, n))));
+    }
+    if let Some((_, e)) = first_word(c, p, &["weekdays", "weekday", "workdays", "workday"]) {
+        return Some((e, Piece::Repeat(Repeat::workdays())));
+    }
+    if let Some((_, e)) = first_word(c, p, &["weeks", "week"]) {
+        return Some(weekly_on(c, e, n));
+    }
+    if let Some((_, e)) = first_word(c, p, &["months", "month"]) {
+        return Some(monthly_on(c, e, n));
+    }
+    let (weekdays, e) = en_weekday_list(c, p)?;
+    let rep = Repeat {
+        weekdays,
+        ..Repeat::new(RepeatKind::Weekly, n)
+    };
+    Some((e, Piece::Repeat(rep)))
+}
+
+/// `weekly` 之后可选的 ` on mon, wed`。
+fn weekly_on(c: &[char], e: usize, n: u32) -> (usize, Piece) {
+    let mut rep = Repeat::new(RepeatKind::Weekly, n);
+    let list = ws1(c, e)
+        .and_then(|p| word(c, p, "on"))
+        .and_then(|p| ws1(c, p))
+        .and_then(|p| en_weekday_list(c, p));
+    let e = match list {
+        Some((weekdays, e2)) => {
+            rep.weekdays = weekdays;
+            e2
+        }
+        None => e,
+    };
+    (e, Piece::Repeat(rep))
+}
+
+/// `monthly` 之后可选的 ` on the 15th` / ` on day 15`。
+fn monthly_on(c: &[char], e: usize, n: u32) -> (usize, Piece) {
+    let mut rep = Repeat::new(RepeatKind::Monthly, n);
+    let day = ws1(c, e)
+        .and_then(|p| word(c, p, "on"))
+        .and_then(|p| ws1(c, p))
+        .map(|p| {
+            first_word(c, p, &["the", "day"])
+                .and_then(|(_, e)| ws1(c, e))
+                .unwrap_or(p)
+        })
+        .and_then(|p| ordinal_day(c, p));
+    let e = match day {
+        Some((d, e2)) => {
+            rep.month_day = Some(d);
+            e2
+        }
+        None => e,
+    };
+    (e, Piece::Repeat(rep))
+}
+
+fn en_weekday_list(c: &[char], p: usize) -> Option<(Vec<u32>, usize)> {
+    let (first, mut e) = en_weekday(c, p, true)?;
+    let mut out = vec![first];
+    loop {
+        let q = skip_ws(c, e);
+        let sep = first_lit(c, q, &[",", "&", "/"])
+            .map(|(_, e)| e)
+            .or_else(|| word(c, q, "and"));
+        let Some(q) = sep else { break };
+        let Some((wd, e2)) = en_weekday(c, skip_ws(c, q), true) else {
+            break;
+        };
+        if !out.contains(&wd) {
+            out.push(wd);
+        }
+        e = e2;
+    }
+    out.sort_unstable();
+    Some((out, e))
+}
+
+// =============================================================================
+// 日期
+// =============================================================================
+
+/// `2026-05-20` / `2026/5/20` / `2026.5.20`
+fn date_iso(c: &[char], i: usize) -> Option<(usize, Piece)> {
+    if !at_boundary(c, i) {
+        return None;
+    }
+    let (y, p) = digits(c, i, 4).filter(|&(_, e)| e - i == 4)?;
+    let sep = *c.get(p).filter(|ch| matches!(ch, '-' | '/' | '.'))?;
+    let (m, p) = digits(c, p + 1, 2)?;
+    if c.get(p) != Some(&sep) {
+        return None;
+    }
+    let (d, e) = digits(c, p + 1, 2)?;
+    if !end_boundary(c, e) {
+        return None;
+    }
+    let date = NaiveDate::from_ymd_opt(y as i32, m, d)?;
+    Some((e, Piece::Date(date, None)))
+}
+
+/// 今天 / 明晚 / 后天 / 周五 / 下周一 / 3天后 / 5月20日 / 2026年5月20号
+fn date_zh(c: &[char], i: usize, today: NaiveDate) -> Option<(usize, Piece)> {
+    const RELATIVE: [(&str, i64, Option<Period>); 10] = [
+        ("大后天", 3, None),
+        ("后天", 2, None),
+        ("明天", 1, None),
+        ("明日", 1, None),
+        ("明早", 1, Some(Period::Am)),
+        ("明晚", 1, Some(Period::Pm)),
+        ("今天", 0, None),
+        ("今日", 0, None),
+        ("今早", 0, Some(Period::Am)),
+        ("今晚", 0, Some(Period::Pm)),
+    ];
+    for (s, days, period) in RELATIVE {
+        if let Some(e) = lit(c, i, s) {
+            return Some((e, Piece::Date(today + Duration::days(days), period)));
+        }
+    }
+
+    // 周X：不带前缀 = 今天起最近的那个；这周 / 下周 / 下下周 = 按自然周（周一开始）
+    let (weeks, p) = match first_lit(c, i, &["下下个", "下下", "下个", "下", "这个", "这", "本"])
+    {
+        Some((k, e)) => (Some([2, 2, 1, 1, 0, 0, 0][k]), e),
+        None => (None, i),
+    };
+    if let Some((_, p)) = first_lit(c, p, &["星期", "礼拜", "周"]) {
+        if let Some(wd) = c.get(p).and_then(|&ch| zh_weekday(ch)) {
+            return Some((p + 1, Piece::Date(weekday_date(today, wd, weeks), None)));
+        }
+    }
+
+    let (n, p) = number(c, i)?;
+    if let Some((k, e)) = first_lit(
+        c,
+        p,
+        &["天后", "天之后", "日后", "个星期后", "星期后", "周后"],
+    ) {
+        let days = if k < 3 { n } else { n * 7 };
+        return Some((e, Piece::Date(today + Duration::days(days as i64), None)));
+    }
+
+    // [YYYY年]M月D日
+    let (year, m, p) = match lit(c, p, "年") {
+        Some(p) => {
+            let (m, p) = number(c, p)?;
+            (Some(n as i32), m, p)
+        }
+        None => (None, n, p),
+    };
+    let p = lit(c, p, "月")?;
+    let (d, p) = number(c, p)?;
+    let (_, e) = first_lit(c, p, &["日", "号"])?;
+    let date = month_day_date(today, year, m, d)?;
+    Some((e, Piece::Date(date, None)))
+}
+
+/// today / tonight / tomorrow / day after tomorrow / in 3 days /
+/// friday / next mon / this fri / May 20 / 20th May，可带 on / by 前缀
+fn date_en(c: &[char], i: usize, today: NaiveDate) -> Option<(usize, Piece)> {
+    if !at_boundary(c, i) {
+        return None;
+    }
+    let prefixed = first_word(c, i, &["on", "by"]).and_then(|(_, e)| ws1(c, e));
+    let p = prefixed.unwrap_or(i);
+    let date =
+        |e: usize, days: i64, period| Some((e, Piece::Date(today + Duration::days(days), period)));
+
+    if let Some(e) = word(c, p, "today") {
+        return date(e, 0, None);
+    }
+    if let Some(e) = word(c, p, "tonight") {
+        return date(e, 0, Some(Period::Pm));
+    }
+    if let Some((_, e)) = first_word(c, p, &["tomorrow", "tmrw", "tmr"]) {
+        return date(e, 1, None);
+    }
+    let after_tomorrow = word(c, p, "day")
+        .and_then(|e| ws1(c, e))
+        .and_then(|e| word(c, e, "after"))
+        .and_then(|e| ws1(c, e))
+        .and_then(|e| word(c, e, "tomorrow"));
+    if let Some(e) = after_tomorrow {
+        return date(e, 2, None);
+    }
+    if let Some(q) = word(c, p, "in").and_then(|e| ws1(c, e)) {
+        let count =
+            digits(c, q, 3).or_else(|| first_word(c, q, &["a", "one"]).map(|(_, e)| (1, e)));
+        if let Some((n, q)) = count {
+            if let Some((k, e)) =
+                ws1(c, q).and_then(|q| first_word(c, q, &["days", "day", "weeks", "week"]))
+            {
+                let days = if k < 2 { n } else { n * 7 };
+                return date(e, days as i64, None);
+            }
+        }
+    }
+
+    // next / this + 星期（可用缩写）；裸星期名只认全称，免得 `sun` 之类误伤
+    if let Some((k, q)) = first_word(c, p, &["next", "this"]) {
+        if let Some((wd, e)) = ws1(c, q).and_then(|q| en_weekday(c, q, true)) {
+            let weeks = if k == 0 { 1 } else { 0 };
+            return Some((e, Piece::Date(weekday_date(today, wd, Some(weeks)), None)));
+        }
+    }
+    if let Some((wd, e)) = en_weekday(c, p, prefixed.is_some()) {
+        return Some((e, Piece::Date(weekday_date(today, wd, None), None)));
+    }
+
+    // May 20 / May 20th / 20 May / 20th of May
+    if let Some((m, q)) = en_month(c, p) {
+        if let Some((d, e)) = ws1(c, q).and_then(|q| ordinal_day(c, q)) {
+            let date = month_day_date(today, None, m, d)?;
+            return Some((e, Piece::Date(date, None)));
+        }
+    }
+    let (d, q) = ordinal_day(c, p)?;
+    let q = ws1(c, q)?;
+    let q = word(c, q, "of").and_then(|e| ws1(c, e)).unwrap_or(q);
+    let (m, e) = en_month(c, q)?;
+    let date = month_day_date(today, None, m, d)?;
+    Some((e, Piece::Date(date, None)))
+}
+
+/// `weeks == None`：今天起最近的一个（含今天）；否则本周一 + `weeks` 周的那一天。
+fn weekday_date(today: NaiveDate, wd: u32, weeks: Option<u32>) -> NaiveDate {
+    let cur = today.weekday().number_from_monday();
+    match weeks {
+        None => today + Duration::days(((wd + 7 - cur) % 7) as i64),
+        Some(w) => {
+            let monday = today - Duration::days((cur - 1) as i64);
+            monday + Duration::days((w * 7 + wd - 1) as i64)
+        }
+    }
+}
+
+/// 没写年份时，已经过去的日期指明年。
+fn month_day_date(today: NaiveDate, year: Option<i32>, m: u32, d: u32) -> Option<NaiveDate> {
+    match year {
+        Some(y) => NaiveDate::from_ymd_opt(y, m, d),
+        None => {
+            let date = NaiveDate::from_ymd_opt(today.year(), m, d)?;
+            if date < today {
+                NaiveDate::from_ymd_opt(today.year() + 1, m, d)
+            } else {
+                Some(date)
+            }
+        }
+    }
+}
+
+// =============================================================================
+// 时刻
+// =============================================================================
+
+/// 单个时刻或时间段（`下午3点到5点` / `3pm-5pm` / `from 9:00 to 10:30`），
+/// 可带 at / from 前缀。时间段的后半没写时段时沿用前半的。
+fn time_range(c: &[char], i: usize, inherit: Option<Period>) -> Option<(usize, Piece)> {
+    if !at_boundary(c, i) {
+        return None;
+    }
+    let p = first_word(c, i, &["at", "from"])
+        .and_then(|(_, e)| ws1(c, e))
+        .unwrap_or(i);
+    let (start, e, period) = clock(c, p, inherit)?;
+
+    let q = skip_ws(c, e);
+    let sep = first_lit(c, q, &["到", "至", "~", "～", "-", "–", "—"])
+        .map(|(_, e)| e)
+        .or_else(|| first_word(c, q, &["to", "until", "till"]).map(|(_, e)| e));
+    if let Some(q) = sep {
+        if let Some((end, e2, _)) = clock(c, skip_ws(c, q), period.or(inherit)) {
+            return Some((e2, Piece::Time(start, Some(end))));
+        }
+    }
+    Some((e, Piece::Time(start, None)))
+}
+
+/// `下午3点半` / `三点一刻` / `15:30` / `3pm` / `3:30 p.m.` / `noon`。
+/// 光秃秃的数字不算时刻：必须有 点 / 冒号 / am pm 之一。
+fn clock(
+    c: &[char],
+    i: usize,
+    inherit: Option<Period>,
+) -> Option<(NaiveTime, usize, Option<Period>)> {
+    let mut p = i;
+    let mut period = None;
+    const PERIODS: [&str; 10] = [
+        "上午", "早上", "早晨", "凌晨", "中午", "下午", "傍晚", "晚上", "夜里", "晚",
+    ];
+    if let Some((k, e)) = first_lit(c, p, &PERIODS) {
+        period = Some(match k {
+            0..=3 => Period::Am,
+            4 => Period::Noon,
+            _ => Period::Pm,
+        });
+        p = skip_ws(c, e);
+    } else if let Some(e) = word(c, p, "noon") {
+        return Some((NaiveTime::from_hms_opt(12, 0, 0)?, e, Some(Period::Noon)));
+    }
+
+    let ascii = c.get(p)?.is_ascii_digit();
+    let (mut h, mut e) = if ascii {
+        digits(c, p, 2)?
+    } else {
+        cn_number(c, p)?
+    };
+    let mut m = 0;
+    let mut explicit = false;
+
+    if ascii && matches!(c.get(e), Some(':' | '：')) {
+        let (mm, e2) = digits(c, e + 1, 2).filter(|&(_, e2)| e2 - (e + 1) == 2)?;
+        m = mm;
+        e = e2;
+        explicit = true;
+    } else if let Some((_, e2)) = first_lit(c, e, &["点", "时"]) {
+        explicit = true;
+        e = e2;
+        if let Some(e3) = lit(c, e, "半") {
+            m = 30;
+            e = e3;
+        } else if let Some(e3) = lit(c, e, "一刻") {
+            m = 15;
+            e = e3;
+        } else if let Some(e3) = lit(c, e, "三刻") {
+            m = 45;
+            e = e3;
+        } else if let Some(e3) = lit(c, e, "整") {
+            e = e3;
+        } else if let Some((mm, e3)) = number(c, e).filter(|&(mm, _)| mm < 60) {
+            m = mm;
+            e = lit(c, e3, "分").unwrap_or(e3);
+        }
+    }
+    if ascii {
+        let q = skip_ws(c, e);
+        if let Some((k, e2)) = first_lit(c, q, &["a.m.", "p.m.", "am", "pm"]) {
+            if end_boundary(c, e2) {
+                period = Some(if k % 2 == 0 { Period::Am } else { Period::Pm });
+                e = e2;
+                explicit = true;
+            }
+        }
+    }
+    if !explicit || (c[e - 1].is_ascii_alphanumeric() && !end_boundary(c, e)) {
+        return None;
+    }
+
+    let period = period.or(inherit);
+    h = match (period, h) {
+        (Some(Period::Am), 12) => 0,
+        (Some(Period::Noon), 0..=3) => h + 12,
+        (Some(Period::Pm), 0..=11) => h + 12,
+        _ => h,
+    };
+    Some((NaiveTime::from_hms_opt(h, m, 0)?, e, period))
+}
+
+// =============================================================================
+// 工具
+// =============================================================================
+
+const EN_WEEKDAYS: [(&str, u32, bool); 17] = [
+    ("monday", 1, false),
+    ("mon", 1, true),
+    ("tuesday", 2, false),
+    ("tues", 2, true),
+    ("tue", 2, true),
+    ("wednesday", 3, false),
+    ("wed", 3, true),
+    ("thursday", 4, false),
+    ("thurs", 4, true),
+    ("thur", 4, true),
+    ("thu", 4, true),
+    ("friday", 5, false),
+    ("fri", 5, true),
+    ("saturday", 6, false),
+    ("sat", 6, true),
+    ("sunday", 7, false),
+    ("sun", 7, true),
+];
+
+const EN_MONTHS: [(&str, u32); 24] = [
+    ("january", 1),
+    ("jan", 1),
+    ("february", 2),
+    ("feb", 2),
+    ("march", 3),
+    ("mar", 3),
+    ("april", 4),
+    ("apr", 4),
+    ("may", 5),
+    ("june", 6),
+    ("jun", 6),
+    ("july", 7),
+    ("jul", 7),
+    ("august", 8),
+    ("aug", 8),
+    ("september", 9),
+    ("sept", 9),
+    ("sep", 9),
+    ("october", 10),
+    ("oct", 10),
+    ("november", 11),
+    ("nov", 11),
+    ("december", 12),
+    ("dec", 12),
+];
+
+/// 英文星期名（可带复数 s：`every mondays` 也认）；`abbr` 控制是否接受缩写。
+fn en_weekday(c: &[char], i: usize, abbr: bool) -> Option<(u32, usize)> {
+    if !at_boundary(c, i) {
+        return None;
+    }
+    EN_WEEKDAYS.iter().find_map(|&(name, wd, is_abbr)| {
+        if is_abbr && !abbr {
+            return None;
+        }
+        let e = lit(c, i, name)?;
+        let e = if c.get(e).is_some_and(|ch| ch.eq_ignore_ascii_case(&'s')) {
+            e + 1
+        } else {
+            e
+        };
+        end_boundary(c, e).then_some((wd, e))
+    })
+}
+
+fn en_month(c: &[char], i: usize) -> Option<(u32, usize)> {
+    EN_MONTHS.iter().find_map(|&(name, m)| {
+        let e = word(c, i, name)?;
+        let e = if c.get(e) == Some(&'.') { e + 1 } else { e };
+        Some((m, e))
+    })
+}
+
+/// `15` / `15th` / `1st` / `22nd` / `3rd`
+fn ordinal_day(c: &[char], i: usize) -> Option<(u32, usize)> {
+    let (d, e) = digits(c, i, 2)?;
+    let e = first_lit(c, e, &["st", "nd", "rd", "th"]).map_or(e, |(_, e)| e);
+    (end_boundary(c, e) && (1..=31).contains(&d)).then_some((d, e))
+}
+
+fn zh_weekday(ch: char) -> Option<u32> {
+    match ch {
+        '一' | '1' => Some(1),
+        '二' | '2' => Some(2),
+        '三' | '3' => Some(3),
+        '四' | '4' => Some(4),
+        '五' | '5' => Some(5),
+        '六' | '6' => Some(6),
+        '日' | '天' | '7' => Some(7),
+        _ => None,
+    }
+}
+
+/// ASCII 数字或中文数字（到 九十九）。
+fn number(c: &[char], i: usize) -> Option<(u32, usize)> {
+    digits(c, i, 4).or_else(|| cn_number(c, i))
+}
+
+fn digits(c: &[char], i: usize, max: usize) -> Option<(u32, usize)> {
+    let mut e = i;
+    let mut n = 0u32;
+    while e < c.len() && e - i < max {
+        let Some(d) = c[e].to_digit(10) else {
+            break;
+        };
+        n = n * 10 + d;
+        e += 1;
+    }
+    (e > i).then_some((n, e))
+}
+
+/// 一 / 两 / 十 / 十二 / 二十 / 二十三
+fn cn_number(c: &[char], i: usize) -> Option<(u32, usize)> {
+    let digit = |ch: char| {
+        "零一二三四五六七八九"
+            .chars()
+            .position(|d| d == ch)
+            .map(|d| d as u32)
+    };
+    let mut e = i;
+    let mut total = 0;
+    let mut cur: Option<u32> = None;
+    let mut seen_ten = false;
+    while e < c.len() {
+        let ch = c[e];
+        if ch == '十' && !seen_ten {
+            total += cur.take().unwrap_or(1) * 10;
+            seen_ten = true;
+        } else if let Some(d) = digit(ch).or((ch == '两').then_some(2)) {
+            if cur.is_some() {
+                break;
+            }
+            cur = Some(d);
+        } else {
+            break;
+        }
+        e += 1;
+    }
+    (e > i).then(|| (total + cur.unwrap_or(0), e))
+}
+
+fn last_day_of_month(d: NaiveDate) -> u32 {
+    let (y, m) = if d.month() == 12 {
+        (d.year() + 1, 1)
+    } else {
+        (d.year(), d.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(y, m, 1)
+        .and_then(|first| first.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+/// 大小写不敏感地匹配字面量，返回结束位置。
+fn lit(c: &[char], i: usize, s: &str) -> Option<usize> {
+    let mut p = i;
+    for ch in s.chars() {
+        if !c.get(p)?.eq_ignore_ascii_case(&ch) {
+            return None;
+        }
+        p += 1;
+    }
+    Some(p)
+}
+
+/// 依次尝试，返回第一个命中的下标与结束位置（长的写在前面）。
+fn first_lit(c: &[char], i: usize, list: &[&str]) -> Option<(usize, usize)> {
+    list.iter()
+        .enumerate()
+        .find_map(|(k, s)| lit(c, i, s).map(|e| (k, e)))
+}
+
+/// 英文单词：两侧都要是词边界。
+fn word(c: &[char], i: usize, s: &str) -> Option<usize> {
+    if !at_boundary(c, i) {
+        return None;
+    }
+    lit(c, i, s).filter(|&e| end_boundary(c, e))
+}
+
+fn first_word(c: &[char], i: usize, list: &[&str]) -> Option<(usize, usize)> {
+    list.iter()
+        .enumerate()
+        .find_map(|(k, s)| word(c, i, s).map(|e| (k, e)))
+}
+
+fn at_boundary(c: &[char], i: usize) -> bool {
+    i == 0 || !c[i - 1].is_ascii_alphanumeric()
+}
+
+fn end_boundary(c: &[char], i: usize) -> bool {
+    i >= c.len() || !c[i].is_ascii_alphanumeric()
+}
+
+fn after_space(c: &[char], i: usize) -> bool {
+    i == 0 || c[i - 1].is_whitespace()
+}
+
+fn skip_ws(c: &[char], i: usize) -> usize {
+    let mut e = i;
+    while e < c.len() && c[e].is_whitespace() {
+        e += 1;
+    }
+    e
+}
+
+/// 至少一个空白。
+fn ws1(c: &[char], i: usize) -> Option<usize> {
+    let e = skip_ws(c, i);
+    (e > i).then_some(e)
+}